@@ -0,0 +1,162 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use jif::bits::BitReader;
+
+use std::fs;
+
+const GIFS: [&str; 2] = ["homeless-nah-id-win.gif", "sample_1.gif"];
+
+// the reader as it was before it kept an accumulator, kept here as the baseline
+struct BitByBitReader<'a> {
+    buf: &'a [u8],
+    position: usize,
+    length: usize,
+}
+
+impl<'a> BitByBitReader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self {
+            buf,
+            position: 0,
+            length: buf.len() * 8,
+        }
+    }
+
+    fn next(&mut self, count: u32) -> Option<u16> {
+        let start_position = self.position;
+        let end_position = self.position + count as usize;
+
+        if end_position > self.length {
+            return None;
+        }
+        let mut value: u16 = 0;
+        for (out_shift, i) in (start_position..end_position).enumerate() {
+            let byte = self.buf[i / 8];
+            let bit = (byte >> (i % 8)) as u16 & 1;
+            value |= bit << out_shift;
+        }
+        self.position = end_position;
+        Some(value)
+    }
+}
+
+/// The LZW data of every image in a GIF, with the sub-block lengths stripped, along with its
+/// minimum code size.
+fn image_data_streams(gif: &[u8]) -> Vec<(u32, Vec<u8>)> {
+    fn skip_color_table(position: &mut usize, packed: u8) {
+        if packed & 0x80 != 0 {
+            *position += 3 << ((packed & 0x07) + 1);
+        }
+    }
+
+    fn read_sub_blocks(gif: &[u8], position: &mut usize, out: &mut Vec<u8>) {
+        loop {
+            let length = gif[*position] as usize;
+            *position += 1;
+            if length == 0 {
+                return;
+            }
+            out.extend_from_slice(&gif[*position..*position + length]);
+            *position += length;
+        }
+    }
+
+    let mut streams = Vec::new();
+    // header and logical screen descriptor
+    let mut position = 13;
+    skip_color_table(&mut position, gif[10]);
+
+    loop {
+        match gif[position] {
+            0x21 => {
+                position += 2;
+                read_sub_blocks(gif, &mut position, &mut Vec::new());
+            }
+            0x2C => {
+                let packed = gif[position + 9];
+                position += 10;
+                skip_color_table(&mut position, packed);
+
+                let minimum_code_size = gif[position] as u32;
+                position += 1;
+                let mut data = Vec::new();
+                read_sub_blocks(gif, &mut position, &mut data);
+                streams.push((minimum_code_size, data));
+            }
+            _ => return streams,
+        }
+    }
+}
+
+// reads codes with the widths an LZW decoder would ask for, growing as the table fills up and
+// starting over once it's full, without the cost of actually decoding
+macro_rules! read_codes {
+    ($reader:expr, $minimum_code_size:expr) => {{
+        let mut reader = $reader;
+        let clear_code = 1_u32 << $minimum_code_size;
+        let mut code_size = $minimum_code_size + 1;
+        let mut next_code = clear_code + 2;
+        let mut sum = 0_u64;
+
+        while let Some(code) = reader.next(code_size) {
+            sum += code as u64;
+            next_code += 1;
+            if next_code == 1 << code_size {
+                if code_size < 12 {
+                    code_size += 1;
+                } else {
+                    code_size = $minimum_code_size + 1;
+                    next_code = clear_code + 2;
+                }
+            }
+        }
+        sum
+    }};
+}
+
+fn read_image_data(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bit_reader");
+
+    for gif in GIFS {
+        let data = fs::read(gif).expect("benchmarks run from the crate root");
+        let streams = image_data_streams(&data);
+        let length: usize = streams.iter().map(|(_, stream)| stream.len()).sum();
+        group.throughput(Throughput::Bytes(length as u64));
+
+        for (size, stream) in &streams {
+            assert_eq!(
+                read_codes!(BitByBitReader::new(stream), *size),
+                read_codes!(BitReader::new(stream), *size)
+            );
+        }
+
+        group.bench_with_input(
+            BenchmarkId::new("bit_by_bit", gif),
+            &streams,
+            |b, streams| {
+                b.iter(|| {
+                    streams
+                        .iter()
+                        .map(|(size, stream)| read_codes!(BitByBitReader::new(stream), *size))
+                        .sum::<u64>()
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("accumulator", gif),
+            &streams,
+            |b, streams| {
+                b.iter(|| {
+                    streams
+                        .iter()
+                        .map(|(size, stream)| read_codes!(BitReader::new(stream), *size))
+                        .sum::<u64>()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, read_image_data);
+criterion_main!(benches);
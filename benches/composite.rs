@@ -0,0 +1,72 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use jif::animation::Animation;
+use jif::compositor::Compositor;
+
+use std::fs;
+
+const GIFS: [&str; 2] = ["homeless-nah-id-win.gif", "test-100x75.gif"];
+
+fn pixel_count(animation: &Animation) -> u64 {
+    animation
+        .frames()
+        .iter()
+        .map(|frame| frame.width as u64 * frame.height as u64)
+        .sum()
+}
+
+// turning palette indicies into RGBA, one frame at a time with nothing underneath
+fn frames_to_rgba(c: &mut Criterion) {
+    let mut group = c.benchmark_group("index_to_rgba");
+    group.sample_size(10);
+
+    for gif in GIFS {
+        let data = fs::read(gif).expect("benchmarks run from the crate root");
+        let animation = Animation::decode(data.as_slice()).unwrap();
+
+        group.throughput(Throughput::Elements(pixel_count(&animation)));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(gif),
+            &animation,
+            |b, animation| {
+                b.iter(|| {
+                    animation
+                        .frames()
+                        .iter()
+                        .map(|frame| frame.rgba().len())
+                        .sum::<usize>()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+// drawing every frame on top of the last, with transparency and disposal
+fn composite_frames(c: &mut Criterion) {
+    let mut group = c.benchmark_group("composite");
+    group.sample_size(10);
+
+    for gif in GIFS {
+        let data = fs::read(gif).expect("benchmarks run from the crate root");
+        let animation = Animation::decode(data.as_slice()).unwrap();
+
+        group.throughput(Throughput::Elements(pixel_count(&animation)));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(gif),
+            &animation,
+            |b, animation| {
+                b.iter(|| {
+                    let mut compositor = Compositor::new(animation.width(), animation.height());
+                    for frame in animation.frames() {
+                        compositor.draw(frame);
+                    }
+                    compositor.canvas().len()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, frames_to_rgba, composite_frames);
+criterion_main!(benches);
@@ -0,0 +1,69 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use jif::parser::Decoder;
+
+use std::fs;
+
+// 253 frames of 284x373, big enough that LZW decoding dominates
+const LARGE_GIF: &str = "homeless-nah-id-win.gif";
+// a large animation, a small one and a single tiny image
+const GIFS: [&str; 3] = [LARGE_GIF, "test-100x75.gif", "sample_1.gif"];
+
+fn decode_large_gif(c: &mut Criterion) {
+    let data = fs::read(LARGE_GIF).expect("benchmarks run from the crate root");
+
+    let mut group = c.benchmark_group("decode");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function(LARGE_GIF, |b| {
+        b.iter(|| {
+            let mut decoder = Decoder::new(data.as_slice());
+            decoder.parse().unwrap();
+            decoder.frames().len()
+        })
+    });
+    #[cfg(feature = "parallel")]
+    group.bench_function(format!("{} (parallel)", LARGE_GIF), |b| {
+        b.iter(|| {
+            let mut decoder = Decoder::new(data.as_slice());
+            decoder.parse_parallel().unwrap();
+            decoder.frames().len()
+        })
+    });
+    group.finish();
+}
+
+// every file start to finish, into frames of palette indicies
+fn decode_gifs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode_file");
+
+    for gif in GIFS {
+        let data = fs::read(gif).expect("benchmarks run from the crate root");
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(gif), &data, |b, data| {
+            b.iter(|| {
+                let mut decoder = Decoder::new(data.as_slice());
+                decoder.parse().unwrap();
+                decoder.frames().len()
+            })
+        });
+    }
+    group.finish();
+}
+
+// walking the blocks of each file without decoding the image data, so this is the parser by
+// itself
+fn scan_gifs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scan");
+
+    for gif in GIFS {
+        let data = fs::read(gif).expect("benchmarks run from the crate root");
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(gif), &data, |b, data| {
+            b.iter(|| Decoder::new(data.as_slice()).scan().unwrap().frame_count)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode_large_gif, decode_gifs, scan_gifs);
+criterion_main!(benches);
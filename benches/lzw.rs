@@ -0,0 +1,53 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use jif::encoder::lzw::lzw_encode;
+use jif::parser::lzw::lzw_decode;
+
+const SIZE: usize = 2048;
+
+// a 2048x2048 frame, big enough that the decoder runs through the code table many times
+fn large_frames() -> [(&'static str, u32, Vec<u8>); 3] {
+    // xorshift, so the noise is the same every run
+    let mut state = 0x2545f491_u32;
+    let noise = (0..SIZE * SIZE)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+
+    // long runs of the same index, like flat areas in a cartoon
+    let stripes = (0..SIZE * SIZE).map(|i| (i / SIZE / 64) as u8).collect();
+    // a dithered gradient over 16 colors, somewhere between the two
+    let dithered = (0..SIZE * SIZE)
+        .map(|i| ((i % SIZE * 16 / SIZE) as u8 + ((i / SIZE + i) % 2) as u8).min(15))
+        .collect();
+
+    [
+        ("noise", 8, noise),
+        ("stripes", 5, stripes),
+        ("dithered", 4, dithered),
+    ]
+}
+
+fn decode_large_frames(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lzw_decode");
+    group.sample_size(10);
+
+    for (name, minimum_code_size, indicies) in large_frames() {
+        let data = lzw_encode(&indicies, minimum_code_size);
+        group.throughput(Throughput::Elements(indicies.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &data, |b, data| {
+            b.iter(|| {
+                lzw_decode(data, minimum_code_size, indicies.len())
+                    .unwrap()
+                    .len()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode_large_frames);
+criterion_main!(benches);
@@ -0,0 +1,272 @@
+//! Decodes a GIF handed over from JavaScript as an `ArrayBuffer` and plays it on a `<canvas>`,
+//! drawing with wgpu through WebGPU, or WebGL where the browser doesn't have WebGPU.
+//!
+//! ```sh
+//! cargo build --release --example wasm --target wasm32-unknown-unknown
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/examples/wasm.wasm
+//! ```
+//!
+//! ```js
+//! import init, { play } from "./pkg/wasm.js";
+//!
+//! await init();
+//! const gif = await fetch("cat.gif").then((response) => response.arrayBuffer());
+//! await play(document.querySelector("canvas"), gif);
+//! ```
+//!
+//! On other targets this builds to an empty library.
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use js_sys::{ArrayBuffer, Promise, Uint8Array};
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen_futures::JsFuture;
+    use web_sys::HtmlCanvasElement;
+
+    use std::time::Duration;
+
+    use jif::animation::Animation;
+
+    /// Plays `gif` on `canvas` forever, resizing the canvas to the GIF. Only returns early if
+    /// the GIF can't be decoded or the browser can't draw on the canvas.
+    #[wasm_bindgen]
+    pub async fn play(canvas: HtmlCanvasElement, gif: ArrayBuffer) -> Result<(), JsError> {
+        let data = Uint8Array::new(&gif).to_vec();
+        let animation =
+            Animation::decode(data.as_slice()).map_err(|err| JsError::new(&err.to_string()))?;
+        if animation.frames().is_empty() {
+            return Err(JsError::new("the GIF doesn't contain any frames"));
+        }
+
+        let (width, height) = (animation.width(), animation.height());
+        canvas.set_width(width as u32);
+        canvas.set_height(height as u32);
+        let renderer = Renderer::new(canvas, width, height).await?;
+
+        // every canvas up front, so playing doesn't have to composite
+        let frames: Vec<(Vec<u8>, Duration)> = animation
+            .composited_frames()
+            .zip(animation.frames().iter().map(|frame| frame.duration()))
+            .collect();
+        loop {
+            for (rgba, duration) in &frames {
+                renderer.draw(rgba)?;
+                sleep(*duration).await?;
+            }
+        }
+    }
+
+    async fn sleep(duration: Duration) -> Result<(), JsError> {
+        let promise = Promise::new(&mut |resolve, _| {
+            if let Some(window) = web_sys::window() {
+                let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+                    &resolve,
+                    duration.as_millis() as i32,
+                );
+            }
+        });
+        JsFuture::from(promise)
+            .await
+            .map_err(|_| JsError::new("waiting for the next frame failed"))?;
+        Ok(())
+    }
+
+    struct Renderer {
+        surface: wgpu::Surface<'static>,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        pipeline: wgpu::RenderPipeline,
+        bind_group: wgpu::BindGroup,
+        texture: wgpu::Texture,
+    }
+
+    impl Renderer {
+        async fn new(canvas: HtmlCanvasElement, width: u16, height: u16) -> Result<Self, JsError> {
+            let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+                backends: wgpu::Backends::BROWSER_WEBGPU | wgpu::Backends::GL,
+                ..Default::default()
+            });
+            let surface = instance.create_surface(wgpu::SurfaceTarget::Canvas(canvas))?;
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    compatible_surface: Some(&surface),
+                    ..Default::default()
+                })
+                .await
+                .ok_or_else(|| JsError::new("the browser doesn't support WebGPU or WebGL"))?;
+            let (device, queue) = adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        required_limits: wgpu::Limits::downlevel_webgl2_defaults()
+                            .using_resolution(adapter.limits()),
+                        ..Default::default()
+                    },
+                    None,
+                )
+                .await?;
+
+            let config = surface
+                .get_default_config(&adapter, width as u32, height as u32)
+                .ok_or_else(|| JsError::new("the canvas can't be drawn on"))?;
+            surface.configure(&device, &config);
+
+            // srgb surfaces convert back from linear, others take the bytes as they are
+            let texture_format = if config.format.is_srgb() {
+                wgpu::TextureFormat::Rgba8UnormSrgb
+            } else {
+                wgpu::TextureFormat::Rgba8Unorm
+            };
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                size: wgpu::Extent3d {
+                    width: width as u32,
+                    height: height as u32,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: texture_format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                label: None,
+                view_formats: &[],
+            });
+            let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+                mag_filter: wgpu::FilterMode::Nearest,
+                min_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            });
+
+            let bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                multisampled: false,
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                    ],
+                    label: None,
+                });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+                label: None,
+            });
+
+            // the viewer's shader, which draws the texture over the whole surface
+            let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(include_str!("../src/shader.wgsl").into()),
+            });
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+            let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: config.format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            });
+
+            Ok(Self {
+                surface,
+                device,
+                queue,
+                pipeline,
+                bind_group,
+                texture,
+            })
+        }
+
+        fn draw(&self, rgba: &[u8]) -> Result<(), JsError> {
+            let size = self.texture.size();
+            self.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &self.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::default(),
+                },
+                rgba,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * size.width),
+                    rows_per_image: Some(size.height),
+                },
+                size,
+            );
+
+            let output = self.surface.get_current_texture()?;
+            let view = output
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default());
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                render_pass.set_pipeline(&self.pipeline);
+                render_pass.set_bind_group(0, &self.bind_group, &[]);
+                render_pass.draw(0..6, 0..1);
+            }
+            self.queue.submit(std::iter::once(encoder.finish()));
+            output.present();
+            Ok(())
+        }
+    }
+}
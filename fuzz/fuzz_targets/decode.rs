@@ -0,0 +1,18 @@
+#![no_main]
+
+// cargo fuzz run decode fuzz/corpus/decode tests/fixtures
+
+use jif::parser::{DecodeOptions, Decoder};
+use libfuzzer_sys::fuzz_target;
+
+use std::io::Cursor;
+
+fuzz_target!(|data: &[u8]| {
+    // keep allocations bounded, otherwise every header claiming a huge frame is reported as an
+    // out of memory crash.
+    for strict in [false, true] {
+        let options = DecodeOptions::new().strict(strict).max_memory(1 << 26);
+        let mut decoder = Decoder::new_with_options(Cursor::new(data), options);
+        let _ = decoder.parse();
+    }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+// cargo fuzz run lzw_decode
+
+use jif::parser::lzw_decode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // first byte is the minimum code size, the rest is the code stream
+    if let Some((&minimum_code_size, stream)) = data.split_first() {
+        let _ = lzw_decode(stream, minimum_code_size.into(), 1 << 20);
+    }
+});
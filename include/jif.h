@@ -0,0 +1,117 @@
+#ifndef JIF_H
+#define JIF_H
+
+/* Generated by cbindgen from src/ffi.rs, don't edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * What the `jif_decoder_*` functions return.
+ */
+typedef enum JifResult {
+  JIF_RESULT_OK = 0,
+  /**
+   * A pointer that has to be set was null.
+   */
+  JIF_RESULT_NULL_POINTER = -1,
+  /**
+   * There's no frame with that index.
+   */
+  JIF_RESULT_OUT_OF_RANGE = -2,
+  /**
+   * The output buffer is smaller than `width * height * 4` bytes.
+   */
+  JIF_RESULT_BUFFER_TOO_SMALL = -3,
+  /**
+   * The frame couldn't be decoded.
+   */
+  JIF_RESULT_DECODE_FAILED = -4,
+} JifResult;
+
+/**
+ * A decoded GIF, created by `jif_decoder_new` and freed by `jif_decoder_free`.
+ */
+typedef struct JifDecoder JifDecoder;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Decodes the `len` bytes of GIF at `data`, which are copied and can be freed as soon as this
+ * returns. Returns null if they aren't a GIF that can be decoded.
+ *
+ * # Safety
+ *
+ * `data` has to point to `len` readable bytes.
+ */
+struct JifDecoder *jif_decoder_new(const uint8_t *data, uintptr_t len);
+
+/**
+ * Width of the logical screen every frame is composited onto, 0 for a null decoder.
+ *
+ * # Safety
+ *
+ * `decoder` has to be null or returned by `jif_decoder_new` and not freed yet.
+ */
+uint32_t jif_decoder_width(const struct JifDecoder *decoder);
+
+/**
+ * Height of the logical screen every frame is composited onto, 0 for a null decoder.
+ *
+ * # Safety
+ *
+ * `decoder` has to be null or returned by `jif_decoder_new` and not freed yet.
+ */
+uint32_t jif_decoder_height(const struct JifDecoder *decoder);
+
+/**
+ * Number of frames, 0 for a null decoder.
+ *
+ * # Safety
+ *
+ * `decoder` has to be null or returned by `jif_decoder_new` and not freed yet.
+ */
+uintptr_t jif_decoder_frame_count(const struct JifDecoder *decoder);
+
+/**
+ * How long frame `index` is shown for in milliseconds, with delays too short for browsers to
+ * honour played at 100ms like they do. 0 if there's no such frame.
+ *
+ * # Safety
+ *
+ * `decoder` has to be null or returned by `jif_decoder_new` and not freed yet.
+ */
+uint32_t jif_decoder_frame_duration_ms(const struct JifDecoder *decoder, uintptr_t index);
+
+/**
+ * Writes what's on screen while frame `index` is shown to `out`, as `width * height` RGBA
+ * pixels row by row. `len` is the size of `out` in bytes.
+ *
+ * # Safety
+ *
+ * `decoder` has to be null or returned by `jif_decoder_new` and not freed yet, and `out` has
+ * to point to `len` writable bytes.
+ */
+enum JifResult jif_decoder_frame_rgba(struct JifDecoder *decoder,
+                                      uintptr_t index,
+                                      uint8_t *out,
+                                      uintptr_t len);
+
+/**
+ * Frees a decoder. Null is ignored.
+ *
+ * # Safety
+ *
+ * `decoder` has to be null or returned by `jif_decoder_new`, and not used again afterwards.
+ */
+void jif_decoder_free(struct JifDecoder *decoder);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* JIF_H */
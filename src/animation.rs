@@ -0,0 +1,753 @@
+mod builder;
+mod concat;
+mod intermediate;
+mod optimize;
+mod palettes;
+
+use anyhow::Result;
+use thiserror::Error;
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io::prelude::*;
+use std::ops::Range;
+
+use crate::compositor::{blend_over, changed_pixels, Compositor, Rect};
+use crate::encoder::Encoder;
+use crate::export::ImageFormat;
+use crate::parser::{
+    DecodeOptions, Decoder, DisposalMethod, Frame, FrameStats, LoopCount, PartialDecode,
+    MINIMUM_DELAY_TIME,
+};
+use crate::scale;
+use crate::watermark::{Position, Watermark};
+
+pub use builder::GifBuilder;
+pub use intermediate::{Compression, EXTENSION as SAVED_EXTENSION};
+pub use optimize::OptimizeOptions;
+pub use palettes::{PaletteAnalysis, PaletteCluster};
+
+#[derive(Error, Debug)]
+enum AnimationError {
+    #[error("the frames before frame {0} leave more than 256 colors on screen, which can't be kept in one frame")]
+    TooManyColors(usize),
+    #[error("frame {0} changes more than 256 colors, which can't be kept in one frame")]
+    TooManyChangedColors(usize),
+}
+
+/// A fully decoded GIF: the logical screen and every frame drawn on it.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    width: u16,
+    height: u16,
+    loop_count: Option<LoopCount>,
+    pixel_aspect_ratio: Option<f32>,
+    frames: Vec<Frame>,
+}
+
+impl Animation {
+    pub fn decode<R: Read + Debug>(reader: R) -> Result<Self> {
+        Self::decode_with_options(reader, DecodeOptions::default())
+    }
+
+    pub fn decode_with_options<R: Read + Debug>(reader: R, options: DecodeOptions) -> Result<Self> {
+        let mut decoder = Decoder::new_with_options(reader, options);
+        decoder.parse()?;
+        Ok(Self::from_decoder(decoder))
+    }
+
+    /// Decodes as much of a GIF as there is. When it breaks off partway through the frames,
+    /// the animation has the frames before the break and the error comes back alongside it, to
+    /// be warned about rather than given up on. See [`Decoder::parse_partial`]. Errors before
+    /// the first frame, in a file that isn't a GIF say, are still errors.
+    pub fn decode_partial<R: Read + Debug>(
+        reader: R,
+        options: DecodeOptions,
+    ) -> Result<(Self, Option<anyhow::Error>)> {
+        let mut decoder = Decoder::new_with_options(reader, options);
+        match decoder.parse_partial() {
+            Ok(()) => Ok((Self::from_decoder(decoder), None)),
+            Err(partial) if partial.frames.is_empty() => Err(partial.error),
+            Err(PartialDecode { frames, error }) => {
+                let mut animation = Self::from_decoder(decoder);
+                animation.frames = frames;
+                Ok((animation, Some(error)))
+            }
+        }
+    }
+
+    /// Takes the frames out of a decoder that has already parsed its input.
+    pub fn from_decoder<R: Read + Debug>(decoder: Decoder<R>) -> Self {
+        let (width, height) = decoder.screen_size().unwrap_or((0, 0));
+        let loop_count = decoder.loop_count();
+        let pixel_aspect_ratio = decoder.pixel_aspect_ratio();
+
+        Self {
+            width,
+            height,
+            loop_count,
+            pixel_aspect_ratio,
+            frames: decoder.into_frames(),
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn loop_count(&self) -> Option<LoopCount> {
+        self.loop_count
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// The width of a pixel over its height, if the GIF says. Everything but the viewer and
+    /// [`Animation::correct_aspect_ratio`] treats pixels as square.
+    pub fn pixel_aspect_ratio(&self) -> Option<f32> {
+        self.pixel_aspect_ratio
+    }
+
+    pub fn set_loop_count(&mut self, loop_count: Option<LoopCount>) {
+        self.loop_count = loop_count;
+    }
+
+    /// Stretches the animation so that shown with square pixels it looks like it does with
+    /// the pixels the GIF asks for, see [`scale::square_pixels`]. Nothing changes for GIFs
+    /// that don't set a pixel aspect ratio.
+    pub fn correct_aspect_ratio(&mut self) {
+        if let Some(pixel_aspect_ratio) = self.pixel_aspect_ratio.take() {
+            let (width, height) = scale::square_pixels(self.width, self.height, pixel_aspect_ratio);
+            self.resize(width, height);
+        }
+    }
+
+    /// Cuts every frame down to `rect`, which becomes the new logical screen. Frames that end
+    /// up entirely outside of it are kept as a single transparent pixel so the timing stays
+    /// the same.
+    pub fn crop(&mut self, rect: Rect) {
+        let rect = rect.intersect(Rect::new(0, 0, self.width, self.height));
+
+        for frame in &mut self.frames {
+            let bounds = Rect::new(
+                frame.left_position,
+                frame.top_position,
+                frame.width,
+                frame.height,
+            );
+            let visible = bounds.intersect(rect);
+
+            if visible.is_empty() {
+                let transparent_index = frame.transparent_color_index.unwrap_or(0);
+                *frame = frame.with_image(0, 0, 1, 1, Box::new([transparent_index]));
+                frame.transparent_color_index = Some(transparent_index);
+                frame.disposal_method = Some(DisposalMethod::DoNotDispose);
+                continue;
+            }
+
+            let frame_width = frame.width as usize;
+            let left = (visible.left - bounds.left) as usize;
+            let mut indicies = Vec::with_capacity(visible.width as usize * visible.height as usize);
+            for y in (visible.top - bounds.top) as usize
+                ..(visible.top - bounds.top + visible.height) as usize
+            {
+                let start = y * frame_width + left;
+                indicies
+                    .extend_from_slice(&frame.indicies()[start..start + visible.width as usize]);
+            }
+
+            *frame = frame.with_image(
+                visible.left - rect.left,
+                visible.top - rect.top,
+                visible.width,
+                visible.height,
+                indicies.into_boxed_slice(),
+            );
+        }
+
+        self.width = rect.width;
+        self.height = rect.height;
+    }
+
+    /// Scales the logical screen and every frame on it to `width` x `height`. Indicies are
+    /// picked by nearest neighbour so palettes and transparency stay as they are. Frames keep
+    /// their place on the screen, and ones that would shrink to nothing keep a single pixel.
+    pub fn resize(&mut self, width: u16, height: u16) {
+        let (width, height) = (width.max(1), height.max(1));
+        // where an edge on the old screen ends up on the new one
+        let scale = |position: u16, old_size: u16, new_size: u16| {
+            if old_size == 0 {
+                return 0;
+            }
+            ((position as u32 * new_size as u32 + old_size as u32 / 2) / old_size as u32) as u16
+        };
+
+        for frame in &mut self.frames {
+            if frame.width == 0 || frame.height == 0 {
+                continue;
+            }
+
+            let left = scale(frame.left_position, self.width, width);
+            let top = scale(frame.top_position, self.height, height);
+            let right = scale(frame.left_position.saturating_add(frame.width), self.width, width);
+            let bottom = scale(frame.top_position.saturating_add(frame.height), self.height, height);
+            let (frame_width, frame_height) = ((right - left).max(1), (bottom - top).max(1));
+
+            let indicies = scale::resize_indexed(
+                frame.indicies(),
+                frame.width,
+                frame.height,
+                frame_width,
+                frame_height,
+            );
+            *frame = frame.with_image(
+                left.min(width - 1),
+                top.min(height - 1),
+                frame_width,
+                frame_height,
+                indicies.into_boxed_slice(),
+            );
+        }
+
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Keeps only the frames in `range`. Whatever the dropped frames leave on screen is
+    /// flattened into the first frame that's kept, which fails if it has more than 256 colors.
+    pub fn trim(&mut self, range: Range<usize>) -> Result<()> {
+        let end = range.end.min(self.frames.len());
+        let start = range.start.min(end);
+
+        let mut compositor = self.screen_before(start);
+        self.frames.truncate(end);
+        self.frames.drain(..start);
+
+        let background_is_empty = compositor
+            .canvas()
+            .chunks_exact(4)
+            .all(|pixel| pixel[3] == 0);
+        if background_is_empty || self.frames.is_empty() {
+            return Ok(());
+        }
+
+        let first = &self.frames[0];
+        if matches!(
+            first.disposal_method,
+            None | Some(DisposalMethod::None) | Some(DisposalMethod::DoNotDispose)
+        ) {
+            // nothing needs to be put back after the first frame, so it can be merged with
+            // what's under it
+            compositor.draw(first);
+            let mut flattened = flatten(self.width, self.height, &compositor.canvas())
+                .ok_or(AnimationError::TooManyColors(start))?;
+            flattened.delay_time = first.delay_time;
+            flattened.needs_user_input = first.needs_user_input;
+            self.frames[0] = flattened;
+        } else {
+            // the first frame gets disposed of, so what's under it has to stay a separate frame
+            let flattened = flatten(self.width, self.height, &compositor.canvas())
+                .ok_or(AnimationError::TooManyColors(start))?;
+            self.frames.insert(0, flattened);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the frames before frame `index` leave nothing on screen once they're disposed
+    /// of, so the frames from there on show the same by themselves.
+    pub fn is_clear_before(&self, index: usize) -> bool {
+        self.screen_before(index.min(self.frames.len()))
+            .canvas()
+            .chunks_exact(4)
+            .all(|pixel| pixel[3] == 0)
+    }
+
+    // what's left for frame `index` to be drawn onto
+    fn screen_before(&self, index: usize) -> Compositor {
+        let mut compositor = Compositor::new(self.width, self.height);
+        for frame in &self.frames[..index] {
+            compositor.draw(frame);
+        }
+        compositor.dispose();
+        compositor
+    }
+
+    /// Every frame as a full frame of what's on screen at that point, which is never disposed
+    /// of unless the next frame clears pixels, like ImageMagick's `-coalesce`. Fails if a frame
+    /// shows more than 256 colors at once.
+    pub fn coalesce(&self) -> Result<Animation> {
+        self.full_frames(self.composited_frames().enumerate())
+    }
+
+    /// The frames played backwards, each one showing what was on screen for it before and
+    /// keeping its delay. Frames are turned into full frames like with
+    /// [`Animation::coalesce`], since they usually only hold what changed since the frame
+    /// before. Fails if a frame shows more than 256 colors at once.
+    pub fn reverse(&self) -> Result<Animation> {
+        let canvases: Vec<(usize, Vec<u8>)> = self.composited_frames().enumerate().collect();
+        self.full_frames(canvases.into_iter().rev())
+    }
+
+    /// Burns `watermark` into every frame at `position`, alpha blended over what's on screen.
+    /// Blended pixels take the closest color in the palette of the frame being shown, so the
+    /// watermark doesn't bring in colors of its own. Frames are turned into full frames like
+    /// with [`Animation::coalesce`], and [`Animation::optimize`] crops them down again. Fails
+    /// if a frame shows more than 256 colors at once.
+    pub fn watermark(&self, watermark: &Watermark, position: Position) -> Result<Animation> {
+        let rect = watermark.rect(self.width, self.height, position);
+        let canvases = self
+            .composited_frames()
+            .enumerate()
+            .map(|(index, mut canvas)| {
+                self.blend_watermark(&mut canvas, &self.frames[index], watermark, rect);
+                (index, canvas)
+            });
+        self.full_frames(canvases)
+    }
+
+    // blends `watermark` into `canvas` over `rect`, in the colors of `frame`
+    fn blend_watermark(&self, canvas: &mut [u8], frame: &Frame, watermark: &Watermark, rect: Rect) {
+        let visible = rect.intersect(Rect::new(0, 0, self.width, self.height));
+        let mut closest = HashMap::new();
+
+        for y in visible.top as usize..(visible.top + visible.height) as usize {
+            for x in visible.left as usize..(visible.left + visible.width) as usize {
+                let pixel = (y * self.width as usize + x) * 4;
+                let over =
+                    ((y - rect.top as usize) * rect.width as usize + x - rect.left as usize) * 4;
+                let under: [u8; 4] = canvas[pixel..pixel + 4].try_into().unwrap();
+                let blended =
+                    blend_over(under, watermark.rgba()[over..over + 4].try_into().unwrap());
+                if blended == under {
+                    continue;
+                }
+
+                let color = [blended[0], blended[1], blended[2]];
+                let [red, green, blue] = *closest
+                    .entry(color)
+                    .or_insert_with(|| closest_color(frame, color));
+                canvas[pixel..pixel + 4].copy_from_slice(&[red, green, blue, 255]);
+            }
+        }
+    }
+
+    // a full frame for each canvas, with the timing of the frame at the index it comes with
+    fn full_frames(&self, canvases: impl Iterator<Item = (usize, Vec<u8>)>) -> Result<Animation> {
+        let mut frames = Vec::with_capacity(self.frames.len());
+
+        let mut canvases = canvases.peekable();
+        while let Some((index, canvas)) = canvases.next() {
+            let original = &self.frames[index];
+            let mut frame = flatten(self.width, self.height, &canvas)
+                .ok_or(AnimationError::TooManyColors(index + 1))?;
+            frame.delay_time = original.delay_time;
+            frame.needs_user_input = original.needs_user_input;
+
+            // the next frame's transparent pixels would show this one if it was kept
+            if let Some((_, next)) = canvases.peek() {
+                let clears = canvas
+                    .chunks_exact(4)
+                    .zip(next.chunks_exact(4))
+                    .any(|(pixel, next)| pixel[3] != 0 && next[3] == 0);
+                if clears {
+                    frame.disposal_method = Some(DisposalMethod::RestoreToBackgroundColor);
+                }
+            }
+            frames.push(frame);
+        }
+
+        Ok(Animation {
+            width: self.width,
+            height: self.height,
+            loop_count: self.loop_count,
+            pixel_aspect_ratio: self.pixel_aspect_ratio,
+            frames,
+        })
+    }
+
+    /// Speeds playback up by `factor`, 0.5 plays at half speed. It's how long frames are
+    /// played for that's scaled, see [`Frame::duration`], so frames without a delay speed up
+    /// from a tenth of a second too. Delays never get short enough to be played slower than
+    /// asked for. Factors that aren't positive are ignored.
+    pub fn change_speed(&mut self, factor: f32) {
+        if !(factor.is_finite() && factor > 0.0) {
+            return;
+        }
+
+        for frame in &mut self.frames {
+            let delay_time = frame.duration().as_secs_f64() * 100.0 / factor as f64;
+            frame.delay_time = delay_time
+                .round()
+                .clamp(MINIMUM_DELAY_TIME as f64, u16::MAX as f64) as u16;
+        }
+    }
+
+    /// Encodes the animation as a GIF. The largest cluster of similar palettes, see
+    /// [`Animation::analyze_palettes`], is merged into the global palette, so only frames with
+    /// a different one carry their own.
+    pub fn encode<W: Write>(&self, writer: W) -> Result<W> {
+        let mut encoder = Encoder::new(writer, self.width, self.height);
+
+        let shared = self.share_palette();
+        if let Some((palette, _)) = &shared {
+            encoder.set_global_palette(palette)?;
+        }
+        if let Some(loop_count) = self.loop_count {
+            encoder.set_loop_count(loop_count);
+        }
+        encoder.set_pixel_aspect_ratio(self.pixel_aspect_ratio);
+
+        let frames = match &shared {
+            Some((_, frames)) => frames,
+            None => &self.frames,
+        };
+        for frame in frames {
+            encoder.write_frame(frame)?;
+        }
+        encoder.finish()
+    }
+
+    /// Every frame as it would be displayed, composited onto the logical screen as RGBA.
+    pub fn composited_frames(&self) -> CompositedFrames<'_> {
+        CompositedFrames {
+            frames: self.frames.iter(),
+            compositor: Compositor::new(self.width, self.height),
+        }
+    }
+
+    /// [`Frame::stats`] for every frame, with how many pixels on screen each one changes
+    /// from the frame before. The first frame is compared with an empty screen.
+    pub fn frame_stats(&self) -> Vec<FrameStats> {
+        let mut previous = vec![0; self.width as usize * self.height as usize * 4];
+        self.frames
+            .iter()
+            .zip(self.composited_frames())
+            .map(|(frame, canvas)| {
+                let mut stats = frame.stats();
+                stats.changed_pixels = Some(changed_pixels(&previous, &canvas));
+                previous = canvas;
+                stats
+            })
+            .collect()
+    }
+
+    /// Every composited frame encoded as `F`, e.g. `animation.frames_as::<Png>()`. Frames are
+    /// encoded one at a time as the iterator is advanced.
+    pub fn frames_as<F: ImageFormat>(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        let (width, height) = (self.width, self.height);
+        self.composited_frames()
+            .map(move |rgba| F::encode(width, height, &rgba))
+    }
+}
+
+// the color in `frame`'s palette closest to `color`, leaving out its transparent index.
+// `color` itself if the frame has no palette
+fn closest_color(frame: &Frame, color: [u8; 3]) -> [u8; 3] {
+    let distance = |other: &[u8]| {
+        color
+            .iter()
+            .zip(other)
+            .map(|(a, b)| (*a as i32 - *b as i32).pow(2))
+            .sum::<i32>()
+    };
+    frame
+        .palette()
+        .unwrap_or(&[])
+        .chunks_exact(3)
+        .enumerate()
+        .filter(|&(index, _)| Some(index as u8) != frame.transparent_color_index)
+        .min_by_key(|(_, other)| distance(other))
+        .map_or(color, |(_, other)| [other[0], other[1], other[2]])
+}
+
+/// Turns an RGBA canvas back into a full screen frame with its own palette, `None` if it has
+/// too many colors for that.
+fn flatten(width: u16, height: u16, rgba: &[u8]) -> Option<Frame> {
+    let has_transparency = rgba.chunks_exact(4).any(|pixel| pixel[3] == 0);
+    let max_colors = if has_transparency { 255 } else { 256 };
+
+    let mut palette = Vec::new();
+    let mut color_indicies: HashMap<[u8; 3], u8> = HashMap::new();
+    let mut indicies = Vec::with_capacity(rgba.len() / 4);
+
+    for pixel in rgba.chunks_exact(4) {
+        if pixel[3] == 0 {
+            // the transparent index goes after every color, filled in below
+            indicies.push(None);
+            continue;
+        }
+
+        let color = [pixel[0], pixel[1], pixel[2]];
+        let index = match color_indicies.get(&color) {
+            Some(&index) => index,
+            None => {
+                if color_indicies.len() == max_colors {
+                    return None;
+                }
+                let index = color_indicies.len() as u8;
+                color_indicies.insert(color, index);
+                palette.extend_from_slice(&color);
+                index
+            }
+        };
+        indicies.push(Some(index));
+    }
+
+    let transparent_index = color_indicies.len() as u8;
+    if has_transparency {
+        palette.extend_from_slice(&[0, 0, 0]);
+    }
+
+    let indicies = indicies
+        .into_iter()
+        .map(|index| index.unwrap_or(transparent_index))
+        .collect();
+    let mut frame = Frame::new(width, height, indicies, palette.into_boxed_slice());
+    frame.transparent_color_index = has_transparency.then_some(transparent_index);
+    frame.disposal_method = Some(DisposalMethod::DoNotDispose);
+
+    Some(frame)
+}
+
+pub struct CompositedFrames<'a> {
+    frames: std::slice::Iter<'a, Frame>,
+    compositor: Compositor,
+}
+
+impl Iterator for CompositedFrames<'_> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.frames.next()?;
+        self.compositor.draw(frame);
+        Some(self.compositor.canvas().into_owned())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.frames.size_hint()
+    }
+}
+
+impl ExactSizeIterator for CompositedFrames<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::Animation;
+    use crate::compositor::Rect;
+    use crate::export::{Png, Ppm};
+    use crate::fixtures::{two_color_frame, two_color_gif};
+    use crate::parser::{DecodeOptions, DisposalMethod};
+    use crate::scale::{resize_rgba, Filter};
+    use crate::watermark::{Position, Watermark};
+
+    // a red background with a blue pixel moving over it, the last one cleared afterwards
+    fn moving_pixel() -> Animation {
+        let mut background = two_color_frame(4, 1, &[0; 4]);
+        background.disposal_method = Some(DisposalMethod::DoNotDispose);
+
+        let mut frames = vec![background];
+        for x in 1..4 {
+            let mut pixel = two_color_frame(1, 1, &[1]);
+            pixel.left_position = x;
+            pixel.delay_time = 10;
+            frames.push(pixel);
+        }
+        frames[3].disposal_method = Some(DisposalMethod::RestoreToBackgroundColor);
+
+        Animation {
+            width: 4,
+            height: 1,
+            loop_count: None,
+            pixel_aspect_ratio: None,
+            frames,
+        }
+    }
+
+    #[test]
+    fn keeps_what_decoded_of_a_broken_gif() {
+        let gif = moving_pixel().encode(Vec::new()).unwrap();
+        // cut off in the last frame, which strict decoding won't make do with
+        let cut = &gif[..gif.len() - 4];
+        let options = DecodeOptions::new().strict(true);
+        let (animation, error) = Animation::decode_partial(cut, options.clone()).unwrap();
+        assert_eq!(animation.frames().len(), 3);
+        assert_eq!((animation.width(), animation.height()), (4, 1));
+        assert!(error.is_some());
+
+        let (animation, error) =
+            Animation::decode_partial(gif.as_slice(), options.clone()).unwrap();
+        assert_eq!(animation.frames().len(), 4);
+        assert!(error.is_none());
+
+        assert!(Animation::decode_partial(&b"not a gif"[..], options).is_err());
+    }
+
+    #[test]
+    fn edits_keep_what_is_on_screen() {
+        let original = moving_pixel();
+        let composited: Vec<Vec<u8>> = original.composited_frames().collect();
+
+        let mut trimmed = original.clone();
+        trimmed.trim(2..10).unwrap();
+        assert_eq!(trimmed.frames().len(), 2);
+        assert_eq!(trimmed.frames()[0].delay_time, 10);
+        assert!(trimmed
+            .composited_frames()
+            .eq(composited[2..].iter().cloned()));
+
+        // the last frame gets cleared, so what's under it can't be merged into it
+        let mut trimmed = original.clone();
+        trimmed.trim(3..4).unwrap();
+        assert_eq!(trimmed.frames().len(), 2);
+        assert_eq!(
+            trimmed.composited_frames().last(),
+            composited.last().cloned()
+        );
+
+        let mut cropped = original.clone();
+        cropped.crop(Rect::new(2, 0, 9, 9));
+        assert_eq!((cropped.width(), cropped.height()), (2, 1));
+        let expected = composited.iter().map(|canvas| canvas[8..].to_vec());
+        assert!(cropped.composited_frames().eq(expected));
+
+        let mut resized = original.clone();
+        resized.resize(8, 2);
+        assert_eq!(resized.frames()[2].indicies(), [1; 4]);
+        let expected = composited
+            .iter()
+            .map(|canvas| resize_rgba(canvas, 4, 1, 8, 2, Filter::Nearest));
+        assert!(resized.composited_frames().eq(expected));
+
+        // a delay of 0 plays for a tenth of a second, and one of 1 would play for that long too
+        let mut sped_up = original;
+        sped_up.frames[2].delay_time = 4;
+        sped_up.change_speed(4.0);
+        let delays: Vec<u16> = sped_up.frames().iter().map(|frame| frame.delay_time).collect();
+        assert_eq!(delays, [3, 3, 2, 3]);
+    }
+
+    #[test]
+    fn counts_changed_pixels() {
+        let stats = moving_pixel().frame_stats();
+        let changed: Vec<_> = stats.iter().map(|stats| stats.changed_pixels).collect();
+        assert_eq!(changed, [Some(4), Some(1), Some(1), Some(1)]);
+        assert_eq!(stats[0].unique_indicies, 1);
+    }
+
+    #[test]
+    fn corrects_the_pixel_aspect_ratio() {
+        let mut wide = moving_pixel();
+        wide.pixel_aspect_ratio = Some(0.5);
+        let encoded = wide.encode(Vec::new()).unwrap();
+        let mut decoded = Animation::decode(encoded.as_slice()).unwrap();
+        assert_eq!(decoded.pixel_aspect_ratio(), Some(0.5));
+
+        // half as wide as they're tall, so twice as many rows
+        let composited: Vec<Vec<u8>> = decoded.composited_frames().collect();
+        decoded.correct_aspect_ratio();
+        assert_eq!((decoded.width(), decoded.height()), (4, 2));
+        assert_eq!(decoded.pixel_aspect_ratio(), None);
+        let expected = composited.iter().map(|canvas| canvas.repeat(2));
+        assert!(decoded.composited_frames().eq(expected));
+    }
+
+    #[test]
+    fn coalesces_into_full_frames() {
+        let original = moving_pixel();
+        let coalesced = original.coalesce().unwrap();
+
+        assert!(coalesced.frames().iter().all(|frame| {
+            (frame.width, frame.height) == (4, 1)
+                && frame.disposal_method == Some(DisposalMethod::DoNotDispose)
+        }));
+        let delays = |animation: &Animation| -> Vec<u16> {
+            animation.frames().iter().map(|frame| frame.delay_time).collect()
+        };
+        assert_eq!(delays(&coalesced), delays(&original));
+        assert!(coalesced
+            .composited_frames()
+            .eq(original.composited_frames()));
+
+        // the last pixel is cleared by the time the next frame is drawn
+        let mut original = original;
+        let mut pixel = two_color_frame(1, 1, &[1]);
+        pixel.delay_time = 10;
+        original.frames.push(pixel);
+        let coalesced = original.coalesce().unwrap();
+        assert_eq!(
+            coalesced.frames()[3].disposal_method,
+            Some(DisposalMethod::RestoreToBackgroundColor)
+        );
+        assert!(coalesced
+            .composited_frames()
+            .eq(original.composited_frames()));
+    }
+
+    #[test]
+    fn plays_backwards() {
+        let original = moving_pixel();
+        let mut composited: Vec<Vec<u8>> = original.composited_frames().collect();
+        composited.reverse();
+
+        let reversed = original.reverse().unwrap();
+        let delays: Vec<u16> = reversed.frames().iter().map(|frame| frame.delay_time).collect();
+        assert_eq!(delays, [10, 10, 10, 0]);
+        assert!(reversed.composited_frames().eq(composited));
+
+        assert!(original.is_clear_before(0));
+        assert!(!original.is_clear_before(2));
+        // nothing is left once every frame is cleared away
+        let mut cleared = original;
+        for frame in &mut cleared.frames {
+            frame.disposal_method = Some(DisposalMethod::RestoreToBackgroundColor);
+        }
+        assert!(cleared.is_clear_before(4));
+        assert!(cleared.is_clear_before(9));
+    }
+
+    #[test]
+    fn burns_in_a_watermark() {
+        let original = moving_pixel();
+        // a dark blue pixel, and a half transparent red one that doesn't show over either color
+        let watermark = Watermark::new(2, 1, vec![0, 0, 200, 255, 250, 10, 0, 128]);
+        let watermarked = original.watermark(&watermark, Position::Center).unwrap();
+
+        let (red, blue) = ([255, 0, 0, 255], [0, 0, 255, 255]);
+        let expected = [
+            [red, blue, red, red],
+            [red, blue, red, red],
+            [red, blue, blue, red],
+            [red, blue, blue, blue],
+        ];
+        assert!(watermarked
+            .composited_frames()
+            .eq(expected.iter().map(|canvas| canvas.concat())));
+        assert_eq!(watermarked.frames()[1].delay_time, 10);
+    }
+
+    #[test]
+    fn encodes_every_composited_frame() {
+        let frames = [
+            two_color_frame(2, 1, &[0, 1]),
+            two_color_frame(2, 1, &[1, 0]),
+        ];
+        let gif = two_color_gif(2, 1, &frames);
+
+        let animation = Animation::decode(gif.as_slice()).unwrap();
+
+        let ppms: Vec<Vec<u8>> = animation.frames_as::<Ppm>().collect();
+        assert_eq!(ppms.len(), 2);
+        assert_eq!(ppms[0], b"P6\n2 1 255\n\xff\x00\x00\x00\x00\xff");
+        assert_eq!(ppms[1], b"P6\n2 1 255\n\x00\x00\xff\xff\x00\x00");
+
+        for png in animation.frames_as::<Png>() {
+            assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        }
+    }
+}
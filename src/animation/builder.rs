@@ -0,0 +1,194 @@
+//! Creating GIFs from RGBA frames.
+
+use anyhow::Result;
+use thiserror::Error;
+
+use std::io::Write;
+use std::time::Duration;
+
+use super::{Animation, OptimizeOptions};
+use crate::parser::{DisposalMethod, Frame, LoopCount};
+use crate::quantize::{self, QuantizeOptions};
+
+#[derive(Error, Debug)]
+enum BuilderError {
+    #[error("the size has to be set before frames can be encoded")]
+    MissingSize,
+    #[error("frame {index} is {length} bytes, but {width}x{height} RGBA is {expected}")]
+    WrongFrameSize {
+        index: usize,
+        length: usize,
+        width: u16,
+        height: u16,
+        expected: usize,
+    },
+}
+
+/// Builds an animation out of RGBA frames, taking care of palettes and compression:
+///
+/// ```no_run
+/// # use jif::animation::GifBuilder;
+/// # use jif::parser::LoopCount;
+/// # use std::time::Duration;
+/// # let frames: Vec<Vec<u8>> = Vec::new();
+/// let mut builder = GifBuilder::new();
+/// builder.set_size(64, 64).loop_count(LoopCount::Infinite);
+/// for rgba in frames {
+///     builder.add_frame(rgba, Duration::from_millis(50));
+/// }
+/// builder.write_to(std::fs::File::create("out.gif")?)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GifBuilder {
+    size: Option<(u16, u16)>,
+    loop_count: Option<LoopCount>,
+    quantize_options: QuantizeOptions,
+    frames: Vec<(Vec<u8>, Duration)>,
+}
+
+impl GifBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_size(&mut self, width: u16, height: u16) -> &mut Self {
+        self.size = Some((width, height));
+        self
+    }
+
+    /// Without a loop count the animation plays once.
+    pub fn loop_count(&mut self, loop_count: LoopCount) -> &mut Self {
+        self.loop_count = Some(loop_count);
+        self
+    }
+
+    /// How frames with more than 256 colors are dithered, they aren't by default.
+    pub fn quantize_options(&mut self, options: QuantizeOptions) -> &mut Self {
+        self.quantize_options = options;
+        self
+    }
+
+    /// Adds a frame of `width` x `height` RGBA pixels, shown for `delay` rounded to the
+    /// hundredth of a second. Pixels that are less than half opaque become transparent, and
+    /// the rest opaque, as GIFs have nothing in between.
+    pub fn add_frame(&mut self, rgba: Vec<u8>, delay: Duration) -> &mut Self {
+        self.frames.push((rgba, delay));
+        self
+    }
+
+    /// The frames as an animation, each one only storing what changed since the one before it.
+    /// Frames with more than 256 colors are reduced with [`quantize::median_cut`].
+    pub fn build(&self) -> Result<Animation> {
+        let (width, height) = self.size.ok_or(BuilderError::MissingSize)?;
+        let expected = width as usize * height as usize * 4;
+
+        let mut frames = Vec::with_capacity(self.frames.len());
+        for (index, (rgba, delay)) in self.frames.iter().enumerate() {
+            if rgba.len() != expected {
+                return Err(BuilderError::WrongFrameSize {
+                    index,
+                    length: rgba.len(),
+                    width,
+                    height,
+                    expected,
+                }
+                .into());
+            }
+
+            let quantized =
+                quantize::median_cut_with_options(rgba, width, 256, self.quantize_options);
+            let mut frame = Frame::new(width, height, quantized.indicies, quantized.palette);
+            frame.transparent_color_index = quantized.transparent_index;
+            frame.delay_time = ((delay.as_millis() + 5) / 10).min(u16::MAX as u128) as u16;
+            // cleared before the next frame, so its transparent pixels don't show this one
+            frame.disposal_method = Some(DisposalMethod::RestoreToBackgroundColor);
+            frames.push(frame);
+        }
+
+        let animation = Animation {
+            width,
+            height,
+            loop_count: self.loop_count,
+            pixel_aspect_ratio: None,
+            frames,
+        };
+        animation.optimize(OptimizeOptions::default())
+    }
+
+    /// Encodes the frames as a GIF, see [`GifBuilder::build`].
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<W> {
+        self.build()?.encode(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GifBuilder;
+    use crate::animation::Animation;
+    use crate::parser::LoopCount;
+
+    use std::time::Duration;
+
+    #[test]
+    fn builds_gifs_from_rgba_frames() {
+        let (red, blue, clear) = ([255, 0, 0, 255], [0, 0, 255, 255], [0; 4]);
+        let frames: Vec<Vec<u8>> = vec![
+            [red, red, red, red].concat(),
+            [red, blue, red, red].concat(),
+            // half transparent pixels are either kept or cleared
+            [red, [0, 0, 255, 200], [9, 9, 9, 100], red].concat(),
+        ];
+
+        let mut builder = GifBuilder::new();
+        builder.set_size(2, 2).loop_count(LoopCount::Infinite);
+        for rgba in frames {
+            builder.add_frame(rgba, Duration::from_millis(104));
+        }
+        let gif = builder.write_to(Vec::new()).unwrap();
+
+        let animation = Animation::decode(gif.as_slice()).unwrap();
+        assert_eq!(animation.loop_count(), Some(LoopCount::Infinite));
+        let delays: Vec<u16> = animation.frames().iter().map(|f| f.delay_time).collect();
+        assert_eq!(delays, [10, 10, 10]);
+        let frames: Vec<Vec<u8>> = animation.composited_frames().collect();
+        assert_eq!(
+            frames,
+            [
+                [red, red, red, red].concat(),
+                [red, blue, red, red].concat(),
+                [red, blue, clear, red].concat(),
+            ]
+        );
+
+        assert!(GifBuilder::new()
+            .add_frame(Vec::new(), Duration::ZERO)
+            .build()
+            .is_err());
+        assert!(GifBuilder::new()
+            .set_size(1, 1)
+            .add_frame(vec![0; 8], Duration::ZERO)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn reduces_frames_with_too_many_colors() {
+        // 32x32 pixels that are all a different color
+        let rgba: Vec<u8> = (0..32 * 32)
+            .flat_map(|pixel| [(pixel % 32 * 8) as u8, (pixel / 32 * 8) as u8, 128, 255])
+            .collect();
+        let gif = GifBuilder::new()
+            .set_size(32, 32)
+            .add_frame(rgba.clone(), Duration::from_millis(100))
+            .write_to(Vec::new())
+            .unwrap();
+
+        let animation = Animation::decode(gif.as_slice()).unwrap();
+        let frame = animation.composited_frames().next().unwrap();
+        assert!(frame
+            .iter()
+            .zip(&rgba)
+            .all(|(&encoded, &original)| encoded.abs_diff(original) <= 16));
+    }
+}
@@ -0,0 +1,157 @@
+//! Joining animations into one that plays them one after another.
+
+use super::Animation;
+use crate::compositor::Rect;
+use crate::parser::{DisposalMethod, Frame};
+use crate::quantize::{self, QuantizeOptions};
+
+impl Animation {
+    /// Plays `animations` one after another, with the first one's loop count. The logical
+    /// screen is as big as the largest of them, and smaller ones are centered on it with
+    /// transparent padding around them.
+    ///
+    /// Each animation starts on an empty screen. When one leaves something behind, its last
+    /// frame is replaced by a full frame of what's on screen that's cleared afterwards,
+    /// reduced with [`quantize::median_cut`] if it shows more than 256 colors. Palettes are
+    /// kept as they are, and `encode` shares whichever are similar in the global palette.
+    pub fn concat(animations: &[Animation]) -> Animation {
+        let width = animations.iter().map(Animation::width).max().unwrap_or(0);
+        let height = animations.iter().map(Animation::height).max().unwrap_or(0);
+
+        let mut frames = Vec::new();
+        for (index, animation) in animations.iter().enumerate() {
+            let mut animation = animation.clone();
+            // on a bigger screen, whatever hung off the edge of this one would show up
+            let screen = Rect::new(0, 0, animation.width, animation.height);
+            let overhangs = animation.frames.iter().any(|frame| {
+                let bounds = Rect::new(
+                    frame.left_position,
+                    frame.top_position,
+                    frame.width,
+                    frame.height,
+                );
+                bounds.intersect(screen) != bounds
+            });
+            if overhangs {
+                animation.crop(screen);
+            }
+
+            let is_last = index + 1 == animations.len();
+            if !is_last && !animation.is_clear_before(animation.frames.len()) {
+                animation.clear_after_last_frame();
+            }
+
+            let left = (width - animation.width) / 2;
+            let top = (height - animation.height) / 2;
+            for mut frame in animation.frames {
+                frame.left_position += left;
+                frame.top_position += top;
+                frames.push(frame);
+            }
+        }
+
+        Animation {
+            width,
+            height,
+            loop_count: animations.first().and_then(Animation::loop_count),
+            // pixels can only have one shape, the first animation's like its loop count
+            pixel_aspect_ratio: animations.first().and_then(Animation::pixel_aspect_ratio),
+            frames,
+        }
+    }
+
+    // replaces the last frame with a full frame of what's on screen that's disposed of, so the
+    // screen is empty afterwards
+    fn clear_after_last_frame(&mut self) {
+        let Some(canvas) = self.composited_frames().last() else {
+            return;
+        };
+        let quantized =
+            quantize::median_cut_with_options(&canvas, self.width, 256, QuantizeOptions::default());
+
+        let last = self
+            .frames
+            .last_mut()
+            .expect("there's a canvas for every frame");
+        let mut frame = Frame::new(
+            self.width,
+            self.height,
+            quantized.indicies,
+            quantized.palette,
+        );
+        frame.transparent_color_index = quantized.transparent_index;
+        frame.delay_time = last.delay_time;
+        frame.needs_user_input = last.needs_user_input;
+        frame.disposal_method = Some(DisposalMethod::RestoreToBackgroundColor);
+        *last = frame;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::animation::Animation;
+    use crate::parser::{DisposalMethod, Frame, LoopCount};
+
+    // `rgba` of a `width` pixel wide screen in the middle of a transparent 4x3 one
+    fn centered(rgba: &[u8], width: usize) -> Vec<u8> {
+        let height = rgba.len() / 4 / width;
+        let (left, top) = ((4 - width) / 2, (3 - height) / 2);
+        let mut screen = vec![0; 4 * 3 * 4];
+        for (y, row) in rgba.chunks_exact(width * 4).enumerate() {
+            let offset = ((top + y) * 4 + left) * 4;
+            screen[offset..offset + row.len()].copy_from_slice(row);
+        }
+        screen
+    }
+
+    #[test]
+    fn plays_one_after_another() {
+        let palette: Box<[u8]> = Box::new([255, 0, 0, 0, 0, 255, 0, 0, 0]);
+        // a red strip a blue pixel is drawn on, which stays on screen
+        let mut strip = Frame::new(2, 1, Box::new([0, 0]), palette.clone());
+        strip.disposal_method = Some(DisposalMethod::DoNotDispose);
+        let mut dot = Frame::new(1, 1, Box::new([1]), palette.clone());
+        dot.left_position = 1;
+        dot.delay_time = 20;
+        let mut first = Animation {
+            width: 2,
+            height: 1,
+            loop_count: Some(LoopCount::Number(2)),
+            pixel_aspect_ratio: None,
+            frames: vec![strip, dot],
+        };
+        // a frame hanging off the edge, which is left on screen
+        first
+            .frames
+            .push(Frame::new(3, 1, Box::new([1, 1, 1]), palette.clone()));
+
+        // mostly transparent, so anything left behind would show through
+        let mut sparse = Frame::new(4, 3, Box::new([2; 12]), palette);
+        sparse.transparent_color_index = Some(2);
+        let second = Animation {
+            width: 4,
+            height: 3,
+            loop_count: None,
+            pixel_aspect_ratio: None,
+            frames: vec![sparse],
+        };
+
+        let joined = Animation::concat(&[first.clone(), second.clone(), first.clone()]);
+        assert_eq!((joined.width(), joined.height()), (4, 3));
+        assert_eq!(joined.loop_count(), Some(LoopCount::Number(2)));
+        assert_eq!(joined.frames().len(), 7);
+        assert_eq!(joined.frames()[1].delay_time, 20);
+        assert_eq!(
+            joined.frames()[2].disposal_method,
+            Some(DisposalMethod::RestoreToBackgroundColor)
+        );
+
+        let mut expected: Vec<Vec<u8>> = first
+            .composited_frames()
+            .map(|canvas| centered(&canvas, 2))
+            .collect();
+        expected.push(vec![0; 4 * 3 * 4]);
+        expected.extend(first.composited_frames().map(|canvas| centered(&canvas, 2)));
+        assert!(joined.composited_frames().eq(expected));
+    }
+}
@@ -0,0 +1,406 @@
+//! jif's own format for saving animations, which keeps everything an [`Animation`] holds and
+//! skips LZW, so saving and loading is quick. All numbers are little endian:
+//!
+//! - `JIFA`, the format version as a u16, and a flags byte with bit 0 set if everything after
+//!   it is zstd compressed
+//! - the screen width and height as u16s, and the loop count as a tag byte (0 for none, 1 for
+//!   forever, 2 for a number) followed by a u16
+//! - since version 2, the pixel aspect ratio as it's stored in a GIF, 0 for none
+//! - the global palette as a u16 length in bytes followed by its colors
+//! - the frame count as a u32, then for every frame its left, top, width, height and delay as
+//!   u16s, its disposal method (0xff for none), a byte that's 1 if it has a transparent index
+//!   followed by the index, a byte that's 1 if it waits for user input, its palette as a tag
+//!   byte (0 for none, 1 for the global palette, 2 for its own followed by a u16 length in
+//!   bytes and the colors), and finally width x height indicies
+
+use anyhow::Result;
+use thiserror::Error;
+
+use std::io::{self, prelude::*};
+use std::sync::Arc;
+
+use super::Animation;
+use crate::encoder::pixel_aspect_ratio_byte;
+use crate::parser::{pixel_aspect_ratio, DisposalMethod, Frame, LoopCount};
+
+/// The extension files saved with [`Animation::save`] are given by jif's commands.
+pub const EXTENSION: &str = "jifa";
+
+const MAGIC: &[u8; 4] = b"JIFA";
+const FORMAT_VERSION: u16 = 2;
+const FLAG_ZSTD: u8 = 0b0000_0001;
+
+const NO_DISPOSAL_METHOD: u8 = 0xff;
+
+const LOOP_COUNT_NONE: u8 = 0;
+const LOOP_COUNT_INFINITE: u8 = 1;
+const LOOP_COUNT_NUMBER: u8 = 2;
+
+const PALETTE_NONE: u8 = 0;
+const PALETTE_GLOBAL: u8 = 1;
+const PALETTE_LOCAL: u8 = 2;
+
+#[derive(Error, Debug)]
+enum IntermediateError {
+    #[error("not an animation saved by jif")]
+    NotAnAnimation,
+    #[error("saved with format version {0}, newer than this version of jif can read")]
+    UnsupportedVersion(u16),
+    #[cfg(not(feature = "zstd"))]
+    #[error("the animation is zstd compressed, which needs jif built with the zstd feature")]
+    NeedsZstd,
+    #[error("invalid {0} in a saved animation")]
+    Invalid(&'static str),
+}
+
+/// How [`Animation::save`] compresses what it writes. Which variants there are depends on
+/// the features jif is built with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    #[default]
+    None,
+    /// zstd at a level from 1 to 22, 3 is a good default.
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+impl Animation {
+    /// Writes the animation in jif's own format, see [`Animation::load`]. Unlike encoding it
+    /// as a GIF nothing is lost, and frames keep whether they use the global palette.
+    pub fn save<W: Write>(&self, mut writer: W, compression: Compression) -> Result<W> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+
+        match compression {
+            Compression::None => {
+                writer.write_all(&[0])?;
+                self.write_body(&mut writer)?;
+                Ok(writer)
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(level) => {
+                writer.write_all(&[FLAG_ZSTD])?;
+                let mut encoder = zstd::stream::Encoder::new(writer, level)?;
+                self.write_body(&mut encoder)?;
+                Ok(encoder.finish()?)
+            }
+        }
+    }
+
+    /// Reads an animation written by [`Animation::save`] with this or an earlier version of
+    /// jif.
+    pub fn load<R: Read>(mut reader: R) -> Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(IntermediateError::NotAnAnimation.into());
+        }
+        let version = read_u16(&mut reader)?;
+        if version > FORMAT_VERSION {
+            return Err(IntermediateError::UnsupportedVersion(version).into());
+        }
+
+        match read_u8(&mut reader)? {
+            0 => Self::read_body(&mut reader, version),
+            #[cfg(feature = "zstd")]
+            FLAG_ZSTD => Self::read_body(&mut zstd::stream::Decoder::new(reader)?, version),
+            #[cfg(not(feature = "zstd"))]
+            FLAG_ZSTD => Err(IntermediateError::NeedsZstd.into()),
+            _ => Err(IntermediateError::Invalid("flags").into()),
+        }
+    }
+
+    fn write_body<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.width.to_le_bytes())?;
+        writer.write_all(&self.height.to_le_bytes())?;
+        let (tag, count) = match self.loop_count {
+            None => (LOOP_COUNT_NONE, 0),
+            Some(LoopCount::Infinite) => (LOOP_COUNT_INFINITE, 0),
+            Some(LoopCount::Number(count)) => (LOOP_COUNT_NUMBER, count),
+        };
+        writer.write_all(&[tag])?;
+        writer.write_all(&count.to_le_bytes())?;
+        writer.write_all(&[pixel_aspect_ratio_byte(self.pixel_aspect_ratio)])?;
+
+        // frames decoded together share one global palette, anything else is written as the
+        // frame's own
+        let global_palette = self
+            .frames
+            .iter()
+            .find(|frame| !frame.has_local_palette())
+            .and_then(Frame::palette);
+        write_palette(writer, global_palette.unwrap_or_default())?;
+
+        writer.write_all(&(self.frames.len() as u32).to_le_bytes())?;
+        for frame in &self.frames {
+            for value in [
+                frame.left_position,
+                frame.top_position,
+                frame.width,
+                frame.height,
+                frame.delay_time,
+            ] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            let disposal_method = frame
+                .disposal_method
+                .map_or(NO_DISPOSAL_METHOD, |method| method as u8);
+            writer.write_all(&[
+                disposal_method,
+                frame.transparent_color_index.is_some() as u8,
+                frame.transparent_color_index.unwrap_or(0),
+                frame.needs_user_input as u8,
+            ])?;
+
+            match frame.palette() {
+                None => writer.write_all(&[PALETTE_NONE])?,
+                Some(palette) if !frame.has_local_palette() && Some(palette) == global_palette => {
+                    writer.write_all(&[PALETTE_GLOBAL])?
+                }
+                Some(palette) => {
+                    writer.write_all(&[PALETTE_LOCAL])?;
+                    write_palette(writer, palette)?;
+                }
+            }
+
+            writer.write_all(frame.indicies())?;
+        }
+
+        Ok(())
+    }
+
+    fn read_body<R: Read>(reader: &mut R, version: u16) -> Result<Self> {
+        let width = read_u16(reader)?;
+        let height = read_u16(reader)?;
+        let tag = read_u8(reader)?;
+        let count = read_u16(reader)?;
+        let loop_count = match tag {
+            LOOP_COUNT_NONE => None,
+            LOOP_COUNT_INFINITE => Some(LoopCount::Infinite),
+            LOOP_COUNT_NUMBER => Some(LoopCount::Number(count)),
+            _ => return Err(IntermediateError::Invalid("loop count").into()),
+        };
+        let pixel_aspect_ratio = match version {
+            1 => None,
+            _ => pixel_aspect_ratio(read_u8(reader)?),
+        };
+
+        let global_palette: Option<Arc<[u8]>> = match read_palette(reader)? {
+            palette if palette.is_empty() => None,
+            palette => Some(palette.into()),
+        };
+
+        let frame_count = read_u32(reader)?;
+        // not trusting the count with an allocation up front
+        let mut frames = Vec::new();
+        for _ in 0..frame_count {
+            let left_position = read_u16(reader)?;
+            let top_position = read_u16(reader)?;
+            let frame_width = read_u16(reader)?;
+            let frame_height = read_u16(reader)?;
+            let delay_time = read_u16(reader)?;
+
+            let disposal_method = read_u8(reader)?;
+            let has_transparent_index = read_u8(reader)? != 0;
+            let transparent_index = read_u8(reader)?;
+            let needs_user_input = read_u8(reader)? != 0;
+            let palette_tag = read_u8(reader)?;
+
+            let disposal_method = match disposal_method {
+                NO_DISPOSAL_METHOD => None,
+                method => Some(
+                    DisposalMethod::from_u8(method)
+                        .ok_or(IntermediateError::Invalid("disposal method"))?,
+                ),
+            };
+
+            let palette = match palette_tag {
+                PALETTE_NONE | PALETTE_GLOBAL => None,
+                PALETTE_LOCAL => Some(read_palette(reader)?),
+                _ => return Err(IntermediateError::Invalid("palette").into()),
+            };
+
+            let pixel_count = frame_width as usize * frame_height as usize;
+            let indicies = read_bytes(reader, pixel_count)?;
+
+            let mut frame = Frame::new(
+                frame_width,
+                frame_height,
+                indicies,
+                palette.unwrap_or_default(),
+            );
+            match palette_tag {
+                PALETTE_NONE => frame.set_global_palette(None),
+                PALETTE_GLOBAL => {
+                    let palette = global_palette
+                        .clone()
+                        .ok_or(IntermediateError::Invalid("palette"))?;
+                    frame.set_global_palette(Some(palette));
+                }
+                _ => {}
+            }
+            frame.left_position = left_position;
+            frame.top_position = top_position;
+            frame.delay_time = delay_time;
+            frame.disposal_method = disposal_method;
+            frame.transparent_color_index = has_transparent_index.then_some(transparent_index);
+            frame.needs_user_input = needs_user_input;
+            frames.push(frame);
+        }
+
+        Ok(Self {
+            width,
+            height,
+            loop_count,
+            pixel_aspect_ratio,
+            frames,
+        })
+    }
+}
+
+fn write_palette<W: Write>(writer: &mut W, palette: &[u8]) -> io::Result<()> {
+    writer.write_all(&(palette.len() as u16).to_le_bytes())?;
+    writer.write_all(palette)
+}
+
+fn read_palette<R: Read>(reader: &mut R) -> Result<Box<[u8]>> {
+    let length = read_u16(reader)? as usize;
+    if !length.is_multiple_of(3) || length > 3 * 256 {
+        return Err(IntermediateError::Invalid("palette").into());
+    }
+    read_bytes(reader, length)
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> io::Result<u16> {
+    let mut bytes = [0; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+// reads in as much as is there rather than allocating `length` bytes for a truncated file
+fn read_bytes<R: Read>(reader: &mut R, length: usize) -> Result<Box<[u8]>> {
+    let mut bytes = Vec::new();
+    reader.take(length as u64).read_to_end(&mut bytes)?;
+    if bytes.len() < length {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(bytes.into_boxed_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Compression;
+    use crate::animation::Animation;
+    use crate::encoder::Encoder;
+    use crate::parser::{DisposalMethod, Frame, LoopCount};
+
+    fn animation() -> Animation {
+        let global: Box<[u8]> = Box::new([255, 0, 0, 0, 0, 255]);
+        let mut encoder = Encoder::new(Vec::new(), 3, 2);
+        encoder.set_global_palette(&global).unwrap();
+        encoder.set_loop_count(LoopCount::Number(3));
+        encoder.set_pixel_aspect_ratio(Some(0.5));
+
+        let mut first = Frame::new(3, 2, Box::new([0, 1, 0, 1, 0, 1]), global);
+        first.delay_time = 12;
+        first.disposal_method = Some(DisposalMethod::DoNotDispose);
+        encoder.write_frame(&first).unwrap();
+
+        let mut second = Frame::new(
+            2,
+            1,
+            Box::new([0, 2]),
+            Box::new([0, 255, 0, 1, 2, 3, 9, 9, 9]),
+        );
+        second.left_position = 1;
+        second.top_position = 1;
+        second.transparent_color_index = Some(2);
+        second.disposal_method = Some(DisposalMethod::RestoreToPrevious);
+        second.needs_user_input = true;
+        encoder.write_frame(&second).unwrap();
+
+        Animation::decode(encoder.finish().unwrap().as_slice()).unwrap()
+    }
+
+    fn assert_same(loaded: &Animation, animation: &Animation) {
+        assert_eq!(
+            (loaded.width(), loaded.height(), loaded.loop_count()),
+            (
+                animation.width(),
+                animation.height(),
+                animation.loop_count()
+            )
+        );
+        assert_eq!(loaded.pixel_aspect_ratio(), animation.pixel_aspect_ratio());
+        assert_eq!(loaded.frames().len(), animation.frames().len());
+        for (loaded, frame) in loaded.frames().iter().zip(animation.frames()) {
+            assert_eq!(
+                (
+                    loaded.left_position,
+                    loaded.top_position,
+                    loaded.width,
+                    loaded.height
+                ),
+                (
+                    frame.left_position,
+                    frame.top_position,
+                    frame.width,
+                    frame.height
+                )
+            );
+            assert_eq!(loaded.delay_time, frame.delay_time);
+            assert_eq!(loaded.disposal_method, frame.disposal_method);
+            assert_eq!(
+                loaded.transparent_color_index,
+                frame.transparent_color_index
+            );
+            assert_eq!(loaded.needs_user_input, frame.needs_user_input);
+            assert_eq!(loaded.has_local_palette(), frame.has_local_palette());
+            assert_eq!(loaded.palette(), frame.palette());
+            assert_eq!(loaded.indicies(), frame.indicies());
+        }
+    }
+
+    #[test]
+    fn saves_and_loads_animations() {
+        let animation = animation();
+        let saved = animation.save(Vec::new(), Compression::None).unwrap();
+        assert_same(&Animation::load(saved.as_slice()).unwrap(), &animation);
+
+        // cut short, or from a newer version of jif
+        assert!(Animation::load(&saved[..saved.len() - 1]).is_err());
+        let mut newer = saved.clone();
+        newer[4] = 3;
+        assert!(Animation::load(newer.as_slice()).is_err());
+
+        // version 1 didn't have the pixel aspect ratio after the loop count
+        let mut older = saved.clone();
+        older[4] = 1;
+        older.remove(14);
+        let loaded = Animation::load(older.as_slice()).unwrap();
+        assert_eq!(loaded.pixel_aspect_ratio(), None);
+        assert_eq!(loaded.frames().len(), 2);
+        assert!(Animation::load(&b"GIF89a"[..]).is_err());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn saves_compressed_animations() {
+        let animation = animation();
+        let saved = animation.save(Vec::new(), Compression::Zstd(3)).unwrap();
+        assert_same(&Animation::load(saved.as_slice()).unwrap(), &animation);
+    }
+}
@@ -0,0 +1,593 @@
+//! Re-encoding that only stores what changes from one frame to the next.
+
+use anyhow::Result;
+
+use std::collections::{HashMap, HashSet};
+use std::iter::{Enumerate, Peekable, Zip};
+use std::slice;
+
+use super::{Animation, AnimationError, CompositedFrames};
+use crate::compositor::{dirty_rect, Rect};
+use crate::encoder::{lzw, minimum_code_size};
+use crate::parser::{DisposalMethod, Frame};
+use crate::ssim::ssim;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OptimizeOptions {
+    /// How far apart each channel of a pixel can be from what's already on screen and still be
+    /// left as it is. 0 keeps every frame exactly the same.
+    pub lossy_level: u8,
+    /// Frames whose SSIM, the structural similarity index, against the last frame that was
+    /// kept is at least this are dropped, and that frame is shown for their delay as well.
+    /// Frames waiting for user input are always kept.
+    pub drop_similar: Option<f64>,
+}
+
+// how far frames are reduced
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Reduction {
+    Optimize(OptimizeOptions),
+    /// Frames are only cropped, and none are merged.
+    Crop,
+}
+
+impl Animation {
+    /// An animation that plays the same, where every frame only covers the rectangle that
+    /// changed. Pixels inside it that didn't change are left transparent, or drawn again
+    /// when that compresses better. Frames that change nothing are merged into the one before
+    /// them, and every frame whose colors fit shares one palette.
+    pub fn optimize(&self, options: OptimizeOptions) -> Result<Animation> {
+        self.reduce(Reduction::Optimize(options))
+    }
+
+    /// An animation that plays the same, where every frame is cropped to the rectangle that
+    /// changed, like ImageMagick's `-deconstruct`. Frames that change nothing become a single
+    /// pixel, so there are as many frames as before.
+    pub fn deconstruct(&self) -> Result<Animation> {
+        self.reduce(Reduction::Crop)
+    }
+
+    fn reduce(&self, reduction: Reduction) -> Result<Animation> {
+        let options = match reduction {
+            Reduction::Optimize(options) => options,
+            Reduction::Crop => OptimizeOptions::default(),
+        };
+
+        let (width, height) = (self.width as usize, self.height as usize);
+        if width == 0 || height == 0 || self.frames.is_empty() {
+            return Ok(self.clone());
+        }
+
+        // what the optimized animation has on screen so far
+        let mut shown = vec![0; width * height * 4];
+        let mut palette = SharedPalette::default();
+        let mut frames: Vec<OptimizedFrame> = Vec::with_capacity(self.frames.len());
+
+        let mut canvases = KeptFrames {
+            animation: self,
+            canvases: self.composited_frames().zip(&self.frames).enumerate().peekable(),
+            drop_similar: options.drop_similar,
+        }
+        .peekable();
+        while let Some((index, canvas, original)) = canvases.next() {
+            let mut rect = match options.lossy_level {
+                0 => dirty_rect(&shown, &canvas, self.width, self.height)
+                    .unwrap_or(Rect::new(0, 0, 0, 0)),
+                _ => bounding_rect(self.width, self.height, |pixel| {
+                    !same_pixel(&shown[pixel..pixel + 4], &canvas[pixel..pixel + 4], options)
+                }),
+            };
+
+            // a frame can't make pixels transparent again, only disposing of the frame
+            // before it can, so this frame has to cover whatever the next one clears
+            let cleared = match canvases.peek() {
+                Some((_, next, _)) => bounding_rect(self.width, self.height, |pixel| {
+                    canvas[pixel + 3] != 0 && next[pixel + 3] == 0
+                }),
+                None => Rect::new(0, 0, 0, 0),
+            };
+            rect = rect.union(cleared);
+
+            if rect.is_empty() {
+                match frames.last_mut() {
+                    Some(last)
+                        if reduction != Reduction::Crop
+                            && last.disposal_method == DisposalMethod::DoNotDispose =>
+                    {
+                        last.delay_time = last.delay_time.saturating_add(original.delay_time);
+                        continue;
+                    }
+                    // there has to be a first frame, one after a disposed frame for the
+                    // disposal to show, and one for every frame when cropping
+                    _ => rect = Rect::new(0, 0, 1, 1),
+                }
+            }
+
+            let mut pixels = delta(&shown, &canvas, self.width, rect, Some(options));
+            if reduction == Reduction::Crop || color_count(&pixels) > 256 {
+                // no index is left over for transparency, so the unchanged pixels are drawn too
+                pixels = delta(&shown, &canvas, self.width, rect, None);
+            }
+            let mut frame = palette
+                .add(rect, &pixels)
+                .ok_or(AnimationError::TooManyChangedColors(index))?;
+            frame.delay_time = original.delay_time;
+            frame.needs_user_input = original.needs_user_input;
+
+            for (y, row) in frame.indicies.chunks(rect.width as usize).enumerate() {
+                let start = ((rect.top as usize + y) * width + rect.left as usize) * 4;
+                for (x, &index) in row.iter().enumerate() {
+                    if Some(index) != frame.transparent_index {
+                        let pixel = start + x * 4;
+                        shown[pixel..pixel + 4].copy_from_slice(&canvas[pixel..pixel + 4]);
+                    }
+                }
+            }
+
+            if !cleared.is_empty() {
+                frame.disposal_method = DisposalMethod::RestoreToBackgroundColor;
+                for y in rect.top as usize..(rect.top + rect.height) as usize {
+                    let start = (y * width + rect.left as usize) * 4;
+                    shown[start..start + rect.width as usize * 4].fill(0);
+                }
+            }
+            frames.push(frame);
+        }
+
+        let shared: Box<[u8]> = palette.colors.into_iter().flatten().collect();
+        let frames = frames
+            .into_iter()
+            .map(|frame| frame.finish(&shared))
+            .collect();
+
+        Ok(Animation {
+            width: self.width,
+            height: self.height,
+            loop_count: self.loop_count,
+            pixel_aspect_ratio: self.pixel_aspect_ratio,
+            frames,
+        })
+    }
+}
+
+// the composited frames that are kept with their index, and a copy of the frame with the delay
+// of the ones dropped after it added on
+struct KeptFrames<'a> {
+    animation: &'a Animation,
+    canvases: Peekable<Enumerate<Zip<CompositedFrames<'a>, slice::Iter<'a, Frame>>>>,
+    drop_similar: Option<f64>,
+}
+
+impl Iterator for KeptFrames<'_> {
+    type Item = (usize, Vec<u8>, Frame);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (index, (canvas, original)) = self.canvases.next()?;
+        let mut kept = original.clone();
+
+        if let Some(threshold) = self.drop_similar {
+            let (width, height) = (self.animation.width, self.animation.height);
+            while let Some((_, (next, frame))) = self.canvases.peek() {
+                if frame.needs_user_input || ssim(width, height, &canvas, next) < threshold {
+                    break;
+                }
+                kept.delay_time = kept.delay_time.saturating_add(frame.delay_time);
+                self.canvases.next();
+            }
+        }
+
+        Some((index, canvas, kept))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Pixel {
+    Draw([u8; 3]),
+    /// Close enough to what's on screen to be left as it is, but it can still be drawn with
+    /// this color if it isn't transparent.
+    Keep(Option<[u8; 3]>),
+}
+
+fn same_pixel(shown: &[u8], pixel: &[u8], options: OptimizeOptions) -> bool {
+    match (shown[3], pixel[3]) {
+        (0, 0) => true,
+        (0, _) | (_, 0) => false,
+        _ => shown[..3]
+            .iter()
+            .zip(&pixel[..3])
+            .all(|(a, b)| a.abs_diff(*b) <= options.lossy_level),
+    }
+}
+
+// the smallest rectangle holding every pixel `includes` is true for, given the byte offset of
+// the pixel in an RGBA canvas
+fn bounding_rect(width: u16, height: u16, includes: impl Fn(usize) -> bool) -> Rect {
+    let (mut left, mut top) = (u16::MAX, u16::MAX);
+    let (mut right, mut bottom) = (0, 0);
+
+    for y in 0..height {
+        for x in 0..width {
+            if includes((y as usize * width as usize + x as usize) * 4) {
+                left = left.min(x);
+                top = top.min(y);
+                right = right.max(x + 1);
+                bottom = bottom.max(y + 1);
+            }
+        }
+    }
+
+    if right == 0 {
+        return Rect::new(0, 0, 0, 0);
+    }
+    Rect::new(left, top, right - left, bottom - top)
+}
+
+// the pixels of `rect` that turn `shown` into `canvas`. Without options only the transparent
+// ones are kept
+fn delta(
+    shown: &[u8],
+    canvas: &[u8],
+    width: u16,
+    rect: Rect,
+    options: Option<OptimizeOptions>,
+) -> Vec<Pixel> {
+    let mut pixels = Vec::with_capacity(rect.width as usize * rect.height as usize);
+
+    for y in rect.top as usize..(rect.top + rect.height) as usize {
+        for x in rect.left as usize..(rect.left + rect.width) as usize {
+            let pixel = (y * width as usize + x) * 4;
+            let target = &canvas[pixel..pixel + 4];
+            let color = [target[0], target[1], target[2]];
+
+            let unchanged = match options {
+                Some(options) => same_pixel(&shown[pixel..pixel + 4], target, options),
+                None => target[3] == 0,
+            };
+            pixels.push(match unchanged {
+                true => Pixel::Keep((target[3] != 0).then_some(color)),
+                false => Pixel::Draw(color),
+            });
+        }
+    }
+
+    pixels
+}
+
+// distinct colors drawn, plus one for transparency if anything is kept
+fn color_count(pixels: &[Pixel]) -> usize {
+    let colors: HashSet<Option<[u8; 3]>> = pixels
+        .iter()
+        .map(|pixel| match pixel {
+            Pixel::Draw(color) => Some(*color),
+            Pixel::Keep(_) => None,
+        })
+        .collect();
+    colors.len()
+}
+
+// for every pixel, the index to write and the one that could be written instead. Kept pixels
+// are drawn again if the palette holds their color, unless being transparent compresses better
+fn choices(
+    pixels: &[Pixel],
+    indicies: &HashMap<[u8; 3], u8>,
+    transparent_index: Option<u8>,
+) -> Vec<(u8, Option<u8>)> {
+    let transparent_index = transparent_index.unwrap_or_default();
+
+    pixels
+        .iter()
+        .map(|pixel| match pixel {
+            Pixel::Draw(color) => (indicies[color], None),
+            Pixel::Keep(color) => match color.and_then(|color| indicies.get(&color)) {
+                Some(&index) => (index, Some(transparent_index)),
+                None => (transparent_index, None),
+            },
+        })
+        .collect()
+}
+
+// picking greedily can still lose to drawing every kept pixel again, or to leaving all of them
+// transparent, so whichever compresses best is used
+fn pick_indicies(choices: &[(u8, Option<u8>)], palette: &[u8]) -> Box<[u8]> {
+    let minimum_code_size = minimum_code_size(palette);
+    let candidates: [Vec<u8>; 3] = [
+        lzw::pick_indicies(choices, minimum_code_size),
+        choices.iter().map(|&(index, _)| index).collect(),
+        choices
+            .iter()
+            .map(|&(index, alternative)| alternative.unwrap_or(index))
+            .collect(),
+    ];
+
+    candidates
+        .into_iter()
+        .min_by_key(|indicies| lzw::lzw_encode(indicies, minimum_code_size).len())
+        .unwrap()
+        .into_boxed_slice()
+}
+
+/// One palette for as many frames as fit in it, written once as the global color table
+/// instead of once per frame. Each frame makes an entry it doesn't use its transparent index.
+#[derive(Default)]
+struct SharedPalette {
+    colors: Vec<[u8; 3]>,
+    indicies: HashMap<[u8; 3], u8>,
+}
+
+impl SharedPalette {
+    /// Turns `pixels` into a frame covering `rect`, using the shared palette if its colors can
+    /// be added to it and a local one otherwise. `None` if they don't fit in a palette at all.
+    fn add(&mut self, rect: Rect, pixels: &[Pixel]) -> Option<OptimizedFrame> {
+        let mut used = Vec::new();
+        let mut seen = HashSet::new();
+        for pixel in pixels {
+            if let Pixel::Draw(color) = pixel {
+                if seen.insert(*color) {
+                    used.push(*color);
+                }
+            }
+        }
+        let transparent = pixels.iter().any(|pixel| matches!(pixel, Pixel::Keep(_)));
+        if used.len() + transparent as usize > 256 {
+            return None;
+        }
+
+        let new_colors = used
+            .iter()
+            .filter(|color| !self.indicies.contains_key(*color))
+            .count();
+        let shared_len = self.colors.len() + new_colors;
+        let needs_spare = transparent && shared_len == used.len();
+        if shared_len + needs_spare as usize > 256 {
+            return Some(OptimizedFrame::local(rect, pixels, &used));
+        }
+
+        for &color in &used {
+            if !self.indicies.contains_key(&color) {
+                self.indicies.insert(color, self.colors.len() as u8);
+                self.colors.push(color);
+            }
+        }
+        if needs_spare {
+            // never looked up, so it stays free even if the color shows up later
+            self.colors.push([0, 0, 0]);
+        }
+
+        let transparent_index = transparent.then(|| {
+            let mut in_use = [false; 256];
+            for color in &used {
+                in_use[self.indicies[color] as usize] = true;
+            }
+            (0..self.colors.len())
+                .find(|&index| !in_use[index])
+                .unwrap() as u8
+        });
+
+        // kept pixels of colors the frame doesn't draw can't be drawn with the transparent index
+        let indicies = self
+            .indicies
+            .iter()
+            .filter(|&(_, &index)| Some(index) != transparent_index)
+            .map(|(&color, &index)| (color, index))
+            .collect();
+        let palette: Vec<u8> = self.colors.iter().flatten().copied().collect();
+        let choices = choices(pixels, &indicies, transparent_index);
+
+        Some(OptimizedFrame::new(
+            rect,
+            pick_indicies(&choices, &palette),
+            None,
+            transparent_index,
+        ))
+    }
+}
+
+// a frame that's waiting for the shared palette to be complete
+struct OptimizedFrame {
+    rect: Rect,
+    indicies: Box<[u8]>,
+    /// `None` for frames that use the shared palette.
+    local_palette: Option<Box<[u8]>>,
+    transparent_index: Option<u8>,
+    delay_time: u16,
+    needs_user_input: bool,
+    disposal_method: DisposalMethod,
+}
+
+impl OptimizedFrame {
+    fn new(
+        rect: Rect,
+        indicies: Box<[u8]>,
+        local_palette: Option<Box<[u8]>>,
+        transparent_index: Option<u8>,
+    ) -> Self {
+        Self {
+            rect,
+            indicies,
+            local_palette,
+            transparent_index,
+            delay_time: 0,
+            needs_user_input: false,
+            disposal_method: DisposalMethod::DoNotDispose,
+        }
+    }
+
+    // a frame with a palette of just `used`, and a transparent index after it if needed
+    fn local(rect: Rect, pixels: &[Pixel], used: &[[u8; 3]]) -> Self {
+        let indicies: HashMap<[u8; 3], u8> = used
+            .iter()
+            .enumerate()
+            .map(|(index, color)| (*color, index as u8))
+            .collect();
+        let transparent_index = pixels
+            .iter()
+            .any(|pixel| matches!(pixel, Pixel::Keep(_)))
+            .then_some(used.len() as u8);
+
+        let mut palette: Vec<u8> = used.iter().flatten().copied().collect();
+        if transparent_index.is_some() {
+            palette.extend_from_slice(&[0, 0, 0]);
+        }
+        let choices = choices(pixels, &indicies, transparent_index);
+
+        Self::new(
+            rect,
+            pick_indicies(&choices, &palette),
+            Some(palette.into_boxed_slice()),
+            transparent_index,
+        )
+    }
+
+    fn finish(self, shared: &[u8]) -> Frame {
+        let palette = self.local_palette.unwrap_or_else(|| shared.into());
+        let mut frame = Frame::new(self.rect.width, self.rect.height, self.indicies, palette);
+        frame.left_position = self.rect.left;
+        frame.top_position = self.rect.top;
+        frame.delay_time = self.delay_time;
+        frame.needs_user_input = self.needs_user_input;
+        frame.disposal_method = Some(self.disposal_method);
+        frame.transparent_color_index = self.transparent_index;
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OptimizeOptions;
+    use crate::animation::{Animation, GifBuilder};
+    use crate::fixtures::encode;
+    use crate::parser::{DisposalMethod, Frame};
+
+    use std::time::Duration;
+
+    // a red strip with blue pixels drawn on it, one of them cleared again, and repeated frames
+    fn blue_pixels() -> Animation {
+        let palette: Box<[u8]> = Box::new([255, 0, 0, 0, 0, 255, 0, 0, 0]);
+        let full_frame = |indicies: [u8; 4], delay_time, disposal_method| {
+            let mut frame = Frame::new(4, 1, Box::new(indicies), palette.clone());
+            frame.delay_time = delay_time;
+            frame.disposal_method = Some(disposal_method);
+            frame.transparent_color_index = Some(2);
+            frame
+        };
+
+        let frames = [
+            full_frame([0, 0, 0, 0], 10, DisposalMethod::DoNotDispose),
+            full_frame([0, 1, 0, 0], 10, DisposalMethod::DoNotDispose),
+            full_frame([0, 1, 0, 0], 5, DisposalMethod::DoNotDispose),
+            full_frame([0, 1, 1, 0], 10, DisposalMethod::RestoreToBackgroundColor),
+            // the third pixel was cleared by the disposal above
+            full_frame([0, 1, 2, 0], 10, DisposalMethod::DoNotDispose),
+            full_frame([0, 1, 2, 0], 10, DisposalMethod::DoNotDispose),
+        ];
+        Animation::decode(encode(4, 1, Some(&palette), &frames).as_slice()).unwrap()
+    }
+
+    #[test]
+    fn stores_only_what_changes() {
+        let animation = blue_pixels();
+        let optimized = animation.optimize(OptimizeOptions::default()).unwrap();
+        let gif = optimized.encode(Vec::new()).unwrap();
+        let optimized = Animation::decode(gif.as_slice()).unwrap();
+
+        let expected: Vec<Vec<u8>> = animation
+            .composited_frames()
+            .enumerate()
+            .filter(|(index, _)| ![2, 5].contains(index))
+            .map(|(_, canvas)| canvas)
+            .collect();
+        let composited: Vec<Vec<u8>> = optimized.composited_frames().collect();
+        assert_eq!(composited, expected);
+
+        let frames = optimized.frames();
+        let delays: Vec<u16> = frames.iter().map(|frame| frame.delay_time).collect();
+        assert_eq!(delays, [10, 15, 10, 20]);
+        let sizes: Vec<(u16, u16)> = frames
+            .iter()
+            .map(|frame| (frame.left_position, frame.width))
+            .collect();
+        assert_eq!(sizes, [(0, 4), (1, 1), (2, 1), (0, 1)]);
+        assert!(frames
+            .iter()
+            .all(|frame| frame.palette() == frames[0].palette()));
+    }
+
+    #[test]
+    fn deconstructs_into_cropped_frames() {
+        let animation = blue_pixels();
+        let gif = animation.deconstruct().unwrap().encode(Vec::new()).unwrap();
+        let deconstructed = Animation::decode(gif.as_slice()).unwrap();
+
+        assert!(deconstructed
+            .composited_frames()
+            .eq(animation.composited_frames()));
+
+        let frames = deconstructed.frames();
+        let delays: Vec<u16> = frames.iter().map(|frame| frame.delay_time).collect();
+        assert_eq!(delays, [10, 10, 5, 10, 10, 10]);
+        let sizes: Vec<(u16, u16)> = frames
+            .iter()
+            .map(|frame| (frame.left_position, frame.width))
+            .collect();
+        assert_eq!(sizes, [(0, 4), (1, 1), (0, 1), (2, 1), (0, 1), (0, 1)]);
+    }
+
+    #[test]
+    fn leaves_colors_that_barely_change() {
+        let frames = [[100, 100, 100], [102, 98, 100], [120, 100, 100]].map(|color| {
+            let palette: Box<[u8]> = color.into_iter().chain([0, 0, 0]).collect();
+            Frame::new(2, 1, Box::new([0, 1]), palette)
+        });
+        let animation = Animation::decode(encode(2, 1, None, &frames).as_slice()).unwrap();
+
+        let lossless = animation.optimize(OptimizeOptions::default()).unwrap();
+        assert_eq!(lossless.frames().len(), 3);
+
+        let lossy = animation
+            .optimize(OptimizeOptions {
+                lossy_level: 4,
+                ..OptimizeOptions::default()
+            })
+            .unwrap();
+        let composited: Vec<Vec<u8>> = lossy.composited_frames().collect();
+        assert_eq!(
+            composited,
+            [
+                [100, 100, 100, 255, 0, 0, 0, 255],
+                [120, 100, 100, 255, 0, 0, 0, 255]
+            ]
+        );
+    }
+
+    #[test]
+    fn drops_frames_that_look_the_same() {
+        let gradient: Vec<u8> = (0..16 * 16)
+            .flat_map(|pixel| {
+                let value = ((pixel % 16 + pixel / 16) * 8) as u8;
+                [value, value, value, 255]
+            })
+            .collect();
+        let mut nudged = gradient.clone();
+        nudged[0] += 2;
+        let inverted: Vec<u8> = gradient.iter().map(|value| 255 - value).collect();
+
+        let mut builder = GifBuilder::new();
+        builder.set_size(16, 16);
+        for (rgba, delay) in [(&gradient, 100), (&nudged, 50), (&inverted, 100)] {
+            builder.add_frame(rgba.clone(), Duration::from_millis(delay));
+        }
+        let animation = builder.build().unwrap();
+        assert_eq!(animation.frames().len(), 3);
+
+        let dropped = animation
+            .optimize(OptimizeOptions {
+                drop_similar: Some(0.99),
+                ..OptimizeOptions::default()
+            })
+            .unwrap();
+        let delays: Vec<u16> = dropped.frames().iter().map(|frame| frame.delay_time).collect();
+        assert_eq!(delays, [15, 10]);
+        let composited: Vec<Vec<u8>> = dropped.composited_frames().collect();
+        assert_eq!(composited[0], gradient);
+    }
+}
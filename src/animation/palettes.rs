@@ -0,0 +1,317 @@
+//! Finding frames whose palettes are close enough to share one color table.
+
+use std::collections::{HashMap, HashSet};
+
+use super::Animation;
+use crate::parser::Frame;
+
+/// How many palettes an animation stores, and how few it could get away with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteAnalysis {
+    /// Palettes that differ in any way, frames without a palette aren't counted.
+    pub distinct_palettes: usize,
+    /// Frames grouped by palettes that share most of their colors, most frames first.
+    pub clusters: Vec<PaletteCluster>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteCluster {
+    /// Indicies of the frames in the cluster, in order.
+    pub frames: Vec<usize>,
+    /// How many distinct palettes the frames have now.
+    pub palettes: usize,
+    /// One palette with every color the frames use. It starts with the palette most of them
+    /// already have, so those frames don't need to change.
+    pub palette: Box<[u8]>,
+}
+
+// the frames that have one exact palette
+struct PaletteGroup<'a> {
+    palette: &'a [u8],
+    frames: Vec<usize>,
+    // every color drawn, which leaves out transparent pixels
+    colors: HashSet<[u8; 3]>,
+    has_transparency: bool,
+    // indicies past the end of the palette can't be moved to another one
+    can_merge: bool,
+}
+
+// a cluster as it's being built, the first group keeps its palette as it is
+struct ClusterPlan<'a> {
+    groups: Vec<PaletteGroup<'a>>,
+    palette: Vec<[u8; 3]>,
+    // where each color is first found in `palette`
+    positions: HashMap<[u8; 3], u8>,
+}
+
+impl<'a> ClusterPlan<'a> {
+    fn new(group: PaletteGroup<'a>) -> Self {
+        let palette: Vec<[u8; 3]> = group
+            .palette
+            .chunks_exact(3)
+            .map(|color| [color[0], color[1], color[2]])
+            .collect();
+        let mut positions = HashMap::new();
+        for (index, &color) in palette.iter().enumerate() {
+            positions.entry(color).or_insert(index as u8);
+        }
+
+        Self {
+            groups: vec![group],
+            palette,
+            positions,
+        }
+    }
+
+    fn frame_count(&self) -> usize {
+        self.groups.iter().map(|group| group.frames.len()).sum()
+    }
+
+    // adds `group` if at least half of its colors are in the palette already and the rest fit,
+    // giving it back otherwise
+    fn try_add(&mut self, group: PaletteGroup<'a>) -> Result<(), PaletteGroup<'a>> {
+        if !group.can_merge {
+            return Err(group);
+        }
+
+        let new_colors: Vec<[u8; 3]> = group
+            .colors
+            .iter()
+            .filter(|color| !self.positions.contains_key(*color))
+            .copied()
+            .collect();
+        if new_colors.len() * 2 > group.colors.len() {
+            return Err(group);
+        }
+
+        let length = self.palette.len() + new_colors.len();
+        // transparent pixels need an index that isn't one of the group's colors
+        let needs_spare = group.has_transparency && length <= group.colors.len();
+        if length + needs_spare as usize > 256 {
+            return Err(group);
+        }
+
+        let mut new_colors = new_colors;
+        new_colors.sort_unstable();
+        if needs_spare {
+            new_colors.push([0, 0, 0]);
+        }
+        for color in new_colors {
+            self.positions
+                .entry(color)
+                .or_insert(self.palette.len() as u8);
+            self.palette.push(color);
+        }
+        self.groups.push(group);
+        Ok(())
+    }
+
+    fn cluster(&self) -> PaletteCluster {
+        let mut frames: Vec<usize> = self
+            .groups
+            .iter()
+            .flat_map(|group| group.frames.iter().copied())
+            .collect();
+        frames.sort_unstable();
+
+        PaletteCluster {
+            frames,
+            palettes: self.groups.len(),
+            palette: self.palette.iter().flatten().copied().collect(),
+        }
+    }
+
+    // every frame of the cluster drawn with the cluster's palette, by index
+    fn remapped_frames(&self, frames: &[Frame]) -> Vec<(usize, Frame)> {
+        let palette: Box<[u8]> = self.palette.iter().flatten().copied().collect();
+        let mut remapped = Vec::new();
+
+        // the first group already uses the palette
+        for group in &self.groups[1..] {
+            let mut map = [0_u8; 256];
+            for (index, color) in group.palette.chunks_exact(3).enumerate() {
+                if let Some(&position) = self.positions.get(color) {
+                    map[index] = position;
+                }
+            }
+            let used: HashSet<u8> = group
+                .colors
+                .iter()
+                .map(|color| self.positions[color])
+                .collect();
+            let spare = (0..self.palette.len() as u8).find(|index| !used.contains(index));
+
+            for &index in &group.frames {
+                let frame = &frames[index];
+                let transparent = frame.transparent_color_index;
+                let indicies = frame
+                    .indicies()
+                    .iter()
+                    .map(|&index| match Some(index) == transparent {
+                        true => spare.unwrap_or(0),
+                        false => map[index as usize],
+                    })
+                    .collect();
+
+                let mut frame = frame.with_palette(indicies, palette.clone());
+                frame.transparent_color_index = transparent.and(spare);
+                remapped.push((index, frame));
+            }
+        }
+
+        remapped
+    }
+}
+
+impl Animation {
+    /// Groups frames whose palettes share at least half their colors, as long as the colors
+    /// of a group still fit in one palette.
+    pub fn analyze_palettes(&self) -> PaletteAnalysis {
+        let plans = self.plan_palettes();
+        PaletteAnalysis {
+            distinct_palettes: plans.iter().map(|plan| plan.groups.len()).sum(),
+            clusters: plans.iter().map(ClusterPlan::cluster).collect(),
+        }
+    }
+
+    /// The frames with the largest palette cluster drawn with its palette, which becomes the
+    /// global palette when encoding. `None` if no frame has a palette.
+    pub(super) fn share_palette(&self) -> Option<(Box<[u8]>, Vec<Frame>)> {
+        let plans = self.plan_palettes();
+        let plan = plans.first()?;
+
+        let mut frames = self.frames.clone();
+        for (index, frame) in plan.remapped_frames(&self.frames) {
+            frames[index] = frame;
+        }
+        Some((plan.cluster().palette, frames))
+    }
+
+    // clusters, most frames first, with ties going to the one used first
+    fn plan_palettes(&self) -> Vec<ClusterPlan<'_>> {
+        let mut groups: Vec<PaletteGroup> = Vec::new();
+        let mut group_indicies: HashMap<&[u8], usize> = HashMap::new();
+
+        for (index, frame) in self.frames.iter().enumerate() {
+            let Some(palette) = frame.palette() else {
+                continue;
+            };
+            let group = *group_indicies.entry(palette).or_insert_with(|| {
+                groups.push(PaletteGroup {
+                    palette,
+                    frames: Vec::new(),
+                    colors: HashSet::new(),
+                    has_transparency: false,
+                    can_merge: palette.len() % 3 == 0 && palette.len() <= 3 * 256,
+                });
+                groups.len() - 1
+            });
+            let group = &mut groups[group];
+            group.frames.push(index);
+
+            let mut drawn = [false; 256];
+            for &index in frame.indicies() {
+                drawn[index as usize] = true;
+            }
+            for (index, _) in drawn.iter().enumerate().filter(|(_, &drawn)| drawn) {
+                let index = index as u8;
+                if Some(index) == frame.transparent_color_index {
+                    group.has_transparency = true;
+                    continue;
+                }
+                match frame.color(index) {
+                    Some(color) => {
+                        group.colors.insert(color);
+                    }
+                    None => group.can_merge = false,
+                }
+            }
+        }
+
+        // the most used palettes go first, so they're the ones that stay as they are
+        groups.sort_by_key(|group| std::cmp::Reverse(group.frames.len()));
+
+        let mut plans: Vec<ClusterPlan> = Vec::new();
+        'groups: for group in groups {
+            let mut group = group;
+            for plan in &mut plans {
+                match plan.try_add(group) {
+                    Ok(()) => continue 'groups,
+                    Err(rejected) => group = rejected,
+                }
+            }
+            plans.push(ClusterPlan::new(group));
+        }
+
+        plans.sort_by_key(|plan| {
+            let first = plan.groups.iter().map(|group| group.frames[0]).min();
+            (std::cmp::Reverse(plan.frame_count()), first)
+        });
+        plans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::animation::Animation;
+    use crate::fixtures::encode;
+    use crate::parser::Frame;
+
+    const RED: [u8; 3] = [255, 0, 0];
+    const GREEN: [u8; 3] = [0, 255, 0];
+    const BLUE: [u8; 3] = [0, 0, 255];
+    const WHITE: [u8; 3] = [255, 255, 255];
+
+    fn frame(palette: &[[u8; 3]], indicies: [u8; 2], transparent: Option<u8>) -> Frame {
+        let mut frame = Frame::new(2, 1, Box::new(indicies), palette.concat().into());
+        frame.transparent_color_index = transparent;
+        frame
+    }
+
+    fn animation(frames: &[Frame]) -> Animation {
+        Animation::decode(encode(2, 1, None, frames).as_slice()).unwrap()
+    }
+
+    #[test]
+    fn clusters_palettes_that_share_colors() {
+        let animation = animation(&[
+            frame(&[RED, GREEN], [0, 1], None),
+            frame(&[GREEN, RED], [1, 0], None),
+            frame(&[RED, GREEN], [1, 1], None),
+            // nothing in common with the others
+            frame(&[WHITE, BLUE], [0, 1], None),
+            // blue is new, but red isn't
+            frame(&[RED, BLUE], [0, 1], None),
+        ]);
+
+        let analysis = animation.analyze_palettes();
+        assert_eq!(analysis.distinct_palettes, 4);
+        assert_eq!(analysis.clusters.len(), 2);
+        assert_eq!(analysis.clusters[0].frames, [0, 1, 2, 4]);
+        assert_eq!(analysis.clusters[0].palettes, 3);
+        assert_eq!(*analysis.clusters[0].palette, [RED, GREEN, BLUE].concat());
+        assert_eq!(analysis.clusters[1].frames, [3]);
+    }
+
+    #[test]
+    fn encodes_frames_with_the_shared_palette() {
+        let animation = animation(&[
+            frame(&[RED, GREEN, BLUE, WHITE], [0, 1], Some(3)),
+            frame(&[BLUE, GREEN], [1, 0], None),
+            frame(&[GREEN, RED], [0, 1], Some(0)),
+            frame(&[WHITE, BLUE], [1, 0], None),
+        ]);
+
+        let gif = animation.encode(Vec::new()).unwrap();
+        let encoded = Animation::decode(gif.as_slice()).unwrap();
+        let local_palettes = encoded
+            .frames()
+            .iter()
+            .filter(|frame| frame.has_local_palette())
+            .count();
+        assert_eq!(local_palettes, 0);
+        assert!(encoded
+            .composited_frames()
+            .eq(animation.composited_frames()));
+    }
+}
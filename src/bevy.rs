@@ -0,0 +1,173 @@
+//! Loads GIFs as bevy assets, so they can be used as sprite animations directly. Each GIF
+//! becomes a [`SpriteSheet`] texture with a [`TextureAtlasLayout`] of its frames, and a
+//! [`GifAnimation`] with how long each frame is shown:
+//!
+//! ```ignore
+//! app.add_plugins(GifPlugin);
+//!
+//! let gif: Handle<GifAnimation> = asset_server.load("walk.gif");
+//! // or just the texture or layout
+//! let image: Handle<Image> = asset_server.load("walk.gif#image");
+//! let layout: Handle<TextureAtlasLayout> = asset_server.load("walk.gif#layout");
+//! ```
+//!
+//! The layout is an asset of bevy's `TextureAtlasPlugin`, which is part of `DefaultPlugins`.
+
+use anyhow::Result;
+use bevy_app::{App, Plugin};
+use bevy_asset::io::Reader;
+use bevy_asset::{Asset, AssetApp, AssetLoader, Handle, LoadContext, RenderAssetUsages};
+use bevy_image::{Image, TextureAtlasLayout};
+use bevy_math::{URect, UVec2};
+use bevy_reflect::TypePath;
+use serde::{Deserialize, Serialize};
+use wgpu_types::{Extent3d, TextureDimension, TextureFormat};
+
+use std::time::Duration;
+
+use crate::animation::Animation;
+use crate::parser::{frame_duration, LoopCount};
+use crate::sheet::SpriteSheet;
+
+/// Registers [`GifLoader`] and the [`GifAnimation`] asset.
+pub struct GifPlugin;
+
+impl Plugin for GifPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<GifAnimation>()
+            .register_asset_loader(GifLoader);
+    }
+}
+
+/// A GIF loaded as a sprite sheet. Frame `n` of the animation is texture `n` of `layout`.
+#[derive(Asset, TypePath, Debug)]
+pub struct GifAnimation {
+    #[dependency]
+    pub image: Handle<Image>,
+    #[dependency]
+    pub layout: Handle<TextureAtlasLayout>,
+    /// How long each frame is shown, with delays of 0 and 1 played as a tenth of a second.
+    pub durations: Vec<Duration>,
+    pub loop_count: Option<LoopCount>,
+}
+
+impl GifAnimation {
+    /// How long one play through the animation takes.
+    pub fn total_duration(&self) -> Duration {
+        self.durations.iter().sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct GifLoaderSettings {
+    /// Frames per row of the sprite sheet, see [`SpriteSheet::square`] for how it's laid out
+    /// without it.
+    pub columns: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GifLoader;
+
+impl AssetLoader for GifLoader {
+    type Asset = GifAnimation;
+    type Settings = GifLoaderSettings;
+    type Error = anyhow::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        settings: &GifLoaderSettings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<GifAnimation> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let animation = Animation::decode(bytes.as_slice())?;
+
+        let sheet = match settings.columns {
+            Some(columns) => SpriteSheet::new(&animation, columns),
+            None => SpriteSheet::square(&animation),
+        };
+        let durations = durations(&sheet);
+        let layout = atlas_layout(&sheet);
+
+        Ok(GifAnimation {
+            image: load_context.add_labeled_asset("image".to_string(), image(sheet)),
+            layout: load_context.add_labeled_asset("layout".to_string(), layout),
+            durations,
+            loop_count: animation.loop_count(),
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["gif"]
+    }
+}
+
+fn image(sheet: SpriteSheet) -> Image {
+    let size = Extent3d {
+        width: sheet.width,
+        height: sheet.height,
+        depth_or_array_layers: 1,
+    };
+
+    Image::new(
+        size,
+        TextureDimension::D2,
+        sheet.rgba,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
+
+fn atlas_layout(sheet: &SpriteSheet) -> TextureAtlasLayout {
+    let mut layout = TextureAtlasLayout::new_empty(UVec2::new(sheet.width, sheet.height));
+    for frame in &sheet.frames {
+        let min = UVec2::new(frame.x, frame.y);
+        layout.add_texture(URect::from_corners(
+            min,
+            min + UVec2::new(frame.width, frame.height),
+        ));
+    }
+    layout
+}
+
+fn durations(sheet: &SpriteSheet) -> Vec<Duration> {
+    sheet
+        .frames
+        .iter()
+        .map(|frame| frame_duration(frame.delay_time))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{atlas_layout, durations, image};
+    use crate::animation::Animation;
+    use crate::fixtures::three_frame_gif;
+    use crate::sheet::SpriteSheet;
+
+    use bevy_math::{URect, UVec2};
+
+    use std::time::Duration;
+
+    #[test]
+    fn describes_the_sheet_for_bevy() {
+        let animation = Animation::decode(three_frame_gif(5).as_slice()).unwrap();
+        let sheet = SpriteSheet::new(&animation, 2);
+
+        let layout = atlas_layout(&sheet);
+        assert_eq!(layout.size, UVec2::new(4, 2));
+        assert_eq!(
+            layout.textures,
+            [
+                URect::new(0, 0, 2, 1),
+                URect::new(2, 0, 4, 1),
+                URect::new(0, 1, 2, 2)
+            ]
+        );
+        assert_eq!(durations(&sheet), [Duration::from_millis(50); 3]);
+
+        let image = image(sheet);
+        assert_eq!((image.width(), image.height()), (4, 2));
+    }
+}
@@ -0,0 +1,162 @@
+//! LSB-first bit packing, the order used by both GIF's LZW streams and deflate.
+
+// a 16 bit value at any bit offset fits in the 24 bits of the three bytes it can span
+const MAX_BITS: u32 = 16;
+
+/// Reads values of up to 16 bits, starting from the least significant bit of each byte.
+#[derive(Debug, Clone)]
+pub struct BitReader<'a> {
+    buf: &'a [u8],
+    // next byte to move into the accumulator
+    position: usize,
+    // unread bits, the next one in the lowest bit
+    accumulator: u64,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self {
+            buf,
+            position: 0,
+            accumulator: 0,
+            bit_count: 0,
+        }
+    }
+
+    /// Reads the next `count` bits, or `None` once fewer than `count` bits are left.
+    #[inline]
+    pub fn next(&mut self, count: u32) -> Option<u16> {
+        assert!(count <= MAX_BITS, "can't read more than {} bits at once", MAX_BITS);
+
+        if self.bit_count < count {
+            self.refill();
+            if self.bit_count < count {
+                return None;
+            }
+        }
+
+        let value = self.accumulator & ((1 << count) - 1);
+        self.accumulator >>= count;
+        self.bit_count -= count;
+        Some(value as u16)
+    }
+
+    /// Number of bits left to read.
+    pub fn remaining(&self) -> usize {
+        self.bit_count as usize + (self.buf.len() - self.position) * 8
+    }
+
+    // tops the accumulator up to at least 56 bits, or with whatever is left of the buffer.
+    fn refill(&mut self) {
+        if let Some(bytes) = self.buf.get(self.position..self.position + 8) {
+            let word = u64::from_le_bytes(bytes.try_into().unwrap());
+            // bytes that don't fully fit are shifted in partially too, but they're the same
+            // bits the next refill puts there so they can be left in place
+            self.accumulator |= word << self.bit_count;
+            let consumed = (63 - self.bit_count) / 8;
+            self.position += consumed as usize;
+            self.bit_count += consumed * 8;
+        } else {
+            while self.bit_count <= 56 && self.position < self.buf.len() {
+                self.accumulator |= (self.buf[self.position] as u64) << self.bit_count;
+                self.position += 1;
+                self.bit_count += 8;
+            }
+        }
+    }
+}
+
+/// Packs values of up to 16 bits, filling each byte from its least significant bit.
+#[derive(Debug, Clone, Default)]
+pub struct BitWriter {
+    buf: Vec<u8>,
+    accumulator: u32,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes the low `count` bits of `value`.
+    pub fn write(&mut self, value: u16, count: u32) {
+        assert!(count <= MAX_BITS, "can't write more than {} bits at once", MAX_BITS);
+
+        let mask = (1_u32 << count) - 1;
+        self.accumulator |= (value as u32 & mask) << self.bit_count;
+        self.bit_count += count;
+
+        while self.bit_count >= 8 {
+            self.buf.push(self.accumulator as u8);
+            self.accumulator >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    /// Returns the written bytes, with the last one padded with zero bits.
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.buf.push(self.accumulator as u8);
+        }
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitReader, BitWriter};
+
+    #[test]
+    fn it_works() {
+        let buffer = &[
+            0b10000100, 0b10001111, 0b10101001, 0b11001011, 0b11101101, 0b00001111, 0b10100011,
+        ];
+        let mut reader = BitReader::new(buffer);
+        assert_eq!(reader.next(3), Some(0b00000100));
+        assert_eq!(reader.next(3), Some(0b00000000));
+        assert_eq!(reader.next(3), Some(0b00000110));
+        assert_eq!(reader.next(3), Some(0b00000111));
+        assert_eq!(reader.next(3), Some(0b00000000));
+        assert_eq!(reader.next(3), Some(0b00000011));
+        assert_eq!(reader.next(3), Some(0b00000010));
+        assert_eq!(reader.next(3), Some(0b00000101));
+        assert_eq!(reader.remaining(), 32);
+    }
+
+    #[test]
+    fn reads_across_three_bytes() {
+        let buffer = &[0b1010_0000, 0b1111_0000, 0b0000_0101, 0b1000_0000];
+        let mut reader = BitReader::new(buffer);
+        assert_eq!(reader.next(5), Some(0));
+        assert_eq!(reader.next(16), Some(0b0010_1111_1000_0101));
+        assert_eq!(reader.remaining(), 11);
+        assert_eq!(reader.next(12), None);
+        assert_eq!(reader.next(11), Some(0b100_0000_0000));
+        assert_eq!(reader.remaining(), 0);
+        assert_eq!(reader.next(1), None);
+    }
+
+    #[test]
+    fn round_trips_through_the_writer() {
+        let values: Vec<(u16, u32)> = (0..500_u32)
+            .map(|i| {
+                let count = i % 16 + 1;
+                ((i * 7919) as u16 & ((1_u32 << count) - 1) as u16, count)
+            })
+            .collect();
+
+        let mut writer = BitWriter::new();
+        for &(value, count) in &values {
+            writer.write(value, count);
+        }
+        let buf = writer.finish();
+
+        let mut reader = BitReader::new(&buf);
+        for &(value, count) in &values {
+            assert_eq!(reader.next(count), Some(value));
+        }
+        assert!(reader.remaining() < 8);
+    }
+}
@@ -0,0 +1,559 @@
+//! A GIF as the blocks it's made of, for comparing how two files are put together rather than
+//! what they show, e.g. to review what an optimizer changed.
+
+use anyhow::Result;
+use thiserror::Error;
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::mem;
+
+use crate::parser::{Decoder, DisposalMethod, Frame, SpecialPurposeExtension, Version};
+
+// past this many old x new blocks, finding the longest run of unchanged blocks takes too much
+// memory, and blocks are paired up in order instead
+const MAX_ALIGNMENT_CELLS: usize = 16 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+enum BlocksError {
+    #[error("the file ends before the end of its header")]
+    MissingHeader,
+}
+
+/// A block of a GIF. Graphic control extensions are part of the image they apply to.
+#[derive(Debug, Clone)]
+pub enum Block {
+    /// The header and logical screen descriptor.
+    Header {
+        version: Version,
+        width: u16,
+        height: u16,
+    },
+    GlobalColorTable(Box<[u8]>),
+    Application {
+        /// The identifier followed by the authentication code, like `NETSCAPE2.0`.
+        identifier: String,
+        data: Box<[u8]>,
+    },
+    Comment(Box<[u8]>),
+    /// An extension jif doesn't know.
+    Extension {
+        label: u8,
+        data: Box<[u8]>,
+    },
+    /// Frame `index`, with its graphic control extension and local color table.
+    Image {
+        index: usize,
+        frame: Frame,
+    },
+}
+
+/// One difference between two lists of blocks, by position in each list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockChange {
+    Added(usize),
+    Removed(usize),
+    Changed {
+        old: usize,
+        new: usize,
+        fields: Vec<FieldChange>,
+    },
+}
+
+/// A field of a block with its old and new value, written out for people to read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+impl FieldChange {
+    fn new(field: &'static str, old: impl ToString, new: impl ToString) -> Self {
+        Self {
+            field,
+            old: old.to_string(),
+            new: new.to_string(),
+        }
+    }
+}
+
+/// Parses a whole GIF into its blocks. Extensions come right after the global color table,
+/// since the decoder doesn't keep where they were between the frames.
+pub fn read_blocks<R: Read + Debug>(reader: R) -> Result<Vec<Block>> {
+    let mut decoder = Decoder::new(reader);
+    decoder.parse()?;
+
+    let (Some(version), Some((width, height))) = (decoder.version(), decoder.screen_size()) else {
+        return Err(BlocksError::MissingHeader.into());
+    };
+    let mut blocks = vec![Block::Header {
+        version,
+        width,
+        height,
+    }];
+    if let Some(palette) = decoder.global_palette() {
+        blocks.push(Block::GlobalColorTable(palette.into()));
+    }
+
+    for extension in decoder.extensions() {
+        blocks.push(match extension {
+            SpecialPurposeExtension::ApplicationBlock(application) => Block::Application {
+                identifier: format!(
+                    "{}{}",
+                    application.identifier,
+                    String::from_utf8_lossy(&application.authentication_code)
+                ),
+                data: application.data.clone(),
+            },
+            SpecialPurposeExtension::CommentBlock(comment) => Block::Comment(comment.clone()),
+            SpecialPurposeExtension::Unknown { label, data } => Block::Extension {
+                label: *label,
+                data: data.clone(),
+            },
+        });
+    }
+
+    let frames = decoder.into_frames();
+    blocks.extend(
+        frames
+            .into_iter()
+            .enumerate()
+            .map(|(index, frame)| Block::Image { index, frame }),
+    );
+    Ok(blocks)
+}
+
+impl Block {
+    /// What the block is, like `frame 3` or `NETSCAPE2.0 application extension`.
+    pub fn describe(&self) -> String {
+        match self {
+            Block::Header { .. } => "header".to_string(),
+            Block::GlobalColorTable(_) => "global color table".to_string(),
+            Block::Application { identifier, .. } => {
+                format!("{} application extension", identifier)
+            }
+            Block::Comment(_) => "comment".to_string(),
+            Block::Extension { label, .. } => format!("extension 0x{:02x}", label),
+            Block::Image { index, .. } => format!("frame {}", index),
+        }
+    }
+
+    /// How `new` differs from `self`, `None` if they're different kinds of block and can't be
+    /// compared. Frames are compared whatever their index.
+    pub fn changes(&self, new: &Block) -> Option<Vec<FieldChange>> {
+        let mut fields = Vec::new();
+        match (self, new) {
+            (
+                Block::Header {
+                    version,
+                    width,
+                    height,
+                },
+                Block::Header {
+                    version: new_version,
+                    width: new_width,
+                    height: new_height,
+                },
+            ) => {
+                if version != new_version {
+                    fields.push(FieldChange::new("version", version, new_version));
+                }
+                if (width, height) != (new_width, new_height) {
+                    fields.push(FieldChange::new(
+                        "size",
+                        format!("{}x{}", width, height),
+                        format!("{}x{}", new_width, new_height),
+                    ));
+                }
+            }
+            (Block::GlobalColorTable(palette), Block::GlobalColorTable(new_palette)) => {
+                fields.extend(palette_change("colors", Some(palette), Some(new_palette)));
+            }
+            (
+                Block::Application { identifier, data },
+                Block::Application {
+                    identifier: new_identifier,
+                    data: new_data,
+                },
+            ) => {
+                if identifier != new_identifier {
+                    return None;
+                }
+                if data != new_data {
+                    fields.push(FieldChange::new("data", hex(data), hex(new_data)));
+                }
+            }
+            (Block::Comment(comment), Block::Comment(new_comment)) => {
+                if comment != new_comment {
+                    fields.push(FieldChange::new(
+                        "text",
+                        format!("{:?}", String::from_utf8_lossy(comment)),
+                        format!("{:?}", String::from_utf8_lossy(new_comment)),
+                    ));
+                }
+            }
+            (
+                Block::Extension { label, data },
+                Block::Extension {
+                    label: new_label,
+                    data: new_data,
+                },
+            ) => {
+                if label != new_label {
+                    return None;
+                }
+                if data != new_data {
+                    fields.push(FieldChange::new("data", hex(data), hex(new_data)));
+                }
+            }
+            (Block::Image { frame, .. }, Block::Image { frame: new, .. }) => {
+                fields = frame_changes(frame, new);
+            }
+            _ => return None,
+        }
+        Some(fields)
+    }
+
+    // equal for blocks without changes, so they can be matched up without comparing every
+    // pixel of every pair of frames
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        mem::discriminant(self).hash(&mut hasher);
+        match self {
+            Block::Header {
+                version,
+                width,
+                height,
+            } => (*version as u8, width, height).hash(&mut hasher),
+            Block::GlobalColorTable(palette) => palette.hash(&mut hasher),
+            Block::Application { identifier, data } => (identifier, data).hash(&mut hasher),
+            Block::Comment(comment) => comment.hash(&mut hasher),
+            Block::Extension { label, data } => (label, data).hash(&mut hasher),
+            Block::Image { frame, .. } => {
+                (
+                    frame.left_position,
+                    frame.top_position,
+                    frame.width,
+                    frame.height,
+                )
+                    .hash(&mut hasher);
+                (frame.delay_time, frame.needs_user_input).hash(&mut hasher);
+                frame
+                    .disposal_method
+                    .map(|method| method as u8)
+                    .hash(&mut hasher);
+                frame.transparent_color_index.hash(&mut hasher);
+                local_palette(frame).hash(&mut hasher);
+                frame.indicies().hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+}
+
+/// Lines up two lists of blocks, keeping as many unchanged blocks as possible, and pairs up
+/// what's left of the same kind in between as changed blocks.
+pub fn diff_blocks(old: &[Block], new: &[Block]) -> Vec<BlockChange> {
+    let old_fingerprints: Vec<u64> = old.iter().map(Block::fingerprint).collect();
+    let new_fingerprints: Vec<u64> = new.iter().map(Block::fingerprint).collect();
+
+    let mut changes = Vec::new();
+    let (mut old_start, mut new_start) = (0, 0);
+    for (old_index, new_index) in unchanged_blocks(&old_fingerprints, &new_fingerprints) {
+        pair_up(
+            old,
+            new,
+            old_start..old_index,
+            new_start..new_index,
+            &mut changes,
+        );
+        (old_start, new_start) = (old_index + 1, new_index + 1);
+    }
+    pair_up(
+        old,
+        new,
+        old_start..old.len(),
+        new_start..new.len(),
+        &mut changes,
+    );
+
+    changes
+}
+
+// positions of the blocks that stay the same, in order: the common start and end of both
+// lists, and the longest common subsequence of what's between them
+fn unchanged_blocks(old: &[u64], new: &[u64]) -> Vec<(usize, usize)> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut unchanged: Vec<(usize, usize)> = (0..prefix).map(|index| (index, index)).collect();
+
+    let columns = new_middle.len() + 1;
+    if (old_middle.len() + 1) * columns <= MAX_ALIGNMENT_CELLS {
+        // lengths[i * columns + j] is the longest common subsequence of old_middle[i..] and
+        // new_middle[j..]
+        let mut lengths = vec![0_u32; (old_middle.len() + 1) * columns];
+        for i in (0..old_middle.len()).rev() {
+            for j in (0..new_middle.len()).rev() {
+                lengths[i * columns + j] = if old_middle[i] == new_middle[j] {
+                    lengths[(i + 1) * columns + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * columns + j].max(lengths[i * columns + j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < old_middle.len() && j < new_middle.len() {
+            if old_middle[i] == new_middle[j] {
+                unchanged.push((prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if lengths[(i + 1) * columns + j] >= lengths[i * columns + j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    }
+
+    let old_suffix = old.len() - suffix;
+    let new_suffix = new.len() - suffix;
+    unchanged.extend((0..suffix).map(|offset| (old_suffix + offset, new_suffix + offset)));
+    unchanged
+}
+
+// each removed block is paired with the next added block it can be compared to, anything
+// skipped over on either side was added or removed
+fn pair_up(
+    old: &[Block],
+    new: &[Block],
+    removed: std::ops::Range<usize>,
+    added: std::ops::Range<usize>,
+    changes: &mut Vec<BlockChange>,
+) {
+    let mut next_added = added.start;
+    for old_index in removed {
+        let pair = (next_added..added.end).find_map(|new_index| {
+            old[old_index]
+                .changes(&new[new_index])
+                .map(|fields| (new_index, fields))
+        });
+        match pair {
+            Some((new_index, fields)) => {
+                changes.extend((next_added..new_index).map(BlockChange::Added));
+                changes.push(BlockChange::Changed {
+                    old: old_index,
+                    new: new_index,
+                    fields,
+                });
+                next_added = new_index + 1;
+            }
+            None => changes.push(BlockChange::Removed(old_index)),
+        }
+    }
+    changes.extend((next_added..added.end).map(BlockChange::Added));
+}
+
+fn frame_changes(old: &Frame, new: &Frame) -> Vec<FieldChange> {
+    let mut fields = Vec::new();
+    if (old.left_position, old.top_position) != (new.left_position, new.top_position) {
+        fields.push(FieldChange::new(
+            "position",
+            format!("{},{}", old.left_position, old.top_position),
+            format!("{},{}", new.left_position, new.top_position),
+        ));
+    }
+    if (old.width, old.height) != (new.width, new.height) {
+        fields.push(FieldChange::new(
+            "size",
+            format!("{}x{}", old.width, old.height),
+            format!("{}x{}", new.width, new.height),
+        ));
+    }
+    if old.delay_time != new.delay_time {
+        fields.push(FieldChange::new(
+            "delay",
+            format!("{:.2}s", old.delay_time as f64 / 100.0),
+            format!("{:.2}s", new.delay_time as f64 / 100.0),
+        ));
+    }
+    if old.disposal_method != new.disposal_method {
+        fields.push(FieldChange::new(
+            "disposal",
+            disposal(old.disposal_method),
+            disposal(new.disposal_method),
+        ));
+    }
+    if old.transparent_color_index != new.transparent_color_index {
+        let index = |index: Option<u8>| index.map_or("none".to_string(), |index| index.to_string());
+        fields.push(FieldChange::new(
+            "transparent index",
+            index(old.transparent_color_index),
+            index(new.transparent_color_index),
+        ));
+    }
+    if old.needs_user_input != new.needs_user_input {
+        fields.push(FieldChange::new(
+            "waits for input",
+            old.needs_user_input,
+            new.needs_user_input,
+        ));
+    }
+    fields.extend(palette_change(
+        "local color table",
+        local_palette(old),
+        local_palette(new),
+    ));
+
+    let (old_indicies, new_indicies) = (old.indicies(), new.indicies());
+    if old_indicies != new_indicies {
+        let changed = match old_indicies.len() == new_indicies.len() {
+            true => {
+                let changed = old_indicies
+                    .iter()
+                    .zip(new_indicies)
+                    .filter(|(old, new)| old != new)
+                    .count();
+                format!("{} pixels, {} changed", new_indicies.len(), changed)
+            }
+            false => format!("{} pixels", new_indicies.len()),
+        };
+        fields.push(FieldChange::new(
+            "image data",
+            format!("{} pixels", old_indicies.len()),
+            changed,
+        ));
+    }
+
+    fields
+}
+
+fn palette_change(
+    field: &'static str,
+    old: Option<&[u8]>,
+    new: Option<&[u8]>,
+) -> Option<FieldChange> {
+    if old == new {
+        return None;
+    }
+
+    let describe = |palette: Option<&[u8]>| match palette {
+        Some(palette) => format!("{} colors", palette.len() / 3),
+        None => "none".to_string(),
+    };
+    let mut new_description = describe(new);
+    if let (Some(old), Some(new)) = (old, new) {
+        if old.len() == new.len() {
+            let changed = old
+                .chunks(3)
+                .zip(new.chunks(3))
+                .filter(|(old, new)| old != new)
+                .count();
+            new_description = format!("{}, {} changed", new_description, changed);
+        }
+    }
+    Some(FieldChange::new(field, describe(old), new_description))
+}
+
+fn local_palette(frame: &Frame) -> Option<&[u8]> {
+    match frame.has_local_palette() {
+        true => frame.palette(),
+        false => None,
+    }
+}
+
+fn disposal(method: Option<DisposalMethod>) -> &'static str {
+    match method {
+        None => "unspecified",
+        Some(DisposalMethod::None) => "none",
+        Some(DisposalMethod::DoNotDispose) => "do not dispose",
+        Some(DisposalMethod::RestoreToBackgroundColor) => "restore to background",
+        Some(DisposalMethod::RestoreToPrevious) => "restore to previous",
+    }
+}
+
+// short data in full, longer data by its length
+fn hex(data: &[u8]) -> String {
+    if data.len() > 8 {
+        return format!("{} bytes", data.len());
+    }
+    data.iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_blocks, read_blocks, BlockChange, FieldChange};
+    use crate::encoder::Encoder;
+    use crate::parser::{Frame, LoopCount};
+
+    fn gif(delays: &[u16], palette: &[u8], loop_count: Option<LoopCount>) -> Vec<u8> {
+        let mut encoder = Encoder::new(Vec::new(), 2, 1);
+        encoder.set_global_palette(palette).unwrap();
+        if let Some(loop_count) = loop_count {
+            encoder.set_loop_count(loop_count);
+        }
+        for (index, &delay) in delays.iter().enumerate() {
+            let mut frame = Frame::new(2, 1, Box::new([index as u8 % 2, 1]), palette.into());
+            frame.delay_time = delay;
+            encoder.write_frame(&frame).unwrap();
+        }
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn explains_what_changed_between_versions() {
+        let old = gif(
+            &[10, 10, 10, 10],
+            &[255, 0, 0, 0, 0, 255],
+            Some(LoopCount::Infinite),
+        );
+        // the last frame is dropped, the second one shown for longer and blue is now green
+        let new = gif(&[10, 20, 10], &[255, 0, 0, 0, 255, 0], None);
+
+        let old = read_blocks(old.as_slice()).unwrap();
+        let new = read_blocks(new.as_slice()).unwrap();
+        let changes = diff_blocks(&old, &new);
+
+        assert_eq!(
+            changes,
+            [
+                BlockChange::Changed {
+                    old: 1,
+                    new: 1,
+                    fields: vec![FieldChange::new(
+                        "colors",
+                        "2 colors",
+                        "2 colors, 1 changed"
+                    )],
+                },
+                BlockChange::Removed(2),
+                BlockChange::Changed {
+                    old: 4,
+                    new: 3,
+                    fields: vec![FieldChange::new("delay", "0.10s", "0.20s")],
+                },
+                BlockChange::Removed(6),
+            ]
+        );
+        assert_eq!(old[2].describe(), "NETSCAPE2.0 application extension");
+        assert_eq!(new[4].describe(), "frame 2");
+    }
+}
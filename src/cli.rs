@@ -0,0 +1,538 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use std::ops::Range;
+use std::path::PathBuf;
+
+use jif::export::NameTemplate;
+
+#[derive(Debug, Parser)]
+#[command(name = "jif", version, about = "Decodes, inspects and plays GIFs")]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    /// GIFs to open in the viewer, or directories of them. N and P switch between them. With the
+    /// http feature, http(s) URLs are played while they download
+    #[arg(default_value = "./homeless-nah-id-win.gif")]
+    pub files: Vec<PathBuf>,
+
+    /// Present frames without waiting for vertical sync, for testing latency
+    #[arg(long)]
+    pub no_vsync: bool,
+
+    /// Render every frame the viewer would show to PNGs in DIR instead of opening a window, for
+    /// machines with no display
+    #[arg(long, value_name = "DIR")]
+    pub headless: Option<PathBuf>,
+
+    /// Show pixels square, even in GIFs that say they're wider or taller than that
+    #[arg(long)]
+    pub ignore_aspect_ratio: bool,
+
+    /// Where S saves what's on screen, as frame_<n>.png or in --screenshot-format
+    #[arg(long, value_name = "DIR", default_value = ".")]
+    pub screenshot_dir: PathBuf,
+
+    #[arg(long, value_enum, default_value_t = FrameFormat::Png)]
+    pub screenshot_format: FrameFormat,
+
+    /// How the GIF is sampled when it's scaled to the window, L switches while it plays
+    #[arg(long, value_enum, default_value_t = Filter::Nearest)]
+    pub filter: Filter,
+
+    /// Only scale the GIF by whole numbers, in the middle of the window, I switches while it
+    /// plays
+    #[arg(long)]
+    pub pixel_perfect: bool,
+
+    /// Write every frame that's presented to DIR as a numbered PNG, with the time each one was
+    /// presented at in DIR/timing.csv, for checking playback keeps to the GIF's delays
+    #[arg(long, value_name = "DIR")]
+    pub record: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Summarize every GIF in a directory without decoding any image data
+    Stats(StatsArgs),
+    /// Print what's in a GIF
+    Info(InfoArgs),
+    /// Print the palettes of a GIF and which of their colors are used
+    Palette(PaletteArgs),
+    /// Compare how two versions of a GIF are put together, block by block
+    ExplainDiff(ExplainDiffArgs),
+    /// Compare two GIFs frame by frame: timing, palettes and the pixels on screen
+    Diff(DiffArgs),
+    /// Apply the edits described in a pipeline manifest to a GIF
+    Run(RunArgs),
+    /// Write composited frames to a directory as images
+    Extract(ExtractArgs),
+    /// Write every composited frame to stdout as raw RGBA, for piping into ffmpeg
+    Raw(RawArgs),
+    /// Write a still image of the first frame, without decoding the rest
+    Thumb(ThumbArgs),
+    /// Write a still image of one frame as it's shown, flattened onto a background color
+    Poster(PosterArgs),
+    /// Lay every frame out on a grid in one image, like the sprite sheets game engines use
+    Sheet(SheetArgs),
+    /// Scale a GIF to a new size, keeping its palettes
+    Resize(ResizeArgs),
+    /// Re-encode a GIF so each frame only stores what changed
+    Optimize(OptimizeArgs),
+    /// Change the delays and loop count of a GIF, copying its image data as it is
+    Retime(RetimeArgs),
+    /// Keep a range of frames, copying their image data as it is where that shows the same
+    Cut(CutArgs),
+    /// Play a GIF backwards
+    Reverse(LayersArgs),
+    /// Join GIFs into one that plays them one after another
+    Concat(ConcatArgs),
+    /// Turn every frame into a full frame of what's on screen, like ImageMagick's -coalesce
+    Coalesce(LayersArgs),
+    /// Crop every frame to what changed since the one before, like ImageMagick's -deconstruct
+    Deconstruct(LayersArgs),
+    /// Convert a GIF to an animated PNG with the same frames, delays and transparency
+    Apng(LayersArgs),
+    /// Play GIFs side by side in one window on the same clock, to watch them against each other
+    Compare(CompareArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    /// Directory to scan, subdirectories included
+    pub dir: PathBuf,
+
+    /// Print the report as JSON instead of a table
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct InfoArgs {
+    /// GIF to describe
+    pub input: PathBuf,
+
+    /// Also decode every frame and report which palettes are similar enough to share
+    #[arg(long)]
+    pub analyze: bool,
+
+    /// Also decode every frame and print how many colors it uses, how much of it is
+    /// transparent and how many pixels on screen it changes
+    #[arg(long)]
+    pub verbose: bool,
+
+    /// Also print the XMP metadata, if there is any
+    #[arg(long)]
+    pub xmp: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct PaletteArgs {
+    /// GIF to read the palettes of
+    pub input: PathBuf,
+
+    /// Only print the palette of this frame, counting from 0
+    #[arg(long)]
+    pub frame: Option<usize>,
+
+    /// Also draw the palettes to an image, as PNG or PPM by its extension. Unused colors are
+    /// drawn smaller
+    #[arg(long, value_name = "IMAGE")]
+    pub swatch: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ExplainDiffArgs {
+    /// The GIF before it was changed
+    pub old: PathBuf,
+
+    /// The GIF after it was changed
+    pub new: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    /// The GIF to compare against
+    pub a: PathBuf,
+
+    /// The GIF to compare
+    pub b: PathBuf,
+
+    /// Write a PNG showing where the pixels differ, brighter where they differ in more frames
+    #[arg(long, value_name = "PNG")]
+    pub heatmap: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    /// TOML manifest listing the ops to run
+    pub pipeline: PathBuf,
+
+    /// GIF to edit, or an animation saved by an earlier pipeline as .jifa
+    pub input: PathBuf,
+
+    /// Where to write the result, overrides the manifest's `output`. Naming it .jifa saves it
+    /// in jif's own format, which keeps everything for another pipeline to pick up
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ExtractArgs {
+    /// GIF to take the frames from
+    pub input: PathBuf,
+
+    /// Directory to write the frames to, created if it doesn't exist
+    pub dir: PathBuf,
+
+    #[arg(long, value_enum, default_value_t = FrameFormat::Png)]
+    pub format: FrameFormat,
+
+    /// Frames to extract, like 10..50, 10.. or ..50. The end is exclusive
+    #[arg(long, value_parser = parse_frame_range)]
+    pub range: Option<Range<usize>>,
+
+    /// Start of every file name, followed by the zero-padded frame number
+    #[arg(long, default_value = "frame_", conflicts_with = "name")]
+    pub prefix: String,
+
+    /// Name files after a template instead, like "{stem}_{index:04}.{ext}". {stem} is the
+    /// input's name, {index} the frame number, {ms} when the frame is shown in milliseconds
+    /// and {ext} the format's extension
+    #[arg(long, value_name = "TEMPLATE", value_parser = NameTemplate::parse)]
+    pub name: Option<NameTemplate>,
+
+    /// Number the first frame written this instead of its frame number. The rest follow on
+    /// from it
+    #[arg(long, value_name = "N")]
+    pub start_number: Option<usize>,
+
+    /// Write frames with square pixels, as they're stored, instead of stretching them to the
+    /// pixel aspect ratio the GIF asks for
+    #[arg(long)]
+    pub ignore_aspect_ratio: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct RawArgs {
+    /// GIF to take the frames from
+    pub input: PathBuf,
+
+    /// Print an ffmpeg command that turns the frames into a video instead of writing them
+    #[arg(long)]
+    pub print_ffmpeg_cmd: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct SheetArgs {
+    /// GIF to take the frames from
+    pub input: PathBuf,
+
+    /// Where to write the sheet, as PNG or PPM by its extension. <input>.sheet.png next to the
+    /// input by default
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Frames per row, about as many as there are rows by default
+    #[arg(long)]
+    pub columns: Option<u32>,
+
+    /// Also write where each frame is on the sheet and its delay to this JSON file
+    #[arg(long, value_name = "PATH")]
+    pub json: Option<PathBuf>,
+
+    /// Lay out frames with square pixels, as they're stored, instead of stretching them to the
+    /// pixel aspect ratio the GIF asks for
+    #[arg(long)]
+    pub ignore_aspect_ratio: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ThumbArgs {
+    /// GIF to take the first frame from
+    pub input: PathBuf,
+
+    /// Where to write the thumbnail, as PNG, PPM or QOI by its extension. <input>.thumb.png
+    /// next to the input by default
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Scale down to fit in a square this many pixels wide, keeping the aspect ratio
+    #[arg(long, value_name = "PIXELS", value_parser = clap::value_parser!(u16).range(1..))]
+    pub max_size: Option<u16>,
+
+    /// How pixels are picked when scaling down
+    #[arg(long, value_enum, default_value_t = ScaleFilter::Box)]
+    pub filter: ScaleFilter,
+
+    /// Keep pixels square, as they're stored, instead of stretching the thumbnail to the pixel
+    /// aspect ratio the GIF asks for
+    #[arg(long)]
+    pub ignore_aspect_ratio: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct PosterArgs {
+    /// GIF to take the frame from
+    pub input: PathBuf,
+
+    /// Where to write the poster, as PNG, PPM or QOI by its extension. <input>.poster.png next
+    /// to the input by default
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Frame to show, counting from 0. The first frame that leaves nothing on screen
+    /// transparent by default
+    #[arg(long)]
+    pub frame: Option<usize>,
+
+    /// Color to put behind transparent pixels, like #ffffff or #fff. Transparency is kept
+    /// without one, or turned black for PPM
+    #[arg(long, value_name = "COLOR", value_parser = parse_color)]
+    pub background: Option<[u8; 3]>,
+
+    /// Keep pixels square, as they're stored, instead of stretching the poster to the pixel
+    /// aspect ratio the GIF asks for
+    #[arg(long)]
+    pub ignore_aspect_ratio: bool,
+}
+
+#[derive(Debug, Args)]
+#[command(group = clap::ArgGroup::new("size").required(true).multiple(true))]
+pub struct ResizeArgs {
+    /// GIF to resize
+    pub input: PathBuf,
+
+    /// Where to write the result
+    pub output: PathBuf,
+
+    /// New width, follows the aspect ratio of the height without it
+    #[arg(long, group = "size", value_parser = clap::value_parser!(u16).range(1..))]
+    pub width: Option<u16>,
+
+    /// New height, follows the aspect ratio of the width without it
+    #[arg(long, group = "size", value_parser = clap::value_parser!(u16).range(1..))]
+    pub height: Option<u16>,
+}
+
+#[derive(Debug, Args)]
+pub struct RetimeArgs {
+    /// GIF to retime
+    pub input: PathBuf,
+
+    /// Where to write the result
+    #[arg(short, long)]
+    pub output: PathBuf,
+
+    /// Delay of every frame in hundredths of a second, unchanged by default
+    #[arg(long)]
+    pub delay: Option<u16>,
+
+    /// How many times to play the animation, 0 loops forever, unchanged by default
+    #[arg(long = "loop")]
+    pub loop_count: Option<u16>,
+}
+
+#[derive(Debug, Args)]
+pub struct ConcatArgs {
+    /// GIFs to join, in the order they play
+    #[arg(required = true, num_args = 2..)]
+    pub inputs: Vec<PathBuf>,
+
+    /// Where to write the result
+    #[arg(short, long)]
+    pub output: PathBuf,
+
+    /// How many times to play the animation, 0 loops forever. The first GIF's by default
+    #[arg(long = "loop")]
+    pub loop_count: Option<u16>,
+}
+
+#[derive(Debug, Args)]
+pub struct CutArgs {
+    /// GIF to cut
+    pub input: PathBuf,
+
+    /// Where to write the result
+    #[arg(short, long)]
+    pub output: PathBuf,
+
+    /// Frames to keep, like 10..50, 10.. or ..50. The end is exclusive
+    #[arg(long, value_parser = parse_frame_range)]
+    pub range: Range<usize>,
+}
+
+#[derive(Debug, Args)]
+pub struct OptimizeArgs {
+    /// GIF to optimize
+    pub input: PathBuf,
+
+    /// Where to write the result, <input>.optimized.gif next to the input by default
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// How far each channel of a color can drift from what's on screen before it's redrawn,
+    /// 0 is lossless
+    #[arg(long, default_value_t = 0)]
+    pub lossy_level: u8,
+
+    /// Drop frames that look at least this alike to the last frame kept, from 0 to 1 by SSIM,
+    /// and show that frame for longer instead. 0.98 leaves out frames that barely change
+    #[arg(long, value_name = "SSIM", value_parser = parse_similarity)]
+    pub drop_similar: Option<f64>,
+
+    /// Image to burn into every frame, a binary PPM or a PAM with alpha
+    #[arg(long, value_name = "IMAGE")]
+    pub watermark: Option<PathBuf>,
+
+    /// Where the watermark goes
+    #[arg(long, value_enum, default_value_t = WatermarkPosition::BottomRight)]
+    pub position: WatermarkPosition,
+
+    /// How opaque the watermark is, from 0 to 1
+    #[arg(long, default_value_t = 1.0, value_parser = parse_opacity)]
+    pub opacity: f32,
+}
+
+#[derive(Debug, Args)]
+pub struct LayersArgs {
+    /// GIF to read
+    pub input: PathBuf,
+
+    /// Where to write the result
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct CompareArgs {
+    /// Two to four GIFs or URLs, the first top left
+    #[arg(required = true, num_args = 2..=4)]
+    pub files: Vec<PathBuf>,
+
+    /// Present frames without waiting for vertical sync
+    #[arg(long)]
+    pub no_vsync: bool,
+
+    /// Show pixels square, even in GIFs that say they're wider or taller than that
+    #[arg(long)]
+    pub ignore_aspect_ratio: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Filter {
+    /// Keep pixels sharp
+    Nearest,
+    /// Blend neighbouring pixels, smoother for photos scaled by odd amounts
+    Linear,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FrameFormat {
+    Png,
+    Ppm,
+    Qoi,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ScaleFilter {
+    Nearest,
+    Box,
+    Bilinear,
+}
+
+fn parse_similarity(text: &str) -> Result<f64, String> {
+    let similarity: f64 = text.parse().map_err(|err| format!("{}: {}", text, err))?;
+    if !(0.0..=1.0).contains(&similarity) {
+        return Err(format!("expected a similarity from 0 to 1, got {}", text));
+    }
+    Ok(similarity)
+}
+
+fn parse_opacity(text: &str) -> Result<f32, String> {
+    let opacity: f32 = text.parse().map_err(|err| format!("{}: {}", text, err))?;
+    if !(0.0..=1.0).contains(&opacity) {
+        return Err(format!("expected an opacity from 0 to 1, got {}", text));
+    }
+    Ok(opacity)
+}
+
+// a hex color like #ff8000 or the #f80 shorthand for it, the # can be left out
+fn parse_color(text: &str) -> Result<[u8; 3], String> {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    let invalid = || format!("expected a color like #ff8000 or #f80, got {}", text);
+    if !hex.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| invalid());
+    match hex.len() {
+        3 => {
+            let short = |index: usize| channel(&hex[index..index + 1]).map(|value| value * 17);
+            Ok([short(0)?, short(1)?, short(2)?])
+        }
+        6 => Ok([
+            channel(&hex[..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..])?,
+        ]),
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_frame_range(text: &str) -> Result<Range<usize>, String> {
+    let (start, end) = text
+        .split_once("..")
+        .ok_or_else(|| format!("expected a range like 10..50, got {}", text))?;
+
+    let parse_bound = |bound: &str, default: usize| {
+        if bound.is_empty() {
+            Ok(default)
+        } else {
+            bound
+                .parse::<usize>()
+                .map_err(|err| format!("{}: {}", bound, err))
+        }
+    };
+    let start = parse_bound(start, 0)?;
+    let end = parse_bound(end, usize::MAX)?;
+
+    if start > end {
+        return Err(format!("{} ends before it starts", text));
+    }
+    Ok(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_color, parse_frame_range};
+
+    #[test]
+    fn parses_frame_ranges() {
+        assert_eq!(parse_frame_range("10..50"), Ok(10..50));
+        assert_eq!(parse_frame_range("10.."), Ok(10..usize::MAX));
+        assert_eq!(parse_frame_range("..5"), Ok(0..5));
+        assert!(parse_frame_range("5").is_err());
+        assert!(parse_frame_range("5..2").is_err());
+        assert!(parse_frame_range("a..2").is_err());
+    }
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!(parse_color("#ff8000"), Ok([255, 128, 0]));
+        assert_eq!(parse_color("FF8000"), Ok([255, 128, 0]));
+        assert_eq!(parse_color("#f80"), Ok([255, 136, 0]));
+        assert!(parse_color("#ff80").is_err());
+        assert!(parse_color("#gg8000").is_err());
+        assert!(parse_color("#+f+f+f").is_err());
+    }
+}
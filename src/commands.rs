@@ -0,0 +1,19 @@
+pub mod apng;
+pub mod concat;
+pub mod cut;
+pub mod diff;
+pub mod explain_diff;
+pub mod extract;
+pub mod info;
+pub mod layers;
+pub mod optimize;
+pub mod palette;
+pub mod poster;
+pub mod raw;
+pub mod resize;
+pub mod retime;
+pub mod reverse;
+pub mod run;
+pub mod sheet;
+pub mod stats;
+pub mod thumb;
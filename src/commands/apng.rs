@@ -0,0 +1,24 @@
+use anyhow::Result;
+
+use std::io::Write;
+
+use crate::cli::LayersArgs;
+use crate::input;
+use jif::export::encode_apng;
+use jif::output::{write_atomically, OutputOptions};
+
+pub fn run(args: LayersArgs) -> Result<()> {
+    let animation = input::decode_partial(&args.input)?;
+    let apng = encode_apng(&animation);
+
+    write_atomically(&args.output, OutputOptions::default(), |file| {
+        Ok(file.write_all(&apng)?)
+    })?;
+
+    println!(
+        "wrote {} frames to {}",
+        animation.frames().len(),
+        args.output.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use anyhow::Result;
+
+
+use crate::cli::ConcatArgs;
+use crate::input;
+use jif::animation::Animation;
+use jif::output::{AtomicFile, OutputOptions};
+use jif::parser::LoopCount;
+
+pub fn run(args: ConcatArgs) -> Result<()> {
+    let animations = args
+        .inputs
+        .iter()
+        .map(|path| Animation::decode(input::open(path)?))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut joined = Animation::concat(&animations);
+    match args.loop_count {
+        Some(0) => joined.set_loop_count(Some(LoopCount::Infinite)),
+        Some(count) => joined.set_loop_count(Some(LoopCount::Number(count))),
+        None => {}
+    }
+
+    let file = AtomicFile::create_with_options(&args.output, OutputOptions::default())?;
+    joined.encode(file)?.commit()?;
+
+    println!(
+        "joined {} GIFs into {} frames at {}x{} and wrote {}",
+        animations.len(),
+        joined.frames().len(),
+        joined.width(),
+        joined.height(),
+        args.output.display()
+    );
+    Ok(())
+}
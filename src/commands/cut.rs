@@ -0,0 +1,45 @@
+use anyhow::Result;
+
+use crate::cli::CutArgs;
+use crate::commands::retime::passthrough_encoder;
+use crate::input;
+use jif::animation::Animation;
+use jif::output::{AtomicFile, OutputOptions};
+use jif::parser::Decoder;
+
+pub fn run(args: CutArgs) -> Result<()> {
+    let input = input::read(&args.input)?;
+    let mut animation = Animation::decode(input.as_slice())?;
+    let end = args.range.end.min(animation.frames().len());
+    let start = args.range.start.min(end);
+
+    // the frames can be copied over as they are if the ones that are cut off don't leave
+    // anything on screen for them, otherwise what they leave is flattened into the first one
+    if animation.is_clear_before(start) {
+        let mut decoder = Decoder::new(input.as_slice());
+        let raw = decoder.parse_raw()?;
+        let mut encoder = passthrough_encoder(&decoder, &args.output, decoder.loop_count())?;
+        for (frame, raw) in decoder.frames()[start..end].iter().zip(&raw[start..end]) {
+            encoder.write_raw_frame(frame, raw)?;
+        }
+        encoder.finish()?.commit()?;
+        println!(
+            "copied frames {}..{} to {}",
+            start,
+            end,
+            args.output.display()
+        );
+    } else {
+        animation.trim(start..end)?;
+        let file = AtomicFile::create_with_options(&args.output, OutputOptions::default())?;
+        animation.encode(file)?.commit()?;
+        println!(
+            "re-encoded frames {}..{} to {}",
+            start,
+            end,
+            args.output.display()
+        );
+    }
+
+    Ok(())
+}
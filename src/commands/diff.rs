@@ -0,0 +1,219 @@
+use anyhow::Result;
+
+use std::io::Write;
+
+use crate::cli::DiffArgs;
+use crate::input;
+use jif::animation::Animation;
+use jif::export::{ImageFormat, Png};
+use jif::output::{write_atomically, OutputOptions};
+use jif::parser::{DisposalMethod, Frame};
+
+pub fn run(args: DiffArgs) -> Result<()> {
+    let a = Animation::decode(input::open(&args.a)?)?;
+    let b = Animation::decode(input::open(&args.b)?)?;
+
+    println!("{} -> {}", args.a.display(), args.b.display());
+    let mut differences = 0;
+
+    let (a_size, b_size) = ((a.width(), a.height()), (b.width(), b.height()));
+    if a_size != b_size {
+        println!(
+            "size: {}x{} -> {}x{}, pixels aren't compared",
+            a_size.0, a_size.1, b_size.0, b_size.1
+        );
+        differences += 1;
+    }
+    if a.frames().len() != b.frames().len() {
+        println!(
+            "frames: {} -> {}, only the first {} are compared",
+            a.frames().len(),
+            b.frames().len(),
+            a.frames().len().min(b.frames().len())
+        );
+        differences += 1;
+    }
+    if a.loop_count() != b.loop_count() {
+        println!("loop count: {:?} -> {:?}", a.loop_count(), b.loop_count());
+        differences += 1;
+    }
+
+    // how many frames each pixel differs in
+    let mut heat = vec![0_u32; a.width() as usize * a.height() as usize];
+    let mut differing_frames = 0;
+    let frames = a.frames().iter().zip(b.frames());
+    let canvases = a.composited_frames().zip(b.composited_frames());
+    for (index, ((a_frame, b_frame), (a_canvas, b_canvas))) in frames.zip(canvases).enumerate() {
+        let mut notes = frame_differences(a_frame, b_frame);
+        if a_size == b_size {
+            let diff = pixel_diff(&a_canvas, &b_canvas, &mut heat);
+            if diff.pixels > 0 {
+                notes.push(format!(
+                    "{} of {} pixels differ, by up to {}",
+                    diff.pixels,
+                    heat.len(),
+                    diff.max_difference
+                ));
+            }
+        }
+
+        if !notes.is_empty() {
+            println!("frame {}: {}", index, notes.join(", "));
+            differing_frames += 1;
+        }
+    }
+
+    if let Some(path) = &args.heatmap {
+        if a_size == b_size {
+            let image = Png::encode(a.width(), a.height(), &heatmap(&heat));
+            write_atomically(path, OutputOptions::default(), |file| {
+                Ok(file.write_all(&image)?)
+            })?;
+            println!("wrote a heatmap to {}", path.display());
+        } else {
+            println!("no heatmap, the sizes don't match");
+        }
+    }
+
+    println!();
+    match differences + differing_frames {
+        0 => println!("no differences"),
+        _ => println!(
+            "{} frames differ, {} other differences",
+            differing_frames, differences
+        ),
+    }
+    Ok(())
+}
+
+// what's different about a frame apart from its pixels
+fn frame_differences(a: &Frame, b: &Frame) -> Vec<String> {
+    let mut notes = Vec::new();
+    if a.delay_time != b.delay_time {
+        notes.push(format!("delay {} -> {}", a.delay_time, b.delay_time));
+    }
+    if a.disposal_method != b.disposal_method {
+        notes.push(format!(
+            "disposal {} -> {}",
+            describe_disposal(a.disposal_method),
+            describe_disposal(b.disposal_method)
+        ));
+    }
+    if a.transparent_color_index != b.transparent_color_index {
+        let describe =
+            |index: Option<u8>| index.map_or("none".to_string(), |index| index.to_string());
+        notes.push(format!(
+            "transparent index {} -> {}",
+            describe(a.transparent_color_index),
+            describe(b.transparent_color_index)
+        ));
+    }
+
+    let (a_palette, b_palette) = (a.palette().unwrap_or(&[]), b.palette().unwrap_or(&[]));
+    if a_palette.len() != b_palette.len() {
+        notes.push(format!(
+            "palette {} -> {} colors",
+            a_palette.len() / 3,
+            b_palette.len() / 3
+        ));
+    } else {
+        let changed = a_palette
+            .chunks_exact(3)
+            .zip(b_palette.chunks_exact(3))
+            .filter(|(a, b)| a != b)
+            .count();
+        if changed > 0 {
+            notes.push(format!(
+                "{} of {} palette colors changed",
+                changed,
+                a_palette.len() / 3
+            ));
+        }
+    }
+    notes
+}
+
+fn describe_disposal(disposal_method: Option<DisposalMethod>) -> &'static str {
+    match disposal_method {
+        None => "unset",
+        Some(DisposalMethod::None) => "none",
+        Some(DisposalMethod::DoNotDispose) => "keep",
+        Some(DisposalMethod::RestoreToBackgroundColor) => "restore to background",
+        Some(DisposalMethod::RestoreToPrevious) => "restore to previous",
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct PixelDiff {
+    pixels: usize,
+    // the largest difference in any one channel
+    max_difference: u8,
+}
+
+// compares two canvases of the same size, counting each differing pixel in `heat`
+fn pixel_diff(a: &[u8], b: &[u8], heat: &mut [u32]) -> PixelDiff {
+    let mut diff = PixelDiff {
+        pixels: 0,
+        max_difference: 0,
+    };
+    let pixels = a.chunks_exact(4).zip(b.chunks_exact(4));
+    for ((a, b), heat) in pixels.zip(heat) {
+        // every fully transparent pixel looks the same, whatever its color
+        if a == b || (a[3] == 0 && b[3] == 0) {
+            continue;
+        }
+
+        let difference = a.iter().zip(b).map(|(a, b)| a.abs_diff(*b)).max();
+        diff.max_difference = diff.max_difference.max(difference.unwrap_or(0));
+        diff.pixels += 1;
+        *heat += 1;
+    }
+    diff
+}
+
+// black where nothing changed, going through red to yellow for the pixels that differ in the
+// most frames
+fn heatmap(heat: &[u32]) -> Vec<u8> {
+    let hottest = heat.iter().copied().max().unwrap_or(0).max(1);
+    heat.iter()
+        .flat_map(|&count| {
+            if count == 0 {
+                return [0, 0, 0, 255];
+            }
+            // anything that differs at all is at least dark red, so single pixels stand out
+            let level = 64 + (count as u64 * 446 / hottest as u64) as u32;
+            [
+                level.min(255) as u8,
+                level.saturating_sub(255) as u8,
+                0,
+                255,
+            ]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{heatmap, pixel_diff, PixelDiff};
+
+    #[test]
+    fn counts_differing_pixels() {
+        let a = [[255, 0, 0, 255], [0, 0, 0, 0], [10, 10, 10, 255]].concat();
+        let b = [[255, 0, 0, 255], [9, 9, 9, 0], [10, 40, 10, 255]].concat();
+        let mut heat = [0; 3];
+        assert_eq!(
+            pixel_diff(&a, &b, &mut heat),
+            PixelDiff {
+                pixels: 1,
+                max_difference: 30
+            }
+        );
+        pixel_diff(&a, &[0; 12], &mut heat);
+        assert_eq!(heat, [1, 0, 2]);
+
+        assert_eq!(
+            heatmap(&heat),
+            [[255, 32, 0, 255], [0, 0, 0, 255], [255, 255, 0, 255]].concat()
+        );
+    }
+}
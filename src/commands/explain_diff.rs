@@ -0,0 +1,54 @@
+use anyhow::Result;
+
+
+use crate::cli::ExplainDiffArgs;
+use crate::input;
+use jif::blocks::{diff_blocks, read_blocks, BlockChange};
+
+pub fn run(args: ExplainDiffArgs) -> Result<()> {
+    let old = read_blocks(input::open(&args.old)?)?;
+    let new = read_blocks(input::open(&args.new)?)?;
+    let changes = diff_blocks(&old, &new);
+
+    println!("{} -> {}", args.old.display(), args.new.display());
+    if changes.is_empty() {
+        println!("no structural differences");
+        return Ok(());
+    }
+
+    let (mut added, mut removed, mut changed) = (0, 0, 0);
+    for change in &changes {
+        match change {
+            BlockChange::Added(index) => {
+                println!("+ {}", new[*index].describe());
+                added += 1;
+            }
+            BlockChange::Removed(index) => {
+                println!("- {}", old[*index].describe());
+                removed += 1;
+            }
+            BlockChange::Changed {
+                old: old_index,
+                new: new_index,
+                fields,
+            } => {
+                let (old_name, new_name) = (old[*old_index].describe(), new[*new_index].describe());
+                match old_name == new_name {
+                    true => println!("~ {}", old_name),
+                    false => println!("~ {}, now {}", old_name, new_name),
+                }
+                for field in fields {
+                    println!("    {}: {} -> {}", field.field, field.old, field.new);
+                }
+                changed += 1;
+            }
+        }
+    }
+
+    println!();
+    println!(
+        "{} blocks added, {} removed, {} changed",
+        added, removed, changed
+    );
+    Ok(())
+}
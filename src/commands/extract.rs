@@ -0,0 +1,71 @@
+use anyhow::{anyhow, Result};
+
+use std::fs;
+use std::io::Write;
+use std::time::Duration;
+
+use crate::cli::{ExtractArgs, FrameFormat};
+use crate::input;
+use jif::animation::Animation;
+use jif::export::{ImageFormat, Numbering, Png, Ppm, Qoi};
+use jif::output::{write_atomically, OutputOptions};
+use jif::parser::Frame;
+
+pub fn run(args: ExtractArgs) -> Result<()> {
+    let mut animation = input::decode_partial(&args.input)?;
+    if !args.ignore_aspect_ratio {
+        animation.correct_aspect_ratio();
+    }
+
+    let frame_count = animation.frames().len();
+    let range = args.range.clone().unwrap_or(0..usize::MAX);
+    if range.start >= frame_count {
+        return Err(anyhow!(
+            "{} only has {} frames",
+            args.input.display(),
+            frame_count
+        ));
+    }
+
+    fs::create_dir_all(&args.dir)?;
+    let written = match args.format {
+        FrameFormat::Png => extract::<Png>(&animation, &args)?,
+        FrameFormat::Ppm => extract::<Ppm>(&animation, &args)?,
+        FrameFormat::Qoi => extract::<Qoi>(&animation, &args)?,
+    };
+
+    println!("wrote {} frames to {}", written, args.dir.display());
+    Ok(())
+}
+
+fn extract<F: ImageFormat>(animation: &Animation, args: &ExtractArgs) -> Result<usize> {
+    let range = args.range.clone().unwrap_or(0..usize::MAX);
+    let (width, height) = (animation.width(), animation.height());
+
+    let stem = args.input.file_stem().unwrap_or_default().to_string_lossy();
+    let numbering = Numbering {
+        template: args.name.as_ref(),
+        prefix: &args.prefix,
+        start_number: args.start_number,
+    };
+    let durations: Vec<Duration> = animation.frames().iter().map(Frame::duration).collect();
+    // every name is worked out before anything's written, a template without the frame number
+    // in it can name frames the same
+    let names = numbering.names(&stem, F::EXTENSION, &durations, range.clone())?;
+
+    let mut written = 0;
+    // every frame has to be composited, even the ones before the range
+    let frames = animation
+        .composited_frames()
+        .take(range.end)
+        .skip(range.start);
+    for (rgba, name) in frames.zip(names) {
+        let image = F::encode(width, height, &rgba);
+        write_atomically(args.dir.join(name), OutputOptions::default(), |file| {
+            Ok(file.write_all(&image)?)
+        })?;
+        written += 1;
+    }
+
+    Ok(written)
+}
@@ -0,0 +1,116 @@
+use anyhow::Result;
+
+
+use crate::cli::InfoArgs;
+use crate::input;
+use jif::animation::Animation;
+use jif::parser::{ApplicationExtension, Decoder, LoopCount};
+
+pub fn run(args: InfoArgs) -> Result<()> {
+    let summary = Decoder::new(input::open(&args.input)?).scan()?;
+
+    println!("{}: GIF{}", args.input.display(), summary.version);
+    println!("size:            {}x{}", summary.width, summary.height);
+    println!("frames:          {}", summary.frame_count);
+    println!("duration:        {:.2}s", summary.duration.as_secs_f64());
+    let loops = match summary.loop_count {
+        Some(LoopCount::Infinite) => "forever".to_string(),
+        Some(LoopCount::Number(count)) => count.to_string(),
+        None => "plays once".to_string(),
+    };
+    println!("loops:           {}", loops);
+    match summary.global_palette_colors {
+        Some(colors) => println!("global palette:  {} colors", colors),
+        None => println!("global palette:  none"),
+    }
+    println!("local palettes:  {}", summary.local_palette_count);
+    for application in &summary.application_extensions {
+        println!(
+            "application:     {}{}, {} bytes",
+            application.identifier,
+            String::from_utf8_lossy(&application.authentication_code),
+            application.data.len()
+        );
+    }
+
+    let xmp = summary
+        .application_extensions
+        .iter()
+        .find_map(ApplicationExtension::xmp);
+    if args.xmp {
+        println!();
+        match xmp {
+            Some(xmp) => println!("{}", xmp.trim()),
+            None => println!("no XMP metadata"),
+        }
+    }
+
+    if args.analyze {
+        let animation = Animation::decode(input::read(&args.input)?.as_slice())?;
+        let analysis = animation.analyze_palettes();
+
+        println!();
+        println!(
+            "{} distinct palettes, {} once similar ones are merged:",
+            analysis.distinct_palettes,
+            analysis.clusters.len()
+        );
+        for cluster in &analysis.clusters {
+            println!(
+                "  {} palettes, {} colors: frames {}",
+                cluster.palettes,
+                cluster.palette.len() / 3,
+                format_frames(&cluster.frames)
+            );
+        }
+    }
+
+    if args.verbose {
+        let animation = Animation::decode(input::read(&args.input)?.as_slice())?;
+        println!();
+        for (index, stats) in animation.frame_stats().iter().enumerate() {
+            println!(
+                "frame {}: {} colors, {:.1}% transparent, {} pixels changed",
+                index,
+                stats.unique_indicies,
+                stats.transparent_fraction() * 100.0,
+                stats.changed_pixels.unwrap_or(0)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// sorted frame numbers as runs, like 0-12, 15, 20-21
+fn format_frames(frames: &[usize]) -> String {
+    let mut runs: Vec<String> = Vec::new();
+    let mut index = 0;
+    while index < frames.len() {
+        let start = frames[index];
+        let mut end = start;
+        while index + 1 < frames.len() && frames[index + 1] == end + 1 {
+            index += 1;
+            end += 1;
+        }
+        index += 1;
+
+        runs.push(match start == end {
+            true => start.to_string(),
+            false => format!("{}-{}", start, end),
+        });
+    }
+    runs.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_frames;
+
+    #[test]
+    fn formats_frames_as_runs() {
+        assert_eq!(format_frames(&[0, 1, 2, 5, 7, 8]), "0-2, 5, 7-8");
+        assert_eq!(format_frames(&[3]), "3");
+        assert_eq!(format_frames(&[]), "");
+    }
+}
@@ -0,0 +1,33 @@
+//! Commands named after ImageMagick's `-layers` operations, which change how frames are stored
+//! without changing what's shown.
+
+use anyhow::Result;
+
+
+use crate::cli::LayersArgs;
+use crate::input;
+use jif::animation::Animation;
+use jif::output::{AtomicFile, OutputOptions};
+
+pub fn coalesce(args: LayersArgs) -> Result<()> {
+    rewrite(args, Animation::coalesce)
+}
+
+pub fn deconstruct(args: LayersArgs) -> Result<()> {
+    rewrite(args, Animation::deconstruct)
+}
+
+fn rewrite(args: LayersArgs, change: fn(&Animation) -> Result<Animation>) -> Result<()> {
+    let animation = Animation::decode(input::open(&args.input)?)?;
+    let changed = change(&animation)?;
+
+    let file = AtomicFile::create_with_options(&args.output, OutputOptions::default())?;
+    changed.encode(file)?.commit()?;
+
+    println!(
+        "wrote {} frames to {}",
+        changed.frames().len(),
+        args.output.display()
+    );
+    Ok(())
+}
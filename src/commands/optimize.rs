@@ -0,0 +1,73 @@
+use anyhow::Result;
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::cli::{OptimizeArgs, WatermarkPosition};
+use crate::input;
+use jif::animation::{Animation, OptimizeOptions};
+use jif::output::{write_atomically, OutputOptions};
+use jif::watermark::{Position, Watermark};
+
+pub fn run(args: OptimizeArgs) -> Result<()> {
+    let input = input::read(&args.input)?;
+    let mut animation = Animation::decode(input.as_slice())?;
+    let frame_count = animation.frames().len();
+
+    if let Some(path) = &args.watermark {
+        let mut watermark = Watermark::decode_pnm(&fs::read(path)?)?;
+        watermark.set_opacity(args.opacity);
+        let position = match args.position {
+            WatermarkPosition::TopLeft => Position::TopLeft,
+            WatermarkPosition::TopRight => Position::TopRight,
+            WatermarkPosition::BottomLeft => Position::BottomLeft,
+            WatermarkPosition::BottomRight => Position::BottomRight,
+            WatermarkPosition::Center => Position::Center,
+        };
+        animation = animation.watermark(&watermark, position)?;
+    }
+
+    let optimized = animation.optimize(OptimizeOptions {
+        lossy_level: args.lossy_level,
+        drop_similar: args.drop_similar,
+    })?;
+    let encoded = optimized.encode(Vec::new())?;
+
+    let output = args.output.unwrap_or_else(|| default_output(&args.input));
+
+    // GIFs written by a better optimizer than this one are left as they are, unless they're
+    // being changed
+    let kept_input = encoded.len() >= input.len() && args.watermark.is_none();
+    let (bytes, kept_frame_count) = if kept_input {
+        (&input, frame_count)
+    } else {
+        (&encoded, optimized.frames().len())
+    };
+    write_atomically(&output, OutputOptions::default(), |file| {
+        Ok(file.write_all(bytes)?)
+    })?;
+
+    println!(
+        "{}: {} -> {} bytes ({:+.1}%), {} -> {} frames",
+        output.display(),
+        input.len(),
+        bytes.len(),
+        (bytes.len() as f64 - input.len() as f64) / input.len().max(1) as f64 * 100.0,
+        frame_count,
+        kept_frame_count
+    );
+    if kept_input {
+        println!(
+            "re-encoding came out at {} bytes, so the input was copied as is",
+            encoded.len()
+        );
+    }
+
+    Ok(())
+}
+
+fn default_output(input: &Path) -> PathBuf {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    input.with_file_name(format!("{}.optimized.gif", stem))
+}
@@ -0,0 +1,208 @@
+use anyhow::{anyhow, Result};
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use crate::cli::PaletteArgs;
+use crate::input;
+use jif::export::{ImageFormat, Png, Ppm};
+use jif::output::{write_atomically, OutputOptions};
+use jif::parser::{Decoder, Frame};
+
+// how many colors are printed on a line and drawn in a row of the swatch
+const COLORS_PER_ROW: usize = 8;
+// the size of a color in the swatch, in pixels
+const CELL_SIZE: usize = 16;
+// the space between palettes in the swatch
+const GAP: usize = 8;
+
+// a palette and which of its entries the frames using it refer to
+struct Palette<'a> {
+    name: String,
+    colors: &'a [u8],
+    used: [bool; 256],
+}
+
+impl Palette<'_> {
+    fn len(&self) -> usize {
+        self.colors.len() / 3
+    }
+
+    fn used_count(&self) -> usize {
+        self.used[..self.len()].iter().filter(|&&used| used).count()
+    }
+}
+
+pub fn run(args: PaletteArgs) -> Result<()> {
+    let mut decoder = Decoder::new(input::open(&args.input)?);
+    decoder.parse()?;
+    let frames = decoder.frames();
+
+    let palettes = match args.frame {
+        Some(index) => {
+            let frame = frames.get(index).ok_or_else(|| {
+                anyhow!("{} only has {} frames", args.input.display(), frames.len())
+            })?;
+            let name = match frame.has_local_palette() {
+                true => format!("frame {}", index),
+                false => format!("frame {}, global palette", index),
+            };
+            let colors = frame.palette().unwrap_or_default();
+            vec![palette(name, colors, [frame])]
+        }
+        None => {
+            let mut palettes = Vec::new();
+            if let Some(colors) = decoder.global_palette() {
+                let users = frames.iter().filter(|frame| !frame.has_local_palette());
+                palettes.push(palette("global palette".to_string(), colors, users));
+            }
+            for (index, frame) in frames.iter().enumerate() {
+                if frame.has_local_palette() {
+                    let colors = frame.palette().unwrap_or_default();
+                    palettes.push(palette(format!("frame {}", index), colors, [frame]));
+                }
+            }
+            palettes
+        }
+    };
+
+    if palettes.is_empty() {
+        println!("no palettes, every pixel is black");
+    }
+    for palette in &palettes {
+        println!(
+            "{}: {} colors, {} used",
+            palette.name,
+            palette.len(),
+            palette.used_count()
+        );
+        for (row, colors) in palette.colors.chunks(COLORS_PER_ROW * 3).enumerate() {
+            let colors: Vec<String> = colors
+                .chunks_exact(3)
+                .enumerate()
+                .map(|(column, rgb)| {
+                    let hex = format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2]);
+                    match palette.used[row * COLORS_PER_ROW + column] {
+                        true => format!(" {} ", hex),
+                        false => format!("({})", hex),
+                    }
+                })
+                .collect();
+            let line = colors.join(" ");
+            println!("  {:>3}  {}", row * COLORS_PER_ROW, line.trim_end());
+        }
+    }
+
+    // colors repeated across palettes only count once
+    let distinct: HashSet<&[u8]> = palettes
+        .iter()
+        .flat_map(|palette| {
+            let used = palette.used;
+            palette
+                .colors
+                .chunks_exact(3)
+                .enumerate()
+                .filter(move |(index, _)| used[*index])
+                .map(|(_, rgb)| rgb)
+        })
+        .collect();
+    println!();
+    println!("colors in brackets aren't used by any pixel");
+    println!("{} distinct colors are used", distinct.len());
+    if distinct.len() > 256 {
+        println!("that's more than one palette can hold, so frames can't share a global palette");
+    }
+
+    if let Some(path) = &args.swatch {
+        let encode = match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case(Png::EXTENSION) => Png::encode,
+            Some(extension) if extension.eq_ignore_ascii_case(Ppm::EXTENSION) => Ppm::encode,
+            _ => return Err(anyhow!("{} should end in .png or .ppm", path.display())),
+        };
+        let (width, height, rgba) = swatch(&palettes);
+        let image = encode(width, height, &rgba);
+        write_atomically(path, OutputOptions::default(), |file| {
+            Ok(file.write_all(&image)?)
+        })?;
+        println!("wrote a swatch to {}", path.display());
+    }
+    Ok(())
+}
+
+fn palette<'a, 'b>(
+    name: String,
+    colors: &'a [u8],
+    users: impl IntoIterator<Item = &'b Frame>,
+) -> Palette<'a> {
+    let mut used = [false; 256];
+    for frame in users {
+        for &index in frame.indicies() {
+            used[index as usize] = true;
+        }
+    }
+    Palette { name, colors, used }
+}
+
+// every palette as rows of squares, one after the other. Unused colors are drawn smaller, the
+// rest of their square is left transparent
+fn swatch(palettes: &[Palette]) -> (u16, u16, Vec<u8>) {
+    let width = COLORS_PER_ROW * CELL_SIZE;
+    let heights: Vec<usize> = palettes
+        .iter()
+        .map(|palette| palette.len().div_ceil(COLORS_PER_ROW) * CELL_SIZE)
+        .collect();
+    let height = heights.iter().sum::<usize>() + GAP * palettes.len().saturating_sub(1);
+
+    let mut rgba = vec![0; width * height * 4];
+    let mut top = 0;
+    for (palette, palette_height) in palettes.iter().zip(heights) {
+        for (index, rgb) in palette.colors.chunks_exact(3).enumerate() {
+            let left = index % COLORS_PER_ROW * CELL_SIZE;
+            let cell_top = top + index / COLORS_PER_ROW * CELL_SIZE;
+            let inset = match palette.used[index] {
+                true => 0,
+                false => CELL_SIZE / 4,
+            };
+            for y in cell_top + inset..cell_top + CELL_SIZE - inset {
+                for x in left + inset..left + CELL_SIZE - inset {
+                    let offset = (y * width + x) * 4;
+                    rgba[offset..offset + 4].copy_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
+                }
+            }
+        }
+        top += palette_height + GAP;
+    }
+    (width as u16, height as u16, rgba)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{palette, swatch, CELL_SIZE, COLORS_PER_ROW};
+    use jif::parser::Frame;
+
+    #[test]
+    fn marks_used_colors() {
+        let colors = [255, 0, 0, 0, 255, 0, 0, 0, 255];
+        let first = Frame::new(2, 1, Box::new([0, 2]), Box::new(colors));
+        let second = Frame::new(1, 1, Box::new([2]), Box::new(colors));
+        let palette = palette("global palette".to_string(), &colors, [&first, &second]);
+        assert_eq!(palette.used_count(), 2);
+        assert_eq!(palette.used[..3], [true, false, true]);
+
+        let (width, height, rgba) = swatch(&[palette]);
+        assert_eq!(
+            (width as usize, height as usize),
+            (COLORS_PER_ROW * CELL_SIZE, CELL_SIZE)
+        );
+        let pixel = |x: usize, y: usize| &rgba[(y * width as usize + x) * 4..][..4];
+        assert_eq!(pixel(0, 0), [255, 0, 0, 255]);
+        // the unused green is drawn smaller
+        assert_eq!(pixel(CELL_SIZE, 0), [0, 0, 0, 0]);
+        assert_eq!(
+            pixel(CELL_SIZE + CELL_SIZE / 2, CELL_SIZE / 2),
+            [0, 255, 0, 255]
+        );
+        assert_eq!(pixel(CELL_SIZE * 2, 0), [0, 0, 255, 255]);
+        assert_eq!(pixel(CELL_SIZE * 3, 0), [0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::{anyhow, Result};
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::cli::PosterArgs;
+use crate::input;
+use jif::export::{ImageFormat, Png, Ppm, Qoi};
+use jif::output::{write_atomically, OutputOptions};
+use jif::parser::Decoder;
+use jif::scale::Filter;
+
+pub fn run(args: PosterArgs) -> Result<()> {
+    let output = args.output.unwrap_or_else(|| default_output(&args.input));
+    let encode = match output.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) if extension.eq_ignore_ascii_case(Png::EXTENSION) => Png::encode,
+        Some(extension) if extension.eq_ignore_ascii_case(Ppm::EXTENSION) => Ppm::encode,
+        Some(extension) if extension.eq_ignore_ascii_case(Qoi::EXTENSION) => Qoi::encode,
+        _ => {
+            return Err(anyhow!(
+                "{} should end in .png, .ppm or .qoi",
+                output.display()
+            ))
+        }
+    };
+
+    let mut decoder = Decoder::new(input::open(&args.input)?);
+    let Some(mut poster) = decoder.decode_poster(args.frame)? else {
+        return Err(anyhow!(
+            "{} only has {} frames",
+            args.input.display(),
+            decoder.frames().len()
+        ));
+    };
+    if let Some(background) = args.background {
+        poster = poster.flatten(background);
+    }
+    if let Some(pixel_aspect_ratio) = decoder.pixel_aspect_ratio() {
+        if !args.ignore_aspect_ratio {
+            poster = poster.correct_aspect_ratio(pixel_aspect_ratio, Filter::Box);
+        }
+    }
+
+    let image = encode(poster.width, poster.height, &poster.rgba);
+    write_atomically(&output, OutputOptions::default(), |file| {
+        Ok(file.write_all(&image)?)
+    })?;
+
+    println!(
+        "wrote a {}x{} poster to {}",
+        poster.width,
+        poster.height,
+        output.display()
+    );
+    Ok(())
+}
+
+fn default_output(input: &Path) -> PathBuf {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    input.with_file_name(format!("{}.poster.png", stem))
+}
@@ -0,0 +1,111 @@
+use anyhow::Result;
+
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::cli::RawArgs;
+use crate::input;
+
+pub fn run(args: RawArgs) -> Result<()> {
+    let animation = input::decode_partial(&args.input)?;
+
+    if args.print_ffmpeg_cmd {
+        let duration: f64 = animation
+            .frames()
+            .iter()
+            .map(|frame| frame.duration().as_secs_f64())
+            .sum();
+        // raw video has one frame rate for every frame, so delays are averaged out
+        let frame_rate = match duration > 0.0 {
+            true => animation.frames().len() as f64 / duration,
+            false => 10.0,
+        };
+        println!(
+            "{}",
+            ffmpeg_command(
+                &args.input,
+                animation.width(),
+                animation.height(),
+                frame_rate
+            )
+        );
+        return Ok(());
+    }
+
+    let mut stdout = BufWriter::new(io::stdout().lock());
+    for rgba in animation.composited_frames() {
+        match stdout.write_all(&rgba) {
+            // whatever's reading has had enough, like `ffmpeg -frames:v`
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            result => result?,
+        }
+    }
+    match stdout.flush() {
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+fn ffmpeg_command(input: &Path, width: u16, height: u16, frame_rate: f64) -> String {
+    let output = format!(
+        "{}.mp4",
+        input.file_stem().unwrap_or_default().to_string_lossy()
+    );
+    // yuv420p, which every player handles, needs an even width and height
+    let pad = match !width.is_multiple_of(2) || !height.is_multiple_of(2) {
+        true => format!(" -vf {}", quote("pad=ceil(iw/2)*2:ceil(ih/2)*2")),
+        false => String::new(),
+    };
+
+    format!(
+        "jif raw {} | ffmpeg -f rawvideo -pixel_format rgba -video_size {}x{} -framerate {} -i -{} -pix_fmt yuv420p {}",
+        quote(&input.to_string_lossy()),
+        width,
+        height,
+        format_frame_rate(frame_rate),
+        pad,
+        quote(&output)
+    )
+}
+
+// up to three decimals, without trailing zeroes
+fn format_frame_rate(frame_rate: f64) -> String {
+    let formatted = format!("{:.3}", frame_rate);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+// quotes `text` for a POSIX shell, unless it's safe as it is
+fn quote(text: &str) -> String {
+    let safe = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._-/+=:,@".contains(c));
+    match safe {
+        true => text.to_string(),
+        false => format!("'{}'", text.replace('\'', "'\\''")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ffmpeg_command;
+
+    use std::path::Path;
+
+    #[test]
+    fn prints_a_matching_ffmpeg_command() {
+        assert_eq!(
+            ffmpeg_command(Path::new("cat.gif"), 100, 76, 12.5),
+            "jif raw cat.gif | ffmpeg -f rawvideo -pixel_format rgba -video_size 100x76 \
+             -framerate 12.5 -i - -pix_fmt yuv420p cat.mp4"
+        );
+        assert_eq!(
+            ffmpeg_command(Path::new("my cat's.gif"), 99, 76, 10.0),
+            "jif raw 'my cat'\\''s.gif' | ffmpeg -f rawvideo -pixel_format rgba -video_size 99x76 \
+             -framerate 10 -i - -vf 'pad=ceil(iw/2)*2:ceil(ih/2)*2' -pix_fmt yuv420p 'my cat'\\''s.mp4'"
+        );
+    }
+}
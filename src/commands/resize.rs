@@ -0,0 +1,38 @@
+use anyhow::Result;
+
+
+use crate::cli::ResizeArgs;
+use crate::input;
+use jif::animation::Animation;
+use jif::output::{AtomicFile, OutputOptions};
+
+pub fn run(args: ResizeArgs) -> Result<()> {
+    let mut animation = Animation::decode(input::open(&args.input)?)?;
+    let (old_width, old_height) = (animation.width().max(1), animation.height().max(1));
+
+    // the side that's left out keeps the aspect ratio
+    let follow = |side: u16, old_side: u16, old_other: u16| {
+        ((side as u32 * old_other as u32 + old_side as u32 / 2) / old_side as u32)
+            .clamp(1, u16::MAX as u32) as u16
+    };
+    let (width, height) = match (args.width, args.height) {
+        (Some(width), Some(height)) => (width, height),
+        (Some(width), None) => (width, follow(width, old_width, old_height)),
+        (None, Some(height)) => (follow(height, old_height, old_width), height),
+        (None, None) => unreachable!("clap requires --width or --height"),
+    };
+    animation.resize(width, height);
+
+    let file = AtomicFile::create_with_options(&args.output, OutputOptions::default())?;
+    animation.encode(file)?.commit()?;
+
+    println!(
+        "resized {}x{} to {}x{} and wrote {}",
+        old_width,
+        old_height,
+        width,
+        height,
+        args.output.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use anyhow::Result;
+
+use std::fmt::Debug;
+use std::io::Read;
+use std::path::Path;
+
+use crate::cli::RetimeArgs;
+use crate::input;
+use jif::encoder::Encoder;
+use jif::output::{AtomicFile, OutputOptions};
+use jif::parser::{Decoder, LoopCount};
+
+pub fn run(args: RetimeArgs) -> Result<()> {
+    let mut decoder = Decoder::new(input::open(&args.input)?);
+    let raw = decoder.parse_raw()?;
+
+    let loop_count = match args.loop_count {
+        Some(0) => Some(LoopCount::Infinite),
+        Some(count) => Some(LoopCount::Number(count)),
+        None => decoder.loop_count(),
+    };
+    let mut encoder = passthrough_encoder(&decoder, &args.output, loop_count)?;
+
+    for (frame, raw) in decoder.frames().iter().zip(&raw) {
+        let mut frame = frame.clone();
+        if let Some(delay) = args.delay {
+            frame.delay_time = delay;
+        }
+        encoder.write_raw_frame(&frame, raw)?;
+    }
+    encoder.finish()?.commit()?;
+
+    println!(
+        "retimed {} frames and wrote {}",
+        raw.len(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+/// An encoder writing to `path` with everything `decoder` read apart from the frames and the
+/// loop count, for copying frames over without decoding them.
+pub fn passthrough_encoder<R: Read + Debug>(
+    decoder: &Decoder<R>,
+    path: &Path,
+    loop_count: Option<LoopCount>,
+) -> Result<Encoder<AtomicFile>> {
+    let (width, height) = decoder.screen_size().unwrap_or((0, 0));
+    let mut encoder = Encoder::create(path, width, height, OutputOptions::default())?;
+    encoder.set_pixel_aspect_ratio(decoder.pixel_aspect_ratio());
+    if let Some(palette) = decoder.global_palette() {
+        encoder.set_global_palette(palette)?;
+    }
+    if let Some(loop_count) = loop_count {
+        encoder.set_loop_count(loop_count);
+    }
+
+    // the loop count is written with the header, everything else is copied as it is
+    for application in decoder.application_extensions() {
+        if application.identifier.as_ref() != "NETSCAPE" {
+            encoder.write_application_extension(application)?;
+        }
+    }
+    for comment in decoder.comments() {
+        encoder.write_comment(comment)?;
+    }
+    Ok(encoder)
+}
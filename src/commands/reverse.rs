@@ -0,0 +1,22 @@
+use anyhow::Result;
+
+
+use crate::cli::LayersArgs;
+use crate::input;
+use jif::animation::Animation;
+use jif::output::{AtomicFile, OutputOptions};
+
+pub fn run(args: LayersArgs) -> Result<()> {
+    let animation = Animation::decode(input::open(&args.input)?)?;
+    let reversed = animation.reverse()?;
+
+    let file = AtomicFile::create_with_options(&args.output, OutputOptions::default())?;
+    reversed.encode(file)?.commit()?;
+
+    println!(
+        "reversed {} frames and wrote {}",
+        reversed.frames().len(),
+        args.output.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use anyhow::{anyhow, Result};
+
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::cli::RunArgs;
+use crate::input;
+use jif::animation::{Animation, Compression};
+use jif::output::{AtomicFile, OutputOptions};
+use jif::pipeline::Pipeline;
+
+pub fn run(args: RunArgs) -> Result<()> {
+    let manifest = fs::read_to_string(&args.pipeline)?;
+    let pipeline = Pipeline::from_toml(&manifest)?;
+
+    // outputs named in the manifest live next to it, not wherever jif happens to be run from
+    let output = match (args.output, &pipeline.output) {
+        (Some(output), _) => output,
+        (None, Some(output)) => args.pipeline.parent().unwrap_or(Path::new("")).join(output),
+        (None, None) => {
+            return Err(anyhow!(
+                "{} doesn't name an output, pass one with --output",
+                args.pipeline.display()
+            ))
+        }
+    };
+
+    // pipelines can hand each other animations in jif's own format, which loses nothing
+    // between them
+    let input = input::open(&args.input)?;
+    let mut animation = if input::is_saved(&args.input) {
+        Animation::load(BufReader::new(input))?
+    } else {
+        Animation::decode(input)?
+    };
+    pipeline.run(&mut animation)?;
+
+    let file = AtomicFile::create_with_options(&output, OutputOptions::default())?;
+    if input::is_saved(&output) {
+        animation.save(file, Compression::default())?.commit()
+    } else {
+        animation.encode(file)?.commit()
+    }
+}
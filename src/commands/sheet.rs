@@ -0,0 +1,86 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::cli::SheetArgs;
+use crate::input;
+use jif::export::{ImageFormat, Png, Ppm};
+use jif::output::{write_atomically, OutputOptions};
+use jif::sheet::{SheetFrame, SpriteSheet};
+
+// what the JSON sidecar holds, everything but the pixels
+#[derive(Serialize)]
+struct Sidecar<'a> {
+    image: String,
+    width: u32,
+    height: u32,
+    columns: u32,
+    rows: u32,
+    frames: &'a [SheetFrame],
+}
+
+pub fn run(args: SheetArgs) -> Result<()> {
+    let output = args.output.unwrap_or_else(|| default_output(&args.input));
+    let encode = match output.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) if extension.eq_ignore_ascii_case(Png::EXTENSION) => Png::encode,
+        Some(extension) if extension.eq_ignore_ascii_case(Ppm::EXTENSION) => Ppm::encode,
+        _ => return Err(anyhow!("{} should end in .png or .ppm", output.display())),
+    };
+
+    let mut animation = input::decode_partial(&args.input)?;
+    if !args.ignore_aspect_ratio {
+        animation.correct_aspect_ratio();
+    }
+    let sheet = match args.columns {
+        Some(columns) => SpriteSheet::new(&animation, columns),
+        None => SpriteSheet::square(&animation),
+    };
+
+    // both formats are written with 16 bit sizes
+    let (Ok(width), Ok(height)) = (u16::try_from(sheet.width), u16::try_from(sheet.height)) else {
+        return Err(anyhow!(
+            "a {}x{} sheet is too large, try a different number of --columns",
+            sheet.width,
+            sheet.height
+        ));
+    };
+    let image = encode(width, height, &sheet.rgba);
+    write_atomically(&output, OutputOptions::default(), |file| {
+        Ok(file.write_all(&image)?)
+    })?;
+
+    if let Some(json) = &args.json {
+        let sidecar = Sidecar {
+            image: output
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            width: sheet.width,
+            height: sheet.height,
+            columns: sheet.columns,
+            rows: sheet.rows,
+            frames: &sheet.frames,
+        };
+        write_atomically(json, OutputOptions::default(), |file| {
+            serde_json::to_writer_pretty(&mut *file, &sidecar)?;
+            Ok(writeln!(file)?)
+        })?;
+    }
+
+    println!(
+        "wrote {} frames in {} columns and {} rows to {}",
+        sheet.frames.len(),
+        sheet.columns,
+        sheet.rows,
+        output.display()
+    );
+    Ok(())
+}
+
+fn default_output(input: &Path) -> PathBuf {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    input.with_file_name(format!("{}.sheet.png", stem))
+}
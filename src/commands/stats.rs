@@ -0,0 +1,290 @@
+use anyhow::Result;
+use log::warn;
+use serde::Serialize;
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::cli::StatsArgs;
+use jif::parser::{Decoder, Summary};
+
+pub fn run(args: StatsArgs) -> Result<()> {
+    let mut paths = Vec::new();
+    collect_gifs(&args.dir, &mut paths)?;
+    paths.sort();
+
+    let mut collector = Collector::default();
+    for path in paths {
+        match scan_file(&path) {
+            Ok((file_size, summary)) => collector.add(file_size, &summary),
+            Err(err) => {
+                warn!("could not scan {}: {:#}", path.display(), err);
+                collector.failed.push(Failure {
+                    path,
+                    error: format!("{:#}", err),
+                });
+            }
+        }
+    }
+
+    let report = collector.finish();
+    let mut stdout = io::stdout().lock();
+    if args.json {
+        serde_json::to_writer_pretty(&mut stdout, &report)?;
+        writeln!(stdout)?;
+    } else {
+        write_table(&mut stdout, &args.dir, &report)?;
+    }
+
+    Ok(())
+}
+
+/// Adds every GIF under `dir` to `paths`, in no particular order.
+pub fn collect_gifs(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        // file_type doesn't follow symlinks, so a link back up the tree can't loop forever
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_gifs(&path, paths)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"))
+        {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn scan_file(path: &Path) -> Result<(u64, Summary)> {
+    let file = File::open(path)?;
+    let file_size = file.metadata()?.len();
+    let summary = Decoder::new(file).scan()?;
+    Ok((file_size, summary))
+}
+
+#[derive(Debug, Default)]
+struct Collector {
+    file_sizes: Vec<f64>,
+    frame_counts: Vec<f64>,
+    durations: Vec<f64>,
+    global_palettes: BTreeMap<usize, usize>,
+    without_global_palette: usize,
+    with_local_palettes: usize,
+    versions: BTreeMap<String, usize>,
+    failed: Vec<Failure>,
+}
+
+impl Collector {
+    fn add(&mut self, file_size: u64, summary: &Summary) {
+        self.file_sizes.push(file_size as f64);
+        self.frame_counts.push(summary.frame_count as f64);
+        self.durations.push(summary.duration.as_secs_f64());
+
+        match summary.global_palette_colors {
+            Some(colors) => *self.global_palettes.entry(colors).or_default() += 1,
+            None => self.without_global_palette += 1,
+        }
+        if summary.local_palette_count > 0 {
+            self.with_local_palettes += 1;
+        }
+
+        *self
+            .versions
+            .entry(summary.version.to_string())
+            .or_default() += 1;
+    }
+
+    fn finish(self) -> Report {
+        Report {
+            files: self.file_sizes.len(),
+            file_size_bytes: Distribution::new(self.file_sizes),
+            frame_count: Distribution::new(self.frame_counts),
+            duration_seconds: Distribution::new(self.durations),
+            global_palette_colors: self.global_palettes,
+            files_without_global_palette: self.without_global_palette,
+            files_with_local_palettes: self.with_local_palettes,
+            versions: self.versions,
+            failed: self.failed,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    /// Files that were scanned successfully.
+    files: usize,
+    file_size_bytes: Option<Distribution>,
+    frame_count: Option<Distribution>,
+    duration_seconds: Option<Distribution>,
+    /// Number of files by the size of their global color table.
+    global_palette_colors: BTreeMap<usize, usize>,
+    files_without_global_palette: usize,
+    files_with_local_palettes: usize,
+    versions: BTreeMap<String, usize>,
+    failed: Vec<Failure>,
+}
+
+#[derive(Debug, Serialize)]
+struct Failure {
+    path: PathBuf,
+    error: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct Distribution {
+    min: f64,
+    median: f64,
+    p90: f64,
+    max: f64,
+    mean: f64,
+}
+
+impl Distribution {
+    /// `None` when there are no values to describe.
+    fn new(mut values: Vec<f64>) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+
+        // nearest-rank percentiles
+        let percentile = |p: f64| {
+            let rank = (p * values.len() as f64).ceil() as usize;
+            values[rank.clamp(1, values.len()) - 1]
+        };
+
+        Some(Self {
+            min: values[0],
+            median: percentile(0.5),
+            p90: percentile(0.9),
+            max: values[values.len() - 1],
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+        })
+    }
+}
+
+fn write_table(out: &mut impl Write, dir: &Path, report: &Report) -> Result<()> {
+    writeln!(
+        out,
+        "{} GIFs in {} ({} could not be read)",
+        report.files,
+        dir.display(),
+        report.failed.len()
+    )?;
+
+    if report.files > 0 {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{:<14}{:>12}{:>12}{:>12}{:>12}{:>12}",
+            "", "min", "median", "p90", "max", "mean"
+        )?;
+        write_row(out, "file size", report.file_size_bytes, format_bytes)?;
+        write_row(out, "frames", report.frame_count, |value| {
+            format!("{:.0}", value)
+        })?;
+        write_row(out, "duration", report.duration_seconds, |value| {
+            format!("{:.2}s", value)
+        })?;
+
+        writeln!(out)?;
+        let versions: Vec<String> = report
+            .versions
+            .iter()
+            .map(|(version, count)| format!("{} {}", version, count))
+            .collect();
+        writeln!(out, "versions:         {}", versions.join(", "))?;
+
+        let mut palettes: Vec<String> = report
+            .global_palette_colors
+            .iter()
+            .map(|(colors, count)| format!("{} colors {}", colors, count))
+            .collect();
+        if report.files_without_global_palette > 0 {
+            palettes.push(format!("none {}", report.files_without_global_palette));
+        }
+        writeln!(out, "global palettes:  {}", palettes.join(", "))?;
+        writeln!(
+            out,
+            "local palettes:   used by {} files",
+            report.files_with_local_palettes
+        )?;
+    }
+
+    if !report.failed.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "could not be read:")?;
+        for failure in &report.failed {
+            writeln!(out, "  {}: {}", failure.path.display(), failure.error)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_row(
+    out: &mut impl Write,
+    name: &str,
+    distribution: Option<Distribution>,
+    format: fn(f64) -> String,
+) -> Result<()> {
+    let Some(distribution) = distribution else {
+        return Ok(());
+    };
+    writeln!(
+        out,
+        "{:<14}{:>12}{:>12}{:>12}{:>12}{:>12}",
+        name,
+        format(distribution.min),
+        format(distribution.median),
+        format(distribution.p90),
+        format(distribution.max),
+        format(distribution.mean),
+    )?;
+    Ok(())
+}
+
+fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{:.0} {}", value, UNITS[unit])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Distribution;
+
+    #[test]
+    fn describes_distributions() {
+        let distribution = Distribution::new(vec![5.0, 1.0, 4.0, 2.0, 3.0]).unwrap();
+        assert_eq!(
+            distribution,
+            Distribution {
+                min: 1.0,
+                median: 3.0,
+                p90: 5.0,
+                max: 5.0,
+                mean: 3.0,
+            }
+        );
+        assert_eq!(Distribution::new(Vec::new()), None);
+    }
+}
@@ -0,0 +1,60 @@
+use anyhow::{anyhow, Result};
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::cli::{ScaleFilter, ThumbArgs};
+use crate::input;
+use jif::export::{ImageFormat, Png, Ppm, Qoi};
+use jif::output::{write_atomically, OutputOptions};
+use jif::parser::Decoder;
+use jif::scale::Filter;
+
+pub fn run(args: ThumbArgs) -> Result<()> {
+    let output = args.output.unwrap_or_else(|| default_output(&args.input));
+    let encode = match output.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) if extension.eq_ignore_ascii_case(Png::EXTENSION) => Png::encode,
+        Some(extension) if extension.eq_ignore_ascii_case(Ppm::EXTENSION) => Ppm::encode,
+        Some(extension) if extension.eq_ignore_ascii_case(Qoi::EXTENSION) => Qoi::encode,
+        _ => {
+            return Err(anyhow!(
+                "{} should end in .png, .ppm or .qoi",
+                output.display()
+            ))
+        }
+    };
+
+    let mut decoder = Decoder::new(input::open(&args.input)?);
+    let mut thumbnail = decoder.decode_first_frame()?;
+    let filter = match args.filter {
+        ScaleFilter::Nearest => Filter::Nearest,
+        ScaleFilter::Box => Filter::Box,
+        ScaleFilter::Bilinear => Filter::Bilinear,
+    };
+    if let Some(pixel_aspect_ratio) = decoder.pixel_aspect_ratio() {
+        if !args.ignore_aspect_ratio {
+            thumbnail = thumbnail.correct_aspect_ratio(pixel_aspect_ratio, filter);
+        }
+    }
+    if let Some(max_size) = args.max_size {
+        thumbnail = thumbnail.fit(max_size, filter);
+    }
+
+    let image = encode(thumbnail.width, thumbnail.height, &thumbnail.rgba);
+    write_atomically(&output, OutputOptions::default(), |file| {
+        Ok(file.write_all(&image)?)
+    })?;
+
+    println!(
+        "wrote a {}x{} thumbnail to {}",
+        thumbnail.width,
+        thumbnail.height,
+        output.display()
+    );
+    Ok(())
+}
+
+fn default_output(input: &Path) -> PathBuf {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    input.with_file_name(format!("{}.thumb.png", stem))
+}
@@ -0,0 +1,475 @@
+use std::borrow::Cow;
+
+use crate::parser::{DecodeBuffers, DisposalMethod, Frame};
+use crate::trace::Span;
+
+/// A rectangle on the logical screen, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub left: u16,
+    pub top: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    pub fn new(left: u16, top: u16, width: u16, height: u16) -> Self {
+        Self {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// The part of `self` that's also inside `other`, empty if they don't overlap.
+    pub fn intersect(&self, other: Rect) -> Rect {
+        let left = self.left.max(other.left);
+        let top = self.top.max(other.top);
+        let right =
+            (self.left as u32 + self.width as u32).min(other.left as u32 + other.width as u32);
+        let bottom =
+            (self.top as u32 + self.height as u32).min(other.top as u32 + other.height as u32);
+
+        Rect {
+            left,
+            top,
+            width: right.saturating_sub(left as u32) as u16,
+            height: bottom.saturating_sub(top as u32) as u16,
+        }
+    }
+
+    /// The smallest rectangle holding both `self` and `other`, ignoring either if it's empty.
+    pub fn union(&self, other: Rect) -> Rect {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return other;
+        }
+
+        let left = self.left.min(other.left);
+        let top = self.top.min(other.top);
+        let right =
+            (self.left as u32 + self.width as u32).max(other.left as u32 + other.width as u32);
+        let bottom =
+            (self.top as u32 + self.height as u32).max(other.top as u32 + other.height as u32);
+
+        Rect {
+            left,
+            top,
+            width: (right - left as u32) as u16,
+            height: (bottom - top as u32) as u16,
+        }
+    }
+}
+
+/// Draws frames onto an RGBA canvas the size of the logical screen, honouring each frame's
+/// position, transparent index and disposal method. After `draw` the canvas holds the image a
+/// viewer would show for that frame.
+///
+/// The canvas only grows down to the lowest row a frame has touched, so a huge logical screen
+/// with small frames doesn't allocate the whole screen up front. Reading it fills in the rest
+/// with transparent pixels.
+#[derive(Debug, Clone)]
+pub struct Compositor {
+    width: u16,
+    height: u16,
+    // the rows drawn on so far, everything below them is transparent
+    canvas: Vec<u8>,
+    pending_disposal: Option<PendingDisposal>,
+    // a canvas restored over by `DisposalMethod::RestoreToPrevious`, kept so the next copy of
+    // the canvas can reuse its memory
+    spare: Vec<u8>,
+}
+
+// the disposal method of a frame only applies right before the next frame is drawn
+#[derive(Debug, Clone)]
+struct PendingDisposal {
+    method: DisposalMethod,
+    left: u16,
+    top: u16,
+    width: u16,
+    height: u16,
+    previous_canvas: Option<Vec<u8>>,
+}
+
+impl Compositor {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            canvas: Vec::new(),
+            pending_disposal: None,
+            spare: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// The whole logical screen as RGBA, only copied if frames haven't reached the bottom.
+    pub fn canvas(&self) -> Cow<'_, [u8]> {
+        let length = self.width as usize * self.height as usize * 4;
+        if self.canvas.len() == length {
+            return Cow::Borrowed(&self.canvas);
+        }
+
+        let mut canvas = Vec::with_capacity(length);
+        canvas.extend_from_slice(&self.canvas);
+        canvas.resize(length, 0);
+        Cow::Owned(canvas)
+    }
+
+    /// Clears the canvas, for starting over at the first frame.
+    pub fn reset(&mut self) {
+        self.canvas.clear();
+        if let Some(previous_canvas) = self
+            .pending_disposal
+            .take()
+            .and_then(|disposal| disposal.previous_canvas)
+        {
+            self.spare = previous_canvas;
+        }
+    }
+
+    /// Disposes of the previously drawn frame, leaving the canvas the next frame is drawn onto.
+    pub fn dispose(&mut self) {
+        self.dispose_previous();
+    }
+
+    /// Disposes of the previously drawn frame and draws `frame` on top of what's left. Returns
+    /// the part of the canvas that may have changed, the frame and whatever was disposed of.
+    pub fn draw(&mut self, frame: &Frame) -> Rect {
+        self.draw_indicies(frame, frame.indicies())
+    }
+
+    /// Like `draw`, for a frame from `Decoder::decode_frame_into` whose indicies were decoded
+    /// into `buffers`.
+    pub fn draw_from(&mut self, frame: &Frame, buffers: &DecodeBuffers) -> Rect {
+        self.draw_indicies(frame, buffers.indicies())
+    }
+
+    fn draw_indicies(&mut self, frame: &Frame, indicies: &[u8]) -> Rect {
+        let mut span = Span::start("composite");
+        span.field("left", frame.left_position);
+        span.field("top", frame.top_position);
+        span.field("width", frame.width);
+        span.field("height", frame.height);
+        let disposed = self.dispose_previous();
+
+        let previous_canvas = (frame.disposal_method == Some(DisposalMethod::RestoreToPrevious))
+            .then(|| {
+                let mut previous_canvas = std::mem::take(&mut self.spare);
+                previous_canvas.clone_from(&self.canvas);
+                previous_canvas
+            });
+
+        self.blit(frame, indicies);
+
+        self.pending_disposal = frame.disposal_method.map(|method| PendingDisposal {
+            method,
+            left: frame.left_position,
+            top: frame.top_position,
+            width: frame.width,
+            height: frame.height,
+            previous_canvas,
+        });
+
+        let screen = Rect::new(0, 0, self.width, self.height);
+        let drawn = Rect::new(
+            frame.left_position,
+            frame.top_position,
+            frame.width,
+            frame.height,
+        );
+        drawn.intersect(screen).union(disposed)
+    }
+
+    // returns the part of the canvas that was disposed of, clipped to the canvas
+    fn dispose_previous(&mut self) -> Rect {
+        let Some(disposal) = self.pending_disposal.take() else {
+            return Rect::new(0, 0, 0, 0);
+        };
+
+        let screen = Rect::new(0, 0, self.width, self.height);
+        let disposed = Rect::new(disposal.left, disposal.top, disposal.width, disposal.height);
+        match disposal.method {
+            DisposalMethod::None | DisposalMethod::DoNotDispose => return Rect::new(0, 0, 0, 0),
+            // like browsers, restore to a transparent background rather than the background color
+            DisposalMethod::RestoreToBackgroundColor => {
+                // rows past the end of the canvas are transparent already
+                let drawn = self.canvas.len();
+                for row in
+                    self.clipped_rows(disposal.left, disposal.top, disposal.width, disposal.height)
+                {
+                    if row.end > drawn {
+                        break;
+                    }
+                    self.canvas[row].fill(0);
+                }
+            }
+            DisposalMethod::RestoreToPrevious => {
+                if let Some(previous_canvas) = disposal.previous_canvas {
+                    self.spare = std::mem::replace(&mut self.canvas, previous_canvas);
+                }
+            }
+        }
+        disposed.intersect(screen)
+    }
+
+    fn blit(&mut self, frame: &Frame, indicies: &[u8]) {
+        let frame_width = frame.width as usize;
+        if frame_width == 0 {
+            return;
+        }
+
+        let rows = self.clipped_rows(
+            frame.left_position,
+            frame.top_position,
+            frame.width,
+            frame.height,
+        );
+        let bottom = (frame.top_position as usize + frame.height as usize).min(self.height as usize);
+        self.grow_to(bottom);
+
+        for (row, canvas_row) in indicies.chunks(frame_width).zip(rows) {
+            let pixels = self.canvas[canvas_row].chunks_exact_mut(4);
+            for (&index, pixel) in row.iter().zip(pixels) {
+                // transparent pixels leave what's under them, which takes in indicies past the
+                // end of the palette with `PaletteIndexPolicy::MapToTransparent`
+                let color = frame.pixel(index).unwrap_or([0, 0, 0, 255]);
+                if color[3] != 0 {
+                    pixel.copy_from_slice(&color);
+                }
+            }
+        }
+    }
+
+    // makes sure the canvas holds the rows above `bottom`, without the spare capacity a
+    // growing `Vec` would usually reserve
+    fn grow_to(&mut self, bottom: usize) {
+        let length = bottom * self.width as usize * 4;
+        if length > self.canvas.len() {
+            self.canvas.reserve_exact(length - self.canvas.len());
+            self.canvas.resize(length, 0);
+        }
+    }
+
+    // byte ranges of the canvas covered by a rectangle, clipped to the canvas
+    fn clipped_rows(
+        &self,
+        left: u16,
+        top: u16,
+        width: u16,
+        height: u16,
+    ) -> impl Iterator<Item = std::ops::Range<usize>> {
+        let canvas_width = self.width as usize;
+        let left = (left as usize).min(canvas_width);
+        let right = (left + width as usize).min(canvas_width);
+        let top = top as usize;
+        let bottom = (top + height as usize).min(self.height as usize);
+
+        (top..bottom).map(move |y| (y * canvas_width + left) * 4..(y * canvas_width + right) * 4)
+    }
+}
+
+/// The smallest rectangle holding every pixel that differs between two composited canvases
+/// `width` x `height` pixels big, e.g. consecutive frames, or `None` if they're the same.
+/// Transparent pixels count as the same whatever their color.
+pub fn dirty_rect(previous: &[u8], next: &[u8], width: u16, height: u16) -> Option<Rect> {
+    let row_length = width as usize * 4;
+    if row_length == 0 {
+        return None;
+    }
+
+    let same = |offset: usize| {
+        let (a, b) = (&previous[offset..offset + 4], &next[offset..offset + 4]);
+        a == b || (a[3] == 0 && b[3] == 0)
+    };
+    let row_differs = |y: usize| {
+        let start = y * row_length;
+        previous[start..start + row_length] != next[start..start + row_length]
+            && (0..width as usize).any(|x| !same(start + x * 4))
+    };
+
+    let rows = height as usize;
+    let top = (0..rows).find(|&y| row_differs(y))?;
+    let bottom = (top..rows).rev().find(|&y| row_differs(y)).unwrap_or(top) + 1;
+
+    // only the columns outside of what's been found so far need checking on each row
+    let (mut left, mut right) = (width as usize, 0);
+    for y in top..bottom {
+        let start = y * row_length;
+        if let Some(x) = (0..left).find(|&x| !same(start + x * 4)) {
+            left = x;
+        }
+        if let Some(x) = (right..width as usize)
+            .rev()
+            .find(|&x| !same(start + x * 4))
+        {
+            right = x + 1;
+        }
+    }
+
+    Some(Rect::new(
+        left as u16,
+        top as u16,
+        (right - left) as u16,
+        (bottom - top) as u16,
+    ))
+}
+
+/// How many pixels differ between two composited canvases, e.g. consecutive frames.
+/// Transparent pixels count as the same whatever their color, like with [`dirty_rect`].
+pub fn changed_pixels(previous: &[u8], next: &[u8]) -> usize {
+    previous
+        .chunks_exact(4)
+        .zip(next.chunks_exact(4))
+        .filter(|(previous, next)| previous != next && (previous[3] != 0 || next[3] != 0))
+        .count()
+}
+
+/// `over` alpha blended on top of `under`, both RGBA pixels. GIFs only have fully transparent
+/// and opaque pixels, so the result is opaque unless `under` is transparent and `over` is
+/// less than half opaque, which leaves `under` as it is.
+pub fn blend_over(under: [u8; 4], over: [u8; 4]) -> [u8; 4] {
+    let alpha = over[3] as u32;
+    if under[3] == 0 {
+        return match alpha {
+            0..=127 => under,
+            _ => [over[0], over[1], over[2], 255],
+        };
+    }
+
+    let mix = |under: u8, over: u8| {
+        ((over as u32 * alpha + under as u32 * (255 - alpha) + 127) / 255) as u8
+    };
+    [
+        mix(under[0], over[0]),
+        mix(under[1], over[1]),
+        mix(under[2], over[2]),
+        255,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{blend_over, dirty_rect, Compositor, Rect};
+    use crate::parser::{DisposalMethod, Frame};
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const TRANSPARENT: [u8; 4] = [0, 0, 0, 0];
+
+    fn pixel(compositor: &Compositor, x: usize, y: usize) -> [u8; 4] {
+        let offset = (y * compositor.width() as usize + x) * 4;
+        compositor.canvas()[offset..offset + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn applies_disposal_methods() {
+        let palette: Box<[u8]> = Box::new([255, 0, 0, 0, 0, 255]);
+        let mut compositor = Compositor::new(2, 2);
+
+        let mut background = Frame::new(2, 2, Box::new([0; 4]), palette.clone());
+        background.disposal_method = Some(DisposalMethod::DoNotDispose);
+        assert_eq!(compositor.draw(&background), Rect::new(0, 0, 2, 2));
+        assert_eq!(pixel(&compositor, 1, 1), RED);
+
+        // a blue pixel in the corner with a transparent neighbour, put back afterwards
+        let mut overlay = Frame::new(2, 1, Box::new([1, 0]), palette.clone());
+        overlay.left_position = 0;
+        overlay.top_position = 1;
+        overlay.transparent_color_index = Some(0);
+        overlay.disposal_method = Some(DisposalMethod::RestoreToPrevious);
+        assert_eq!(compositor.draw(&overlay), Rect::new(0, 1, 2, 1));
+        assert_eq!(pixel(&compositor, 0, 1), BLUE);
+        assert_eq!(pixel(&compositor, 1, 1), RED);
+
+        // hanging off the edge of the canvas, cleared afterwards
+        let mut clipped = Frame::new(2, 2, Box::new([1; 4]), palette.clone());
+        clipped.left_position = 1;
+        clipped.top_position = 1;
+        clipped.disposal_method = Some(DisposalMethod::RestoreToBackgroundColor);
+        // what changes is the restored row, as the rest of the frame is off the canvas
+        assert_eq!(compositor.draw(&clipped), Rect::new(0, 1, 2, 1));
+        assert_eq!(pixel(&compositor, 0, 1), RED);
+        assert_eq!(pixel(&compositor, 1, 1), BLUE);
+
+        let mut empty = Frame::new(1, 1, Box::new([0]), palette);
+        empty.transparent_color_index = Some(0);
+        assert_eq!(compositor.draw(&empty), Rect::new(0, 0, 2, 2));
+        assert_eq!(pixel(&compositor, 0, 0), RED);
+        assert_eq!(pixel(&compositor, 1, 1), TRANSPARENT);
+    }
+
+    #[test]
+    fn grows_the_canvas_as_frames_are_drawn() {
+        let palette: Box<[u8]> = Box::new([255, 0, 0, 0, 0, 255]);
+        let mut compositor = Compositor::new(60000, 60000);
+        assert!(compositor.canvas.is_empty());
+
+        let mut frame = Frame::new(1, 2, Box::new([0, 1]), palette);
+        frame.left_position = 3;
+        frame.disposal_method = Some(DisposalMethod::RestoreToPrevious);
+        compositor.draw(&frame);
+        assert_eq!(compositor.canvas.len(), 2 * 60000 * 4);
+        let offset = (60000 + 3) * 4;
+        assert_eq!(compositor.canvas[offset..offset + 4], BLUE);
+
+        // putting back the empty canvas shrinks it again
+        compositor.dispose();
+        assert!(compositor.canvas.is_empty());
+    }
+
+    #[test]
+    fn finds_the_changed_rectangle() {
+        let canvas = [RED; 12].concat();
+        assert_eq!(dirty_rect(&canvas, &canvas, 4, 3), None);
+
+        let mut next = canvas.clone();
+        let set = |canvas: &mut Vec<u8>, x: usize, y: usize, pixel: [u8; 4]| {
+            let offset = (y * 4 + x) * 4;
+            canvas[offset..offset + 4].copy_from_slice(&pixel);
+        };
+        set(&mut next, 2, 1, BLUE);
+        assert_eq!(
+            dirty_rect(&canvas, &next, 4, 3),
+            Some(Rect::new(2, 1, 1, 1))
+        );
+        set(&mut next, 0, 2, TRANSPARENT);
+        set(&mut next, 3, 2, BLUE);
+        assert_eq!(
+            dirty_rect(&canvas, &next, 4, 3),
+            Some(Rect::new(0, 1, 4, 2))
+        );
+
+        // transparent is transparent, whatever color it was left with
+        let clear = [TRANSPARENT; 12].concat();
+        let mut tinted = clear.clone();
+        set(&mut tinted, 1, 1, [255, 0, 0, 0]);
+        assert_eq!(dirty_rect(&clear, &tinted, 4, 3), None);
+        assert_eq!(dirty_rect(&[], &[], 0, 0), None);
+    }
+
+    #[test]
+    fn blends_pixels_over_the_canvas() {
+        assert_eq!(blend_over(RED, BLUE), BLUE);
+        assert_eq!(blend_over(RED, [0, 0, 255, 0]), RED);
+        assert_eq!(blend_over(RED, [0, 0, 255, 128]), [127, 0, 128, 255]);
+
+        // transparent pixels only show what's mostly opaque
+        assert_eq!(blend_over(TRANSPARENT, [0, 0, 255, 100]), TRANSPARENT);
+        assert_eq!(blend_over(TRANSPARENT, [0, 0, 255, 200]), BLUE);
+    }
+}
@@ -0,0 +1,265 @@
+//! Non-destructive editing. Edits are recorded as a list of operations that can be undone and
+//! redone freely, and are only applied to the pixels when the animation is exported.
+
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::animation::{Animation, CompositedFrames};
+use crate::compositor::Rect;
+use crate::parser::frame_duration;
+
+/// One operation on an animation. Each edit applies to what the edits before it left, so a
+/// crop's position or a trim's frame numbers are relative to the result of any earlier crop
+/// or trim.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edit {
+    Crop(Rect),
+    /// Keeps frames `start..end`.
+    Trim {
+        start: usize,
+        end: usize,
+    },
+    /// Multiplies the playback speed, 2.0 plays twice as fast. Factors that aren't positive
+    /// are ignored.
+    Speed(f32),
+}
+
+/// Every edit made to an animation, with a redo stack of the ones that have been undone.
+#[derive(Debug, Clone, Default)]
+pub struct EditList {
+    edits: Vec<Edit>,
+    undone: Vec<Edit>,
+}
+
+impl EditList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records `edit`. Anything that was undone can't be redone after this.
+    pub fn push(&mut self, edit: Edit) {
+        self.edits.push(edit);
+        self.undone.clear();
+    }
+
+    /// Takes back the most recent edit, returning it.
+    pub fn undo(&mut self) -> Option<Edit> {
+        let edit = self.edits.pop()?;
+        self.undone.push(edit);
+        Some(edit)
+    }
+
+    /// Puts back the most recently undone edit, returning it.
+    pub fn redo(&mut self) -> Option<Edit> {
+        let edit = self.undone.pop()?;
+        self.edits.push(edit);
+        Some(edit)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.edits.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    pub fn edits(&self) -> &[Edit] {
+        &self.edits
+    }
+
+    /// What the edits add up to for a `width`x`height` animation of `frame_count` frames.
+    pub fn plan(&self, width: u16, height: u16, frame_count: usize) -> EditPlan {
+        let mut plan = EditPlan {
+            crop: Rect::new(0, 0, width, height),
+            frames: 0..frame_count,
+            speed: 1.0,
+        };
+
+        for edit in &self.edits {
+            match *edit {
+                Edit::Crop(rect) => {
+                    let moved = Rect::new(
+                        plan.crop.left.saturating_add(rect.left),
+                        plan.crop.top.saturating_add(rect.top),
+                        rect.width,
+                        rect.height,
+                    );
+                    plan.crop = moved.intersect(plan.crop);
+                }
+                Edit::Trim { start, end } => {
+                    let kept = plan.frames.clone();
+                    let start = kept.start.saturating_add(start).min(kept.end);
+                    let end = kept.start.saturating_add(end).clamp(start, kept.end);
+                    plan.frames = start..end;
+                }
+                Edit::Speed(factor) => {
+                    if factor.is_finite() && factor > 0.0 {
+                        plan.speed *= factor;
+                    }
+                }
+            }
+        }
+
+        plan
+    }
+
+    /// The frames of `animation` with every edit applied. Frames are composited, cropped and
+    /// retimed one at a time as the iterator is advanced.
+    pub fn apply<'a>(&self, animation: &'a Animation) -> EditedFrames<'a> {
+        let plan = self.plan(
+            animation.width(),
+            animation.height(),
+            animation.frames().len(),
+        );
+
+        EditedFrames {
+            animation,
+            composited: animation.composited_frames(),
+            index: 0,
+            plan,
+        }
+    }
+}
+
+/// The combined effect of an `EditList`, in terms of the original animation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditPlan {
+    /// The visible part of the logical screen.
+    pub crop: Rect,
+    /// Indicies of the frames that are kept.
+    pub frames: Range<usize>,
+    pub speed: f32,
+}
+
+impl EditPlan {
+    /// How long a frame with a GIF delay of `delay_time` hundredths of a second is shown for,
+    /// see [`frame_duration`].
+    pub fn delay(&self, delay_time: u16) -> Duration {
+        frame_duration(delay_time).div_f64(self.speed as f64)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditedFrame {
+    pub rgba: Vec<u8>,
+    pub delay: Duration,
+}
+
+pub struct EditedFrames<'a> {
+    animation: &'a Animation,
+    composited: CompositedFrames<'a>,
+    index: usize,
+    plan: EditPlan,
+}
+
+impl EditedFrames<'_> {
+    pub fn plan(&self) -> &EditPlan {
+        &self.plan
+    }
+
+    pub fn width(&self) -> u16 {
+        self.plan.crop.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.plan.crop.height
+    }
+
+    fn crop(&self, canvas: &[u8]) -> Vec<u8> {
+        let crop = self.plan.crop;
+        let canvas_width = self.animation.width() as usize;
+        let mut rgba = Vec::with_capacity(crop.width as usize * crop.height as usize * 4);
+
+        for y in crop.top as usize..(crop.top + crop.height) as usize {
+            let start = (y * canvas_width + crop.left as usize) * 4;
+            rgba.extend_from_slice(&canvas[start..start + crop.width as usize * 4]);
+        }
+
+        rgba
+    }
+}
+
+impl Iterator for EditedFrames<'_> {
+    type Item = EditedFrame;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // frames before the trim still have to be drawn, later ones can build on them
+        while self.index < self.plan.frames.end {
+            let canvas = self.composited.next()?;
+            let index = self.index;
+            self.index += 1;
+
+            if index < self.plan.frames.start {
+                continue;
+            }
+
+            let delay_time = self.animation.frames()[index].delay_time;
+            return Some(EditedFrame {
+                rgba: self.crop(&canvas),
+                delay: self.plan.delay(delay_time),
+            });
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Edit, EditList};
+    use crate::animation::Animation;
+    use crate::compositor::Rect;
+    use crate::fixtures::{two_color_frame, two_color_gif};
+
+    use std::time::Duration;
+
+    #[test]
+    fn undoes_and_redoes_edits() {
+        let mut edits = EditList::new();
+        edits.push(Edit::Speed(2.0));
+        edits.push(Edit::Trim { start: 1, end: 3 });
+        assert_eq!(edits.undo(), Some(Edit::Trim { start: 1, end: 3 }));
+        assert_eq!(edits.plan(4, 4, 4).frames, 0..4);
+        assert_eq!(edits.redo(), Some(Edit::Trim { start: 1, end: 3 }));
+        assert_eq!(edits.plan(4, 4, 4).frames, 1..3);
+
+        // a new edit drops whatever could have been redone
+        edits.undo();
+        edits.push(Edit::Speed(0.5));
+        assert!(!edits.can_redo());
+        assert_eq!(edits.plan(4, 4, 4).speed, 1.0);
+
+        assert_eq!(edits.undo(), Some(Edit::Speed(0.5)));
+        assert_eq!(edits.undo(), Some(Edit::Speed(2.0)));
+        assert_eq!(edits.undo(), None);
+    }
+
+    #[test]
+    fn applies_edits_when_exporting() {
+        let frames = [[0, 0, 0, 0], [0, 1, 0, 1], [1, 1, 1, 1]].map(|indicies| {
+            let mut frame = two_color_frame(2, 2, &indicies);
+            frame.delay_time = 10;
+            frame
+        });
+        let animation = Animation::decode(two_color_gif(2, 2, &frames).as_slice()).unwrap();
+
+        let mut edits = EditList::new();
+        edits.push(Edit::Trim { start: 1, end: 3 });
+        edits.push(Edit::Crop(Rect::new(1, 0, 1, 2)));
+        edits.push(Edit::Speed(2.0));
+        // the second crop is relative to the first one
+        edits.push(Edit::Crop(Rect::new(0, 1, 5, 5)));
+
+        let frames = edits.apply(&animation);
+        assert_eq!((frames.width(), frames.height()), (1, 1));
+
+        let frames: Vec<_> = frames.collect();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].rgba, [0, 0, 255, 255]);
+        assert_eq!(frames[1].rgba, [0, 0, 255, 255]);
+        assert_eq!(frames[0].delay, Duration::from_millis(50));
+        // no delay plays for a tenth of a second, before it's sped up
+        assert_eq!(edits.plan(2, 2, 3).delay(0), Duration::from_millis(50));
+    }
+}
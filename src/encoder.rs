@@ -0,0 +1,736 @@
+// there's no file system on wasm32-unknown-unknown
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+mod file;
+#[doc(hidden)]
+pub mod lzw;
+
+use anyhow::Result;
+use log::debug;
+use thiserror::Error;
+
+use std::io::prelude::*;
+
+use crate::parser::{ApplicationExtension, DisposalMethod, Frame, LoopCount, RawImageData};
+
+const EXTENSION_INTRODUCER: u8 = 0x21;
+const IMAGE_DESCRIPTOR_LABEL: u8 = 0x2c;
+const TRAILER_LABEL: u8 = 0x3b;
+
+const APPLICATION_EXTENSION: u8 = 0xff;
+const COMMENT_EXTENSION: u8 = 0xfe;
+const GRAPHIC_CONTROL_EXTENSION: u8 = 0xf9;
+
+const CHECKPOINT_MAGIC: &[u8] = b"JIFCKPT";
+const CHECKPOINT_VERSION: u8 = 1;
+
+#[derive(Error, Debug)]
+enum EncoderError {
+    #[error("frame has no local palette and the encoder has no global palette")]
+    MissingPalette,
+
+    #[error("palette has {0} bytes, expected a multiple of 3 with at most 256 colors")]
+    InvalidPalette(usize),
+
+    #[error("frame is {width}x{height} but has {actual} indicies")]
+    IndexCountMismatch {
+        width: u16,
+        height: u16,
+        actual: usize,
+    },
+
+    #[error("checkpoint is invalid: {0}")]
+    InvalidCheckpoint(&'static str),
+
+    #[error("{0} can't be written to a GIF87a file")]
+    NeedsVersion89a(&'static str),
+}
+
+/// The version of the GIF format an encoder writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TargetVersion {
+    /// The original format, without delays, transparency, disposal methods, loop counts or
+    /// extensions of any kind. Only readers too old for GIF89a need it.
+    V87a,
+    #[default]
+    V89a,
+}
+
+impl TargetVersion {
+    /// The oldest version that holds `frames` and `loop_count` without leaving anything out.
+    pub fn oldest_for(frames: &[Frame], loop_count: Option<LoopCount>) -> Self {
+        if loop_count.is_some() || frames.iter().any(needs_graphic_control) {
+            TargetVersion::V89a
+        } else {
+            TargetVersion::V87a
+        }
+    }
+}
+
+/// Everything needed to pick up an interrupted encode where it left off. The output up to
+/// `bytes_written` is a valid GIF stream minus the trailer, so resuming only means truncating the
+/// output to that length and appending frames starting at `frames_written`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncoderCheckpoint {
+    pub width: u16,
+    pub height: u16,
+    pub frames_written: u64,
+    pub bytes_written: u64,
+    global_palette: Option<Box<[u8]>>,
+}
+
+impl EncoderCheckpoint {
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(CHECKPOINT_MAGIC)?;
+        writer.write_all(&[CHECKPOINT_VERSION])?;
+        writer.write_all(&self.width.to_le_bytes())?;
+        writer.write_all(&self.height.to_le_bytes())?;
+        writer.write_all(&self.frames_written.to_le_bytes())?;
+        writer.write_all(&self.bytes_written.to_le_bytes())?;
+
+        let palette = self.global_palette.as_deref().unwrap_or_default();
+        writer.write_all(&(palette.len() as u16).to_le_bytes())?;
+        writer.write_all(palette)?;
+
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self> {
+        let mut magic = [0; 7];
+        reader.read_exact(&mut magic)?;
+        if magic != CHECKPOINT_MAGIC {
+            return Err(EncoderError::InvalidCheckpoint("bad magic").into());
+        }
+
+        let mut version = [0; 1];
+        reader.read_exact(&mut version)?;
+        if version[0] != CHECKPOINT_VERSION {
+            return Err(EncoderError::InvalidCheckpoint("unsupported version").into());
+        }
+
+        let mut u16_buffer = [0; 2];
+        let mut u64_buffer = [0; 8];
+
+        reader.read_exact(&mut u16_buffer)?;
+        let width = u16::from_le_bytes(u16_buffer);
+        reader.read_exact(&mut u16_buffer)?;
+        let height = u16::from_le_bytes(u16_buffer);
+        reader.read_exact(&mut u64_buffer)?;
+        let frames_written = u64::from_le_bytes(u64_buffer);
+        reader.read_exact(&mut u64_buffer)?;
+        let bytes_written = u64::from_le_bytes(u64_buffer);
+
+        reader.read_exact(&mut u16_buffer)?;
+        let palette_length = u16::from_le_bytes(u16_buffer) as usize;
+        let global_palette = if palette_length == 0 {
+            None
+        } else {
+            let mut palette = vec![0; palette_length];
+            reader.read_exact(&mut palette)?;
+            Some(palette.into_boxed_slice())
+        };
+
+        Ok(Self {
+            width,
+            height,
+            frames_written,
+            bytes_written,
+            global_palette,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Encoder<W: Write> {
+    inner: W,
+    width: u16,
+    height: u16,
+    global_palette: Option<Box<[u8]>>,
+    loop_count: Option<LoopCount>,
+    pixel_aspect_ratio: u8,
+    target_version: TargetVersion,
+    header_written: bool,
+    frames_written: u64,
+    bytes_written: u64,
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(inner: W, width: u16, height: u16) -> Self {
+        Self {
+            inner,
+            width,
+            height,
+            global_palette: None,
+            loop_count: None,
+            pixel_aspect_ratio: 0,
+            target_version: TargetVersion::default(),
+            header_written: false,
+            frames_written: 0,
+            bytes_written: 0,
+        }
+    }
+
+    /// Continues an encode from `checkpoint`. `inner` must be positioned right after the bytes
+    /// covered by the checkpoint.
+    pub fn resume(inner: W, checkpoint: &EncoderCheckpoint) -> Self {
+        Self {
+            inner,
+            width: checkpoint.width,
+            height: checkpoint.height,
+            global_palette: checkpoint.global_palette.clone(),
+            loop_count: None,
+            pixel_aspect_ratio: 0,
+            target_version: TargetVersion::default(),
+            header_written: true,
+            frames_written: checkpoint.frames_written,
+            bytes_written: checkpoint.bytes_written,
+        }
+    }
+
+    /// Must be called before the first frame is written, it has no effect afterwards.
+    pub fn set_global_palette(&mut self, palette: &[u8]) -> Result<()> {
+        validate_palette(palette)?;
+        self.global_palette = Some(palette.into());
+        Ok(())
+    }
+
+    /// Must be called before the first frame is written, it has no effect afterwards.
+    pub fn set_loop_count(&mut self, loop_count: LoopCount) {
+        self.loop_count = Some(loop_count);
+    }
+
+    /// Must be called before the first frame is written, it has no effect afterwards. The width
+    /// of a pixel over its height is stored in steps of 1/64 from 1/4 to a little over 4, see
+    /// [`crate::parser::pixel_aspect_ratio`], and `None` leaves it unsaid.
+    pub fn set_pixel_aspect_ratio(&mut self, pixel_aspect_ratio: Option<f32>) {
+        self.pixel_aspect_ratio = pixel_aspect_ratio_byte(pixel_aspect_ratio);
+    }
+
+    /// Must be called before the first frame is written. Writing anything the version doesn't
+    /// have fails, see [`TargetVersion::oldest_for`]. Checkpoints don't keep it, so it has to
+    /// be set again after `resume`.
+    pub fn set_target_version(&mut self, target_version: TargetVersion) {
+        self.target_version = target_version;
+    }
+
+    pub fn frames_written(&self) -> u64 {
+        self.frames_written
+    }
+
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        if !self.header_written {
+            self.write_header()?;
+        }
+
+        let expected_length = frame.width as usize * frame.height as usize;
+        if frame.indicies().len() != expected_length {
+            return Err(EncoderError::IndexCountMismatch {
+                width: frame.width,
+                height: frame.height,
+                actual: frame.indicies().len(),
+            }
+            .into());
+        }
+
+        let frame_palette = frame.palette().ok_or(EncoderError::MissingPalette)?;
+        validate_palette(frame_palette)?;
+
+        // frames that share the global palette don't need to carry their own copy.
+        let local_palette = match self.global_palette.as_deref() {
+            Some(global_palette) if global_palette == frame_palette => None,
+            _ => Some(frame_palette),
+        };
+
+        self.write_graphic_control_extension(frame)?;
+        self.write_image_descriptor(frame, local_palette, false)?;
+
+        // indicies past the end of the palette, which broken GIFs can have, still need a code
+        // of their own, rather than being written as the clear or end of information code
+        let largest_index = frame.indicies().iter().copied().max().unwrap_or(0);
+        let minimum_code_size = minimum_code_size(frame_palette).max(index_bits(largest_index));
+        let data = lzw::lzw_encode(frame.indicies(), minimum_code_size);
+
+        self.write_all(&[minimum_code_size as u8])?;
+        self.write_data_sub_blocks(&data)?;
+
+        self.frames_written += 1;
+        debug!(
+            "encoded frame {}, {} bytes of image data",
+            self.frames_written,
+            data.len()
+        );
+
+        Ok(())
+    }
+
+    /// Writes a frame with image data that's already compressed, like `Decoder::parse_raw`
+    /// returns it, instead of the frame's indicies. A frame without a local palette uses the
+    /// global one, which has to be the one the image data was compressed for.
+    pub fn write_raw_frame(&mut self, frame: &Frame, raw: &RawImageData) -> Result<()> {
+        if !self.header_written {
+            self.write_header()?;
+        }
+
+        let local_palette = frame.palette().filter(|_| frame.has_local_palette());
+        match local_palette {
+            Some(palette) => validate_palette(palette)?,
+            None if self.global_palette.is_none() => {
+                return Err(EncoderError::MissingPalette.into())
+            }
+            None => {}
+        }
+
+        self.write_graphic_control_extension(frame)?;
+        self.write_image_descriptor(frame, local_palette, raw.interlaced)?;
+        self.write_all(&[raw.lzw_code_size])?;
+        self.write_data_sub_blocks(&raw.data)?;
+
+        self.frames_written += 1;
+        debug!(
+            "copied frame {}, {} bytes of image data",
+            self.frames_written,
+            raw.data.len()
+        );
+
+        Ok(())
+    }
+
+    /// Writes an application extension with its data split into sub-blocks the way it was
+    /// read, which XMP packets depend on. Loop counts are written with `set_loop_count` instead.
+    pub fn write_application_extension(&mut self, extension: &ApplicationExtension) -> Result<()> {
+        self.require_version_89a("an application extension")?;
+        if !self.header_written {
+            self.write_header()?;
+        }
+
+        let mut block = vec![EXTENSION_INTRODUCER, APPLICATION_EXTENSION, 11];
+        let mut identifier = extension.identifier.as_bytes().to_vec();
+        identifier.resize(8, b' ');
+        block.extend_from_slice(&identifier);
+        let mut authentication_code = extension.authentication_code.to_vec();
+        authentication_code.resize(3, 0);
+        block.extend_from_slice(&authentication_code);
+        for sub_block in extension
+            .sub_blocks()
+            .filter(|sub_block| !sub_block.is_empty())
+        {
+            block.push(sub_block.len() as u8);
+            block.extend_from_slice(sub_block);
+        }
+        block.push(0);
+
+        self.write_all(&block)
+    }
+
+    pub fn write_comment(&mut self, comment: &[u8]) -> Result<()> {
+        self.require_version_89a("a comment")?;
+        if !self.header_written {
+            self.write_header()?;
+        }
+
+        self.write_all(&[EXTENSION_INTRODUCER, COMMENT_EXTENSION])?;
+        self.write_data_sub_blocks(comment)
+    }
+
+    /// Flushes everything written so far and returns a checkpoint that `Encoder::resume` can
+    /// continue from.
+    pub fn checkpoint(&mut self) -> Result<EncoderCheckpoint> {
+        if !self.header_written {
+            self.write_header()?;
+        }
+        self.inner.flush()?;
+
+        Ok(EncoderCheckpoint {
+            width: self.width,
+            height: self.height,
+            frames_written: self.frames_written,
+            bytes_written: self.bytes_written,
+            global_palette: self.global_palette.clone(),
+        })
+    }
+
+    /// Writes the trailer and hands back the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        if !self.header_written {
+            self.write_header()?;
+        }
+        self.write_all(&[TRAILER_LABEL])?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        if self.loop_count.is_some() {
+            self.require_version_89a("a loop count")?;
+        }
+
+        let mut header = match self.target_version {
+            TargetVersion::V87a => b"GIF87a".to_vec(),
+            TargetVersion::V89a => b"GIF89a".to_vec(),
+        };
+        header.extend_from_slice(&self.width.to_le_bytes());
+        header.extend_from_slice(&self.height.to_le_bytes());
+
+        let packed_fields = match self.global_palette.as_deref() {
+            // color resolution is always reported as 8 bits, nobody uses it.
+            Some(palette) => 0b11110000 | color_table_size_bits(palette),
+            None => 0b01110000,
+        };
+        header.push(packed_fields);
+        // background color index and pixel aspect ratio
+        header.extend_from_slice(&[0, self.pixel_aspect_ratio]);
+        self.write_all(&header)?;
+
+        if let Some(palette) = self.global_palette.clone() {
+            self.write_color_table(&palette)?;
+        }
+
+        if let Some(loop_count) = self.loop_count {
+            let loop_number = match loop_count {
+                LoopCount::Infinite => 0,
+                LoopCount::Number(number) => number,
+            };
+
+            let mut extension = vec![EXTENSION_INTRODUCER, APPLICATION_EXTENSION, 11];
+            extension.extend_from_slice(b"NETSCAPE2.0");
+            extension.extend_from_slice(&[3, 1]);
+            extension.extend_from_slice(&loop_number.to_le_bytes());
+            extension.push(0);
+            self.write_all(&extension)?;
+        }
+
+        self.header_written = true;
+        Ok(())
+    }
+
+    fn write_graphic_control_extension(&mut self, frame: &Frame) -> Result<()> {
+        if self.target_version == TargetVersion::V87a {
+            return match needs_graphic_control(frame) {
+                true => Err(EncoderError::NeedsVersion89a(
+                    "a frame with a delay, transparency, disposal method or waiting for input",
+                )
+                .into()),
+                false => Ok(()),
+            };
+        }
+
+        let disposal_method = frame.disposal_method.unwrap_or(DisposalMethod::None) as u8;
+
+        let mut packed_fields = disposal_method << 2;
+        if frame.needs_user_input {
+            packed_fields |= 0b00000010;
+        }
+        if frame.transparent_color_index.is_some() {
+            packed_fields |= 0b00000001;
+        }
+
+        let mut extension = vec![EXTENSION_INTRODUCER, GRAPHIC_CONTROL_EXTENSION, 4, packed_fields];
+        extension.extend_from_slice(&frame.delay_time.to_le_bytes());
+        extension.push(frame.transparent_color_index.unwrap_or(0));
+        extension.push(0);
+
+        self.write_all(&extension)
+    }
+
+    fn write_image_descriptor(
+        &mut self,
+        frame: &Frame,
+        local_palette: Option<&[u8]>,
+        interlaced: bool,
+    ) -> Result<()> {
+        let mut packed_fields = 0;
+        if let Some(palette) = local_palette {
+            packed_fields |= 0b10000000 | color_table_size_bits(palette);
+        }
+        if interlaced {
+            packed_fields |= 0b01000000;
+        }
+
+        let mut descriptor = vec![IMAGE_DESCRIPTOR_LABEL];
+        descriptor.extend_from_slice(&frame.left_position.to_le_bytes());
+        descriptor.extend_from_slice(&frame.top_position.to_le_bytes());
+        descriptor.extend_from_slice(&frame.width.to_le_bytes());
+        descriptor.extend_from_slice(&frame.height.to_le_bytes());
+        descriptor.push(packed_fields);
+        self.write_all(&descriptor)?;
+
+        if let Some(palette) = local_palette {
+            self.write_color_table(palette)?;
+        }
+        Ok(())
+    }
+
+    fn write_color_table(&mut self, palette: &[u8]) -> Result<()> {
+        // color tables always hold a power of two number of colors, pad the rest with black.
+        let table_length = 3 * (1 << (color_table_size_bits(palette) + 1));
+        let mut table = palette.to_vec();
+        table.resize(table_length, 0);
+
+        self.write_all(&table)
+    }
+
+    fn write_data_sub_blocks(&mut self, data: &[u8]) -> Result<()> {
+        for chunk in data.chunks(255) {
+            self.write_all(&[chunk.len() as u8])?;
+            self.write_all(chunk)?;
+        }
+
+        // block terminator
+        self.write_all(&[0])
+    }
+
+    fn require_version_89a(&self, what: &'static str) -> Result<()> {
+        match self.target_version {
+            TargetVersion::V87a => Err(EncoderError::NeedsVersion89a(what).into()),
+            TargetVersion::V89a => Ok(()),
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.inner.write_all(buf)?;
+        self.bytes_written += buf.len() as u64;
+        Ok(())
+    }
+}
+
+// whether a frame needs a graphic control extension, which came with GIF89a
+fn needs_graphic_control(frame: &Frame) -> bool {
+    frame.delay_time != 0
+        || frame.needs_user_input
+        || frame.transparent_color_index.is_some()
+        || !matches!(frame.disposal_method, None | Some(DisposalMethod::None))
+}
+
+fn validate_palette(palette: &[u8]) -> Result<()> {
+    if palette.is_empty() || !palette.len().is_multiple_of(3) || palette.len() > 3 * 256 {
+        return Err(EncoderError::InvalidPalette(palette.len()).into());
+    }
+    Ok(())
+}
+
+// the 3 bit size field stored in the packed fields, the table holds 2^(n+1) colors.
+fn color_table_size_bits(palette: &[u8]) -> u8 {
+    let colors = palette.len() / 3;
+    let mut bits = 0;
+    while (1 << (bits + 1)) < colors {
+        bits += 1;
+    }
+    bits
+}
+
+pub(crate) fn minimum_code_size(palette: &[u8]) -> u32 {
+    // the spec doesn't allow a minimum code size below 2, even for 2 color images.
+    (color_table_size_bits(palette) as u32 + 1).max(2)
+}
+
+// how many bits `index` takes up
+fn index_bits(index: u8) -> u32 {
+    u8::BITS - index.leading_zeros()
+}
+
+// the inverse of `parser::pixel_aspect_ratio`, rounded to the closest ratio that can be stored
+pub(crate) fn pixel_aspect_ratio_byte(pixel_aspect_ratio: Option<f32>) -> u8 {
+    match pixel_aspect_ratio {
+        Some(ratio) => (ratio * 64.0 - 15.0).round().clamp(1.0, 255.0) as u8,
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Encoder, EncoderCheckpoint, TargetVersion};
+    use crate::fixtures::{encode, two_color_frame};
+    use crate::output::OutputOptions;
+    use crate::parser::{Decoder, Frame, LoopCount, Version};
+
+    use std::fs::{self, File};
+    use std::io::Cursor;
+
+    fn test_frame(seed: u8) -> Frame {
+        let palette: Box<[u8]> = (0..16 * 3).map(|i| i as u8).collect();
+        let indicies: Box<[u8]> = (0..40 * 30)
+            .map(|i: u32| ((i / 7 + seed as u32) % 16) as u8)
+            .collect();
+
+        let mut frame = Frame::new(40, 30, indicies, palette);
+        frame.delay_time = 10;
+        frame
+    }
+
+    #[test]
+    fn round_trips_through_decoder() {
+        let frames: Vec<Frame> = (0..3).map(test_frame).collect();
+
+        let mut encoder = Encoder::new(Vec::new(), 40, 30);
+        encoder.set_loop_count(LoopCount::Infinite);
+        for frame in &frames {
+            encoder.write_frame(frame).unwrap();
+        }
+        let output = encoder.finish().unwrap();
+
+        let mut decoder = Decoder::new(Cursor::new(output));
+        decoder.parse().unwrap();
+
+        assert_eq!(decoder.loop_count(), Some(LoopCount::Infinite));
+        assert_eq!(decoder.frames().len(), frames.len());
+        for (decoded, original) in decoder.frames().iter().zip(&frames) {
+            assert_eq!(decoded.indicies(), original.indicies());
+            assert_eq!(decoded.delay_time, original.delay_time);
+        }
+    }
+
+    #[test]
+    fn keeps_indicies_past_the_palette() {
+        // 2 colors make for a code size of 2, where 5 would be the end of information code
+        let frame = two_color_frame(4, 1, &[0, 5, 1, 5]);
+        let gif = encode(4, 1, None, &[frame]);
+
+        let mut decoder = Decoder::new(Cursor::new(gif));
+        decoder.parse().unwrap();
+        assert_eq!(decoder.frames()[0].indicies(), [0, 5, 1, 5]);
+    }
+
+    #[test]
+    fn resumes_from_checkpoint() {
+        let frames: Vec<Frame> = (0..4).map(test_frame).collect();
+
+        let mut encoder = Encoder::new(Vec::new(), 40, 30);
+        encoder.set_global_palette(frames[0].palette().unwrap()).unwrap();
+        encoder.write_frame(&frames[0]).unwrap();
+        encoder.write_frame(&frames[1]).unwrap();
+        let checkpoint = encoder.checkpoint().unwrap();
+
+        // pretend we crashed halfway through the third frame
+        encoder.write_frame(&frames[2]).unwrap();
+        let mut output = encoder.finish().unwrap();
+        output.truncate(output.len() - 20);
+
+        let mut serialized = Vec::new();
+        checkpoint.write_to(&mut serialized).unwrap();
+        let checkpoint = EncoderCheckpoint::read_from(serialized.as_slice()).unwrap();
+
+        output.truncate(checkpoint.bytes_written as usize);
+        let mut encoder = Encoder::resume(output, &checkpoint);
+        for frame in &frames[checkpoint.frames_written as usize..] {
+            encoder.write_frame(frame).unwrap();
+        }
+        let output = encoder.finish().unwrap();
+
+        let mut decoder = Decoder::new(Cursor::new(output));
+        decoder.parse().unwrap();
+
+        assert_eq!(decoder.frames().len(), frames.len());
+        for (decoded, original) in decoder.frames().iter().zip(&frames) {
+            assert_eq!(decoded.indicies(), original.indicies());
+        }
+    }
+
+    #[test]
+    fn resumes_an_interrupted_file() {
+        let frames: Vec<Frame> = (0..3).map(test_frame).collect();
+        let dir = std::env::temp_dir().join(format!("jif-resume-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.gif");
+
+        let mut encoder = Encoder::create(&path, 40, 30, OutputOptions::default()).unwrap();
+        encoder.write_frame(&frames[0]).unwrap();
+        let checkpoint = encoder.checkpoint_file().unwrap();
+        let temp_path = encoder.temp_path().to_path_buf();
+        // interrupted before it's committed
+        encoder.write_frame(&frames[1]).unwrap();
+        drop(encoder);
+        assert!(!path.exists());
+
+        let mut encoder = Encoder::resume_file(&temp_path, &checkpoint).unwrap();
+        for frame in &frames[1..] {
+            encoder.write_frame(frame).unwrap();
+        }
+        encoder.finish().unwrap();
+        fs::rename(&temp_path, &path).unwrap();
+
+        let mut decoder = Decoder::new(File::open(&path).unwrap());
+        decoder.parse().unwrap();
+        assert_eq!(decoder.frames().len(), frames.len());
+        for (decoded, original) in decoder.frames().iter().zip(&frames) {
+            assert_eq!(decoded.indicies(), original.indicies());
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn copies_raw_frames() {
+        let frames: Vec<Frame> = (0..3).map(test_frame).collect();
+        let mut encoder = Encoder::new(Vec::new(), 40, 30);
+        encoder.set_global_palette(frames[0].palette().unwrap()).unwrap();
+        encoder.write_comment(b"made by hand").unwrap();
+        for frame in &frames {
+            encoder.write_frame(frame).unwrap();
+        }
+        let original = encoder.finish().unwrap();
+
+        let mut decoder = Decoder::new(Cursor::new(original));
+        let raw = decoder.parse_raw().unwrap();
+        assert_eq!(raw.len(), 3);
+        assert!(decoder.frames()[0].indicies().is_empty());
+
+        let mut encoder = Encoder::new(Vec::new(), 40, 30);
+        encoder.set_global_palette(decoder.global_palette().unwrap()).unwrap();
+        encoder.set_loop_count(LoopCount::Number(2));
+        for comment in decoder.comments() {
+            encoder.write_comment(comment).unwrap();
+        }
+        for (frame, raw) in decoder.frames().iter().zip(&raw) {
+            let mut frame = frame.clone();
+            frame.delay_time = 25;
+            encoder.write_raw_frame(&frame, raw).unwrap();
+        }
+        let retimed = encoder.finish().unwrap();
+
+        let mut decoder = Decoder::new(Cursor::new(retimed));
+        decoder.parse().unwrap();
+        assert_eq!(decoder.loop_count(), Some(LoopCount::Number(2)));
+        assert_eq!(decoder.comments().collect::<Vec<_>>(), [b"made by hand"]);
+        for (decoded, original) in decoder.frames().iter().zip(&frames) {
+            assert_eq!(decoded.indicies(), original.indicies());
+            assert_eq!(decoded.delay_time, 25);
+        }
+    }
+
+    #[test]
+    fn targets_gif87a() {
+        let mut still = test_frame(0);
+        still.delay_time = 0;
+        let frames = [still, test_frame(1)];
+        assert_eq!(
+            TargetVersion::oldest_for(&frames[..1], None),
+            TargetVersion::V87a
+        );
+        assert_eq!(
+            TargetVersion::oldest_for(&frames, None),
+            TargetVersion::V89a
+        );
+        assert_eq!(
+            TargetVersion::oldest_for(&frames[..1], Some(LoopCount::Infinite)),
+            TargetVersion::V89a
+        );
+
+        let mut encoder = Encoder::new(Vec::new(), 40, 30);
+        encoder.set_target_version(TargetVersion::V87a);
+        encoder.write_frame(&frames[0]).unwrap();
+        // a delay needs a graphic control extension
+        assert!(encoder.write_frame(&frames[1]).is_err());
+        assert!(encoder.write_comment(b"hi").is_err());
+        let gif = encoder.finish().unwrap();
+        assert!(gif.starts_with(b"GIF87a"));
+
+        let mut decoder = Decoder::new(Cursor::new(gif));
+        decoder.parse().unwrap();
+        assert_eq!(decoder.version(), Some(Version::V87a));
+        assert_eq!(decoder.frames()[0].indicies(), frames[0].indicies());
+
+        let mut encoder = Encoder::new(Vec::new(), 40, 30);
+        encoder.set_target_version(TargetVersion::V87a);
+        encoder.set_loop_count(LoopCount::Infinite);
+        assert!(encoder.write_frame(&frames[0]).is_err());
+    }
+}
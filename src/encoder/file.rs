@@ -0,0 +1,60 @@
+use anyhow::Result;
+
+use std::fs::{File, OpenOptions};
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::path::Path;
+
+use super::{Encoder, EncoderCheckpoint, EncoderError};
+use crate::output::{AtomicFile, OutputOptions};
+
+impl Encoder<AtomicFile> {
+    /// Encodes into a temporary file next to `path`. Call `commit` on the writer returned by
+    /// `finish` to move the finished gif into place. The temporary file is removed if the
+    /// encoder is dropped before that, unless a checkpoint was taken with `checkpoint_file`.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        width: u16,
+        height: u16,
+        options: OutputOptions,
+    ) -> Result<Self> {
+        Ok(Self::new(
+            AtomicFile::create_with_options(path, options)?,
+            width,
+            height,
+        ))
+    }
+
+    /// Like `checkpoint`, but the temporary file is also kept from then on if the encoder is
+    /// dropped without being committed. The encode can be picked up again by passing
+    /// `temp_path` to `Encoder::resume_file`, and the finished file then has to be renamed into
+    /// place by hand.
+    pub fn checkpoint_file(&mut self) -> Result<EncoderCheckpoint> {
+        let checkpoint = self.checkpoint()?;
+        self.inner.keep_on_drop();
+        Ok(checkpoint)
+    }
+
+    /// The temporary file the gif is encoded into until it's committed.
+    pub fn temp_path(&self) -> &Path {
+        self.inner.temp_path()
+    }
+}
+
+impl Encoder<File> {
+    /// Reopens a partially written file and continues encoding after the last checkpointed
+    /// frame, dropping anything that was written after the checkpoint was taken.
+    pub fn resume_file<P: AsRef<Path>>(path: P, checkpoint: &EncoderCheckpoint) -> Result<Self> {
+        let mut file = OpenOptions::new().write(true).open(path)?;
+
+        let length = file.metadata()?.len();
+        if length < checkpoint.bytes_written {
+            return Err(EncoderError::InvalidCheckpoint("output is shorter than the checkpoint").into());
+        }
+
+        file.set_len(checkpoint.bytes_written)?;
+        file.seek(SeekFrom::End(0))?;
+
+        Ok(Self::resume(file, checkpoint))
+    }
+}
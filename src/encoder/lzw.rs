@@ -0,0 +1,108 @@
+use crate::bits::BitWriter;
+
+use std::collections::HashMap;
+
+// the last code an encoder is allowed to hand out before it has to clear the table. the spec
+// allows 4095 but plenty of decoders trip over a completely full table, so stay one below like
+// giflib does.
+const MAX_CODE: u16 = 4095;
+
+pub fn lzw_encode(indicies: &[u8], minimum_code_size: u32) -> Vec<u8> {
+    let clear_code: u16 = 1 << minimum_code_size;
+    let end_of_information_code = clear_code + 1;
+
+    let mut writer = BitWriter::new();
+    let mut code_size = minimum_code_size + 1;
+    let mut next_code = end_of_information_code + 1;
+
+    // maps {CODE}+K to the code that represents it
+    let mut code_table: HashMap<(u16, u8), u16> = HashMap::new();
+
+    writer.write(clear_code, code_size);
+
+    let mut indicies_iter = indicies.iter();
+    let Some(&first_index) = indicies_iter.next() else {
+        writer.write(end_of_information_code, code_size);
+        return writer.finish();
+    };
+
+    let mut current_code = first_index as u16;
+
+    for &index in indicies_iter {
+        if let Some(&code) = code_table.get(&(current_code, index)) {
+            current_code = code;
+            continue;
+        }
+
+        writer.write(current_code, code_size);
+
+        // the decoder adds its entry one code later than we do, so grow the code size only once
+        // the decoder will have caught up.
+        if next_code >= (1 << code_size) && code_size < 12 {
+            code_size += 1;
+        }
+
+        if next_code >= MAX_CODE {
+            writer.write(clear_code, code_size);
+            code_table.clear();
+            code_size = minimum_code_size + 1;
+            next_code = end_of_information_code + 1;
+        } else {
+            code_table.insert((current_code, index), next_code);
+            next_code += 1;
+        }
+
+        current_code = index as u16;
+    }
+
+    writer.write(current_code, code_size);
+    if next_code >= (1 << code_size) && code_size < 12 {
+        code_size += 1;
+    }
+    writer.write(end_of_information_code, code_size);
+
+    writer.finish()
+}
+
+/// Picks one index for every pixel that can be written as either of two, the first unless the
+/// second lets the code being built keep growing. This follows the same table as `lzw_encode`,
+/// so the result is what it compresses best, at least greedily.
+pub fn pick_indicies(choices: &[(u8, Option<u8>)], minimum_code_size: u32) -> Vec<u8> {
+    let end_of_information_code: u16 = (1 << minimum_code_size) + 1;
+    let mut next_code = end_of_information_code + 1;
+    let mut code_table: HashMap<(u16, u8), u16> = HashMap::new();
+
+    let mut indicies = Vec::with_capacity(choices.len());
+    let mut choices_iter = choices.iter();
+    let Some(&(first_index, _)) = choices_iter.next() else {
+        return indicies;
+    };
+    indicies.push(first_index);
+    let mut current_code = first_index as u16;
+
+    for &(index, alternative) in choices_iter {
+        if let Some(&code) = code_table.get(&(current_code, index)) {
+            indicies.push(index);
+            current_code = code;
+            continue;
+        }
+        if let Some(code) = alternative.and_then(|other| code_table.get(&(current_code, other))) {
+            indicies.push(alternative.unwrap());
+            current_code = *code;
+            continue;
+        }
+
+        if next_code >= MAX_CODE {
+            code_table.clear();
+            next_code = end_of_information_code + 1;
+        } else {
+            code_table.insert((current_code, index), next_code);
+            next_code += 1;
+        }
+
+        indicies.push(index);
+        current_code = index as u16;
+    }
+
+    indicies
+}
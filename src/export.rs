@@ -0,0 +1,58 @@
+mod apng;
+mod deflate;
+mod png;
+mod qoi;
+mod template;
+
+pub use apng::encode_apng;
+pub use png::encode_png;
+pub use qoi::encode_qoi;
+pub use template::{FrameName, NameTemplate, Numbering};
+
+use crate::ppm_writer::encode_ppm;
+
+/// An image format frames can be encoded to in memory, see `Animation::frames_as`.
+pub trait ImageFormat {
+    const EXTENSION: &'static str;
+    const MIME_TYPE: &'static str;
+
+    /// Encodes `width` x `height` pixels of RGBA data.
+    fn encode(width: u16, height: u16, rgba: &[u8]) -> Vec<u8>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Png;
+
+impl ImageFormat for Png {
+    const EXTENSION: &'static str = "png";
+    const MIME_TYPE: &'static str = "image/png";
+
+    fn encode(width: u16, height: u16, rgba: &[u8]) -> Vec<u8> {
+        encode_png(width, height, rgba)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Qoi;
+
+impl ImageFormat for Qoi {
+    const EXTENSION: &'static str = "qoi";
+    const MIME_TYPE: &'static str = "image/qoi";
+
+    fn encode(width: u16, height: u16, rgba: &[u8]) -> Vec<u8> {
+        encode_qoi(width, height, rgba)
+    }
+}
+
+/// Binary (P6) PPM. PPM has no alpha channel, so transparent pixels come out black.
+#[derive(Debug, Clone, Copy)]
+pub struct Ppm;
+
+impl ImageFormat for Ppm {
+    const EXTENSION: &'static str = "ppm";
+    const MIME_TYPE: &'static str = "image/x-portable-pixmap";
+
+    fn encode(width: u16, height: u16, rgba: &[u8]) -> Vec<u8> {
+        encode_ppm(width, height, rgba)
+    }
+}
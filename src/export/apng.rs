@@ -0,0 +1,254 @@
+use super::png::{compress_image, write_chunk, write_header, SIGNATURE};
+use crate::animation::Animation;
+use crate::compositor::Rect;
+use crate::parser::{DisposalMethod, Frame, LoopCount};
+
+const DISPOSE_OP_NONE: u8 = 0;
+const DISPOSE_OP_BACKGROUND: u8 = 1;
+const DISPOSE_OP_PREVIOUS: u8 = 2;
+const BLEND_OP_OVER: u8 = 1;
+
+/// Encodes a whole animation as an APNG. Every GIF frame becomes an APNG frame with the same
+/// position, delay and disposal, blended over what's under it so transparent pixels keep
+/// showing the frames before, which makes the conversion lossless. A pixel aspect ratio is
+/// kept in a `pHYs` chunk.
+pub fn encode_apng(animation: &Animation) -> Vec<u8> {
+    let (width, height) = (animation.width(), animation.height());
+    let screen = Rect::new(0, 0, width, height);
+    let frames = animation.frames();
+
+    let mut png = SIGNATURE.to_vec();
+    write_header(&mut png, width, height);
+    if let Some(pixel_aspect_ratio) = animation.pixel_aspect_ratio() {
+        write_chunk(&mut png, b"pHYs", &physical_dimensions(pixel_aspect_ratio));
+    }
+
+    let mut control = Vec::with_capacity(8);
+    control.extend_from_slice(&(frames.len() as u32).to_be_bytes());
+    control.extend_from_slice(&num_plays(animation.loop_count()).to_be_bytes());
+    write_chunk(&mut png, b"acTL", &control);
+
+    let mut sequence_number = 0_u32;
+    for (index, frame) in frames.iter().enumerate() {
+        // the first frame is the default image, which has to cover the whole screen
+        let (rect, rgba, dispose_op) = if index == 0 {
+            (
+                screen,
+                place_on_screen(frame, width, height),
+                dispose_op(frame),
+            )
+        } else {
+            match frame_region(frame, screen) {
+                Some((rect, rgba)) => (rect, rgba, dispose_op(frame)),
+                // frames can't be empty either, so frames entirely off screen become a
+                // transparent pixel, which mustn't clear what's under it when it's disposed of
+                None => (Rect::new(0, 0, 1, 1), vec![0; 4], DISPOSE_OP_NONE),
+            }
+        };
+
+        write_chunk(
+            &mut png,
+            b"fcTL",
+            &frame_control(sequence_number, rect, frame, dispose_op),
+        );
+        sequence_number += 1;
+
+        let image = compress_image(rect.width, rect.height, &rgba);
+        if index == 0 {
+            write_chunk(&mut png, b"IDAT", &image);
+        } else {
+            let mut data = Vec::with_capacity(4 + image.len());
+            data.extend_from_slice(&sequence_number.to_be_bytes());
+            data.extend_from_slice(&image);
+            write_chunk(&mut png, b"fdAT", &data);
+            sequence_number += 1;
+        }
+    }
+
+    if frames.is_empty() {
+        let rgba = vec![0; width as usize * height as usize * 4];
+        write_chunk(&mut png, b"IDAT", &compress_image(width, height, &rgba));
+    }
+
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+// GIFs count how many times the animation repeats after the first play, APNGs count every
+// play, and both use 0 for forever
+fn num_plays(loop_count: Option<LoopCount>) -> u32 {
+    match loop_count {
+        Some(LoopCount::Infinite) => 0,
+        Some(LoopCount::Number(count)) => count as u32 + 1,
+        None => 1,
+    }
+}
+
+// pixels per unit across and down, in no particular unit, which only gives their shape. GIF
+// ratios are in 64ths, so they're kept exactly
+fn physical_dimensions(pixel_aspect_ratio: f32) -> Vec<u8> {
+    let mut dimensions = Vec::with_capacity(9);
+    dimensions.extend_from_slice(&64_u32.to_be_bytes());
+    dimensions.extend_from_slice(&((pixel_aspect_ratio * 64.0).round() as u32).to_be_bytes());
+    dimensions.push(0);
+    dimensions
+}
+
+fn dispose_op(frame: &Frame) -> u8 {
+    match frame.disposal_method {
+        None | Some(DisposalMethod::None) | Some(DisposalMethod::DoNotDispose) => DISPOSE_OP_NONE,
+        Some(DisposalMethod::RestoreToBackgroundColor) => DISPOSE_OP_BACKGROUND,
+        Some(DisposalMethod::RestoreToPrevious) => DISPOSE_OP_PREVIOUS,
+    }
+}
+
+fn frame_control(sequence_number: u32, rect: Rect, frame: &Frame, dispose_op: u8) -> Vec<u8> {
+    // browsers play GIF delays of 0 and 1 as a tenth of a second but APNG ones as fast as they
+    // can, so the delay is written the way the GIF is played
+    let delay = (frame.duration().as_millis() / 10) as u16;
+
+    let mut control = Vec::with_capacity(26);
+    control.extend_from_slice(&sequence_number.to_be_bytes());
+    for value in [rect.width, rect.height, rect.left, rect.top] {
+        control.extend_from_slice(&(value as u32).to_be_bytes());
+    }
+    control.extend_from_slice(&delay.to_be_bytes());
+    control.extend_from_slice(&100_u16.to_be_bytes());
+    control.extend_from_slice(&[dispose_op, BLEND_OP_OVER]);
+    control
+}
+
+// the part of the frame on screen, APNG frames can't hang off the edge. `None` for frames
+// entirely off screen
+fn frame_region(frame: &Frame, screen: Rect) -> Option<(Rect, Vec<u8>)> {
+    let bounds = Rect::new(
+        frame.left_position,
+        frame.top_position,
+        frame.width,
+        frame.height,
+    );
+    let visible = bounds.intersect(screen);
+    if visible.is_empty() {
+        return None;
+    }
+
+    let frame_rgba = frame.rgba();
+    let row_length = frame.width as usize * 4;
+    let left = (visible.left - frame.left_position) as usize * 4;
+    let top = (visible.top - frame.top_position) as usize;
+
+    let mut rgba = Vec::with_capacity(visible.width as usize * visible.height as usize * 4);
+    for row in frame_rgba
+        .chunks(row_length)
+        .skip(top)
+        .take(visible.height as usize)
+    {
+        rgba.extend_from_slice(&row[left..left + visible.width as usize * 4]);
+    }
+    Some((visible, rgba))
+}
+
+fn place_on_screen(frame: &Frame, width: u16, height: u16) -> Vec<u8> {
+    let screen = Rect::new(0, 0, width, height);
+    let mut rgba = vec![0; width as usize * height as usize * 4];
+    let Some((visible, region)) = frame_region(frame, screen) else {
+        return rgba;
+    };
+
+    let row_length = visible.width as usize * 4;
+    for (y, row) in region.chunks(row_length).enumerate() {
+        let offset = ((visible.top as usize + y) * width as usize + visible.left as usize) * 4;
+        rgba[offset..offset + row_length].copy_from_slice(row);
+    }
+    rgba
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_apng, DISPOSE_OP_BACKGROUND, DISPOSE_OP_NONE};
+    use crate::animation::Animation;
+    use crate::encoder::Encoder;
+    use crate::parser::{DisposalMethod, Frame, LoopCount};
+
+    fn chunks(png: &[u8]) -> Vec<([u8; 4], &[u8])> {
+        let mut chunks = Vec::new();
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            chunks.push((rest[4..8].try_into().unwrap(), &rest[8..8 + length]));
+            rest = &rest[12 + length..];
+        }
+        chunks
+    }
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn numbers_chunks_and_keeps_timing() {
+        let palette: Box<[u8]> = Box::new([255, 0, 0, 0, 0, 255]);
+        let mut encoder = Encoder::new(Vec::new(), 4, 4);
+        encoder.set_global_palette(&palette).unwrap();
+        encoder.set_loop_count(LoopCount::Number(2));
+        for (delay_time, left) in [(0, 0), (5, 2), (20, 40)] {
+            let mut frame = Frame::new(2, 2, Box::new([1; 4]), palette.clone());
+            frame.delay_time = delay_time;
+            frame.left_position = left;
+            frame.disposal_method = Some(DisposalMethod::RestoreToBackgroundColor);
+            encoder.write_frame(&frame).unwrap();
+        }
+        let animation = Animation::decode(encoder.finish().unwrap().as_slice()).unwrap();
+
+        let png = encode_apng(&animation);
+        let chunks = chunks(&png);
+        let kinds: Vec<&[u8]> = chunks.iter().map(|(kind, _)| &kind[..]).collect();
+        assert_eq!(
+            kinds,
+            [
+                &b"IHDR"[..],
+                b"acTL",
+                b"fcTL",
+                b"IDAT",
+                b"fcTL",
+                b"fdAT",
+                b"fcTL",
+                b"fdAT",
+                b"IEND"
+            ]
+        );
+
+        // three frames, played three times
+        let control = chunks[1].1;
+        assert_eq!((u32_at(control, 0), u32_at(control, 4)), (3, 3));
+
+        let sequence_numbers: Vec<u32> = chunks
+            .iter()
+            .filter(|(kind, _)| kind == b"fcTL" || kind == b"fdAT")
+            .map(|(_, data)| u32_at(data, 0))
+            .collect();
+        assert_eq!(sequence_numbers, [0, 1, 2, 3, 4]);
+
+        let frame_controls: Vec<&[u8]> = chunks
+            .iter()
+            .filter(|(kind, _)| kind == b"fcTL")
+            .map(|(_, data)| *data)
+            .collect();
+        // a delay of 0 is played as a tenth of a second, like browsers play the GIF
+        let delays: Vec<[u8; 4]> = frame_controls
+            .iter()
+            .map(|control| control[20..24].try_into().unwrap())
+            .collect();
+        assert_eq!(delays, [[0, 10, 0, 100], [0, 5, 0, 100], [0, 20, 0, 100]]);
+        assert_eq!(frame_controls[1][24], DISPOSE_OP_BACKGROUND);
+
+        // the frame off screen is a transparent pixel in the corner that's never cleared
+        let off_screen = frame_controls[2];
+        let rect: Vec<u32> = (4..20)
+            .step_by(4)
+            .map(|offset| u32_at(off_screen, offset))
+            .collect();
+        assert_eq!(rect, [1, 1, 0, 0]);
+        assert_eq!(off_screen[24], DISPOSE_OP_NONE);
+    }
+}
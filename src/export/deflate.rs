@@ -0,0 +1,328 @@
+// Just enough deflate (RFC 1951) to keep PNGs small: greedy LZ77 matching written out as a single
+// block using the fixed Huffman codes, falling back to stored blocks when that doesn't pay off,
+// wrapped in a zlib (RFC 1950) stream.
+
+use crate::bits::BitWriter;
+
+const WINDOW_SIZE: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+// how many earlier occurrences of a hash are tried before settling for the best match so far
+const MAX_CHAIN_LENGTH: usize = 64;
+const HASH_BITS: u32 = 15;
+const NO_POSITION: usize = usize::MAX;
+
+const END_OF_BLOCK: u16 = 256;
+const MAX_STORED_BLOCK_LENGTH: usize = u16::MAX as usize;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u32; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u32; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+// 32K window, no preset dictionary, header checksum making it a multiple of 31
+const ZLIB_HEADER: [u8; 2] = [0x78, 0x9c];
+
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    let mut out = ZLIB_HEADER.to_vec();
+
+    let compressed = deflate_fixed(data);
+    // noisy data can come out larger than it went in, store it as is instead
+    if compressed.len() > data.len() + data.len() / MAX_STORED_BLOCK_LENGTH * 5 + 5 {
+        deflate_stored(data, &mut out);
+    } else {
+        out.extend_from_slice(&compressed);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn deflate_stored(data: &[u8], out: &mut Vec<u8>) {
+    let mut blocks = data.chunks(MAX_STORED_BLOCK_LENGTH).peekable();
+    if blocks.peek().is_none() {
+        // BFINAL = 1, BTYPE = 00, padded to a byte, then an empty block
+        out.extend_from_slice(&[0b001, 0x00, 0x00, 0xff, 0xff]);
+        return;
+    }
+
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        out.push(is_final as u8);
+        out.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+}
+
+fn deflate_fixed(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+
+    // BFINAL = 1, BTYPE = 01 (fixed Huffman codes)
+    writer.write(1, 1);
+    writer.write(0b01, 2);
+
+    let mut matcher = Matcher::new(data.len());
+    let mut position = 0;
+    while position < data.len() {
+        match matcher.longest_match(data, position) {
+            Some((length, distance)) => {
+                write_length(&mut writer, length);
+                write_distance(&mut writer, distance);
+                for offset in 0..length {
+                    matcher.insert(data, position + offset);
+                }
+                position += length;
+            }
+            None => {
+                write_literal_or_length(&mut writer, data[position] as u16);
+                matcher.insert(data, position);
+                position += 1;
+            }
+        }
+    }
+    write_literal_or_length(&mut writer, END_OF_BLOCK);
+
+    writer.finish()
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MODULUS: u32 = 65521;
+    let (mut a, mut b) = (1_u32, 0_u32);
+    // 5552 is the most bytes that can be summed before `b` could overflow
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MODULUS;
+        b %= MODULUS;
+    }
+    (b << 16) | a
+}
+
+// hash chains over every position seen so far, limited to the deflate window
+struct Matcher {
+    head: Vec<usize>,
+    previous: Vec<usize>,
+}
+
+impl Matcher {
+    fn new(data_length: usize) -> Self {
+        Self {
+            head: vec![NO_POSITION; 1 << HASH_BITS],
+            previous: vec![NO_POSITION; data_length.min(WINDOW_SIZE)],
+        }
+    }
+
+    fn hash(data: &[u8], position: usize) -> usize {
+        let key = (data[position] as usize) << 10
+            ^ (data[position + 1] as usize) << 5
+            ^ data[position + 2] as usize;
+        key & ((1 << HASH_BITS) - 1)
+    }
+
+    fn insert(&mut self, data: &[u8], position: usize) {
+        if position + MIN_MATCH > data.len() {
+            return;
+        }
+        let hash = Self::hash(data, position);
+        self.previous[position % WINDOW_SIZE] = self.head[hash];
+        self.head[hash] = position;
+    }
+
+    fn longest_match(&self, data: &[u8], position: usize) -> Option<(usize, usize)> {
+        if position + MIN_MATCH > data.len() {
+            return None;
+        }
+
+        let max_length = MAX_MATCH.min(data.len() - position);
+        let mut best: Option<(usize, usize)> = None;
+        let mut candidate = self.head[Self::hash(data, position)];
+
+        for _ in 0..MAX_CHAIN_LENGTH {
+            if candidate == NO_POSITION || position - candidate > WINDOW_SIZE {
+                break;
+            }
+
+            let length = data[candidate..]
+                .iter()
+                .zip(&data[position..position + max_length])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length >= MIN_MATCH && best.is_none_or(|(best_length, _)| length > best_length) {
+                best = Some((length, position - candidate));
+                if length == max_length {
+                    break;
+                }
+            }
+
+            let next = self.previous[candidate % WINDOW_SIZE];
+            // slots get reused once the window moves on, make sure the chain only goes backwards
+            if next == NO_POSITION || next >= candidate {
+                break;
+            }
+            candidate = next;
+        }
+
+        best
+    }
+}
+
+fn write_literal_or_length(writer: &mut BitWriter, symbol: u16) {
+    let (code, length) = match symbol {
+        0..=143 => (0b0011_0000 + symbol, 8),
+        144..=255 => (0b1_1001_0000 + symbol - 144, 9),
+        256..=279 => (symbol - 256, 7),
+        _ => (0b1100_0000 + symbol - 280, 8),
+    };
+    write_huffman_code(writer, code, length);
+}
+
+fn write_length(writer: &mut BitWriter, length: usize) {
+    let index = LENGTH_BASE
+        .iter()
+        .rposition(|&base| base as usize <= length)
+        .expect("matches are at least MIN_MATCH long");
+    write_literal_or_length(writer, 257 + index as u16);
+    writer.write(
+        (length - LENGTH_BASE[index] as usize) as u16,
+        LENGTH_EXTRA_BITS[index],
+    );
+}
+
+fn write_distance(writer: &mut BitWriter, distance: usize) {
+    let index = DISTANCE_BASE
+        .iter()
+        .rposition(|&base| base as usize <= distance)
+        .expect("distances are at least 1");
+    write_huffman_code(writer, index as u16, 5);
+    writer.write(
+        (distance - DISTANCE_BASE[index] as usize) as u16,
+        DISTANCE_EXTRA_BITS[index],
+    );
+}
+
+// Huffman codes are the one thing in deflate that is packed starting from the most significant bit
+fn write_huffman_code(writer: &mut BitWriter, code: u16, length: u32) {
+    writer.write(code.reverse_bits() >> (16 - length), length);
+}
+
+// reads back what `zlib_compress` writes, which only ever uses stored and fixed Huffman blocks
+#[cfg(test)]
+pub(super) fn zlib_decompress(zlib: &[u8]) -> Vec<u8> {
+    assert_eq!(zlib[..2], ZLIB_HEADER);
+    struct Bits<'a> {
+        data: &'a [u8],
+        // in bits, values are packed starting from the least significant bit
+        position: usize,
+    }
+    impl Bits<'_> {
+        fn read(&mut self, count: u32) -> usize {
+            let mut value = 0;
+            for i in 0..count {
+                let bit = self.data[self.position / 8] >> (self.position % 8) & 1;
+                value |= (bit as usize) << i;
+                self.position += 1;
+            }
+            value
+        }
+
+        // codes are packed starting from their most significant bit
+        fn read_code(&mut self, code: usize, count: u32) -> usize {
+            (0..count).fold(code, |code, _| code << 1 | self.read(1))
+        }
+    }
+    let mut bits = Bits {
+        data: &zlib[2..zlib.len() - 4],
+        position: 0,
+    };
+
+    let mut data = Vec::new();
+    loop {
+        let is_final = bits.read(1) == 1;
+        match bits.read(2) {
+            0b00 => {
+                // the rest of the byte is padding
+                bits.position = bits.position.next_multiple_of(8);
+                let length = bits.read(16);
+                assert_eq!(bits.read(16), !length & 0xffff);
+                for _ in 0..length {
+                    data.push(bits.read(8) as u8);
+                }
+            }
+            0b01 => loop {
+                let code = bits.read_code(0, 7);
+                let symbol = match code {
+                    0..=0b001_0111 => code + 256,
+                    _ => match bits.read_code(code, 1) {
+                        code @ 0b0011_0000..=0b1011_1111 => code - 0b0011_0000,
+                        code @ 0b1100_0000..=0b1100_0111 => code - 0b1100_0000 + 280,
+                        code => bits.read_code(code, 1) - 0b1_1001_0000 + 144,
+                    },
+                };
+                match symbol {
+                    0..=255 => data.push(symbol as u8),
+                    256 => break,
+                    _ => {
+                        let index = symbol - 257;
+                        let length =
+                            LENGTH_BASE[index] as usize + bits.read(LENGTH_EXTRA_BITS[index]);
+                        let index = bits.read_code(0, 5);
+                        let distance =
+                            DISTANCE_BASE[index] as usize + bits.read(DISTANCE_EXTRA_BITS[index]);
+                        for _ in 0..length {
+                            data.push(data[data.len() - distance]);
+                        }
+                    }
+                }
+            },
+            kind => panic!("block type {} isn't written", kind),
+        }
+        if is_final {
+            break;
+        }
+    }
+
+    assert_eq!(zlib[zlib.len() - 4..], adler32(&data).to_be_bytes());
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{adler32, zlib_compress, zlib_decompress};
+
+    #[test]
+    fn computes_adler32() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        // long enough for the sums to be reduced part way through
+        assert_eq!(adler32(&[0xff; 10_000]), 0xb623_eb2b);
+    }
+
+    #[test]
+    fn round_trips() {
+        let repetitive: Vec<u8> = (0..5000).map(|i| (i % 13) as u8).collect();
+        // noisy enough that it's stored instead
+        let noisy: Vec<u8> = (0..70_000_u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect();
+
+        for data in [&b""[..], b"a", b"abcabcabcabc", &repetitive, &noisy] {
+            let compressed = zlib_compress(data);
+            assert_eq!(zlib_decompress(&compressed), data);
+        }
+        assert!(zlib_compress(&repetitive).len() < repetitive.len() / 10);
+    }
+}
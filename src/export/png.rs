@@ -0,0 +1,122 @@
+use super::deflate::zlib_compress;
+
+pub(super) const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+const BIT_DEPTH: u8 = 8;
+const COLOR_TYPE_RGBA: u8 = 6;
+const FILTER_NONE: u8 = 0;
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+/// Encodes an RGBA buffer as an 8 bit truecolor-with-alpha PNG.
+pub fn encode_png(width: u16, height: u16, rgba: &[u8]) -> Vec<u8> {
+    debug_assert_eq!(rgba.len(), width as usize * height as usize * 4);
+
+    let mut png = SIGNATURE.to_vec();
+    write_header(&mut png, width, height);
+    write_chunk(&mut png, b"IDAT", &compress_image(width, height, rgba));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+pub(super) fn write_header(png: &mut Vec<u8>, width: u16, height: u16) {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // bit depth, color type, compression, filter and interlace method
+    header.extend_from_slice(&[BIT_DEPTH, COLOR_TYPE_RGBA, 0, 0, 0]);
+    write_chunk(png, b"IHDR", &header);
+}
+
+/// The zlib compressed scanlines of an RGBA image, what goes in `IDAT` chunks.
+pub(super) fn compress_image(width: u16, height: u16, rgba: &[u8]) -> Vec<u8> {
+    // every scanline is prefixed with its filter type
+    let row_length = width as usize * 4;
+    let mut scanlines = Vec::with_capacity((row_length + 1) * height as usize);
+    if row_length > 0 {
+        for row in rgba.chunks(row_length) {
+            scanlines.push(FILTER_NONE);
+            scanlines.extend_from_slice(row);
+        }
+    }
+    zlib_compress(&scanlines)
+}
+
+pub(super) fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+
+    let crc = !crc_update(crc_update(!0, kind), data);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc_update(crc: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(crc, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::deflate::zlib_decompress;
+    use super::{crc_update, encode_png, SIGNATURE};
+
+    fn crc(bytes: &[u8]) -> u32 {
+        !crc_update(!0, bytes)
+    }
+
+    #[test]
+    fn computes_crc32() {
+        assert_eq!(crc(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc(b"IEND"), 0xae42_6082);
+    }
+
+    #[test]
+    fn stores_the_rows_behind_filter_bytes() {
+        let (width, height) = (3_u16, 2_u16);
+        let rgba: Vec<u8> = (0..width * height * 4).map(|i| i as u8).collect();
+        let png = encode_png(width, height, &rgba);
+        assert_eq!(png[..8], SIGNATURE);
+
+        let mut chunks = Vec::new();
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let (kind_and_data, crc_bytes) = rest[4..].split_at(4 + length);
+            assert_eq!(crc_bytes[..4], crc(kind_and_data).to_be_bytes());
+            chunks.push((&kind_and_data[..4], &kind_and_data[4..]));
+            rest = &crc_bytes[4..];
+        }
+        let kinds: Vec<&[u8]> = chunks.iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(kinds, [&b"IHDR"[..], b"IDAT", b"IEND"]);
+        assert_eq!(chunks[0].1, [0, 0, 0, 3, 0, 0, 0, 2, 8, 6, 0, 0, 0]);
+
+        let scanlines = zlib_decompress(chunks[1].1);
+        let expected: Vec<u8> = rgba
+            .chunks(width as usize * 4)
+            .flat_map(|row| [&[0][..], row].concat())
+            .collect();
+        assert_eq!(scanlines, expected);
+    }
+}
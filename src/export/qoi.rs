@@ -0,0 +1,177 @@
+// The "Quite OK Image" format, see https://qoiformat.org/qoi-specification.pdf
+
+const MAGIC: &[u8] = b"qoif";
+const CHANNELS_RGBA: u8 = 4;
+const COLORSPACE_SRGB: u8 = 0;
+const END_MARKER: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+
+const OP_INDEX: u8 = 0b0000_0000;
+const OP_DIFF: u8 = 0b0100_0000;
+const OP_LUMA: u8 = 0b1000_0000;
+const OP_RUN: u8 = 0b1100_0000;
+const OP_RGB: u8 = 0b1111_1110;
+const OP_RGBA: u8 = 0b1111_1111;
+
+// runs of 63 and 64 would collide with OP_RGB and OP_RGBA
+const MAX_RUN: u8 = 62;
+
+pub fn encode_qoi(width: u16, height: u16, rgba: &[u8]) -> Vec<u8> {
+    debug_assert_eq!(rgba.len(), width as usize * height as usize * 4);
+
+    let mut qoi = Vec::with_capacity(14 + rgba.len() / 2);
+    qoi.extend_from_slice(MAGIC);
+    qoi.extend_from_slice(&(width as u32).to_be_bytes());
+    qoi.extend_from_slice(&(height as u32).to_be_bytes());
+    qoi.extend_from_slice(&[CHANNELS_RGBA, COLORSPACE_SRGB]);
+
+    let mut seen = [[0_u8; 4]; 64];
+    let mut previous = [0, 0, 0, 255];
+    let mut run = 0_u8;
+
+    for pixel in rgba.chunks_exact(4) {
+        let pixel: [u8; 4] = pixel.try_into().expect("chunks are 4 bytes");
+
+        if pixel == previous {
+            run += 1;
+            if run == MAX_RUN {
+                qoi.push(OP_RUN | (run - 1));
+                run = 0;
+            }
+            continue;
+        }
+
+        if run > 0 {
+            qoi.push(OP_RUN | (run - 1));
+            run = 0;
+        }
+
+        let [red, green, blue, alpha] = pixel;
+        let hash =
+            (red as usize * 3 + green as usize * 5 + blue as usize * 7 + alpha as usize * 11) % 64;
+
+        if seen[hash] == pixel {
+            qoi.push(OP_INDEX | hash as u8);
+        } else if alpha == previous[3] {
+            seen[hash] = pixel;
+
+            let red_diff = red.wrapping_sub(previous[0]) as i8;
+            let green_diff = green.wrapping_sub(previous[1]) as i8;
+            let blue_diff = blue.wrapping_sub(previous[2]) as i8;
+            let red_luma = red_diff.wrapping_sub(green_diff);
+            let blue_luma = blue_diff.wrapping_sub(green_diff);
+
+            if [red_diff, green_diff, blue_diff]
+                .iter()
+                .all(|diff| (-2..=1).contains(diff))
+            {
+                qoi.push(
+                    OP_DIFF
+                        | ((red_diff + 2) as u8) << 4
+                        | ((green_diff + 2) as u8) << 2
+                        | (blue_diff + 2) as u8,
+                );
+            } else if (-32..=31).contains(&green_diff)
+                && (-8..=7).contains(&red_luma)
+                && (-8..=7).contains(&blue_luma)
+            {
+                qoi.push(OP_LUMA | (green_diff + 32) as u8);
+                qoi.push(((red_luma + 8) as u8) << 4 | (blue_luma + 8) as u8);
+            } else {
+                qoi.extend_from_slice(&[OP_RGB, red, green, blue]);
+            }
+        } else {
+            seen[hash] = pixel;
+            qoi.extend_from_slice(&[OP_RGBA, red, green, blue, alpha]);
+        }
+
+        previous = pixel;
+    }
+
+    if run > 0 {
+        qoi.push(OP_RUN | (run - 1));
+    }
+
+    qoi.extend_from_slice(&END_MARKER);
+    qoi
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // follows the reference decoder, which keeps every pixel it decodes in the index
+    fn decode_qoi(qoi: &[u8]) -> (u32, u32, Vec<u8>) {
+        assert_eq!(&qoi[..4], MAGIC);
+        let width = u32::from_be_bytes(qoi[4..8].try_into().unwrap());
+        let height = u32::from_be_bytes(qoi[8..12].try_into().unwrap());
+        assert_eq!(qoi[12..14], [CHANNELS_RGBA, COLORSPACE_SRGB]);
+        assert_eq!(qoi[qoi.len() - 8..], END_MARKER);
+
+        let mut seen = [[0_u8; 4]; 64];
+        let mut pixel = [0, 0, 0, 255];
+        let mut rgba = Vec::new();
+        let mut ops = qoi[14..qoi.len() - 8].iter().copied();
+        while let Some(op) = ops.next() {
+            let mut run = 1;
+            match op {
+                OP_RGB => {
+                    for channel in &mut pixel[..3] {
+                        *channel = ops.next().unwrap();
+                    }
+                }
+                OP_RGBA => {
+                    for channel in &mut pixel {
+                        *channel = ops.next().unwrap();
+                    }
+                }
+                _ => match op & 0b1100_0000 {
+                    OP_INDEX => pixel = seen[op as usize],
+                    OP_DIFF => {
+                        pixel[0] = pixel[0].wrapping_add((op >> 4 & 3).wrapping_sub(2));
+                        pixel[1] = pixel[1].wrapping_add((op >> 2 & 3).wrapping_sub(2));
+                        pixel[2] = pixel[2].wrapping_add((op & 3).wrapping_sub(2));
+                    }
+                    OP_LUMA => {
+                        let green = (op & 0b0011_1111).wrapping_sub(32);
+                        let next = ops.next().unwrap();
+                        pixel[0] =
+                            pixel[0].wrapping_add(green.wrapping_add(next >> 4).wrapping_sub(8));
+                        pixel[1] = pixel[1].wrapping_add(green);
+                        pixel[2] =
+                            pixel[2].wrapping_add(green.wrapping_add(next & 0xf).wrapping_sub(8));
+                    }
+                    _ => run = (op & 0b0011_1111) as usize + 1,
+                },
+            }
+            let [red, green, blue, alpha] = pixel.map(|channel| channel as usize);
+            seen[(red * 3 + green * 5 + blue * 7 + alpha * 11) % 64] = pixel;
+            for _ in 0..run {
+                rgba.extend_from_slice(&pixel);
+            }
+        }
+        (width, height, rgba)
+    }
+
+    #[test]
+    fn round_trips() {
+        let (width, height) = (16_u16, 12_u16);
+        let mut rgba = Vec::new();
+        for i in 0..width as u32 * height as u32 {
+            let pixel = match i {
+                // a long run of the starting pixel
+                0..=69 => [0, 0, 0, 255],
+                // small steps for DIFF and LUMA, big ones for RGB
+                70..=99 => [i as u8, i as u8 + 1, i as u8 + 2, 255],
+                100..=129 => [(i * 3) as u8, (i * 5) as u8, (i * 2) as u8, 255],
+                130..=149 => [(i * 37) as u8, (i * 101) as u8, (i * 7) as u8, 255],
+                // changing alpha, and pixels seen before for INDEX
+                _ => [(i % 4) as u8 * 60, 10, 20, if i % 3 == 0 { 0 } else { 128 }],
+            };
+            rgba.extend_from_slice(&pixel);
+        }
+
+        let qoi = encode_qoi(width, height, &rgba);
+        assert!(qoi.len() < rgba.len());
+        assert_eq!(decode_qoi(&qoi), (width as u32, height as u32, rgba));
+    }
+}
@@ -0,0 +1,302 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::ops::Range;
+use std::time::Duration;
+
+#[derive(Error, Debug)]
+enum TemplateError {
+    #[error(
+        "{{{0}}} isn't a placeholder, the placeholders are {{stem}}, {{index}}, {{ms}} and {{ext}}"
+    )]
+    UnknownPlaceholder(String),
+    #[error("{{{0}}} doesn't take a width, only {{index}} does")]
+    UnexpectedWidth(String),
+    #[error("{0:?} isn't a width, widths look like {{index:04}}")]
+    InvalidWidth(String),
+    #[error("a {{ isn't closed, write {{{{ for a brace of its own")]
+    Unclosed,
+    #[error("a }} isn't opened, write }}}} for a brace of its own")]
+    Unopened,
+    #[error("numbering {0} frames from {1} goes past the largest number there can be")]
+    NumberTooLarge(usize, usize),
+    #[error("frames {0} and {1} would both be written to {2}")]
+    SameName(usize, usize, String),
+}
+
+/// What a frame's file name can be made of.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameName<'a> {
+    /// The name of the GIF the frame is from, without its extension.
+    pub stem: &'a str,
+    /// The frame's number.
+    pub index: usize,
+    /// When the frame is shown, from the start of the animation.
+    pub timestamp: Duration,
+    /// The extension of the format the frame is written in.
+    pub extension: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Stem,
+    // zero-padded to at least this many digits
+    Index(usize),
+    Milliseconds,
+    Extension,
+}
+
+/// A template for naming the files frames are written to, like `{stem}_{index:04}.{ext}`.
+/// `{stem}` is the name of the GIF, `{index}` the frame's number, `{ms}` when it's shown in
+/// milliseconds and `{ext}` the format's extension. `{index}` can be padded with zeros to a
+/// width, like `{index:04}`, and `{{` and `}}` stand for braces of their own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameTemplate {
+    parts: Vec<Part>,
+}
+
+impl NameTemplate {
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '}' => return Err(TemplateError::Unopened.into()),
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => return Err(TemplateError::Unclosed.into()),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(parse_placeholder(&placeholder)?);
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Self { parts })
+    }
+
+    pub fn render(&self, name: &FrameName) -> String {
+        let mut rendered = String::new();
+        for part in &self.parts {
+            let _ = match part {
+                Part::Literal(literal) => write!(rendered, "{}", literal),
+                Part::Stem => write!(rendered, "{}", name.stem),
+                Part::Index(width) => write!(rendered, "{:0width$}", name.index, width = width),
+                Part::Milliseconds => write!(rendered, "{}", name.timestamp.as_millis()),
+                Part::Extension => write!(rendered, "{}", name.extension),
+            };
+        }
+        rendered
+    }
+}
+
+/// How a run of frames is named, like `jif extract` names them.
+#[derive(Debug, Clone, Copy)]
+pub struct Numbering<'a> {
+    /// Names frames after a template, or `{prefix}{number}.{ext}` without one, with the
+    /// number zero-padded so the names sort in order.
+    pub template: Option<&'a NameTemplate>,
+    pub prefix: &'a str,
+    /// What the first frame named is numbered, the rest follow on from it. Frames are
+    /// numbered by their index without one.
+    pub start_number: Option<usize>,
+}
+
+impl Numbering<'_> {
+    /// Names the frames in `range` of a GIF called `stem`, whose frames are shown for
+    /// `durations`. Fails if two of them would get the same name, which a template without
+    /// `{index}` does.
+    pub fn names(
+        &self,
+        stem: &str,
+        extension: &str,
+        durations: &[Duration],
+        range: Range<usize>,
+    ) -> Result<Vec<String>> {
+        let end = range.end.min(durations.len());
+        let count = end.saturating_sub(range.start);
+        // pad to the last frame number in the file, so names sort the same whatever the range,
+        // or with a start number to the last number named
+        let last_number = match self.start_number {
+            Some(start_number) => start_number
+                .checked_add(count)
+                .ok_or(TemplateError::NumberTooLarge(count, start_number))?,
+            None => durations.len(),
+        };
+        let digits = last_number.saturating_sub(1).to_string().len();
+
+        let mut names = Vec::with_capacity(count);
+        let mut indicies = HashMap::new();
+        let mut shown = Duration::ZERO;
+        for (index, &duration) in durations.iter().enumerate().take(end) {
+            let timestamp = shown;
+            shown += duration;
+            if index < range.start {
+                continue;
+            }
+
+            let number = match self.start_number {
+                Some(start_number) => start_number + index - range.start,
+                None => index,
+            };
+            let name = match self.template {
+                Some(template) => template.render(&FrameName {
+                    stem,
+                    index: number,
+                    timestamp,
+                    extension,
+                }),
+                None => format!(
+                    "{}{:0digits$}.{}",
+                    self.prefix,
+                    number,
+                    extension,
+                    digits = digits
+                ),
+            };
+            if let Some(named) = indicies.insert(name.clone(), index) {
+                return Err(TemplateError::SameName(named, index, name).into());
+            }
+            names.push(name);
+        }
+        Ok(names)
+    }
+}
+
+fn parse_placeholder(placeholder: &str) -> Result<Part> {
+    let (key, width) = match placeholder.split_once(':') {
+        Some((key, width)) => (key, Some(width)),
+        None => (placeholder, None),
+    };
+    let width = match width {
+        Some(width) => Some(
+            width
+                .parse::<usize>()
+                .map_err(|_| TemplateError::InvalidWidth(width.to_string()))?,
+        ),
+        None => None,
+    };
+
+    let part = match key {
+        "stem" => Part::Stem,
+        "index" => Part::Index(width.unwrap_or(0)),
+        "ms" => Part::Milliseconds,
+        "ext" => Part::Extension,
+        _ => return Err(TemplateError::UnknownPlaceholder(key.to_string()).into()),
+    };
+    if width.is_some() && !matches!(part, Part::Index(_)) {
+        return Err(TemplateError::UnexpectedWidth(key.to_string()).into());
+    }
+    Ok(part)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FrameName, NameTemplate, Numbering};
+
+    use std::time::Duration;
+
+    #[test]
+    fn fills_in_placeholders() {
+        let name = FrameName {
+            stem: "cat",
+            index: 7,
+            timestamp: Duration::from_millis(1250),
+            extension: "png",
+        };
+        let render = |template: &str| NameTemplate::parse(template).unwrap().render(&name);
+
+        assert_eq!(render("{stem}_{index:04}.{ext}"), "cat_0007.png");
+        assert_eq!(render("{index}-{ms}ms.{ext}"), "7-1250ms.png");
+        assert_eq!(render("{{{stem}}}.{ext}"), "{cat}.png");
+
+        assert!(NameTemplate::parse("{frame}.png").is_err());
+        assert!(NameTemplate::parse("{stem:4}.png").is_err());
+        assert!(NameTemplate::parse("{index:four}.png").is_err());
+        assert!(NameTemplate::parse("{index.png").is_err());
+        assert!(NameTemplate::parse("index}.png").is_err());
+    }
+
+    #[test]
+    fn numbers_frames() {
+        let durations = [100, 200, 300, 400].map(Duration::from_millis);
+        let numbering = Numbering {
+            template: None,
+            prefix: "frame_",
+            start_number: None,
+        };
+        let names = |numbering: Numbering, range| numbering.names("cat", "png", &durations, range);
+
+        // padded to the last frame of the file, whatever the range
+        assert_eq!(
+            names(numbering, 1..3).unwrap(),
+            ["frame_1.png", "frame_2.png"]
+        );
+        let eleven_frames = [Duration::ZERO; 11];
+        assert_eq!(
+            numbering
+                .names("cat", "png", &eleven_frames, 8..20)
+                .unwrap(),
+            ["frame_08.png", "frame_09.png", "frame_10.png"]
+        );
+
+        // or to the last number named
+        let numbering = Numbering {
+            start_number: Some(98),
+            ..numbering
+        };
+        assert_eq!(
+            names(numbering, 1..usize::MAX).unwrap(),
+            ["frame_098.png", "frame_099.png", "frame_100.png"]
+        );
+        let numbering = Numbering {
+            start_number: Some(usize::MAX),
+            ..numbering
+        };
+        assert!(names(numbering, 0..usize::MAX).is_err());
+
+        let template = NameTemplate::parse("{stem}_{index:02}_{ms}.{ext}").unwrap();
+        let numbering = Numbering {
+            template: Some(&template),
+            prefix: "",
+            start_number: None,
+        };
+        assert_eq!(
+            names(numbering, 2..4).unwrap(),
+            ["cat_02_300.png", "cat_03_600.png"]
+        );
+
+        // without {index} every frame gets the same name
+        let template = NameTemplate::parse("{stem}.{ext}").unwrap();
+        let numbering = Numbering {
+            template: Some(&template),
+            ..numbering
+        };
+        assert_eq!(names(numbering, 0..1).unwrap(), ["cat.png"]);
+        assert!(names(numbering, 0..2).is_err());
+    }
+}
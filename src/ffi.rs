@@ -0,0 +1,217 @@
+//! A C API for decoding GIFs, for embedding the decoder in C and C++ programs. Build it as a
+//! shared or static library with
+//!
+//! ```sh
+//! cargo rustc --release --lib --features capi --crate-type cdylib
+//! ```
+//!
+//! and include `include/jif.h`, which is generated from this module with
+//! `cbindgen --config cbindgen.toml --output include/jif.h`.
+//!
+//! A decoder owns a copy of the GIF and composites frames when they're asked for, keeping a few
+//! canvases around so going through the frames in order, or seeking back a little, stays cheap.
+
+use std::io::Cursor;
+use std::slice;
+
+use crate::parser::Decoder;
+
+/// What the `jif_decoder_*` functions return.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JifResult {
+    Ok = 0,
+    /// A pointer that has to be set was null.
+    NullPointer = -1,
+    /// There's no frame with that index.
+    OutOfRange = -2,
+    /// The output buffer is smaller than `width * height * 4` bytes.
+    BufferTooSmall = -3,
+    /// The frame couldn't be decoded.
+    DecodeFailed = -4,
+}
+
+/// A decoded GIF, created by `jif_decoder_new` and freed by `jif_decoder_free`.
+pub struct JifDecoder {
+    decoder: Decoder<Cursor<Vec<u8>>>,
+    width: u16,
+    height: u16,
+}
+
+/// Decodes the `len` bytes of GIF at `data`, which are copied and can be freed as soon as this
+/// returns. Returns null if they aren't a GIF that can be decoded.
+///
+/// # Safety
+///
+/// `data` has to point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn jif_decoder_new(data: *const u8, len: usize) -> *mut JifDecoder {
+    if data.is_null() {
+        return std::ptr::null_mut();
+    }
+    let data = slice::from_raw_parts(data, len).to_vec();
+
+    let mut decoder = Decoder::new(Cursor::new(data));
+    if decoder.parse().is_err() {
+        return std::ptr::null_mut();
+    }
+    let (width, height) = decoder.screen_size().unwrap_or((0, 0));
+
+    Box::into_raw(Box::new(JifDecoder {
+        decoder,
+        width,
+        height,
+    }))
+}
+
+/// Width of the logical screen every frame is composited onto, 0 for a null decoder.
+///
+/// # Safety
+///
+/// `decoder` has to be null or returned by `jif_decoder_new` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn jif_decoder_width(decoder: *const JifDecoder) -> u32 {
+    decoder.as_ref().map_or(0, |decoder| decoder.width as u32)
+}
+
+/// Height of the logical screen every frame is composited onto, 0 for a null decoder.
+///
+/// # Safety
+///
+/// `decoder` has to be null or returned by `jif_decoder_new` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn jif_decoder_height(decoder: *const JifDecoder) -> u32 {
+    decoder.as_ref().map_or(0, |decoder| decoder.height as u32)
+}
+
+/// Number of frames, 0 for a null decoder.
+///
+/// # Safety
+///
+/// `decoder` has to be null or returned by `jif_decoder_new` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn jif_decoder_frame_count(decoder: *const JifDecoder) -> usize {
+    decoder
+        .as_ref()
+        .map_or(0, |decoder| decoder.decoder.frames().len())
+}
+
+/// How long frame `index` is shown for in milliseconds, with delays too short for browsers to
+/// honour played at 100ms like they do. 0 if there's no such frame.
+///
+/// # Safety
+///
+/// `decoder` has to be null or returned by `jif_decoder_new` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn jif_decoder_frame_duration_ms(
+    decoder: *const JifDecoder,
+    index: usize,
+) -> u32 {
+    decoder
+        .as_ref()
+        .and_then(|decoder| decoder.decoder.frames().get(index))
+        .map_or(0, |frame| frame.duration().as_millis() as u32)
+}
+
+/// Writes what's on screen while frame `index` is shown to `out`, as `width * height` RGBA
+/// pixels row by row. `len` is the size of `out` in bytes.
+///
+/// # Safety
+///
+/// `decoder` has to be null or returned by `jif_decoder_new` and not freed yet, and `out` has
+/// to point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn jif_decoder_frame_rgba(
+    decoder: *mut JifDecoder,
+    index: usize,
+    out: *mut u8,
+    len: usize,
+) -> JifResult {
+    let Some(decoder) = decoder.as_mut() else {
+        return JifResult::NullPointer;
+    };
+    if out.is_null() {
+        return JifResult::NullPointer;
+    }
+    if index >= decoder.decoder.frames().len() {
+        return JifResult::OutOfRange;
+    }
+    if len < decoder.width as usize * decoder.height as usize * 4 {
+        return JifResult::BufferTooSmall;
+    }
+
+    match decoder.decoder.frame_at(index) {
+        Ok(Some(canvas)) => {
+            slice::from_raw_parts_mut(out, canvas.len()).copy_from_slice(&canvas);
+            JifResult::Ok
+        }
+        Ok(None) | Err(_) => JifResult::DecodeFailed,
+    }
+}
+
+/// Frees a decoder. Null is ignored.
+///
+/// # Safety
+///
+/// `decoder` has to be null or returned by `jif_decoder_new`, and not used again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn jif_decoder_free(decoder: *mut JifDecoder) {
+    if !decoder.is_null() {
+        drop(Box::from_raw(decoder));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        jif_decoder_frame_count, jif_decoder_frame_duration_ms, jif_decoder_frame_rgba,
+        jif_decoder_free, jif_decoder_height, jif_decoder_new, jif_decoder_width, JifResult,
+    };
+    use crate::fixtures::{two_color_frame, two_color_gif};
+
+    #[test]
+    fn decodes_through_the_c_api() {
+        let mut first = two_color_frame(2, 1, &[0, 1]);
+        first.delay_time = 5;
+        let gif = two_color_gif(2, 1, &[first, two_color_frame(1, 1, &[1])]);
+
+        unsafe {
+            assert!(jif_decoder_new(b"not a gif".as_ptr(), 9).is_null());
+
+            let decoder = jif_decoder_new(gif.as_ptr(), gif.len());
+            assert!(!decoder.is_null());
+            assert_eq!(jif_decoder_width(decoder), 2);
+            assert_eq!(jif_decoder_height(decoder), 1);
+            assert_eq!(jif_decoder_frame_count(decoder), 2);
+            assert_eq!(jif_decoder_frame_duration_ms(decoder, 0), 50);
+
+            let mut rgba = [0; 8];
+            let out = rgba.as_mut_ptr();
+            assert_eq!(
+                jif_decoder_frame_rgba(decoder, 1, out, rgba.len()),
+                JifResult::Ok
+            );
+            assert_eq!(rgba, [0, 0, 255, 255, 0, 0, 255, 255]);
+            assert_eq!(
+                jif_decoder_frame_rgba(decoder, 0, out, rgba.len()),
+                JifResult::Ok
+            );
+            assert_eq!(rgba, [255, 0, 0, 255, 0, 0, 255, 255]);
+
+            assert_eq!(
+                jif_decoder_frame_rgba(decoder, 2, out, rgba.len()),
+                JifResult::OutOfRange
+            );
+            assert_eq!(
+                jif_decoder_frame_rgba(decoder, 0, out, 4),
+                JifResult::BufferTooSmall
+            );
+            assert_eq!(
+                jif_decoder_frame_rgba(std::ptr::null_mut(), 0, out, rgba.len()),
+                JifResult::NullPointer
+            );
+
+            jif_decoder_free(decoder);
+        }
+    }
+}
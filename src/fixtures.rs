@@ -0,0 +1,63 @@
+//! GIFs for the tests to decode, built in one place so every test makes them the same way.
+
+use crate::encoder::Encoder;
+use crate::parser::Frame;
+
+/// Red, then blue.
+pub(crate) const RED_BLUE: [u8; 6] = [255, 0, 0, 0, 0, 255];
+
+/// Encodes `frames` on a `width` x `height` screen. Frames whose palette is `global_palette`
+/// share it instead of carrying their own.
+pub(crate) fn encode(
+    width: u16,
+    height: u16,
+    global_palette: Option<&[u8]>,
+    frames: &[Frame],
+) -> Vec<u8> {
+    let mut encoder = Encoder::new(Vec::new(), width, height);
+    if let Some(global_palette) = global_palette {
+        encoder.set_global_palette(global_palette).unwrap();
+    }
+    for frame in frames {
+        encoder.write_frame(frame).unwrap();
+    }
+    encoder.finish().unwrap()
+}
+
+/// A frame in [`RED_BLUE`].
+pub(crate) fn two_color_frame(width: u16, height: u16, indicies: &[u8]) -> Frame {
+    Frame::new(width, height, indicies.into(), Box::new(RED_BLUE))
+}
+
+/// Encodes `frames` with [`RED_BLUE`] as the global palette.
+pub(crate) fn two_color_gif(width: u16, height: u16, frames: &[Frame]) -> Vec<u8> {
+    encode(width, height, Some(&RED_BLUE), frames)
+}
+
+/// Three 2x1 frames in [`RED_BLUE`] shown for `delay_time` each: red and blue, blue and red,
+/// then all blue.
+pub(crate) fn three_frame_gif(delay_time: u16) -> Vec<u8> {
+    let frames = [[0, 1], [1, 0], [1, 1]].map(|indicies| {
+        let mut frame = two_color_frame(2, 1, &indicies);
+        frame.delay_time = delay_time;
+        frame
+    });
+    two_color_gif(2, 1, &frames)
+}
+
+/// `frame_count` 8x8 frames, each with a four color palette of its own and its indicies
+/// shifted along by one from the frame before.
+pub(crate) fn test_frames(frame_count: u8) -> Vec<Frame> {
+    (0..frame_count)
+        .map(|i| {
+            let indicies: Box<[u8]> = (0..64).map(|pixel| (pixel + i) % 4).collect();
+            let palette: Box<[u8]> = (0..12).collect();
+            Frame::new(8, 8, indicies, palette)
+        })
+        .collect()
+}
+
+/// Encodes [`test_frames`] on an 8x8 screen.
+pub(crate) fn encode_test_gif(frame_count: u8) -> Vec<u8> {
+    encode(8, 8, None, &test_frames(frame_count))
+}
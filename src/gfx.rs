@@ -1,37 +1,150 @@
-use std::{fs::File, sync::Arc, time::{Duration, SystemTime}};
+use std::{io::Write, ops::Range, path::{Path, PathBuf}, sync::{mpsc::{Receiver, TryRecvError}, Arc}, time::{Duration, Instant}};
+use anyhow::{anyhow, Result};
+use log::{error, info, warn};
 use pollster::FutureExt as _;
 
 use winit::{
-    application::ApplicationHandler, dpi::PhysicalSize, event::WindowEvent, event_loop::{ActiveEventLoop, EventLoop}, window::{Window, WindowId}
+    application::ApplicationHandler, dpi::{PhysicalPosition, PhysicalSize}, event::{ElementState, KeyEvent, MouseButton, WindowEvent}, event_loop::{ActiveEventLoop, ControlFlow, EventLoop}, keyboard::{Key, ModifiersState}, monitor::MonitorHandle, window::{Fullscreen, Window, WindowId, WindowLevel}
 };
 
-use wgpu::{Adapter, BindGroup, BindGroupLayout, Device, Instance, MemoryHints, PresentMode, Queue, Surface, SurfaceCapabilities, BindGroupLayoutDescriptor, Texture};
+use wgpu::{Adapter, BindGroup, BindGroupLayout, Device, Instance, MemoryHints, PresentMode, Queue, Surface, SurfaceCapabilities, Texture, TextureFormat};
 
-use crate::parser::Decoder;
+use crate::cli::{Filter, FrameFormat};
+use crate::commands::stats::collect_gifs;
+use crate::input::{self, Input};
+use crate::loader::{self, LoadEvent};
+use crate::locale::{Catalog, Message};
+use crate::recorder::Recorder;
+use crate::scrub_bar::{self, ScrubBar};
+use crate::texture::{ChannelOrder, TextureCanvas, TextureWrite};
+use jif::compositor::Rect;
+use jif::edit::{Edit, EditList, EditPlan};
+use jif::export::{ImageFormat, Png, Ppm, Qoi};
+use jif::output::{write_atomically, OutputOptions};
+use jif::parser::{Decoder, Frame, LoopCount};
+use jif::scale;
+use jif::trace::Span;
 
-pub async fn run() {
+pub mod compare;
+
+// how many frames from each end of the animation the seam check plays
+const SEAM_FRAMES: usize = 5;
+// how often the frame number in the title is updated during playback
+const TITLE_INTERVAL: Duration = Duration::from_millis(250);
+// how much of the monitor the window can take up at first
+const MAX_MONITOR_FRACTION: f64 = 0.9;
+
+/// Opens the viewer on the first of `playlist`. Without `vsync` frames are presented as soon as
+/// they're drawn, which lowers latency at the cost of tearing. Every frame that's presented is
+/// recorded with `recorder`, if there is one.
+pub async fn run(playlist: Vec<PathBuf>, vsync: bool, ignore_aspect_ratio: bool, screenshots: Screenshots, recorder: Option<Recorder>, scaling: Scaling) {
     let event_loop = EventLoop::new().unwrap();
-    let mut window_state = StateApplication::new();
+    let mut window_state = StateApplication::new(playlist, Catalog::from_env(), vsync, ignore_aspect_ratio, screenshots, recorder, scaling);
     let _ = event_loop.run_app(&mut window_state);
 
 }
 
+/// Where S saves what's on screen, as `frame_<n>` followed by the format's extension.
+#[derive(Debug, Clone)]
+pub struct Screenshots {
+    pub dir: PathBuf,
+    pub format: FrameFormat,
+}
+
+/// How the GIF is scaled to the window, both of which can be switched while it plays.
+#[derive(Debug, Clone, Copy)]
+pub struct Scaling {
+    pub filter: Filter,
+    // only whole multiples of the GIF's size, with square pixels
+    pub pixel_perfect: bool,
+}
+
 struct StateApplication<'a> {
     state: Option<State<'a>>,
+    // the GIFs that can be switched between, each decoded when it's switched to
+    playlist: Vec<PathBuf>,
+    current: usize,
+    catalog: Catalog,
+    modifiers: ModifiersState,
+    vsync: bool,
+    // show pixels square whatever the GIF says
+    ignore_aspect_ratio: bool,
+    screenshots: Screenshots,
+    // handed to the state once the window is open
+    recorder: Option<Recorder>,
+    scaling: Scaling,
 }
 
 impl<'a> StateApplication<'a> {
-    pub fn new() -> Self {
+    pub fn new(playlist: Vec<PathBuf>, catalog: Catalog, vsync: bool, ignore_aspect_ratio: bool, screenshots: Screenshots, recorder: Option<Recorder>, scaling: Scaling) -> Self {
         Self {
-            state: None
+            state: None,
+            playlist,
+            current: 0,
+            catalog,
+            modifiers: ModifiersState::default(),
+            vsync,
+            ignore_aspect_ratio,
+            screenshots,
+            recorder,
+            scaling,
+        }
+    }
+}
+
+impl<'a> StateApplication<'a> {
+    // moves `step` GIFs along the playlist, wrapping around at either end. A GIF that can't be
+    // opened is skipped over
+    fn switch_gif(&mut self, step: usize) {
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
+
+        let mut next = self.current;
+        for _ in 1..self.playlist.len() {
+            next = (next + step) % self.playlist.len();
+            match OpenedGif::open(&self.playlist[next], self.catalog, self.ignore_aspect_ratio) {
+                Ok(gif) => {
+                    state.load(gif);
+                    self.current = next;
+                    return;
+                },
+                Err(err) => error!("{}", err),
+            }
         }
     }
 }
 
 impl<'a> ApplicationHandler for StateApplication<'a> {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        let window = event_loop.create_window(Window::default_attributes()).unwrap();
-        self.state = Some(State::new(window));
+        // the file is opened first so the window can start out the size of the GIF
+        let gif = match OpenedGif::open(&self.playlist[self.current], self.catalog, self.ignore_aspect_ratio) {
+            Ok(gif) => gif,
+            Err(err) => {
+                error!("{}", err);
+                event_loop.exit();
+                return;
+            }
+        };
+
+        let loading_title = self.catalog.format(Message::Loading, &[("filename", &gif.filename)]);
+        let mut attributes = Window::default_attributes().with_title(loading_title);
+        let monitor = event_loop.primary_monitor().or_else(|| event_loop.available_monitors().next());
+        if let Some(size) = initial_window_size(gif.screen_size, gif.pixel_aspect_ratio, monitor) {
+            attributes = attributes.with_inner_size(size);
+        }
+        let window = event_loop.create_window(attributes).unwrap();
+
+        match State::new(window, gif, self.catalog, self.vsync, self.scaling) {
+            Ok(mut state) => {
+                state.recorder = self.recorder.take();
+                self.state = Some(state);
+            },
+            Err(err) => {
+                error!("{}", err);
+                event_loop.exit();
+            }
+        }
     }
 
     fn window_event(
@@ -40,28 +153,153 @@ impl<'a> ApplicationHandler for StateApplication<'a> {
         window_id: WindowId,
         event: WindowEvent,
     ) {
-        let window = self.state.as_ref().unwrap().window();
+        let Some(state) = self.state.as_ref() else {
+            return;
+        };
+        let window = state.window();
 
         if window.id() == window_id {
             match event {
                 WindowEvent::CloseRequested => {
-                    println!("close has been requested");
                     event_loop.exit();
                 },
                 WindowEvent::Resized(physical_size) => {
-                    self.state.as_mut().unwrap().resize(physical_size);
+                    let state = self.state.as_mut().unwrap();
+                    state.resize(physical_size);
+                    state.window().request_redraw();
                 },
                 WindowEvent::RedrawRequested => {
-                    self.state.as_mut().unwrap().render().unwrap();
+                    let state = self.state.as_mut().unwrap();
+                    match state.render() {
+                        Ok(()) => {},
+                        // the surface no longer fits the window, configuring it again fixes that
+                        Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => state.resize(state.size),
+                        Err(wgpu::SurfaceError::OutOfMemory) => {
+                            error!("{}", self.catalog.get(Message::OutOfMemory));
+                            event_loop.exit();
+                        },
+                        // the frame is drawn again next time around
+                        Err(wgpu::SurfaceError::Timeout) => warn!("timed out waiting for the surface"),
+                    }
+                },
+                WindowEvent::ModifiersChanged(modifiers) => {
+                    self.modifiers = modifiers.state();
+                },
+                WindowEvent::KeyboardInput { event: KeyEvent { logical_key, state: ElementState::Pressed, .. }, .. } => {
+                    let state = self.state.as_mut().unwrap();
+                    match logical_key.as_ref() {
+                        Key::Character("f") | Key::Character("F") => state.toggle_fullscreen(),
+                        Key::Character("t") | Key::Character("T") => state.toggle_always_on_top(),
+                        Key::Character("l") | Key::Character("L") => state.toggle_filter(),
+                        Key::Character("i") | Key::Character("I") => state.toggle_pixel_perfect(),
+                        Key::Character("n") | Key::Character("N") => self.switch_gif(1),
+                        Key::Character("p") | Key::Character("P") => self.switch_gif(self.playlist.len() - 1),
+                        // lowercase s is the seam check
+                        Key::Character("S") => state.save_screenshot(&self.screenshots),
+                        _ => state.handle_key(&logical_key, self.modifiers),
+                    }
+                    self.state.as_ref().unwrap().window().request_redraw();
+                },
+                WindowEvent::CursorMoved { position, .. } => {
+                    let state = self.state.as_mut().unwrap();
+                    state.handle_cursor_moved(position);
+                    if state.is_scrubbing() {
+                        state.window().request_redraw();
+                    }
+                },
+                WindowEvent::MouseInput { state: button_state, button: MouseButton::Left, .. } => {
+                    let state = self.state.as_mut().unwrap();
+                    state.handle_left_button(button_state);
+                    state.window().request_redraw();
                 },
                 _ => {}
             }
         }
     }
 
+    // redraws when the next frame is due, rather than as often as possible
     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
-        let window = self.state.as_ref().unwrap().window();
-        window.request_redraw();
+        let Some(state) = self.state.as_ref() else {
+            return;
+        };
+
+        match state.next_frame_at() {
+            Some(deadline) if deadline > Instant::now() => event_loop.set_control_flow(ControlFlow::WaitUntil(deadline)),
+            Some(_) => {
+                state.window().request_redraw();
+                event_loop.set_control_flow(ControlFlow::Wait);
+            },
+            None => event_loop.set_control_flow(ControlFlow::Wait),
+        }
+    }
+}
+
+/// The texture bound with each of the samplers the viewer can switch between.
+struct TextureBindGroups {
+    nearest: BindGroup,
+    linear: BindGroup,
+}
+
+impl TextureBindGroups {
+    fn get(&self, filter: Filter) -> &BindGroup {
+        match filter {
+            Filter::Nearest => &self.nearest,
+            Filter::Linear => &self.linear,
+        }
+    }
+}
+
+/// A GIF that's started loading, with its first frame in.
+struct OpenedGif {
+    filename: String,
+    screen_size: (u16, u16),
+    // none when pixels are shown square
+    pixel_aspect_ratio: Option<f32>,
+    loop_count: Option<LoopCount>,
+    first_frame: Frame,
+    loader: Receiver<LoadEvent>,
+    seeker: Decoder<Input>,
+}
+
+impl OpenedGif {
+    fn open(path: &Path, catalog: Catalog, ignore_aspect_ratio: bool) -> Result<Self> {
+        let filename = display_name(path);
+        let open_failed = |err: anyhow::Error| {
+            anyhow!(catalog.format(Message::OpenFailed, &[("filename", &filename), ("error", &err)]))
+        };
+        let mut input = input::open(path).map_err(open_failed)?;
+
+        let seeker = Decoder::new(input.reopen().map_err(open_failed)?);
+
+        // decoding carries on in the background, playback starts as soon as the first frame is
+        // in, even while a URL is still downloading
+        let length = input.length();
+        let loader = loader::spawn(input, length);
+        let mut screen_size = (0, 0);
+        let mut pixel_aspect_ratio = None;
+        let mut loop_count = None;
+        let first_frame = loop {
+            match loader.recv() {
+                Ok(LoadEvent::Header { width, height, loop_count: header_loop_count, pixel_aspect_ratio: header_pixel_aspect_ratio }) => {
+                    screen_size = (width, height);
+                    pixel_aspect_ratio = header_pixel_aspect_ratio.filter(|_| !ignore_aspect_ratio);
+                    loop_count = header_loop_count;
+                },
+                Ok(LoadEvent::Frame(frame)) => break frame,
+                Ok(LoadEvent::Progress(_)) => {},
+                Ok(LoadEvent::Finished) => {
+                    return Err(anyhow!(catalog.format(Message::NoFrames, &[("filename", &filename)])));
+                },
+                Ok(LoadEvent::Failed(err)) => {
+                    return Err(anyhow!(catalog.format(Message::DecodeFailed, &[("filename", &filename), ("error", &err)])));
+                },
+                Err(err) => {
+                    return Err(anyhow!(catalog.format(Message::DecodeFailed, &[("filename", &filename), ("error", &err)])));
+                },
+            }
+        };
+
+        Ok(Self { filename, screen_size, pixel_aspect_ratio, loop_count, first_frame, loader, seeker })
     }
 }
 
@@ -70,10 +308,49 @@ struct State<'a> {
     device: Device,
     queue: Queue,
     config: wgpu::SurfaceConfiguration,
-    decoder: Decoder<File>,
-    texture_bind_group: BindGroup,
+    frames: Vec<Frame>,
+    screen_size: (u16, u16),
+    // applied when drawing, so pixels keep their shape however the window's resized
+    pixel_aspect_ratio: Option<f32>,
+    loop_count: Option<LoopCount>,
+    // frames still being decoded, none once everything has arrived
+    loader: Option<Receiver<LoadEvent>>,
+    // how much of the file the loader has read, none if its length isn't known
+    load_percent: Option<u32>,
+    catalog: Catalog,
+    filename: String,
+    edits: EditList,
+    edit_plan: EditPlan,
+    texture_bind_groups: TextureBindGroups,
+    texture_bind_group_layout: BindGroupLayout,
+    scaling: Scaling,
+    // sized to the logical screen of the GIF that's playing
     texture: Texture,
-    last_rendered: Option<SystemTime>,
+    texture_format: TextureFormat,
+    channel_order: ChannelOrder,
+    // what's in the texture, so playing a frame only uploads the pixels it changes
+    texture_canvas: TextureCanvas,
+    // a copy of the texture in memory, in its channel order, for screenshots and recording
+    texture_pixels: Vec<u8>,
+    recorder: Option<Recorder>,
+    // the frame that's been written to the texture but not presented and recorded yet, with its delay
+    unrecorded: Option<(usize, Duration)>,
+    // when the first recorded frame was presented, which the others are timed from
+    recording_started: Option<Instant>,
+    // when the frame on screen has been shown for its delay, none to show the next one right away
+    next_frame_at: Option<Instant>,
+    // playing the end of the animation into its start, to see whether it loops cleanly
+    seam_check: bool,
+    // with the seam check, showing the first and last frame blended instead of playing
+    seam_overlay: bool,
+    scrub_bar: ScrubBar,
+    // a decoder of its own for seeking with the scrub bar, the loader's is on another thread
+    seeker: Decoder<Input>,
+    cursor_position: PhysicalPosition<f64>,
+    // winit can set the window level but not read it back
+    always_on_top: bool,
+    // when the title was last set, it follows playback but not on every frame
+    title_updated: Option<Instant>,
 
     size: PhysicalSize<u32>,
     window: Arc<Window>,
@@ -82,7 +359,9 @@ struct State<'a> {
 }
 
 impl<'a> State<'a> {
-    pub fn new(window: Window) -> Self {
+    pub fn new(window: Window, gif: OpenedGif, catalog: Catalog, vsync: bool, scaling: Scaling) -> Result<Self> {
+        let OpenedGif { filename, screen_size, pixel_aspect_ratio, loop_count, first_frame, loader, seeker } = gif;
+
         let window_arc = Arc::new(window);
         let size = window_arc.inner_size();
         let instance = Self::create_gpu_instance();
@@ -90,39 +369,68 @@ impl<'a> State<'a> {
         let adapter = Self::create_adapter(instance, &surface);
         let (device, queue) = Self::create_device(&adapter);
         let surface_caps = surface.get_capabilities(&adapter);
-        let config = Self::create_surface_config(size, surface_caps);
-        let file = File::open("./homeless-nah-id-win.gif").unwrap();
+        let present_mode = if vsync { PresentMode::AutoVsync } else { PresentMode::AutoNoVsync };
+        let config = Self::create_surface_config(size, surface_caps, present_mode);
+        let (channel_order, texture_format) = ChannelOrder::for_surface(config.format);
 
-        let mut decoder = Decoder::new(file);
-        decoder.parse().unwrap();
-
-        let (texture_bind_group, texture_bind_group_layout, texture) = Self::create_texture_bind_group(&decoder, &device, &queue);
+        let texture_bind_group_layout = Self::create_texture_bind_group_layout(&device);
+        let (texture_bind_groups, texture) = Self::create_texture_bind_groups(screen_size, &device, texture_format, &texture_bind_group_layout);
         let render_pipeline = Self::create_render_pipeline(&device, &config, &texture_bind_group_layout);
+        let scrub_bar = ScrubBar::new(&device, config.format);
 
-        surface.configure(&device, &config);
+        if !is_minimized(size) {
+            surface.configure(&device, &config);
+        }
 
-        Self {
+        let edits = EditList::new();
+        let edit_plan = edits.plan(screen_size.0, screen_size.1, 1);
+
+        Ok(Self {
             surface,
             device,
             queue,
             config,
             size,
-            texture_bind_group,
+            texture_bind_groups,
+            texture_bind_group_layout,
+            scaling,
             texture,
+            texture_format,
+            channel_order,
+            texture_canvas: TextureCanvas::new(screen_size),
+            texture_pixels: vec![0; screen_size.0 as usize * screen_size.1 as usize * 4],
+            recorder: None,
+            unrecorded: None,
+            recording_started: None,
             render_pipeline,
             window: window_arc,
-            decoder,
+            frames: vec![first_frame],
+            screen_size,
+            pixel_aspect_ratio,
+            loop_count,
+            loader: Some(loader),
+            load_percent: None,
+            catalog,
+            filename,
+            edits,
+            edit_plan,
             frame_idx: 0,
-            last_rendered: None
-        }
+            next_frame_at: None,
+            seam_check: false,
+            seam_overlay: false,
+            scrub_bar,
+            seeker,
+            cursor_position: PhysicalPosition::default(),
+            always_on_top: false,
+            title_updated: None,
+        })
     }
 
-    fn create_texture_bind_group(decoder: &Decoder<File>, device: &Device, queue: &Queue) -> (BindGroup, BindGroupLayout, Texture) {
-        let frame = decoder.frames().first().unwrap();
-
+    // the texture starts out transparent, the first frame is written to it when it's played
+    fn create_texture_bind_groups((width, height): (u16, u16), device: &Device, format: TextureFormat, texture_bind_group_layout: &BindGroupLayout) -> (TextureBindGroups, Texture) {
         let texture_size = wgpu::Extent3d {
-            width: frame.width as u32,
-            height: frame.height as u32,
+            width: width.max(1) as u32,
+            height: height.max(1) as u32,
             depth_or_array_layers: 1,
         };
 
@@ -132,56 +440,53 @@ impl<'a> State<'a> {
                 mip_level_count: 1,
                 sample_count: 1,
                 dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                format,
                 usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
                 label: None,
                 view_formats: &[],
             }
         );
 
-        let palette = frame.palette().unwrap();
-        let texture_buffer: Vec<u8> = frame
-            .indicies()
-            .iter()
-            .flat_map(|index| {
-                let color_idx = (*index as usize) * 3;
-
-                let red = *palette.get(color_idx).unwrap();
-                let green = *palette.get(color_idx + 1).unwrap();
-                let blue = *palette.get(color_idx + 2).unwrap();
-
-                vec![red, green, blue, 1]
-            })
-            .collect();
-
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &diffuse_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::default(),
-            },
-            &texture_buffer,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * frame.width as u32),
-                rows_per_image: Some(frame.height as u32),
-            },
-            texture_size
-        );
-
         let diffuse_texture_view = diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let diffuse_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
+        // a bind group for each filter, so switching between them doesn't create anything
+        let bind_group = |filter: wgpu::FilterMode| {
+            let diffuse_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: filter,
+                min_filter: filter,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            });
+
+            device.create_bind_group(
+                &wgpu::BindGroupDescriptor {
+                    layout: texture_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&diffuse_texture_view)
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(&diffuse_sampler)
+                        },
+                    ],
+                    label: None
+                }
+            )
+        };
+
+        let bind_groups = TextureBindGroups {
+            nearest: bind_group(wgpu::FilterMode::Nearest),
+            linear: bind_group(wgpu::FilterMode::Linear),
+        };
+        (bind_groups, diffuse_texture)
+    }
 
-        let texture_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+    fn create_texture_bind_group_layout(device: &Device) -> BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             entries: &[
                 wgpu::BindGroupLayoutEntry {
                     binding: 0,
@@ -201,29 +506,10 @@ impl<'a> State<'a> {
                 }
             ],
             label: None
-        });
-
-        let diffuse_bind_group = device.create_bind_group(
-            &wgpu::BindGroupDescriptor {
-                layout: &texture_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&diffuse_texture_view)
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&diffuse_sampler)
-                    },
-                ],
-                label: None
-            }
-        );
-
-        (diffuse_bind_group, texture_bind_group_layout, diffuse_texture)
+        })
     }
 
-    fn create_surface_config(size: PhysicalSize<u32>, capabilities: SurfaceCapabilities) -> wgpu::SurfaceConfiguration {
+    fn create_surface_config(size: PhysicalSize<u32>, capabilities: SurfaceCapabilities, present_mode: PresentMode) -> wgpu::SurfaceConfiguration {
         let surface_format = capabilities.formats.iter()
             .find(|f| f.is_srgb())
             .copied()
@@ -234,7 +520,7 @@ impl<'a> State<'a> {
             format: surface_format,
             width: size.width,
             height: size.height,
-            present_mode: PresentMode::AutoNoVsync,
+            present_mode,
             alpha_mode: capabilities.alpha_modes[0],
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
@@ -257,7 +543,7 @@ impl<'a> State<'a> {
         instance.request_adapter(
             &wgpu::RequestAdapterOptions {
                 power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
+                compatible_surface: Some(surface),
                 force_fallback_adapter: false,
             }
         ).block_on().unwrap()
@@ -270,80 +556,379 @@ impl<'a> State<'a> {
         })
     }
 
+    /// Swaps in another GIF, played from its first frame without any edits. The window is
+    /// resized to fit it unless it's fullscreen.
+    pub fn load(&mut self, gif: OpenedGif) {
+        let OpenedGif { filename, screen_size, pixel_aspect_ratio, loop_count, first_frame, loader, seeker } = gif;
+
+        let (texture_bind_groups, texture) = Self::create_texture_bind_groups(screen_size, &self.device, self.texture_format, &self.texture_bind_group_layout);
+        self.texture_bind_groups = texture_bind_groups;
+        self.texture = texture;
+        self.texture_canvas = TextureCanvas::new(screen_size);
+        self.texture_pixels = vec![0; screen_size.0 as usize * screen_size.1 as usize * 4];
+        self.unrecorded = None;
+
+        self.filename = filename;
+        self.screen_size = screen_size;
+        self.pixel_aspect_ratio = pixel_aspect_ratio;
+        self.loop_count = loop_count;
+        self.frames = vec![first_frame];
+        self.loader = Some(loader);
+        self.seeker = seeker;
+        self.edits = EditList::new();
+        self.frame_idx = 0;
+        self.next_frame_at = None;
+        self.seam_check = false;
+        self.seam_overlay = false;
+        self.scrub_bar.dragging = false;
+        self.update_edit_plan();
+        self.update_title();
+
+        if self.window.fullscreen().is_none() {
+            if let Some(size) = initial_window_size(screen_size, pixel_aspect_ratio, self.window.current_monitor()) {
+                // the surface is configured for the new size once the resize comes in
+                let _ = self.window.request_inner_size(size);
+            }
+        }
+    }
+
+    pub fn toggle_fullscreen(&mut self) {
+        let fullscreen = match self.window.fullscreen() {
+            Some(_) => None,
+            None => Some(Fullscreen::Borderless(None)),
+        };
+        self.window.set_fullscreen(fullscreen);
+        // some platforms don't send a resize for the change, so don't wait for one
+        self.resize(self.window.inner_size());
+    }
+
+    pub fn toggle_filter(&mut self) {
+        self.scaling.filter = match self.scaling.filter {
+            Filter::Nearest => Filter::Linear,
+            Filter::Linear => Filter::Nearest,
+        };
+    }
+
+    pub fn toggle_pixel_perfect(&mut self) {
+        self.scaling.pixel_perfect = !self.scaling.pixel_perfect;
+    }
+
+    /// Keeps the window above the others, for using it as a little floating reference.
+    pub fn toggle_always_on_top(&mut self) {
+        self.always_on_top = !self.always_on_top;
+        let level = if self.always_on_top { WindowLevel::AlwaysOnTop } else { WindowLevel::Normal };
+        self.window.set_window_level(level);
+    }
+
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
         self.size = new_size;
 
+        // a minimized window has no surface to draw on, it's configured again once it's back
+        if is_minimized(new_size) {
+            return;
+        }
+
         self.config.width = new_size.width;
         self.config.height = new_size.height;
 
         self.surface.configure(&self.device, &self.config);
+    }
 
-        println!("Resized to {:?} from state!", new_size);
+    /// Edits are only recorded here, playback follows their trim and speed but the frames
+    /// themselves are left alone until they're exported.
+    pub fn handle_key(&mut self, key: &Key, modifiers: ModifiersState) {
+        let position = self.shown_frame_idx() - self.edit_plan.frames.start;
+
+        match key.as_ref() {
+            Key::Character("z") if modifiers.control_key() && !modifiers.shift_key() => {
+                if let Some(edit) = self.edits.undo() {
+                    info!("undid {:?}", edit);
+                }
+            },
+            Key::Character("y") | Key::Character("Z") if modifiers.control_key() => {
+                if let Some(edit) = self.edits.redo() {
+                    info!("redid {:?}", edit);
+                }
+            },
+            // trim so the current frame is the first or the last one kept, frames that are still
+            // loading are kept by the first
+            Key::Character("[") => self.record_edit(Edit::Trim { start: position, end: usize::MAX }),
+            Key::Character("]") => self.record_edit(Edit::Trim { start: 0, end: position + 1 }),
+            Key::Character("+") | Key::Character("=") => self.record_edit(Edit::Speed(2.0)),
+            Key::Character("-") => self.record_edit(Edit::Speed(0.5)),
+            Key::Character("s") => {
+                self.seam_check = !self.seam_check;
+                self.seam_overlay = false;
+                if let (true, Some((_, tail_start))) = (self.seam_check, self.seam_windows()) {
+                    self.frame_idx = tail_start;
+                }
+                self.update_title();
+            },
+            Key::Character("o") if self.seam_check => self.seam_overlay = !self.seam_overlay,
+            Key::Character("b") => {
+                self.scrub_bar.visible = !self.scrub_bar.visible;
+                self.scrub_bar.dragging = false;
+            },
+            _ => return,
+        }
+
+        self.update_edit_plan();
+        if self.seam_overlay {
+            self.write_seam_overlay();
+        }
     }
 
-    pub fn write_next_texture(&mut self) {
-        let frame = self.decoder.frames().get(self.frame_idx).unwrap();
-        let should_render = match self.last_rendered {
-            Some(time) => {
-                time.elapsed().unwrap() >= Duration::from_millis(frame.delay_time.into())
+    /// Saves what's on screen, without the scrub bar, named after the frame that's showing.
+    pub fn save_screenshot(&self, screenshots: &Screenshots) {
+        let rgba = self.texture_rgba();
+        let (width, height) = self.screen_size;
+        let (image, extension) = match screenshots.format {
+            FrameFormat::Png => (Png::encode(width, height, &rgba), Png::EXTENSION),
+            FrameFormat::Ppm => (Ppm::encode(width, height, &rgba), Ppm::EXTENSION),
+            FrameFormat::Qoi => (Qoi::encode(width, height, &rgba), Qoi::EXTENSION),
+        };
+        let path = screenshots.dir.join(format!("frame_{}.{}", self.shown_frame_idx(), extension));
+        match write_atomically(&path, OutputOptions::default(), |file| Ok(file.write_all(&image)?)) {
+            Ok(()) => info!("{}", self.catalog.format(Message::ScreenshotSaved, &[("path", &path.display())])),
+            Err(err) => error!("{}", self.catalog.format(Message::ScreenshotFailed, &[("path", &path.display()), ("error", &err)])),
+        }
+    }
+
+    pub fn handle_cursor_moved(&mut self, position: PhysicalPosition<f64>) {
+        self.cursor_position = position;
+        if self.scrub_bar.dragging {
+            self.seek_to_cursor();
+        }
+    }
+
+    /// Clicking the scrub bar seeks to that point, and keeps seeking as the cursor is dragged
+    /// until the button is let go.
+    pub fn handle_left_button(&mut self, button_state: ElementState) {
+        match button_state {
+            ElementState::Pressed if !self.seam_overlay && self.scrub_bar.contains(self.cursor_position.y, self.size.height) => {
+                self.scrub_bar.dragging = true;
+                self.seek_to_cursor();
             },
-            None => {
-                self.last_rendered = Some(SystemTime::now());
-                true
+            ElementState::Released => self.scrub_bar.dragging = false,
+            _ => {},
+        }
+    }
+
+    fn seek_to_cursor(&mut self) {
+        let index = scrub_bar::frame_at_position(self.cursor_position.x, self.size.width, &self.edit_plan.frames);
+        if index == self.shown_frame_idx() {
+            return;
+        }
+
+        // the seeker keeps canvases to start from, which is quicker than compositing from the
+        // first frame for every position the cursor passes
+        match self.seeker.frame_at(index) {
+            Ok(Some(canvas)) => {
+                let screen = Rect::new(0, 0, self.screen_size.0, self.screen_size.1);
+                let texture_write = TextureWrite::region(&canvas, self.screen_size, screen, self.channel_order);
+                self.write_texture(&texture_write);
+                self.texture_canvas.invalidate();
+            },
+            Ok(None) => return,
+            Err(err) => {
+                warn!("couldn't seek to frame {}: {}", index, err);
+                return;
+            },
+        }
+
+        // carry on playing from there, with the frame shown for its full delay
+        self.frame_idx = index;
+        self.frame_idx = self.next_frame_idx();
+        self.next_frame_at = Some(Instant::now() + self.frames[index].duration().div_f32(self.edit_plan.speed));
+    }
+
+    pub fn is_scrubbing(&self) -> bool {
+        self.scrub_bar.dragging
+    }
+
+    /// When the next frame is due, none while playback is held on one frame or there's nowhere
+    /// to show it.
+    pub fn next_frame_at(&self) -> Option<Instant> {
+        if self.seam_overlay || self.scrub_bar.dragging || is_minimized(self.size) {
+            return None;
+        }
+        Some(self.next_frame_at.unwrap_or_else(Instant::now))
+    }
+
+    // how far into the kept frames playback is, from 0 to 1
+    fn progress(&self) -> f32 {
+        let frames = &self.edit_plan.frames;
+        (self.shown_frame_idx() + 1 - frames.start) as f32 / frames.len().max(1) as f32
+    }
+
+    // frame_idx is the next frame to be shown, so the one on screen is the one before it
+    fn shown_frame_idx(&self) -> usize {
+        let frames = &self.edit_plan.frames;
+        match self.seam_windows() {
+            Some((head_end, tail_start)) if self.frame_idx == tail_start => head_end - 1,
+            _ if self.frame_idx > frames.start => self.frame_idx - 1,
+            _ => frames.end - 1,
+        }
+    }
+
+    fn next_frame_idx(&self) -> usize {
+        let frames = &self.edit_plan.frames;
+        let next = self.frame_idx + 1;
+        match self.seam_windows() {
+            _ if next >= frames.end => frames.start,
+            // skip from the first frames over the middle to the last ones
+            Some((head_end, tail_start)) if next >= head_end && next < tail_start => tail_start,
+            _ => next,
+        }
+    }
+
+    // where the first frames the seam check plays end and the last ones start, none when
+    // it's off or there are so few frames that it's the whole animation anyway
+    fn seam_windows(&self) -> Option<(usize, usize)> {
+        seam_windows(&self.edit_plan.frames).filter(|_| self.seam_check)
+    }
+
+    fn update_title(&mut self) {
+        let message = match (self.loader.is_some(), self.seam_check) {
+            (true, _) if self.load_percent.is_some() => Message::LoadingProgress,
+            (true, _) => Message::Loading,
+            (false, true) => Message::SeamCheckTitle,
+            (false, false) => Message::WindowTitle,
+        };
+        let loop_count = match self.loop_count {
+            Some(LoopCount::Infinite) => "∞".to_string(),
+            Some(LoopCount::Number(count)) => count.to_string(),
+            None => self.catalog.get(Message::PlaysOnce).to_string(),
+        };
+        let frames = &self.edit_plan.frames;
+        let title = self.catalog.format(message, &[
+            ("filename", &self.filename),
+            ("width", &self.screen_size.0),
+            ("height", &self.screen_size.1),
+            ("frame", &(self.shown_frame_idx() + 1 - frames.start)),
+            ("frames", &frames.len()),
+            ("loop", &loop_count),
+            ("percent", &self.load_percent.unwrap_or(0)),
+        ]);
+
+        self.window.set_title(&title);
+        self.title_updated = Some(Instant::now());
+    }
+
+    fn record_edit(&mut self, edit: Edit) {
+        info!("{:?}", edit);
+        self.edits.push(edit);
+    }
+
+    fn update_edit_plan(&mut self) {
+        let (width, height) = self.screen_size;
+        self.edit_plan = self.edits.plan(width, height, self.frames.len());
+
+        let frames = &self.edit_plan.frames;
+        if !frames.contains(&self.frame_idx) {
+            self.frame_idx = frames.start;
+        }
+    }
+
+    /// Picks up the frames the loader decoded since the last call.
+    fn receive_frames(&mut self) {
+        let Some(loader) = self.loader.as_ref() else {
+            return;
+        };
+
+        let frame_count = self.frames.len();
+        let mut load_percent = self.load_percent;
+        let finished = loop {
+            match loader.try_recv() {
+                Ok(LoadEvent::Frame(frame)) => self.frames.push(frame),
+                Ok(LoadEvent::Header { .. }) => {},
+                Ok(LoadEvent::Progress(progress)) => {
+                    load_percent = progress.fraction().map(|fraction| (fraction * 100.0) as u32);
+                },
+                Ok(LoadEvent::Finished) => break true,
+                // keep playing whatever was decoded before the error
+                Ok(LoadEvent::Failed(err)) => {
+                    error!("{}", self.catalog.format(Message::DecodeFailed, &[("filename", &self.filename), ("error", &err)]));
+                    break true;
+                },
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
             }
         };
 
-        if !should_render {
-            return
+        if finished {
+            self.loader = None;
+            self.update_title();
+        } else if load_percent != self.load_percent {
+            self.load_percent = load_percent;
+            self.update_title();
+        }
+        if self.frames.len() != frame_count {
+            self.update_edit_plan();
         }
+    }
 
-        self.frame_idx += 1;
-        if self.frame_idx == self.decoder.frames().len() {
-            self.frame_idx = 0;
+    pub fn write_next_texture(&mut self) {
+        self.receive_frames();
+        if self.seam_overlay || self.scrub_bar.dragging {
+            return;
         }
 
-        let texture_size = wgpu::Extent3d {
-            width: frame.width as u32,
-            height: frame.height as u32,
-            depth_or_array_layers: 1,
-        };
+        let now = Instant::now();
+        if self.next_frame_at.is_some_and(|deadline| now < deadline) {
+            return;
+        }
 
-        let palette = frame.palette().unwrap();
-        let texture_buffer: Vec<u8> = frame
-            .indicies()
-            .iter()
-            .flat_map(|index| {
-                let color_idx = (*index as usize) * 3;
-
-                let red = *palette.get(color_idx).unwrap();
-                let green = *palette.get(color_idx + 1).unwrap();
-                let blue = *palette.get(color_idx + 2).unwrap();
-
-                vec![red, green, blue, 1]
-            })
-            .collect();
-
-        self.queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &self.texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::default(),
-            },
-            &texture_buffer,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * frame.width as u32),
-                rows_per_image: Some(frame.height as u32),
+        let delay = self.frames[self.frame_idx].duration().div_f32(self.edit_plan.speed);
+        // stay on schedule, unless playback has fallen more than a frame behind, like after
+        // being held on a frame
+        self.next_frame_at = Some(match self.next_frame_at {
+            Some(deadline) if now - deadline < delay => deadline + delay,
+            _ => now + delay,
+        });
+
+        let texture_write = self.texture_canvas.show(&self.frames, self.frame_idx, self.channel_order);
+        self.write_texture(&texture_write);
+        if self.recorder.is_some() {
+            self.unrecorded = Some((self.frame_idx, delay));
+        }
+        self.frame_idx = self.next_frame_idx();
+
+        if self.loader.is_none() && self.title_updated.is_none_or(|updated| updated.elapsed() >= TITLE_INTERVAL) {
+            self.update_title();
+        }
+    }
+
+    /// Shows the first and last frame that are kept on top of each other, half see-through.
+    fn write_seam_overlay(&mut self) {
+        let frames = self.edit_plan.frames.clone();
+        let (first, last) = match (self.seeker.frame_at(frames.start), self.seeker.frame_at(frames.end - 1)) {
+            (Ok(Some(first)), Ok(Some(last))) => (first, last),
+            (Err(err), _) | (_, Err(err)) => {
+                warn!("couldn't show the seam overlay: {}", err);
+                return;
             },
-            texture_size
-        );
+            _ => return,
+        };
+        self.write_texture(&TextureWrite::blend(&first, &last, self.screen_size, self.channel_order));
+        self.texture_canvas.invalidate();
+    }
 
+    fn write_texture(&mut self, texture_write: &TextureWrite) {
+        if texture_write.pixels.is_empty() {
+            return;
+        }
+        texture_write.apply(&mut self.texture_pixels, self.screen_size.0);
+        upload(&self.queue, &self.texture, texture_write);
     }
 
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        if is_minimized(self.size) {
+            return Ok(());
+        }
+
         self.write_next_texture();
-        let output = self.surface.get_current_texture().unwrap();
+        let output = self.surface.get_current_texture()?;
         let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
 
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -372,16 +957,59 @@ impl<'a> State<'a> {
             });
 
             render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.texture_bind_group, &[]);
+            render_pass.set_bind_group(0, self.texture_bind_groups.get(self.scaling.filter), &[]);
+            let display_size = display_size(self.screen_size, self.pixel_aspect_ratio);
+            let viewport = match (self.scaling.pixel_perfect, self.pixel_aspect_ratio) {
+                (true, _) => Some(pixel_perfect_viewport(self.size, display_size)),
+                // letterboxed, since stretching over the window would change the pixels' shape
+                (false, Some(_)) => Some(fitted_viewport(self.size, display_size)),
+                (false, None) => None,
+            };
+            if let Some((x, y, width, height)) = viewport {
+                render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+            }
             render_pass.draw(0..6, 0..1);
+            // the scrub bar goes along the bottom of the window whatever size the GIF is shown at
+            render_pass.set_viewport(0.0, 0.0, self.size.width as f32, self.size.height as f32, 0.0, 1.0);
+
+            self.scrub_bar.draw(&self.queue, &mut render_pass, self.progress(), self.size.height);
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
         output.present();
+        self.record_presented();
 
         Ok(())
     }
 
+    // records the frame that was just presented, if it's one that hasn't been
+    fn record_presented(&mut self) {
+        let Some((index, delay)) = self.unrecorded.take() else {
+            return;
+        };
+        let presented = self.recording_started.get_or_insert_with(Instant::now).elapsed();
+        let rgba = self.texture_rgba();
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+        if let Err(err) = recorder.record(self.screen_size, rgba, &self.filename, index, delay, presented) {
+            // the rest would most likely fail the same way
+            error!("stopped recording: {}", err);
+            self.recorder = None;
+        }
+    }
+
+    // the texture's pixels in RGBA order, whatever order the texture's in
+    fn texture_rgba(&self) -> Vec<u8> {
+        let mut rgba = self.texture_pixels.clone();
+        if self.channel_order == ChannelOrder::Bgra {
+            for pixel in rgba.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        rgba
+    }
+
     pub fn window(&self) -> &Window {
         &self.window
     }
@@ -437,3 +1065,160 @@ impl<'a> State<'a> {
         })
     }
 }
+
+// the logical screen size, stretched to the pixel aspect ratio and scaled down to fit on
+// `monitor` if it's too large. None for an empty screen, which leaves the size up to winit
+fn initial_window_size((width, height): (u16, u16), pixel_aspect_ratio: Option<f32>, monitor: Option<MonitorHandle>) -> Option<PhysicalSize<u32>> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let (width, height) = match pixel_aspect_ratio {
+        Some(pixel_aspect_ratio) => scale::square_pixels(width, height, pixel_aspect_ratio),
+        None => (width, height),
+    };
+
+    let (width, height) = (width as f64, height as f64);
+    let scale = match monitor {
+        Some(monitor) => {
+            let available = monitor.size();
+            let max_width = available.width as f64 * MAX_MONITOR_FRACTION;
+            let max_height = available.height as f64 * MAX_MONITOR_FRACTION;
+            (max_width / width).min(max_height / height).min(1.0)
+        },
+        None => 1.0,
+    };
+    Some(PhysicalSize::new((width * scale).round().max(1.0) as u32, (height * scale).round().max(1.0) as u32))
+}
+
+// the size the GIF takes up with square pixels, stretched the way `scale::square_pixels` does
+fn display_size((width, height): (u16, u16), pixel_aspect_ratio: Option<f32>) -> (f32, f32) {
+    let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+    match pixel_aspect_ratio {
+        Some(ratio) if ratio >= 1.0 => (width * ratio, height),
+        Some(ratio) => (width, height / ratio),
+        None => (width, height),
+    }
+}
+
+// the largest whole multiple of the GIF's display size that fits in the window, in the middle of
+// it. A window smaller than the GIF shrinks it to fit instead, since there's no whole multiple
+// that would
+fn pixel_perfect_viewport(window: PhysicalSize<u32>, (width, height): (f32, f32)) -> (f32, f32, f32, f32) {
+    let fits = (window.width as f32 / width).min(window.height as f32 / height);
+    let scale = if fits >= 1.0 { fits.floor() } else { fits };
+    centered_viewport(window, (width * scale, height * scale))
+}
+
+// the GIF's display size scaled to fill as much of the window as it can, in the middle of it
+fn fitted_viewport(window: PhysicalSize<u32>, (width, height): (f32, f32)) -> (f32, f32, f32, f32) {
+    let scale = (window.width as f32 / width).min(window.height as f32 / height);
+    centered_viewport(window, (width * scale, height * scale))
+}
+
+fn centered_viewport(window: PhysicalSize<u32>, (width, height): (f32, f32)) -> (f32, f32, f32, f32) {
+    ((window.width as f32 - width) / 2.0, (window.height as f32 - height) / 2.0, width, height)
+}
+
+fn upload(queue: &Queue, texture: &Texture, texture_write: &TextureWrite) {
+    if texture_write.pixels.is_empty() {
+        return;
+    }
+    let mut span = Span::start("upload");
+    span.field("left", texture_write.left);
+    span.field("top", texture_write.top);
+    span.field("width", texture_write.width);
+    span.field("height", texture_write.height);
+    let texture_size = wgpu::Extent3d {
+        width: texture_write.width as u32,
+        height: texture_write.height as u32,
+        depth_or_array_layers: 1,
+    };
+
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d { x: texture_write.left as u32, y: texture_write.top as u32, z: 0 },
+            aspect: wgpu::TextureAspect::default(),
+        },
+        &texture_write.pixels,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * texture_write.width as u32),
+            rows_per_image: Some(texture_write.height as u32),
+        },
+        texture_size
+    );
+}
+
+fn seam_windows(frames: &Range<usize>) -> Option<(usize, usize)> {
+    if frames.len() <= 2 * SEAM_FRAMES {
+        return None;
+    }
+    Some((frames.start + SEAM_FRAMES, frames.end - SEAM_FRAMES))
+}
+
+/// Expands directories in `paths` to the GIFs in them, subdirectories included and sorted by
+/// path. Other paths are kept as they are, whatever their extension.
+pub fn playlist(paths: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+    let mut playlist = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut gifs = Vec::new();
+            collect_gifs(&path, &mut gifs)?;
+            if gifs.is_empty() {
+                return Err(anyhow!(Catalog::from_env().format(Message::NoGifs, &[("directory", &path.display())])));
+            }
+            gifs.sort();
+            playlist.extend(gifs);
+        } else {
+            playlist.push(path);
+        }
+    }
+    Ok(playlist)
+}
+
+fn is_minimized(size: PhysicalSize<u32>) -> bool {
+    size.width == 0 || size.height == 0
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{display_size, fitted_viewport, pixel_perfect_viewport};
+
+    use winit::dpi::PhysicalSize;
+
+    #[test]
+    fn keeps_the_pixel_aspect_ratio_in_any_window() {
+        // pixels twice as wide as they're tall
+        assert_eq!(display_size((10, 10), Some(2.0)), (20.0, 10.0));
+        assert_eq!(display_size((10, 10), Some(0.5)), (10.0, 20.0));
+        assert_eq!(display_size((10, 10), None), (10.0, 10.0));
+
+        // a fullscreen window the wrong shape for the GIF is letterboxed
+        let window = PhysicalSize::new(400, 400);
+        assert_eq!(
+            fitted_viewport(window, (20.0, 10.0)),
+            (0.0, 100.0, 400.0, 200.0)
+        );
+        // and scaled by whole multiples with integer scaling on
+        let window = PhysicalSize::new(100, 100);
+        assert_eq!(
+            pixel_perfect_viewport(window, (20.0, 10.0)),
+            (0.0, 25.0, 100.0, 50.0)
+        );
+        let window = PhysicalSize::new(110, 100);
+        assert_eq!(
+            pixel_perfect_viewport(window, (20.0, 10.0)),
+            (5.0, 25.0, 100.0, 50.0)
+        );
+    }
+}
@@ -0,0 +1,523 @@
+//! Plays two to four GIFs side by side in one window, all on the same clock, for watching an
+//! encoder's or optimizer's output against the original.
+
+use anyhow::{anyhow, Result};
+use log::{error, warn};
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use winit::{
+    application::ApplicationHandler,
+    dpi::PhysicalSize,
+    event::{ElementState, KeyEvent, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    keyboard::{Key, NamedKey},
+    window::{Fullscreen, Window, WindowId},
+};
+
+use wgpu::{BindGroup, Device, PresentMode, Queue, Surface, Texture};
+
+use super::{display_name, initial_window_size, is_minimized, upload, State};
+use crate::input;
+use crate::locale::{Catalog, Message};
+use crate::texture::{ChannelOrder, TextureCanvas};
+use jif::animation::Animation;
+use jif::parser::DecodeOptions;
+
+/// How many GIFs can be compared at once.
+pub const MAX_GIFS: usize = 4;
+
+/// Opens a window playing every one of `paths`, which are all decoded first. Space pauses and
+/// resumes them all together.
+pub fn run(paths: &[PathBuf], vsync: bool, ignore_aspect_ratio: bool) -> Result<()> {
+    if !(2..=MAX_GIFS).contains(&paths.len()) {
+        return Err(anyhow!(
+            "can compare 2 to {} GIFs, not {}",
+            MAX_GIFS,
+            paths.len()
+        ));
+    }
+
+    let catalog = Catalog::from_env();
+    let gifs = paths
+        .iter()
+        .map(|path| ComparedGif::decode(path, catalog, ignore_aspect_ratio))
+        .collect::<Result<Vec<_>>>()?;
+
+    let event_loop = EventLoop::new()?;
+    let mut application = CompareApplication {
+        gifs,
+        vsync,
+        catalog,
+        state: None,
+    };
+    event_loop.run_app(&mut application)?;
+    Ok(())
+}
+
+struct ComparedGif {
+    filename: String,
+    animation: Animation,
+    timeline: Timeline,
+}
+
+impl ComparedGif {
+    fn decode(path: &Path, catalog: Catalog, ignore_aspect_ratio: bool) -> Result<Self> {
+        let filename = display_name(path);
+        let input = input::open(path).map_err(|err| {
+            anyhow!(catalog.format(
+                Message::OpenFailed,
+                &[("filename", &filename), ("error", &err)]
+            ))
+        })?;
+        let decode_failed = |err: &anyhow::Error| {
+            catalog.format(
+                Message::DecodeFailed,
+                &[("filename", &filename), ("error", err)],
+            )
+        };
+        let (mut animation, error) =
+            Animation::decode_partial(input, DecodeOptions::default())
+                .map_err(|err| anyhow!(decode_failed(&err)))?;
+        // like the viewer, whatever was decoded before an error is played
+        if let Some(error) = error {
+            warn!("{}", decode_failed(&error));
+        }
+        if animation.frames().is_empty() {
+            return Err(anyhow!(
+                catalog.format(Message::NoFrames, &[("filename", &filename)])
+            ));
+        }
+        // the GIFs are shown as they'd be seen, so they compare like for like
+        if !ignore_aspect_ratio {
+            animation.correct_aspect_ratio();
+        }
+
+        let timeline = Timeline::new(animation.frames().iter().map(|frame| frame.duration()));
+        Ok(Self {
+            filename,
+            animation,
+            timeline,
+        })
+    }
+
+    fn screen_size(&self) -> (u16, u16) {
+        (self.animation.width(), self.animation.height())
+    }
+}
+
+/// When each frame of a GIF starts, playing it over and over from the start of the shared clock.
+#[derive(Debug, Clone, PartialEq)]
+struct Timeline {
+    starts: Vec<Duration>,
+    length: Duration,
+}
+
+impl Timeline {
+    fn new(delays: impl IntoIterator<Item = Duration>) -> Self {
+        let mut starts = Vec::new();
+        let mut length = Duration::ZERO;
+        for delay in delays {
+            starts.push(length);
+            length += delay;
+        }
+        Self { starts, length }
+    }
+
+    // the frame that's showing `elapsed` into playback, and how long it's still showing for
+    fn frame_at(&self, elapsed: Duration) -> (usize, Duration) {
+        if self.length.is_zero() {
+            return (0, Duration::MAX);
+        }
+        let into_loop = Duration::from_nanos((elapsed.as_nanos() % self.length.as_nanos()) as u64);
+        let index = self.starts.partition_point(|&start| start <= into_loop) - 1;
+        let ends = self.starts.get(index + 1).copied().unwrap_or(self.length);
+        (index, ends - into_loop)
+    }
+}
+
+/// The clock every GIF is played by.
+#[derive(Debug, Clone, Copy)]
+struct Clock {
+    started: Instant,
+    paused_at: Option<Instant>,
+}
+
+impl Clock {
+    fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            paused_at: None,
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.paused_at.unwrap_or_else(Instant::now) - self.started
+    }
+
+    fn toggle_pause(&mut self) {
+        match self.paused_at.take() {
+            // the time spent paused doesn't count
+            Some(paused_at) => self.started += paused_at.elapsed(),
+            None => self.paused_at = Some(Instant::now()),
+        }
+    }
+}
+
+// how many columns and rows `count` GIFs are laid out in, four make a square and fewer a row
+fn grid(count: usize) -> (u32, u32) {
+    match count {
+        4 => (2, 2),
+        count => (count.max(1) as u32, 1),
+    }
+}
+
+// where in its cell of the grid the GIF numbered `position` goes, as big as it fits without
+// changing shape and in the middle
+fn viewport(
+    position: usize,
+    count: usize,
+    window: PhysicalSize<u32>,
+    (width, height): (u16, u16),
+) -> (f32, f32, f32, f32) {
+    let (columns, rows) = grid(count);
+    let cell_width = window.width as f32 / columns as f32;
+    let cell_height = window.height as f32 / rows as f32;
+    let (column, row) = (position as u32 % columns, position as u32 / columns);
+
+    let scale = (cell_width / width.max(1) as f32).min(cell_height / height.max(1) as f32);
+    let (fitted_width, fitted_height) = (width as f32 * scale, height as f32 * scale);
+    (
+        column as f32 * cell_width + (cell_width - fitted_width) / 2.0,
+        row as f32 * cell_height + (cell_height - fitted_height) / 2.0,
+        fitted_width,
+        fitted_height,
+    )
+}
+
+struct CompareApplication<'a> {
+    // moved into the state once the window is open
+    gifs: Vec<ComparedGif>,
+    vsync: bool,
+    catalog: Catalog,
+    state: Option<CompareState<'a>>,
+}
+
+impl<'a> ApplicationHandler for CompareApplication<'a> {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.state.is_some() {
+            return;
+        }
+
+        let title = self
+            .gifs
+            .iter()
+            .map(|gif| gif.filename.as_str())
+            .collect::<Vec<_>>()
+            .join(" | ");
+        let mut attributes = Window::default_attributes().with_title(title);
+        // every cell is big enough for the biggest GIF
+        let (columns, rows) = grid(self.gifs.len());
+        let (cell_width, cell_height) = self.gifs.iter().fold((0, 0), |(width, height), gif| {
+            let (gif_width, gif_height) = gif.screen_size();
+            (width.max(gif_width as u32), height.max(gif_height as u32))
+        });
+        let size = (
+            (cell_width * columns).min(u16::MAX as u32) as u16,
+            (cell_height * rows).min(u16::MAX as u32) as u16,
+        );
+        let monitor = event_loop
+            .primary_monitor()
+            .or_else(|| event_loop.available_monitors().next());
+        if let Some(size) = initial_window_size(size, None, monitor) {
+            attributes = attributes.with_inner_size(size);
+        }
+        let window = event_loop.create_window(attributes).unwrap();
+
+        self.state = Some(CompareState::new(
+            window,
+            std::mem::take(&mut self.gifs),
+            self.vsync,
+        ));
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
+        if state.window.id() != window_id {
+            return;
+        }
+
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(physical_size) => {
+                state.resize(physical_size);
+                state.window.request_redraw();
+            }
+            WindowEvent::RedrawRequested => match state.render() {
+                Ok(()) => {}
+                Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                    state.resize(state.size)
+                }
+                Err(wgpu::SurfaceError::OutOfMemory) => {
+                    error!("{}", self.catalog.get(Message::OutOfMemory));
+                    event_loop.exit();
+                }
+                Err(wgpu::SurfaceError::Timeout) => warn!("timed out waiting for the surface"),
+            },
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key,
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                match logical_key.as_ref() {
+                    Key::Character("f") | Key::Character("F") => state.toggle_fullscreen(),
+                    Key::Named(NamedKey::Space) => state.clock.toggle_pause(),
+                    _ => return,
+                }
+                state.window.request_redraw();
+            }
+            _ => {}
+        }
+    }
+
+    // redraws when the next frame of any of the GIFs is due
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(state) = self.state.as_ref() else {
+            return;
+        };
+
+        match state.next_frame_at {
+            Some(deadline) if deadline > Instant::now() => {
+                event_loop.set_control_flow(ControlFlow::WaitUntil(deadline))
+            }
+            Some(_) => {
+                state.window.request_redraw();
+                event_loop.set_control_flow(ControlFlow::Wait);
+            }
+            None => event_loop.set_control_flow(ControlFlow::Wait),
+        }
+    }
+}
+
+/// One of the GIFs being compared, with a texture of its own.
+struct Pane {
+    gif: ComparedGif,
+    texture: Texture,
+    bind_group: BindGroup,
+    canvas: TextureCanvas,
+}
+
+struct CompareState<'a> {
+    surface: Surface<'a>,
+    device: Device,
+    queue: Queue,
+    config: wgpu::SurfaceConfiguration,
+    size: PhysicalSize<u32>,
+    window: Arc<Window>,
+    render_pipeline: wgpu::RenderPipeline,
+    channel_order: ChannelOrder,
+    panes: Vec<Pane>,
+    clock: Clock,
+    // when the next frame of any of the GIFs is due, none while paused
+    next_frame_at: Option<Instant>,
+}
+
+impl<'a> CompareState<'a> {
+    fn new(window: Window, gifs: Vec<ComparedGif>, vsync: bool) -> Self {
+        let window = Arc::new(window);
+        let size = window.inner_size();
+        let instance = State::create_gpu_instance();
+        let surface = instance.create_surface(window.clone()).unwrap();
+        let adapter = State::create_adapter(instance, &surface);
+        let (device, queue) = State::create_device(&adapter);
+        let surface_caps = surface.get_capabilities(&adapter);
+        let present_mode = if vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        };
+        let config = State::create_surface_config(size, surface_caps, present_mode);
+        let (channel_order, texture_format) = ChannelOrder::for_surface(config.format);
+
+        // the same pipeline draws every GIF, each with its own bind group
+        let bind_group_layout = State::create_texture_bind_group_layout(&device);
+        let render_pipeline = State::create_render_pipeline(&device, &config, &bind_group_layout);
+        let panes = gifs
+            .into_iter()
+            .map(|gif| {
+                let screen_size = gif.screen_size();
+                let (bind_groups, texture) = State::create_texture_bind_groups(
+                    screen_size,
+                    &device,
+                    texture_format,
+                    &bind_group_layout,
+                );
+                Pane {
+                    gif,
+                    texture,
+                    bind_group: bind_groups.nearest,
+                    canvas: TextureCanvas::new(screen_size),
+                }
+            })
+            .collect();
+
+        if !is_minimized(size) {
+            surface.configure(&device, &config);
+        }
+
+        Self {
+            surface,
+            device,
+            queue,
+            config,
+            size,
+            window,
+            render_pipeline,
+            channel_order,
+            panes,
+            clock: Clock::start(),
+            next_frame_at: None,
+        }
+    }
+
+    fn resize(&mut self, new_size: PhysicalSize<u32>) {
+        self.size = new_size;
+        if is_minimized(new_size) {
+            return;
+        }
+        self.config.width = new_size.width;
+        self.config.height = new_size.height;
+        self.surface.configure(&self.device, &self.config);
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        let fullscreen = match self.window.fullscreen() {
+            Some(_) => None,
+            None => Some(Fullscreen::Borderless(None)),
+        };
+        self.window.set_fullscreen(fullscreen);
+        self.resize(self.window.inner_size());
+    }
+
+    // shows the frame each GIF is on by the clock
+    fn write_textures(&mut self) {
+        let elapsed = self.clock.elapsed();
+        let mut next_frame_in = Duration::MAX;
+        for pane in &mut self.panes {
+            let (index, showing_for) = pane.gif.timeline.frame_at(elapsed);
+            let texture_write =
+                pane.canvas
+                    .show(pane.gif.animation.frames(), index, self.channel_order);
+            upload(&self.queue, &pane.texture, &texture_write);
+            next_frame_in = next_frame_in.min(showing_for);
+        }
+
+        self.next_frame_at = match self.clock.paused_at {
+            Some(_) => None,
+            None => Instant::now().checked_add(next_frame_in),
+        };
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        if is_minimized(self.size) {
+            return Ok(());
+        }
+
+        self.write_textures();
+        let output = self.surface.get_current_texture()?;
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Compare Encoder"),
+            });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Compare Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            render_pass.set_pipeline(&self.render_pipeline);
+            for (position, pane) in self.panes.iter().enumerate() {
+                let (x, y, width, height) = viewport(
+                    position,
+                    self.panes.len(),
+                    self.size,
+                    pane.gif.screen_size(),
+                );
+                render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+                render_pass.set_bind_group(0, &pane.bind_group, &[]);
+                render_pass.draw(0..6, 0..1);
+            }
+        }
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{grid, viewport, Timeline};
+
+    use std::time::Duration;
+    use winit::dpi::PhysicalSize;
+
+    #[test]
+    fn follows_the_shared_clock() {
+        let ms = Duration::from_millis;
+        let timeline = Timeline::new([ms(100), ms(50), ms(250)]);
+        assert_eq!(timeline.frame_at(ms(0)), (0, ms(100)));
+        assert_eq!(timeline.frame_at(ms(120)), (1, ms(30)));
+        assert_eq!(timeline.frame_at(ms(150)), (2, ms(250)));
+        // and around again
+        assert_eq!(timeline.frame_at(ms(430)), (0, ms(70)));
+    }
+
+    #[test]
+    fn lays_gifs_out_on_a_grid() {
+        assert_eq!(grid(2), (2, 1));
+        assert_eq!(grid(3), (3, 1));
+        assert_eq!(grid(4), (2, 2));
+
+        let window = PhysicalSize::new(400, 200);
+        assert_eq!(viewport(0, 2, window, (100, 100)), (0.0, 0.0, 200.0, 200.0));
+        // a wide GIF in the second cell sits in the middle of it
+        assert_eq!(
+            viewport(1, 2, window, (200, 100)),
+            (200.0, 50.0, 200.0, 100.0)
+        );
+        assert_eq!(
+            viewport(3, 4, window, (10, 10)),
+            (250.0, 100.0, 100.0, 100.0)
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! Plays GIFs through once without opening a window, writing what the viewer's texture holds
+//! after each frame to a PNG. Nothing here needs a display or a GPU, so it runs on CI machines
+//! and servers. With `--record` the frames are recorded too, presented at the times their delays
+//! add up to.
+
+use anyhow::{anyhow, Result};
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::input;
+use crate::locale::{Catalog, Message};
+use crate::recorder::Recorder;
+use crate::texture::{ChannelOrder, TextureCanvas};
+use jif::export::{ImageFormat, Png};
+use jif::output::{write_atomically, OutputOptions};
+
+/// Renders every GIF in `playlist` to `dir`, as `<name>_<frame>.png`. Frames are stretched to
+/// the pixel aspect ratio like the window is, unless `ignore_aspect_ratio` is set.
+pub fn run(
+    playlist: Vec<PathBuf>,
+    dir: &Path,
+    ignore_aspect_ratio: bool,
+    mut recorder: Option<Recorder>,
+) -> Result<()> {
+    let catalog = Catalog::from_env();
+    fs::create_dir_all(dir)?;
+    // when the frame being rendered would be presented, from the start of the playlist
+    let mut presented = Duration::ZERO;
+
+    for path in playlist {
+        let filename = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut animation = input::decode_partial(&path)?;
+        if !ignore_aspect_ratio {
+            animation.correct_aspect_ratio();
+        }
+        let frames = animation.frames();
+        if frames.is_empty() {
+            return Err(anyhow!(
+                catalog.format(Message::NoFrames, &[("filename", &filename)])
+            ));
+        }
+
+        // the texture is as big as the logical screen, like in the window
+        let (width, height) = (animation.width(), animation.height());
+        let mut texture = vec![0; width as usize * height as usize * 4];
+        let mut canvas = TextureCanvas::new((width, height));
+
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let digits = frames.len().saturating_sub(1).to_string().len();
+        for index in 0..frames.len() {
+            canvas
+                .show(frames, index, ChannelOrder::Rgba)
+                .apply(&mut texture, width);
+
+            let name = format!("{}_{:0digits$}.{}", stem, index, Png::EXTENSION);
+            let image = Png::encode(width, height, &texture);
+            write_atomically(dir.join(name), OutputOptions::default(), |file| {
+                Ok(file.write_all(&image)?)
+            })?;
+
+            let delay = frames[index].duration();
+            if let Some(recorder) = &mut recorder {
+                recorder.record(
+                    (width, height),
+                    texture.clone(),
+                    &filename,
+                    index,
+                    delay,
+                    presented,
+                )?;
+            }
+            presented += delay;
+        }
+
+        println!(
+            "rendered {} frames of {} to {}",
+            frames.len(),
+            filename,
+            dir.display()
+        );
+    }
+
+    Ok(())
+}
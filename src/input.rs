@@ -0,0 +1,384 @@
+//! Where GIFs are read from: files, or http(s) URLs when built with the `http` feature.
+//! Downloads are read as they arrive, so the viewer can start playing before they've finished.
+
+use anyhow::{anyhow, Result};
+use log::warn;
+
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "http")]
+use std::sync::{Arc, Condvar, Mutex};
+
+use jif::animation::{Animation, SAVED_EXTENSION};
+use jif::parser::DecodeOptions;
+
+/// Whether `path` is an http(s) URL rather than a file.
+pub fn is_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| path.starts_with("http://") || path.starts_with("https://"))
+}
+
+/// Opens a file, or starts downloading a URL.
+pub fn open(path: &Path) -> Result<Input> {
+    if !is_url(path) {
+        return Ok(Input::File {
+            file: File::open(path)?,
+            path: path.to_path_buf(),
+        });
+    }
+
+    #[cfg(feature = "http")]
+    return Ok(Input::Download(Download::start(&path.to_string_lossy())?));
+    #[cfg(not(feature = "http"))]
+    Err(anyhow!(
+        "{} is a URL, which needs jif to be built with the http feature",
+        path.display()
+    ))
+}
+
+/// Whether `path` is an animation saved by jif, see [`Animation::save`], rather than a GIF.
+pub fn is_saved(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case(SAVED_EXTENSION))
+}
+
+/// Decodes what there is of the GIF at `path`, for showing and exporting. If it breaks off
+/// partway through the frames, the ones before the break are kept and the error is logged as a
+/// warning. Animations saved by jif are loaded instead.
+pub fn decode_partial(path: &Path) -> Result<Animation> {
+    if is_saved(path) {
+        return Animation::load(BufReader::new(open(path)?));
+    }
+    let (animation, error) = Animation::decode_partial(open(path)?, DecodeOptions::default())?;
+    if let Some(error) = error {
+        warn!(
+            "{} is broken, only its first {} frames decoded: {}",
+            path.display(),
+            animation.frames().len(),
+            error
+        );
+    }
+    Ok(animation)
+}
+
+/// Reads the whole of a file or download.
+pub fn read(path: &Path) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    open(path)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[derive(Debug)]
+pub enum Input {
+    File {
+        file: File,
+        path: PathBuf,
+    },
+    #[cfg(feature = "http")]
+    Download(Download),
+    /// What another reader has downloaded, see [`Input::reopen`].
+    #[cfg(feature = "http")]
+    Replay(Replay),
+}
+
+impl Input {
+    /// How many bytes there are to read, if the file system or the server says.
+    pub fn length(&self) -> Option<u64> {
+        match self {
+            Input::File { file, .. } => file.metadata().ok().map(|metadata| metadata.len()),
+            #[cfg(feature = "http")]
+            Input::Download(download) => download.length,
+            #[cfg(feature = "http")]
+            Input::Replay(_) => None,
+        }
+    }
+
+    /// Another reader of the same GIF from its start, which can be read alongside this one. A
+    /// file is opened again, while a download is read back from what this reader has downloaded,
+    /// waiting for it to download more. Downloads have to be reopened before they're read.
+    pub fn reopen(&mut self) -> Result<Input> {
+        match self {
+            Input::File { path, .. } => open(path),
+            #[cfg(feature = "http")]
+            Input::Download(download) => {
+                if download.read > 0 {
+                    return Err(anyhow!("a download can't be read again once it's started"));
+                }
+                let kept = download.kept.get_or_insert_with(Default::default).clone();
+                Ok(Input::Replay(Replay { kept, position: 0 }))
+            }
+            #[cfg(feature = "http")]
+            Input::Replay(replay) => Ok(Input::Replay(Replay {
+                kept: replay.kept.clone(),
+                position: 0,
+            })),
+        }
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::File { file, .. } => file.read(buf),
+            #[cfg(feature = "http")]
+            Input::Download(download) => download.read(buf),
+            #[cfg(feature = "http")]
+            Input::Replay(replay) => replay.read(buf),
+        }
+    }
+}
+
+/// The body of an http(s) response, read as it arrives.
+#[cfg(feature = "http")]
+pub struct Download {
+    body: Box<dyn Read + Send + Sync>,
+    length: Option<u64>,
+    read: u64,
+    // everything read so far, once the download has been reopened
+    kept: Option<Arc<Kept>>,
+}
+
+#[cfg(feature = "http")]
+impl Download {
+    fn start(url: &str) -> Result<Self> {
+        let response = ureq::get(url).call()?;
+        // a compressed body is decompressed as it's read, so its length isn't the GIF's
+        let length = match response.header("Content-Encoding") {
+            Some(_) => None,
+            None => response
+                .header("Content-Length")
+                .and_then(|length| length.parse().ok()),
+        };
+        Ok(Self {
+            body: response.into_reader(),
+            length,
+            read: 0,
+            kept: None,
+        })
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.body.read(buf);
+        if let Some(kept) = &self.kept {
+            let mut downloaded = kept.downloaded.lock().unwrap();
+            match &read {
+                // an interrupted read is tried again, the download isn't over
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Ok(0) => downloaded.finished = true,
+                Err(err) => {
+                    downloaded.error = Some((err.kind(), err.to_string()));
+                    downloaded.finished = true;
+                }
+                Ok(count) => downloaded.data.extend_from_slice(&buf[..*count]),
+            }
+            kept.arrived.notify_all();
+        }
+        if let Ok(count) = &read {
+            self.read += *count as u64;
+        }
+        read
+    }
+}
+
+#[cfg(feature = "http")]
+impl Drop for Download {
+    // a replay waiting for more would otherwise wait forever
+    fn drop(&mut self) {
+        if let Some(kept) = &self.kept {
+            kept.downloaded.lock().unwrap().finished = true;
+            kept.arrived.notify_all();
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+impl std::fmt::Debug for Download {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Download")
+            .field("length", &self.length)
+            .field("read", &self.read)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "http")]
+#[derive(Debug, Default)]
+struct Kept {
+    downloaded: Mutex<Downloaded>,
+    // notified whenever more has been downloaded, or the download is over
+    arrived: Condvar,
+}
+
+#[cfg(feature = "http")]
+#[derive(Debug, Default)]
+struct Downloaded {
+    data: Vec<u8>,
+    finished: bool,
+    // why the download stopped if it failed, handed on once everything before it was replayed
+    error: Option<(io::ErrorKind, String)>,
+}
+
+/// Reads back what a [`Download`] has downloaded, see [`Input::reopen`].
+#[cfg(feature = "http")]
+#[derive(Debug)]
+pub struct Replay {
+    kept: Arc<Kept>,
+    position: usize,
+}
+
+#[cfg(feature = "http")]
+impl Read for Replay {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut downloaded = self.kept.downloaded.lock().unwrap();
+        while downloaded.data.len() <= self.position && !downloaded.finished {
+            downloaded = self.kept.arrived.wait(downloaded).unwrap();
+        }
+        let available = &downloaded.data[self.position.min(downloaded.data.len())..];
+        if let (true, Some((kind, message))) = (available.is_empty(), &downloaded.error) {
+            return Err(io::Error::new(*kind, message.clone()));
+        }
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_saved, is_url};
+
+    use std::path::Path;
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn replays_downloads() {
+        use super::{Download, Input};
+        use std::io::{Cursor, Read};
+
+        let body: Vec<u8> = (0..=255).collect();
+        let mut download = Input::Download(Download {
+            body: Box::new(Cursor::new(body.clone())),
+            length: Some(256),
+            read: 0,
+            kept: None,
+        });
+        let mut replay = download.reopen().unwrap();
+
+        let mut start = [0; 100];
+        download.read_exact(&mut start).unwrap();
+        assert!(download.reopen().is_err());
+        let mut replayed = [0; 100];
+        replay.read_exact(&mut replayed).unwrap();
+        assert_eq!(replayed, start);
+
+        // the replay waits for the rest, until the download is dropped
+        let reader = std::thread::spawn(move || {
+            let mut rest = Vec::new();
+            replay.read_to_end(&mut rest).unwrap();
+            rest
+        });
+        let mut rest = [0; 50];
+        download.read_exact(&mut rest).unwrap();
+        drop(download);
+        assert_eq!(reader.join().unwrap(), body[100..150]);
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn replays_past_interrupted_reads() {
+        use super::{Download, Input};
+        use std::io::{self, Cursor, Read};
+
+        // interrupted every other read
+        struct Interrupting {
+            body: Cursor<Vec<u8>>,
+            interrupt: bool,
+        }
+
+        impl Read for Interrupting {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.interrupt = !self.interrupt;
+                if self.interrupt {
+                    return Err(io::ErrorKind::Interrupted.into());
+                }
+                self.body.read(buf)
+            }
+        }
+
+        let body: Vec<u8> = (0..=255).collect();
+        let mut download = Input::Download(Download {
+            body: Box::new(Interrupting {
+                body: Cursor::new(body.clone()),
+                interrupt: false,
+            }),
+            length: Some(256),
+            read: 0,
+            kept: None,
+        });
+        let mut replay = download.reopen().unwrap();
+
+        // a replay reading now would wait for the rest rather than stop short
+        let interrupted = download.read(&mut [0; 16]).unwrap_err();
+        assert_eq!(interrupted.kind(), io::ErrorKind::Interrupted);
+        let Input::Download(Download { kept, .. }) = &download else {
+            unreachable!();
+        };
+        let kept = kept.as_ref().unwrap();
+        assert!(!kept.downloaded.lock().unwrap().finished);
+
+        let mut downloaded = Vec::new();
+        download.read_to_end(&mut downloaded).unwrap();
+        let mut replayed = Vec::new();
+        replay.read_to_end(&mut replayed).unwrap();
+        assert_eq!(downloaded, body);
+        assert_eq!(replayed, body);
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn replays_a_failed_download_as_a_failure() {
+        use super::{Download, Input};
+        use std::io::{self, Cursor, Read};
+
+        // the connection drops after the first few bytes
+        struct Dropping;
+
+        impl Read for Dropping {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("connection reset"))
+            }
+        }
+
+        let mut download = Input::Download(Download {
+            body: Box::new(Cursor::new(vec![1, 2, 3]).chain(Dropping)),
+            length: Some(256),
+            read: 0,
+            kept: None,
+        });
+        let mut replay = download.reopen().unwrap();
+        let mut downloaded = Vec::new();
+        assert!(download.read_to_end(&mut downloaded).is_err());
+
+        // what arrived is replayed, then the error rather than the end of the file
+        let mut start = [0; 3];
+        replay.read_exact(&mut start).unwrap();
+        assert_eq!(start, [1, 2, 3]);
+        let err = replay.read(&mut [0; 16]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert_eq!(err.to_string(), "connection reset");
+    }
+
+    #[test]
+    fn tells_urls_from_files() {
+        assert!(is_url(Path::new("https://example.com/cat.gif")));
+        assert!(is_url(Path::new("http://localhost:8000/cat.gif")));
+        assert!(!is_url(Path::new("cat.gif")));
+        assert!(!is_url(Path::new("./http/cat.gif")));
+
+        assert!(is_saved(Path::new("cat.jifa")));
+        assert!(is_saved(Path::new("CAT.JIFA")));
+        assert!(!is_saved(Path::new("cat.gif")));
+    }
+}
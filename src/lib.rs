@@ -0,0 +1,55 @@
+//! Decoding, editing and encoding GIFs.
+//!
+//! [`parser::Decoder`] reads a GIF frame by frame and [`encoder::Encoder`] writes one.
+//! [`animation::Animation`] holds a whole decoded GIF for editing, with the transforms in
+//! [`edit`] and [`pipeline`] on top of it. [`prelude`] re-exports the types most programs need:
+//!
+//! ```
+//! use jif::prelude::*;
+//!
+//! let palette: Box<[u8]> = Box::new([0, 0, 0, 255, 255, 255]);
+//! let mut encoder = Encoder::new(Vec::new(), 2, 1);
+//! encoder.write_frame(&Frame::new(2, 1, Box::new([0, 1]), palette))?;
+//! let gif = encoder.finish()?;
+//!
+//! let mut animation = Animation::decode(gif.as_slice())?;
+//! animation.crop(Rect::new(1, 0, 1, 1));
+//! assert_eq!((animation.width(), animation.height()), (1, 1));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! Everything in these docs follows semver. Modules hidden from them are used by jif's own
+//! command line tool, benchmarks and fuzzers, and can change in any release.
+
+pub mod animation;
+#[cfg(feature = "bevy")]
+pub mod bevy;
+#[doc(hidden)]
+pub mod bits;
+#[doc(hidden)]
+pub mod blocks;
+pub mod compositor;
+pub mod edit;
+pub mod encoder;
+pub mod export;
+#[cfg(feature = "capi")]
+pub mod ffi;
+#[cfg(test)]
+mod fixtures;
+#[doc(hidden)]
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub mod output;
+pub mod parser;
+pub mod pipeline;
+#[doc(hidden)]
+pub mod ppm_writer;
+pub mod prelude;
+pub mod quantize;
+pub mod scale;
+pub mod sheet;
+#[doc(hidden)]
+pub mod ssim;
+pub mod thumbnail;
+#[doc(hidden)]
+pub mod trace;
+pub mod watermark;
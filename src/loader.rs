@@ -0,0 +1,145 @@
+//! Decodes a GIF on a worker thread, so the viewer can start playing the first frames while the
+//! rest are still being decoded.
+
+use std::fmt::Debug;
+use std::io::Read;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use jif::parser::{DecodeOptions, Decoder, Frame, LoopCount, Progress};
+
+pub enum LoadEvent {
+    /// Sent once, right before the first frame.
+    Header {
+        width: u16,
+        height: u16,
+        /// Read from the blocks before the first frame, where it always is in practice.
+        loop_count: Option<LoopCount>,
+        pixel_aspect_ratio: Option<f32>,
+    },
+    Frame(Frame),
+    /// How far along decoding is, sent after every frame and before `Finished`.
+    Progress(Progress),
+    Finished,
+    Failed(anyhow::Error),
+}
+
+/// Starts decoding `reader`, which has `length` bytes to read if that's known, in the
+/// background. Events arrive in file order and always end with either `Finished` or `Failed`,
+/// unless the receiver is dropped first, which stops the worker.
+pub fn spawn<R: Read + Debug + Send + 'static>(
+    reader: R,
+    length: Option<u64>,
+) -> Receiver<LoadEvent> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        // sent after the frame it was reported for, so the header still comes first, and
+        // before `Finished`
+        let progress = Arc::new(Mutex::new(None));
+        let options = DecodeOptions::new().progress({
+            let progress = progress.clone();
+            move |reported: &Progress| *progress.lock().unwrap() = Some(*reported)
+        });
+        let mut decoder = Decoder::new_with_options(reader, options);
+        // without a length there's just no percentage to show
+        if let Some(length) = length {
+            decoder.set_length(length);
+        }
+        let mut sent_header = false;
+
+        loop {
+            let event = match decoder.next_frame() {
+                Ok(Some(frame)) => {
+                    let frame = frame.clone();
+                    if !sent_header {
+                        let (width, height) = decoder.screen_size().unwrap_or((0, 0));
+                        let header = LoadEvent::Header {
+                            width,
+                            height,
+                            loop_count: decoder.loop_count(),
+                            pixel_aspect_ratio: decoder.pixel_aspect_ratio(),
+                        };
+                        if sender.send(header).is_err() {
+                            return;
+                        }
+                        sent_header = true;
+                    }
+                    LoadEvent::Frame(frame)
+                }
+                Ok(None) => LoadEvent::Finished,
+                Err(err) => LoadEvent::Failed(err),
+            };
+
+            let last = matches!(event, LoadEvent::Finished | LoadEvent::Failed(_));
+            let progress = progress.lock().unwrap().take().map(LoadEvent::Progress);
+            let events = if last {
+                [progress, Some(event)]
+            } else {
+                [Some(event), progress]
+            };
+            for event in events.into_iter().flatten() {
+                if sender.send(event).is_err() {
+                    return;
+                }
+            }
+            if last {
+                return;
+            }
+        }
+    });
+
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{spawn, LoadEvent};
+    use jif::encoder::Encoder;
+    use jif::parser::Frame;
+
+    use std::io::Cursor;
+
+    #[test]
+    fn sends_every_frame_then_finishes() {
+        let palette: Box<[u8]> = Box::new([0, 0, 0, 255, 255, 255]);
+        let mut encoder = Encoder::new(Vec::new(), 2, 1);
+        for indicies in [[0, 1], [1, 0], [1, 1]] {
+            encoder
+                .write_frame(&Frame::new(2, 1, Box::new(indicies), palette.clone()))
+                .unwrap();
+        }
+        let gif = encoder.finish().unwrap();
+
+        let length = gif.len() as u64;
+        let events: Vec<LoadEvent> = spawn(Cursor::new(gif), Some(length)).into_iter().collect();
+        assert!(matches!(
+            events[0],
+            LoadEvent::Header {
+                width: 2,
+                height: 1,
+                loop_count: None,
+                pixel_aspect_ratio: None
+            }
+        ));
+        let frames: Vec<&[u8]> = events
+            .iter()
+            .filter_map(|event| match event {
+                LoadEvent::Frame(frame) => Some(frame.indicies()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(frames, [[0, 1], [1, 0], [1, 1]]);
+        assert!(matches!(events.last(), Some(LoadEvent::Finished)));
+
+        let last_progress = events.iter().rev().find_map(|event| match event {
+            LoadEvent::Progress(progress) => Some(progress),
+            _ => None,
+        });
+        assert_eq!(
+            last_progress.and_then(|progress| progress.fraction()),
+            Some(1.0)
+        );
+    }
+}
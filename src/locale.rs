@@ -0,0 +1,209 @@
+use std::env;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    English,
+    German,
+    French,
+    Spanish,
+}
+
+impl Locale {
+    /// Picks the locale from `JIF_LANG`, falling back to the usual POSIX variables and finally
+    /// English.
+    pub fn from_env() -> Self {
+        ["JIF_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find_map(|tag| Self::from_tag(&tag))
+            .unwrap_or(Locale::English)
+    }
+
+    /// Parses tags like `de`, `de_DE` or `de_DE.UTF-8`.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag
+            .split(['_', '-', '.', '@'])
+            .next()?
+            .to_ascii_lowercase();
+
+        match language.as_str() {
+            "en" | "c" | "posix" => Some(Locale::English),
+            "de" => Some(Locale::German),
+            "fr" => Some(Locale::French),
+            "es" => Some(Locale::Spanish),
+            _ => None,
+        }
+    }
+}
+
+/// Every piece of text the viewer shows to the user. Templates use `{name}` placeholders that
+/// are filled in by `Catalog::format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    WindowTitle,
+    SeamCheckTitle,
+    /// What the window title says about the loop count of GIFs that play once.
+    PlaysOnce,
+    Loading,
+    /// The loading title once it's known how much of the file has been read.
+    LoadingProgress,
+    OpenFailed,
+    DecodeFailed,
+    NoFrames,
+    NoGifs,
+    OutOfMemory,
+    ScreenshotSaved,
+    ScreenshotFailed,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Catalog {
+    locale: Locale,
+}
+
+impl Catalog {
+    pub fn new(locale: Locale) -> Self {
+        Self { locale }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(Locale::from_env())
+    }
+
+    pub fn get(&self, message: Message) -> &'static str {
+        use Locale::*;
+        use Message::*;
+
+        match (self.locale, message) {
+            (English, WindowTitle) => "{filename} — {width}x{height}, {frames} frames, loop: {loop} — frame {frame}",
+            (English, SeamCheckTitle) => "{filename} — seam check — frame {frame} of {frames}",
+            (English, PlaysOnce) => "once",
+            (English, Loading) => "Loading {filename}…",
+            (English, LoadingProgress) => "Loading {filename}… {percent}%",
+            (English, OpenFailed) => "Could not open {filename}: {error}",
+            (English, DecodeFailed) => "Could not decode {filename}: {error}",
+            (English, NoFrames) => "{filename} does not contain any frames",
+            (English, NoGifs) => "There are no GIFs in {directory}",
+            (English, OutOfMemory) => "Ran out of graphics memory, closing the viewer",
+            (English, ScreenshotSaved) => "Saved {path}",
+            (English, ScreenshotFailed) => "Could not save {path}: {error}",
+
+            (German, WindowTitle) => "{filename} — {width}x{height}, {frames} Einzelbilder, Schleife: {loop} — Bild {frame}",
+            (German, SeamCheckTitle) => "{filename} — Nahtprüfung — Bild {frame} von {frames}",
+            (German, PlaysOnce) => "einmal",
+            (German, Loading) => "{filename} wird geladen…",
+            (German, LoadingProgress) => "{filename} wird geladen… {percent} %",
+            (German, OpenFailed) => "{filename} konnte nicht geöffnet werden: {error}",
+            (German, DecodeFailed) => "{filename} konnte nicht dekodiert werden: {error}",
+            (German, NoFrames) => "{filename} enthält keine Einzelbilder",
+            (German, NoGifs) => "In {directory} gibt es keine GIFs",
+            (German, OutOfMemory) => "Kein Grafikspeicher mehr frei, der Viewer wird geschlossen",
+            (German, ScreenshotSaved) => "{path} gespeichert",
+            (German, ScreenshotFailed) => "{path} konnte nicht gespeichert werden: {error}",
+
+            (French, WindowTitle) => "{filename} — {width}x{height}, {frames} images, boucle : {loop} — image {frame}",
+            (French, SeamCheckTitle) => "{filename} — vérification de la boucle — image {frame} sur {frames}",
+            (French, PlaysOnce) => "une fois",
+            (French, Loading) => "Chargement de {filename}…",
+            (French, LoadingProgress) => "Chargement de {filename}… {percent} %",
+            (French, OpenFailed) => "Impossible d'ouvrir {filename} : {error}",
+            (French, DecodeFailed) => "Impossible de décoder {filename} : {error}",
+            (French, NoFrames) => "{filename} ne contient aucune image",
+            (French, NoGifs) => "Aucun GIF dans {directory}",
+            (French, OutOfMemory) => "Mémoire graphique épuisée, fermeture de la visionneuse",
+            (French, ScreenshotSaved) => "{path} enregistré",
+            (French, ScreenshotFailed) => "Impossible d'enregistrer {path} : {error}",
+
+            (Spanish, WindowTitle) => "{filename} — {width}x{height}, {frames} fotogramas, bucle: {loop} — fotograma {frame}",
+            (Spanish, SeamCheckTitle) => "{filename} — revisión del bucle — fotograma {frame} de {frames}",
+            (Spanish, PlaysOnce) => "una vez",
+            (Spanish, Loading) => "Cargando {filename}…",
+            (Spanish, LoadingProgress) => "Cargando {filename}… {percent} %",
+            (Spanish, OpenFailed) => "No se pudo abrir {filename}: {error}",
+            (Spanish, DecodeFailed) => "No se pudo decodificar {filename}: {error}",
+            (Spanish, NoFrames) => "{filename} no contiene ningún fotograma",
+            (Spanish, NoGifs) => "No hay ningún GIF en {directory}",
+            (Spanish, OutOfMemory) => "No queda memoria gráfica, se cierra el visor",
+            (Spanish, ScreenshotSaved) => "Se guardó {path}",
+            (Spanish, ScreenshotFailed) => "No se pudo guardar {path}: {error}",
+        }
+    }
+
+    /// Fills in the placeholders of `message` in one pass over the template, so values that
+    /// look like placeholders themselves, like a file called `{error}.gif`, are left alone.
+    pub fn format(&self, message: Message, args: &[(&str, &dyn Display)]) -> String {
+        let mut text = String::new();
+        let mut rest = self.get(message);
+        while let Some(start) = rest.find('{') {
+            text.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+            let placeholder = &rest[..=end];
+            match args.iter().find(|(name, _)| placeholder[1..end] == **name) {
+                Some((_, value)) => text.push_str(&value.to_string()),
+                None => text.push_str(placeholder),
+            }
+            rest = &rest[end + 1..];
+        }
+        text.push_str(rest);
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Catalog, Locale, Message};
+
+    #[test]
+    fn parses_posix_tags() {
+        assert_eq!(Locale::from_tag("de_DE.UTF-8"), Some(Locale::German));
+        assert_eq!(Locale::from_tag("fr"), Some(Locale::French));
+        assert_eq!(Locale::from_tag("C.UTF-8"), Some(Locale::English));
+        assert_eq!(Locale::from_tag("ja_JP"), None);
+    }
+
+    #[test]
+    fn fills_in_placeholders() {
+        let catalog = Catalog::new(Locale::English);
+        let text = catalog.format(
+            Message::OpenFailed,
+            &[("filename", &"cat.gif"), ("error", &"not found")],
+        );
+        assert_eq!(text, "Could not open cat.gif: not found");
+
+        // values are never filled in themselves
+        let text = catalog.format(
+            Message::OpenFailed,
+            &[("filename", &"{error}.gif"), ("error", &"{filename}")],
+        );
+        assert_eq!(text, "Could not open {error}.gif: {filename}");
+    }
+
+    #[test]
+    fn titles_fill_in_everything_in_every_locale() {
+        let locales = [
+            Locale::English,
+            Locale::German,
+            Locale::French,
+            Locale::Spanish,
+        ];
+        for locale in locales {
+            let title = Catalog::new(locale).format(
+                Message::WindowTitle,
+                &[
+                    ("filename", &"cat.gif"),
+                    ("width", &640),
+                    ("height", &480),
+                    ("frame", &3),
+                    ("frames", &12),
+                    ("loop", &"∞"),
+                ],
+            );
+            assert!(!title.contains('{'), "{:?}: {}", locale, title);
+            assert!(title.contains("640x480") && title.contains("12"));
+        }
+    }
+}
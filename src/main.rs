@@ -1,33 +1,58 @@
-use std::fs::File;
 use anyhow::Result;
+use clap::Parser;
 
-mod ppm_writer;
-mod parser;
+mod cli;
+mod commands;
 mod gfx;
+mod headless;
+mod input;
+mod loader;
+mod locale;
+mod recorder;
+mod scrub_bar;
+mod texture;
 
-use parser::Decoder;
-
-fn spit_out_gif() -> Result<()> {
-    let mut file = File::open("./homeless-nah-id-win.gif")?;
-
-    let mut parser = Decoder::new(&mut file);
-    parser.parse()?;
-
-    for (i, frame) in parser.frames().iter().enumerate() {
-        let color_table = frame.palette().expect("expected there to be a color palette of some sort");
-
-        let width = frame.width;
-        let height = frame.height;
-        let indexes = frame.indicies();
-        //indexes.chunks(10).for_each(|chunk|  println!("{:?}", chunk));
-        ppm_writer::write_ppm(&format!("yeah/frame_{}.ppm", i), width, height, indexes, color_table)?;
-    }
-
-    Ok(())
-}
+use cli::{Cli, Command};
+use recorder::Recorder;
 
 fn main() -> Result<()> {
     env_logger::init();
-    pollster::block_on(gfx::run());
-    Ok(())
+
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Stats(args)) => commands::stats::run(args),
+        Some(Command::Info(args)) => commands::info::run(args),
+        Some(Command::Palette(args)) => commands::palette::run(args),
+        Some(Command::ExplainDiff(args)) => commands::explain_diff::run(args),
+        Some(Command::Diff(args)) => commands::diff::run(args),
+        Some(Command::Run(args)) => commands::run::run(args),
+        Some(Command::Extract(args)) => commands::extract::run(args),
+        Some(Command::Raw(args)) => commands::raw::run(args),
+        Some(Command::Thumb(args)) => commands::thumb::run(args),
+        Some(Command::Poster(args)) => commands::poster::run(args),
+        Some(Command::Sheet(args)) => commands::sheet::run(args),
+        Some(Command::Resize(args)) => commands::resize::run(args),
+        Some(Command::Optimize(args)) => commands::optimize::run(args),
+        Some(Command::Retime(args)) => commands::retime::run(args),
+        Some(Command::Cut(args)) => commands::cut::run(args),
+        Some(Command::Reverse(args)) => commands::reverse::run(args),
+        Some(Command::Concat(args)) => commands::concat::run(args),
+        Some(Command::Coalesce(args)) => commands::layers::coalesce(args),
+        Some(Command::Deconstruct(args)) => commands::layers::deconstruct(args),
+        Some(Command::Apng(args)) => commands::apng::run(args),
+        Some(Command::Compare(args)) => gfx::compare::run(&args.files, !args.no_vsync, args.ignore_aspect_ratio),
+        None => {
+            let playlist = gfx::playlist(cli.files)?;
+            let recorder = cli.record.as_deref().map(Recorder::create).transpose()?;
+            match cli.headless {
+                Some(dir) => headless::run(playlist, &dir, cli.ignore_aspect_ratio, recorder),
+                None => {
+                    let screenshots = gfx::Screenshots { dir: cli.screenshot_dir, format: cli.screenshot_format };
+                    let scaling = gfx::Scaling { filter: cli.filter, pixel_perfect: cli.pixel_perfect };
+                    pollster::block_on(gfx::run(playlist, !cli.no_vsync, cli.ignore_aspect_ratio, screenshots, recorder, scaling));
+                    Ok(())
+                }
+            }
+        }
+    }
 }
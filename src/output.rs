@@ -0,0 +1,196 @@
+use anyhow::Result;
+use log::warn;
+
+use std::fs::{self, File};
+use std::io::{self, prelude::*, BufWriter};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutputOptions {
+    /// fsync the file, and the directory holding it, before reporting success. Slower, but the
+    /// output survives a power cut and not just a crash.
+    pub fsync: bool,
+}
+
+/// A file that only shows up at its destination once everything has been written. Data goes to
+/// a temporary file next to the destination, which is renamed over it by `commit`. Dropping an
+/// uncommitted `AtomicFile` removes the temporary file, so an interrupted write never leaves a
+/// truncated output behind, unless `keep_on_drop` asked for it to be kept.
+#[derive(Debug)]
+pub struct AtomicFile {
+    writer: Option<BufWriter<File>>,
+    temp_path: PathBuf,
+    path: PathBuf,
+    options: OutputOptions,
+    keep_on_drop: bool,
+    // set once the temporary file has been renamed into place, until then dropping removes it
+    committed: bool,
+}
+
+impl AtomicFile {
+    pub fn create_with_options<P: AsRef<Path>>(path: P, options: OutputOptions) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let temp_path = temp_path_for(&path);
+        let file = File::create(&temp_path)?;
+
+        Ok(Self {
+            writer: Some(BufWriter::new(file)),
+            temp_path,
+            path,
+            options,
+            keep_on_drop: false,
+            committed: false,
+        })
+    }
+
+    /// Where the data goes until it's committed.
+    pub fn temp_path(&self) -> &Path {
+        &self.temp_path
+    }
+
+    /// Leaves the temporary file at `temp_path` if this is dropped without being committed, so
+    /// what was written so far can be picked up again, like an encode that was checkpointed.
+    pub fn keep_on_drop(&mut self) {
+        self.keep_on_drop = true;
+    }
+
+    /// Flushes the written data and moves it into place.
+    pub fn commit(mut self) -> Result<()> {
+        let writer = self.writer.take().expect("writer is only taken by commit");
+        let file = writer.into_inner().map_err(|err| err.into_error())?;
+
+        if self.options.fsync {
+            file.sync_all()?;
+        }
+        drop(file);
+
+        fs::rename(&self.temp_path, &self.path)?;
+        self.committed = true;
+
+        if self.options.fsync {
+            sync_parent_directory(&self.path)?;
+        }
+
+        Ok(())
+    }
+
+    fn writer(&mut self) -> &mut BufWriter<File> {
+        self.writer.as_mut().expect("writer is only taken by commit")
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer().flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed && !self.keep_on_drop {
+            if let Err(err) = fs::remove_file(&self.temp_path) {
+                warn!(
+                    "could not remove temporary file {}: {}",
+                    self.temp_path.display(),
+                    err
+                );
+            }
+        }
+    }
+}
+
+/// Creates `path` atomically, calling `write` to produce its contents.
+pub fn write_atomically<P, F>(path: P, options: OutputOptions, write: F) -> Result<()>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut AtomicFile) -> Result<()>,
+{
+    let mut file = AtomicFile::create_with_options(path, options)?;
+    write(&mut file)?;
+    file.commit()
+}
+
+// the temporary file has to live in the same directory as the destination, renames across
+// filesystems aren't atomic.
+fn temp_path_for(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let counter = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    path.with_file_name(format!(".{}.{}.{}.tmp", file_name, process::id(), counter))
+}
+
+#[cfg(unix)]
+fn sync_parent_directory(path: &Path) -> Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(parent)?.sync_all()?;
+    Ok(())
+}
+
+// directories can't be opened for syncing on other platforms, the rename is as good as it gets.
+#[cfg(not(unix))]
+fn sync_parent_directory(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_atomically, AtomicFile, OutputOptions};
+
+    use anyhow::anyhow;
+    use std::fs;
+    use std::io::prelude::*;
+
+    #[test]
+    fn only_creates_the_file_on_commit() {
+        let dir = std::env::temp_dir().join(format!("jif-output-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.bin");
+
+        let mut file = AtomicFile::create_with_options(&path, OutputOptions::default()).unwrap();
+        file.write_all(b"hello").unwrap();
+        assert!(!path.exists());
+        file.commit().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"hello");
+
+        let result = write_atomically(&path, OutputOptions { fsync: true }, |file| {
+            file.write_all(b"partial")?;
+            Err(anyhow!("interrupted"))
+        });
+        assert!(result.is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"hello");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn removes_the_temporary_file_when_the_rename_fails() {
+        let dir = std::env::temp_dir().join(format!("jif-output-rename-{}", std::process::id()));
+        // a file can't be renamed over a directory
+        let path = dir.join("out.bin");
+        fs::create_dir_all(path.join("in-the-way")).unwrap();
+
+        let result = write_atomically(&path, OutputOptions::default(), |file| {
+            Ok(file.write_all(b"hello")?)
+        });
+        assert!(result.is_err());
+        assert!(path.is_dir());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,23 +1,46 @@
-mod bit_reader;
-mod lzw;
-
-use anyhow::{anyhow, Result};
-use log::debug;
+#[cfg(feature = "async")]
+mod async_decoder;
+mod buffers;
+#[doc(hidden)]
+pub mod lzw;
+mod options;
+mod seek;
+mod sink;
+mod slice;
+mod stats;
+
+#[cfg(feature = "async")]
+pub use async_decoder::{AsyncDecoder, TokioReader};
+pub use buffers::DecodeBuffers;
+pub use lzw::LzwError;
+pub use options::{DecodeOptions, PaletteIndexPolicy, Progress};
+pub use sink::{Buffered, DecodeSink, FrameDescriptor, Header, ImageData};
+pub use slice::{SliceDecoder, SliceFrame, SubBlocks};
+pub use stats::FrameStats;
+
+#[cfg(fuzzing)]
+pub use lzw::lzw_decode;
+
+use anyhow::Result;
+use log::{debug, warn};
 use thiserror::Error;
 
 use std::fmt::Debug;
-use std::io::prelude::*;
+use std::io::{self, prelude::*, BufReader, SeekFrom};
 use std::str;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::trace::Span;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LoopCount {
     Infinite,
     Number(u16),
 }
 
 #[repr(u8)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DisposalMethod {
     None = 0,
     DoNotDispose = 1,
@@ -37,6 +60,31 @@ impl DisposalMethod {
     }
 }
 
+/// Delays shorter than this are played as `NORMALIZED_DELAY_TIME` instead, like browsers do,
+/// since many GIFs are written with no delay but expect to be played at a normal speed.
+pub(crate) const MINIMUM_DELAY_TIME: u16 = 2;
+const NORMALIZED_DELAY_TIME: u16 = 10;
+
+/// How long a frame with a delay of `delay_time` hundredths of a second is shown for. Delays
+/// of 0 and 1 are played as a tenth of a second.
+pub fn frame_duration(delay_time: u16) -> Duration {
+    let delay_time = if delay_time < MINIMUM_DELAY_TIME {
+        NORMALIZED_DELAY_TIME
+    } else {
+        delay_time
+    };
+    Duration::from_millis(delay_time as u64 * 10)
+}
+
+/// The width of a pixel over its height, from the pixel aspect ratio byte of the logical screen
+/// descriptor. 0 means the file doesn't say, which every viewer takes as square.
+pub fn pixel_aspect_ratio(pixel_aspect_ratio: u8) -> Option<f32> {
+    match pixel_aspect_ratio {
+        0 => None,
+        n => Some((n as f32 + 15.0) / 64.0),
+    }
+}
+
 const EXTENSION_INTRODUCER: u8 = 0x21;
 const IMAGE_DESCRIPTOR_LABEL: u8 = 0x2c;
 const TRAILER_LABEL: u8 = 0x3b;
@@ -53,6 +101,19 @@ enum ExtensionType {
     Comment,
     GraphicControl,
     PlainText,
+    Unknown(u8),
+}
+
+impl ExtensionType {
+    fn name(&self) -> &'static str {
+        match self {
+            ExtensionType::Application => "application extension",
+            ExtensionType::Comment => "comment extension",
+            ExtensionType::GraphicControl => "graphic control extension",
+            ExtensionType::PlainText => "plain text extension",
+            ExtensionType::Unknown(_) => "unknown extension",
+        }
+    }
 }
 
 impl TryFrom<u8> for ExtensionType {
@@ -80,12 +141,44 @@ pub struct Frame {
     pub height: u16,
     pub needs_user_input: bool,
     pub delay_time: u16,
+    pub disposal_method: Option<DisposalMethod>,
+    pub transparent_color_index: Option<u8>,
     local_palette: Option<Box<[u8]>>,
     global_palette: Option<Arc<[u8]>>,
-    indicies: Box<[u8]>,
+    // shared so frames are cheap to clone, e.g. to hand them to another thread
+    indicies: Arc<[u8]>,
+    // how many pixels have each index, counted the first time `stats` is called. Shared like
+    // `indicies`, and replaced along with them
+    index_counts: Arc<OnceLock<[usize; 256]>>,
+    palette_index_policy: PaletteIndexPolicy,
 }
 
 impl Frame {
+    /// Creates a frame covering `width` x `height` pixels at the origin, using `palette` as its
+    /// local color table.
+    pub fn new(width: u16, height: u16, indicies: Box<[u8]>, palette: Box<[u8]>) -> Self {
+        Self {
+            left_position: 0,
+            top_position: 0,
+            width,
+            height,
+            needs_user_input: false,
+            delay_time: 0,
+            disposal_method: None,
+            transparent_color_index: None,
+            local_palette: Some(palette),
+            global_palette: None,
+            indicies: indicies.into(),
+            index_counts: Arc::default(),
+            palette_index_policy: PaletteIndexPolicy::default(),
+        }
+    }
+
+    /// Whether this frame carries its own color table rather than using the global one.
+    pub fn has_local_palette(&self) -> bool {
+        self.local_palette.is_some()
+    }
+
     pub fn palette(&self) -> Option<&[u8]> {
         if let Some(palette) = self.local_palette.as_ref() {
             Some(palette.as_ref())
@@ -97,6 +190,81 @@ impl Frame {
     pub fn indicies(&self) -> &[u8] {
         self.indicies.as_ref()
     }
+
+    /// How long the frame is shown for, see [`frame_duration`].
+    pub fn duration(&self) -> Duration {
+        frame_duration(self.delay_time)
+    }
+
+    /// A copy of this frame with its image replaced by `indicies`, covering `width` x `height`
+    /// pixels at `left`, `top`. Timing, disposal and palette stay the same.
+    pub fn with_image(
+        &self,
+        left: u16,
+        top: u16,
+        width: u16,
+        height: u16,
+        indicies: Box<[u8]>,
+    ) -> Frame {
+        Frame {
+            left_position: left,
+            top_position: top,
+            width,
+            height,
+            indicies: indicies.into(),
+            index_counts: Arc::default(),
+            ..self.clone()
+        }
+    }
+
+    /// A copy of this frame with `indicies` into `palette` as its local color table. Position,
+    /// timing and disposal stay the same.
+    pub fn with_palette(&self, indicies: Box<[u8]>, palette: Box<[u8]>) -> Frame {
+        Frame {
+            local_palette: Some(palette),
+            global_palette: None,
+            indicies: indicies.into(),
+            index_counts: Arc::default(),
+            ..self.clone()
+        }
+    }
+
+    /// Makes `palette` the frame's palette as the global color table rather than its own, or
+    /// leaves it without one for `None`.
+    pub(crate) fn set_global_palette(&mut self, palette: Option<Arc<[u8]>>) {
+        self.local_palette = None;
+        self.global_palette = palette;
+    }
+
+    /// Looks `index` up in the frame's palette. `None` when the index is past the end of the
+    /// palette or the frame has no palette at all.
+    pub fn color(&self, index: u8) -> Option<[u8; 3]> {
+        let offset = index as usize * 3;
+        let rgb = self.palette()?.get(offset..offset + 3)?;
+        Some([rgb[0], rgb[1], rgb[2]])
+    }
+
+    /// What a pixel with `index` shows as RGBA. The transparent index is fully transparent
+    /// black, and indicies without a palette entry follow the [`PaletteIndexPolicy`] the frame
+    /// was decoded with, opaque black for frames made some other way.
+    pub fn pixel(&self, index: u8) -> Option<[u8; 4]> {
+        if Some(index) == self.transparent_color_index {
+            return Some([0, 0, 0, 0]);
+        }
+        self.palette_index_policy
+            .rgba(self.palette().unwrap_or_default(), index)
+    }
+
+    /// Expands the frame's indicies to RGBA, four bytes per pixel, see [`Frame::pixel`].
+    /// Decoding with [`PaletteIndexPolicy::Error`] refuses frames with pixels it has no color
+    /// for, so they never come up.
+    pub fn rgba(&self) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.indicies.len() * 4);
+        for &index in self.indicies.iter() {
+            rgba.extend_from_slice(&self.pixel(index).unwrap_or([0, 0, 0, 255]));
+        }
+        rgba
+    }
 }
 
 
@@ -118,12 +286,11 @@ struct TableBasedImage {
     height: u16,
 
     interlace_flag: bool,
-    sort_flag: bool,
     local_color_table_size: Option<u32>,
 
     local_color_table: Option<Box<[u8]>>,
 
-    image_indexes: Option<Box<[u8]>>,
+    image_indexes: Option<Arc<[u8]>>,
 }
 
 #[derive(Debug)]
@@ -132,26 +299,101 @@ struct GraphicBlock {
     render_block: TableBasedImage,
 }
 
-#[derive(Debug)]
-enum SpecialPurposeExtension {
-    ApplicationBlock {
-        application_identifier: Box<str>,
-        application_authentication_code: Box<[u8]>,
-        application_data: Box<[u8]>,
-    },
+/// An extension that isn't about a frame, see [`DecodeSink::on_extension`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpecialPurposeExtension {
+    ApplicationBlock(ApplicationExtension),
     CommentBlock(Box<[u8]>),
+    Unknown {
+        label: u8,
+        data: Box<[u8]>,
+    },
 }
 
-#[derive(Debug)]
-enum Version {
+// what an XMP packet is followed by, so that readers skipping over it as sub-blocks end up at
+// the block terminator that comes after these 257 bytes whichever byte of it they land on
+const XMP_MAGIC_TRAILER: [u8; 257] = xmp_magic_trailer();
+
+const fn xmp_magic_trailer() -> [u8; 257] {
+    let mut trailer = [0; 257];
+    trailer[0] = 1;
+    let mut index = 1;
+    while index < 256 {
+        trailer[index] = (256 - index) as u8;
+        index += 1;
+    }
+    trailer
+}
+
+/// An application extension, where an application keeps data of its own, like the loop count
+/// in `NETSCAPE2.0` or XMP metadata in `XMP DataXMP`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApplicationExtension {
+    /// Eight characters naming the application, like `NETSCAPE`.
+    pub identifier: Box<str>,
+    /// Three bytes telling versions of the application's data apart, like `2.0`.
+    pub authentication_code: Box<[u8]>,
+    /// The data sub-blocks joined together.
+    pub data: Box<[u8]>,
+    // the size byte in front of each sub-block, which XMP keeps part of its packet in
+    sub_block_sizes: Box<[u8]>,
+}
+
+impl ApplicationExtension {
+    /// The data split into the sub-blocks it was stored in.
+    pub fn sub_blocks(&self) -> impl Iterator<Item = &[u8]> {
+        let mut data = self.data.as_ref();
+        self.sub_block_sizes.iter().map(move |&size| {
+            // the last sub-block is short when the file was cut off in the middle of it
+            let (sub_block, rest) = data.split_at((size as usize).min(data.len()));
+            data = rest;
+            sub_block
+        })
+    }
+
+    /// Whether this extension holds an XMP packet.
+    pub fn is_xmp(&self) -> bool {
+        self.identifier.as_ref() == "XMP Data" && self.authentication_code.as_ref() == b"XMP"
+    }
+
+    /// The XMP packet of an `XMP DataXMP` extension. XMP isn't split into sub-blocks but
+    /// written out as it is, so the sub-block sizes are really bytes of the packet, and it's
+    /// followed by a "magic trailer" that's left out here. Invalid UTF-8 is replaced.
+    pub fn xmp(&self) -> Option<String> {
+        if !self.is_xmp() {
+            return None;
+        }
+
+        let mut packet = Vec::with_capacity(self.sub_block_sizes.len() + self.data.len());
+        for sub_block in self.sub_blocks() {
+            packet.push(sub_block.len() as u8);
+            packet.extend_from_slice(sub_block);
+        }
+        if packet.ends_with(&XMP_MAGIC_TRAILER) {
+            packet.truncate(packet.len() - XMP_MAGIC_TRAILER.len());
+        }
+
+        Some(String::from_utf8_lossy(&packet).into_owned())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
     V87a,
     V89a,
 }
 
-impl TryFrom<&str> for Version {
-    type Error = ParserError;
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Version::V87a => write!(f, "87a"),
+            Version::V89a => write!(f, "89a"),
+        }
+    }
+}
 
-    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
+impl Version {
+    fn parse(value: &str) -> std::result::Result<Self, ParserError> {
         match value {
             "87a" => Ok(Version::V87a),
             "89a" => Ok(Version::V89a),
@@ -164,9 +406,6 @@ impl TryFrom<&str> for Version {
 struct LogicalScreenDescriptor {
     screen_width: u16,
     screen_height: u16,
-    global_color_table_flag: bool,
-    color_resolution: u8,
-    sort_flag: bool,
     global_color_table_size: Option<u32>,
     background_color_index: u8,
     pixel_aspect_ratio: u8,
@@ -199,12 +438,62 @@ enum ParserError {
     #[error("encountered extension with label 0x{0:02x}, this label is not supported")]
     InvalidExtensionLabel(u8),
 
-    #[error("expected image descriptor after graphic control extension")]
-    ExpectedImageDescriptor,
-
     #[error("encountered unexpected label, this label is not supported: {0}")]
     UnexpectedLabel(u8),
 
+    #[error("image is {width}x{height}, which is larger than the configured maximum of {max_width}x{max_height}")]
+    DimensionsTooLarge {
+        width: u16,
+        height: u16,
+        max_width: u16,
+        max_height: u16,
+    },
+
+    #[error("{limit} limit exceeded, needed {requested} but the maximum is {max}")]
+    LimitExceeded {
+        limit: &'static str,
+        requested: usize,
+        max: usize,
+    },
+
+    #[error("sub-block claims {expected} bytes but the stream ended after {actual}")]
+    TruncatedSubBlock { expected: u8, actual: usize },
+
+    #[error("{block} has block size {actual}, expected {expected}")]
+    InvalidBlockSize {
+        block: &'static str,
+        expected: u8,
+        actual: u8,
+    },
+
+    #[error("{0} is missing its block terminator")]
+    MissingBlockTerminator(&'static str),
+
+    #[error("lzw minimum code size {0} is out of range")]
+    InvalidLzwCodeSize(u8),
+
+    #[error("frame {frame} has index {index}, past the end of its {colors} color palette")]
+    IndexOutOfRange {
+        frame: usize,
+        index: u8,
+        colors: usize,
+    },
+
+    #[error("image data decoded to {actual} indicies, expected {expected}")]
+    MissingImageData { expected: usize, actual: usize },
+
+    #[error("image data is corrupt: {0}")]
+    Lzw(#[from] LzwError),
+
+    #[error("decoding took longer than the timeout of {0:?}")]
+    TimedOut(Duration),
+
+    #[error("found a {0}, which GIF87a files can't have")]
+    ExtensionNewerThanVersion(&'static str),
+
+    #[error("encountered application extension with block size {0}, expected 11")]
+    UnexpectedApplicationBlockSize(u8),
+
     #[error("encountered application descriptor with name {name}, expected descriptor data length to be {expected}, actual length is {actual}")]
     UnexpectedApplicationDescriptorDataLength {
         name: String,
@@ -213,41 +502,425 @@ enum ParserError {
     },
 }
 
+/// Parses a GIF, handing every block it reads to a [`DecodeSink`]. By default that's
+/// [`Buffered`], which keeps everything for `frames` and the other accessors to read.
 #[derive(Debug)]
-pub struct Decoder<T: Read> {
-    inner: T,
+pub struct Decoder<T: Read, S: DecodeSink = Buffered> {
+    // buffered here, since blocks are read a byte or two at a time
+    inner: BufReader<T>,
     version: Option<Version>,
     logical_screen_descriptor: Option<LogicalScreenDescriptor>,
     global_color_table: Option<Arc<[u8]>>,
-    special_purpose_extensions: Vec<SpecialPurposeExtension>,
     loop_count: Option<LoopCount>,
-    frames: Vec<Frame>,
+    sink: S,
+    // frames handed to the sink so far
+    frames_read: usize,
+    bytes_read: u64,
+    // set by `set_length` or `measure_length`, for progress reports
+    total_bytes: Option<u64>,
+    options: DecodeOptions,
+    // bytes of decoded image data and color tables, checked against `DecodeOptions::max_memory`
+    memory_used: usize,
+    // the most indicies `decode_frame_into` has decoded into its buffers, which are counted in
+    // `memory_used` once rather than once a frame
+    buffered_indicies: usize,
+    image_data_mode: ImageDataMode,
+    // where parsing stopped, so `next_frame` can carry on from there
+    state: ParserState,
+    // when parsing started, only kept with `DecodeOptions::timeout` set
+    started: Option<Instant>,
+    // image data read in `ImageDataMode::Collect`, waiting to be decoded
+    pending_image_data: Vec<PendingImageData>,
+    // kept to decode the next frame into
+    buffers: DecodeBuffers,
+    // canvases kept by `frame_at`
+    seek_cache: seek::SeekCache,
+    // whether a GIF87a file was already warned about for using GIF89a extensions
+    warned_about_version: bool,
+}
+
+// what ProcessImageData does with the compressed image data of a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImageDataMode {
+    Decode,
+    // set by `scan`, frames are left without indicies
+    Skip,
+    // set by `parse_parallel` and `parse_raw`, frames are left without indicies and their image
+    // data is kept in `pending_image_data`
+    Collect,
+    // set by `decode_frame_into`, frames are left without indicies, which are kept in `buffers`
+    Buffers,
+}
+
+#[derive(Debug)]
+struct PendingImageData {
+    // only decoded from here by `parse_parallel`, `parse_raw` just needs the data
+    #[cfg(feature = "parallel")]
+    frame: usize,
+    lzw_code_size: u8,
+    data: Box<[u8]>,
+    #[cfg(feature = "parallel")]
+    pixel_count: usize,
+    // the row width of an interlaced frame, whose rows have to be put back in order
+    interlaced_width: Option<u16>,
+}
+
+/// The image data of a frame as it's stored in the file, still compressed. Kept by
+/// [`Decoder::parse_raw`] so that frames can be written out again without decoding them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawImageData {
+    pub lzw_code_size: u8,
+    /// The data sub-blocks joined together.
+    pub data: Box<[u8]>,
+    /// Whether the rows are stored in interlaced order.
+    pub interlaced: bool,
+}
+
+/// What `Decoder::scan` finds out about a GIF without decoding any image data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    pub version: Version,
+    pub width: u16,
+    pub height: u16,
+    pub frame_count: usize,
+    /// How long one play through takes, see [`Decoder::total_duration`].
+    pub duration: Duration,
+    /// Number of colors in the global color table.
+    pub global_palette_colors: Option<usize>,
+    /// Number of frames carrying their own color table.
+    pub local_palette_count: usize,
+    pub loop_count: Option<LoopCount>,
+    pub application_extensions: Vec<ApplicationExtension>,
+}
+
+/// What was decoded of a GIF before decoding failed, see [`Decoder::parse_partial`].
+#[derive(Error, Debug)]
+#[error("{error} (after {} frames)", .frames.len())]
+pub struct PartialDecode {
+    pub frames: Vec<Frame>,
+    pub error: anyhow::Error,
 }
 
 impl<T: Read + Debug> Decoder<T> {
     pub fn new(inner: T) -> Self {
+        Self::new_with_options(inner, DecodeOptions::default())
+    }
+
+    pub fn new_with_options(inner: T, options: DecodeOptions) -> Self {
+        Self::with_sink(inner, options, Buffered::default())
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.sink.frames
+    }
+
+    pub fn into_frames(self) -> Vec<Frame> {
+        self.sink.frames
+    }
+
+    /// Like [`Decoder::parse`], except that when a stream breaks off partway through, the
+    /// frames before the break aren't lost: they're moved out of the decoder into the
+    /// [`PartialDecode`] along with the error, so whatever did decode can still be shown. The
+    /// header is kept, `screen_size` and the like still answer.
+    pub fn parse_partial(&mut self) -> Result<(), PartialDecode> {
+        self.parse().map_err(|error| PartialDecode {
+            frames: std::mem::take(&mut self.sink.frames),
+            error,
+        })
+    }
+
+    /// How long each frame parsed so far is shown for, see [`frame_duration`].
+    pub fn frame_durations(&self) -> Vec<Duration> {
+        self.frames().iter().map(Frame::duration).collect()
+    }
+
+    /// How long one play through the frames parsed so far takes.
+    pub fn total_duration(&self) -> Duration {
+        self.frames().iter().map(Frame::duration).sum()
+    }
+
+    /// Application, comment and unknown extensions in the order they were read.
+    pub(crate) fn extensions(&self) -> &[SpecialPurposeExtension] {
+        &self.sink.extensions
+    }
+
+    /// The application extensions read so far, in the order they were read.
+    pub fn application_extensions(&self) -> impl Iterator<Item = &ApplicationExtension> {
+        self.extensions()
+            .iter()
+            .filter_map(|extension| match extension {
+                SpecialPurposeExtension::ApplicationBlock(application) => Some(application),
+                _ => None,
+            })
+    }
+
+    /// The text of the comment extensions read so far, in the order they were read.
+    pub fn comments(&self) -> impl Iterator<Item = &[u8]> {
+        self.extensions()
+            .iter()
+            .filter_map(|extension| match extension {
+                SpecialPurposeExtension::CommentBlock(comment) => Some(comment.as_ref()),
+                _ => None,
+            })
+    }
+
+    /// The XMP packet of the first `XMP DataXMP` extension read so far, see
+    /// [`ApplicationExtension::xmp`].
+    pub fn xmp(&self) -> Option<String> {
+        self.application_extensions().find_map(ApplicationExtension::xmp)
+    }
+
+    /// Parses just far enough to decode one more frame and returns it, or `None` once the end of
+    /// the file has been reached. Frames are still collected in `frames` as they're decoded.
+    pub fn next_frame(&mut self) -> Result<Option<&Frame>> {
+        if self.advance_to_next_frame()? {
+            Ok(self.sink.frames.last())
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Like `next_frame`, but the frame's indicies are decoded into `buffers` rather than into
+    /// memory of its own, and the frame is kept in `frames` without them. Decoding every frame
+    /// into the same buffers, to play a file as it's read say, only allocates while they grow.
+    pub fn decode_frame_into(&mut self, buffers: &mut DecodeBuffers) -> Result<Option<&Frame>> {
+        std::mem::swap(&mut self.buffers, buffers);
+        self.image_data_mode = ImageDataMode::Buffers;
+        let advanced = self.advance_to_next_frame();
+        self.image_data_mode = ImageDataMode::Decode;
+        std::mem::swap(&mut self.buffers, buffers);
+
+        if advanced? {
+            Ok(self.sink.frames.last())
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Like `parse`, but the image data is decoded on rayon's thread pool. The file is read
+    /// once to collect the compressed data of every frame, which is then decoded in parallel,
+    /// so the compressed data of the whole file is held in memory at once.
+    #[cfg(feature = "parallel")]
+    pub fn parse_parallel(&mut self) -> Result<()> {
+        use rayon::prelude::*;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        self.image_data_mode = ImageDataMode::Collect;
+        let parsed = self.parse();
+        self.image_data_mode = ImageDataMode::Decode;
+        let started = self.started;
+
+        // whatever was read before an error is still decoded, like it would be by `parse`
+        let pending = std::mem::take(&mut self.pending_image_data);
+        let strict = self.options.strict;
+        let timeout = self.options.timeout;
+        // everything has been read by now, only the frames decoded go up
+        let progress = self.options.progress.as_ref().map(|progress| &progress.0);
+        let (bytes_read, total_bytes) = (self.bytes_read, self.total_bytes);
+        let frames_decoded = AtomicUsize::new(0);
+        let decoded = pending
+            .par_iter()
+            .map_init(DecodeBuffers::default, |buffers, pending| {
+                check_timeout(timeout, started)?;
+                buffers.decode(
+                    &pending.data,
+                    pending.lzw_code_size,
+                    pending.pixel_count,
+                    pending.interlaced_width,
+                    strict,
+                )?;
+                if let Some(progress) = progress {
+                    progress(&Progress {
+                        bytes_read,
+                        total_bytes,
+                        frames_decoded: frames_decoded.fetch_add(1, Ordering::Relaxed) + 1,
+                    });
+                }
+                Ok(Arc::from(buffers.indicies()))
+            })
+            .collect::<Vec<std::result::Result<_, ParserError>>>();
+
+        // frames are left the way `parse` would leave them, which stops at the first frame
+        // that fails to decode, before any error reading the rest of the file
+        for (pending, indicies) in pending.iter().zip(decoded) {
+            let checked = indicies.and_then(|indicies| {
+                let frame = &mut self.sink.frames[pending.frame];
+                frame.indicies = indicies;
+                frame.index_counts = Arc::default();
+                check_palette_indicies(frame, frame.indicies(), pending.frame)
+            });
+            if let Err(err) = checked {
+                self.sink.frames.truncate(pending.frame);
+                return Err(err.into());
+            }
+        }
+
+        parsed
+    }
+
+    /// Walks the whole file like `parse`, but skips over the image data instead of decoding it,
+    /// which is a lot faster when only the structure of the file is of interest.
+    pub fn scan(mut self) -> Result<Summary> {
+        self.image_data_mode = ImageDataMode::Skip;
+        self.parse()?;
+
+        let (width, height) = self.screen_size().unwrap_or((0, 0));
+
+        Ok(Summary {
+            version: self.version.expect("version is read before anything else"),
+            width,
+            height,
+            frame_count: self.frames().len(),
+            duration: self.total_duration(),
+            global_palette_colors: self.global_color_table.as_ref().map(|palette| palette.len() / 3),
+            local_palette_count: self.frames().iter().filter(|frame| frame.has_local_palette()).count(),
+            loop_count: self.loop_count,
+            application_extensions: self.application_extensions().cloned().collect(),
+        })
+    }
+
+    /// Parses everything that's left of the file without decoding any image data, returning the
+    /// compressed image data of each frame instead. Frames are left without indicies, like with
+    /// `scan`.
+    pub fn parse_raw(&mut self) -> Result<Vec<RawImageData>> {
+        self.image_data_mode = ImageDataMode::Collect;
+        let parsed = self.parse();
+        self.image_data_mode = ImageDataMode::Decode;
+        parsed?;
+
+        Ok(std::mem::take(&mut self.pending_image_data)
+            .into_iter()
+            .map(|pending| RawImageData {
+                lzw_code_size: pending.lzw_code_size,
+                data: pending.data,
+                interlaced: pending.interlaced_width.is_some(),
+            })
+            .collect())
+    }
+}
+
+impl<T: Read + Seek + Debug, S: DecodeSink> Decoder<T, S> {
+    /// Measures how many bytes are left in the stream for progress reports, see
+    /// [`DecodeOptions::progress`], and returns it. The stream is left where it was.
+    pub fn measure_length(&mut self) -> Result<u64> {
+        let position = self.inner.stream_position()?;
+        let end = self.inner.seek(SeekFrom::End(0))?;
+        self.inner.seek(SeekFrom::Start(position))?;
+
+        let length = end.saturating_sub(position);
+        self.set_length(length);
+        Ok(length)
+    }
+}
+
+impl<T: Read + Debug, S: DecodeSink> Decoder<T, S> {
+    /// A decoder handing everything it reads to `sink` instead of keeping it.
+    pub fn with_sink(inner: T, options: DecodeOptions, sink: S) -> Self {
         Self {
-            inner,
+            inner: BufReader::new(inner),
             version: None,
             logical_screen_descriptor: None,
             global_color_table: None,
-            special_purpose_extensions: Vec::new(),
             loop_count: None,
-            frames: Vec::new(),
+            sink,
+            frames_read: 0,
+            bytes_read: 0,
+            total_bytes: None,
+            options,
+            memory_used: 0,
+            buffered_indicies: 0,
+            image_data_mode: ImageDataMode::Decode,
+            state: ParserState::ProcessMagic,
+            started: None,
+            pending_image_data: Vec::new(),
+            buffers: DecodeBuffers::default(),
+            seek_cache: seek::SeekCache::default(),
+            warned_about_version: false,
         }
     }
 
-    pub fn frames(&self) -> &[Frame] {
-        &self.frames
+    pub fn sink(&self) -> &S {
+        &self.sink
     }
 
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    pub fn into_sink(self) -> S {
+        self.sink
+    }
+
+    /// Tells the decoder how many bytes are left in the stream, for progress reports. Streams
+    /// that can seek can be measured with `measure_length` instead.
+    pub fn set_length(&mut self, length: u64) {
+        self.total_bytes = Some(self.bytes_read + length);
+    }
+
+    pub fn loop_count(&self) -> Option<LoopCount> {
+        self.loop_count
+    }
+
+    pub fn version(&self) -> Option<Version> {
+        self.version
+    }
+
+    pub fn global_palette(&self) -> Option<&[u8]> {
+        self.global_color_table.as_deref()
+    }
+
+    /// The color of the global color table the background color index points at, available once
+    /// the global color table has been parsed. `None` without one or when the index is past it.
+    pub fn background_color(&self) -> Option<[u8; 3]> {
+        let index = self.logical_screen_descriptor.as_ref()?.background_color_index as usize;
+        let rgb = self.global_color_table.as_ref()?.get(index * 3..index * 3 + 3)?;
+        Some([rgb[0], rgb[1], rgb[2]])
+    }
+
+    /// The width of a pixel over its height, once the header has been parsed and if the file
+    /// says, see [`pixel_aspect_ratio`].
+    pub fn pixel_aspect_ratio(&self) -> Option<f32> {
+        pixel_aspect_ratio(self.logical_screen_descriptor.as_ref()?.pixel_aspect_ratio)
+    }
+
+    /// Logical screen size as `(width, height)`, available once the header has been parsed.
+    pub fn screen_size(&self) -> Option<(u16, u16)> {
+        self.logical_screen_descriptor
+            .as_ref()
+            .map(|descriptor| (descriptor.screen_width, descriptor.screen_height))
+    }
+
+    /// Parses everything that's left of the file.
     pub fn parse(&mut self) -> Result<()> {
-        let mut state = ParserState::ProcessMagic;
+        while self.advance_to_next_frame()? {}
+        Ok(())
+    }
+
+    // returns false once parsing is done, after an error parsing is done as well
+    fn advance_to_next_frame(&mut self) -> Result<bool> {
+        // the clock is only read with a timeout set, wasm32-unknown-unknown doesn't have one
+        if self.options.timeout.is_some() {
+            self.started.get_or_insert_with(Instant::now);
+        }
+        let started = self.started;
+        let frames_read = self.frames_read;
+        let bytes_read = self.bytes_read;
+        let mut span = Span::start("parse");
+        span.field("frame", frames_read);
 
         loop {
-            state = self.process_next_state(state)?;
+            let state = std::mem::replace(&mut self.state, ParserState::Done);
             if let ParserState::Done = state {
-                break Ok(());
+                span.field("bytes", self.bytes_read - bytes_read);
+                return Ok(false);
+            }
+
+            check_timeout(self.options.timeout, started)?;
+            self.state = self.process_next_state(state)?;
+
+            if self.frames_read > frames_read {
+                span.field("bytes", self.bytes_read - bytes_read);
+                return Ok(true);
             }
         }
     }
@@ -263,7 +936,7 @@ impl<T: Read + Debug> Decoder<T> {
                 }
                 debug!("processed signature, got GIF");
 
-                self.version = Some(Version::try_from(self.read_str(3)?.as_ref())?);
+                self.version = Some(Version::parse(&self.read_str(3)?)?);
                 debug!("processed version, got {:?}", self.version);
 
                 Ok(ProcessLogicalScreenDescriptor)
@@ -276,8 +949,6 @@ impl<T: Read + Debug> Decoder<T> {
 
                 // packed field start
                 let global_color_table_flag = packed_fields & 0b10000000 != 0;
-                let color_resolution = (packed_fields >> 4) & 0b00000111;
-                let sort_flag = packed_fields & 0b00001000 != 0;
                 let global_color_table_size = if global_color_table_flag {
                     Some(3 * 2_u32.pow(((packed_fields & 0b00000111) + 1).into()))
                 } else {
@@ -288,29 +959,32 @@ impl<T: Read + Debug> Decoder<T> {
                 let background_color_index = self.read_byte()?;
                 let pixel_aspect_ratio = self.read_byte()?;
 
+                self.options.check_dimensions(screen_width, screen_height)?;
+                self.options
+                    .check_canvas_pixels(screen_width, screen_height)?;
+
                 self.logical_screen_descriptor = Some(LogicalScreenDescriptor {
                     screen_height,
                     screen_width,
-                    global_color_table_flag,
-                    color_resolution,
-                    sort_flag,
                     global_color_table_size,
                     background_color_index,
                     pixel_aspect_ratio,
                 });
 
                 debug!(
-                    "processed logical screen descriptor, got: {:#?}",
-                    self.logical_screen_descriptor
+                    "processed logical screen descriptor, width={} height={} global_colors={:?} \
+                     background={}",
+                    screen_width,
+                    screen_height,
+                    global_color_table_size.map(|size| size / 3),
+                    background_color_index
                 );
 
-                let next_state = if global_color_table_flag {
-                    ProcessGlobalColorTable
+                if global_color_table_flag {
+                    Ok(ProcessGlobalColorTable)
                 } else {
-                    DetermineNextBlock(None)
-                };
-
-                Ok(next_state)
+                    self.finish_header()
+                }
             }
             ProcessGlobalColorTable => {
                 let screen_desc = self
@@ -321,17 +995,27 @@ impl<T: Read + Debug> Decoder<T> {
                     .global_color_table_size
                     .expect("global color table size should not be none");
 
+                self.reserve_memory(size as usize)?;
                 self.global_color_table = Some(Arc::from(self.read_bytes(size as usize)?));
-                debug!(
-                    "processed global color table, got: {:#?}",
-                    self.global_color_table
-                );
+                debug!("processed global color table, colors={}", size / 3);
 
-                Ok(DetermineNextBlock(None))
+                self.finish_header()
+            }
+            ProcessTrailer => {
+                if self.image_data_mode != ImageDataMode::Collect {
+                    self.report_progress();
+                }
+                Ok(Done)
             }
-            ProcessTrailer => Ok(Done),
             DetermineNextBlock(graphic_control_extension) => {
-                let introducer_or_label = self.read_byte()?;
+                let introducer_or_label = match self.read_byte() {
+                    Ok(introducer_or_label) => introducer_or_label,
+                    Err(err) if is_eof(&err) && self.options.recover_truncated_trailer() => {
+                        warn!("stream ended without a trailer, treating it as the end of the gif");
+                        return Ok(ProcessTrailer);
+                    }
+                    Err(err) => return Err(err),
+                };
 
                 match introducer_or_label {
                     // extension introducer means that a label follows determining what exact type
@@ -342,7 +1026,15 @@ impl<T: Read + Debug> Decoder<T> {
                     label => Err(ParserError::UnexpectedLabel(label).into()),
                 }
             }
-            ProcessExtension(label) => self.process_extension(ExtensionType::try_from(label)?),
+            ProcessExtension(label) => {
+                let extension_type = match ExtensionType::try_from(label) {
+                    Ok(extension_type) => extension_type,
+                    Err(err) if self.options.strict => return Err(err.into()),
+                    Err(_) => ExtensionType::Unknown(label),
+                };
+
+                self.process_extension(extension_type)
+            }
             ProcessImageDescriptor(graphic_control_extension) => {
                 let left_position = self.read_u16()?;
                 let top_position = self.read_u16()?;
@@ -350,11 +1042,12 @@ impl<T: Read + Debug> Decoder<T> {
                 let width = self.read_u16()?;
                 let height = self.read_u16()?;
 
+                self.options.check_dimensions(width, height)?;
+
                 let packed_fields = self.read_byte()?;
 
                 let local_color_table_flag = packed_fields & 0b10000000 != 0;
                 let interlace_flag = packed_fields & 0b01000000 != 0;
-                let sort_flag = packed_fields & 0b00100000 != 0;
                 // TODO: this should be optional!!
                 let local_color_table_size = if local_color_table_flag {
                     Some(3 * 2_u32.pow(((packed_fields & 0b00000111) + 1).into()))
@@ -370,7 +1063,6 @@ impl<T: Read + Debug> Decoder<T> {
                         width,
                         height,
                         interlace_flag,
-                        sort_flag,
                         local_color_table_size,
 
                         local_color_table: None,
@@ -392,41 +1084,88 @@ impl<T: Read + Debug> Decoder<T> {
                     .local_color_table_size
                     .expect("global color table size should not be none");
 
+                self.reserve_memory(size as usize)?;
                 graphic_block.render_block.local_color_table =
                     Some(self.read_bytes(size as usize)?);
 
                 Ok(ProcessImageData(graphic_block))
             }
             ProcessImageData(mut graphic_block) => {
-                let lzw_code_size = self.read_byte()?;
-                let data_stream = self.read_data_sub_blocks()?;
+                self.options.check_total_frames(self.frames_read)?;
+
+                let pixel_count = graphic_block.render_block.width as usize
+                    * graphic_block.render_block.height as usize;
+
+                let render_block = &graphic_block.render_block;
+                let image_data = self.sink.on_frame_descriptor(&FrameDescriptor {
+                    index: self.frames_read,
+                    left: render_block.left_position,
+                    top: render_block.top_position,
+                    width: render_block.width,
+                    height: render_block.height,
+                    interlaced: render_block.interlace_flag,
+                    has_local_palette: render_block.local_color_table.is_some(),
+                })?;
+
+                if self.image_data_mode == ImageDataMode::Skip || image_data == ImageData::Skip {
+                    // lzw minimum code size, then the image data
+                    self.read_byte()?;
+                    self.skip_data_sub_blocks()?;
+                    self.buffers.clear();
+                    graphic_block.render_block.image_indexes = Some(Arc::from([]));
+                    return self.push_frame(graphic_block);
+                }
+
+                self.options.check_pixels_per_frame(pixel_count)?;
+                if self.image_data_mode == ImageDataMode::Buffers {
+                    // the buffers are reused, they only take more for a larger frame
+                    self.reserve_memory(pixel_count.saturating_sub(self.buffered_indicies))?;
+                    self.buffered_indicies = self.buffered_indicies.max(pixel_count);
+                } else {
+                    self.reserve_memory(pixel_count)?;
+                }
 
-                let indicies = lzw::lzw_decode(&data_stream, lzw_code_size.into());
-                graphic_block.render_block.image_indexes = Some(indicies.into_boxed_slice());
+                let interlaced_width = graphic_block
+                    .render_block
+                    .interlace_flag
+                    .then_some(graphic_block.render_block.width);
 
-                let rb = graphic_block.render_block;
-                let ext = graphic_block.extension.as_ref();
+                let lzw_code_size = self.read_byte()?;
+                if self.options.strict && !(2..=8).contains(&lzw_code_size) {
+                    return Err(ParserError::InvalidLzwCodeSize(lzw_code_size).into());
+                }
 
-                let global_palette = if rb.local_color_table.is_some() {
-                    None
-                } else {
-                    Some(self.global_color_table.as_ref().unwrap().clone())
-                };
+                let mut data_stream = std::mem::take(&mut self.buffers.image_data);
+                data_stream.clear();
+                self.read_sub_blocks_into(&mut data_stream, None)?;
+
+                if self.image_data_mode == ImageDataMode::Collect {
+                    self.pending_image_data.push(PendingImageData {
+                        #[cfg(feature = "parallel")]
+                        frame: self.frames_read,
+                        lzw_code_size,
+                        data: data_stream.into_boxed_slice(),
+                        #[cfg(feature = "parallel")]
+                        pixel_count,
+                        interlaced_width,
+                    });
+                    graphic_block.render_block.image_indexes = Some(Arc::from([]));
+                    return self.push_frame(graphic_block);
+                }
 
-                let frame = Frame {
-                    left_position: rb.left_position,
-                    top_position: rb.top_position,
-                    width: rb.width,
-                    height: rb.height,
-                    needs_user_input: ext.map_or(false, |ext| ext.needs_user_input),
-                    delay_time: ext.map_or(1000, |ext| ext.delay_time),
-                    local_palette: rb.local_color_table,
-                    global_palette,
-                    indicies: rb.image_indexes.expect("expected there to be a processed gif frame")
-                };
-                self.frames.push(frame);
+                self.buffers.image_data = data_stream;
+                self.buffers.decode_image_data(
+                    lzw_code_size,
+                    pixel_count,
+                    interlaced_width,
+                    self.options.strict,
+                )?;
+                graphic_block.render_block.image_indexes = Some(match self.image_data_mode {
+                    ImageDataMode::Buffers => Arc::from([]),
+                    _ => Arc::from(self.buffers.indicies()),
+                });
 
-                Ok(DetermineNextBlock(None))
+                self.push_frame(graphic_block)
             }
             _ => {
                 unimplemented!();
@@ -434,67 +1173,189 @@ impl<T: Read + Debug> Decoder<T> {
         }
     }
 
+    // the header is done once the global color table, if there is one, has been read
+    fn finish_header(&mut self) -> Result<ParserState> {
+        let descriptor = self
+            .logical_screen_descriptor
+            .as_ref()
+            .expect("logical screen descriptor should not be none");
+        self.sink.on_header(&Header {
+            version: self.version.expect("version is read before anything else"),
+            width: descriptor.screen_width,
+            height: descriptor.screen_height,
+            background_color_index: descriptor.background_color_index,
+            pixel_aspect_ratio: descriptor.pixel_aspect_ratio,
+            global_palette: self.global_color_table.clone(),
+        })?;
+
+        Ok(ParserState::DetermineNextBlock(None))
+    }
+
+    fn push_frame(&mut self, graphic_block: GraphicBlock) -> Result<ParserState> {
+        let rb = graphic_block.render_block;
+        let ext = graphic_block.extension.as_ref();
+
+        let global_palette = if rb.local_color_table.is_some() {
+            None
+        } else {
+            self.global_color_table.clone()
+        };
+
+        let frame = Frame {
+            left_position: rb.left_position,
+            top_position: rb.top_position,
+            width: rb.width,
+            height: rb.height,
+            needs_user_input: ext.is_some_and(|ext| ext.needs_user_input),
+            delay_time: ext.map_or(0, |ext| ext.delay_time),
+            disposal_method: ext.and_then(|ext| ext.disposal_method),
+            transparent_color_index: ext.and_then(|ext| ext.transparent_color_index),
+            local_palette: rb.local_color_table,
+            global_palette,
+            indicies: rb
+                .image_indexes
+                .expect("expected there to be a processed gif frame"),
+            index_counts: Arc::default(),
+            palette_index_policy: self.options.palette_index_policy,
+        };
+        let indicies = match self.image_data_mode {
+            ImageDataMode::Buffers => self.buffers.indicies(),
+            _ => frame.indicies(),
+        };
+        check_palette_indicies(&frame, indicies, self.frames_read)?;
+        self.sink.on_frame_data(frame)?;
+        self.frames_read += 1;
+        // collected frames are counted once they're decoded
+        if self.image_data_mode != ImageDataMode::Collect {
+            self.report_progress();
+        }
+
+        if self
+            .options
+            .max_frame_count
+            .is_some_and(|max_frame_count| self.frames_read >= max_frame_count)
+        {
+            debug!("reached the maximum frame count, stopping");
+            return Ok(ParserState::Done);
+        }
+
+        Ok(ParserState::DetermineNextBlock(None))
+    }
+
     fn process_extension(&mut self, label: ExtensionType) -> Result<ParserState> {
         use ExtensionType::*;
 
         debug!("processing extension type: {:?}", label);
+        check_extension_version(
+            &self.options,
+            self.version,
+            &label,
+            &mut self.warned_about_version,
+        )?;
         match label {
             Application => {
                 let block_size = self.read_byte()?;
-                debug_assert_eq!(block_size, 11);
-                let application_identifier = self.read_str(8)?;
+                if block_size != 11 {
+                    if self.options.strict {
+                        return Err(ParserError::UnexpectedApplicationBlockSize(block_size).into());
+                    }
+
+                    // we can't make sense of the identifier, keep the whole thing as an unknown
+                    // extension so that the data isn't lost.
+                    let mut data = self.read_bytes(block_size.into())?.into_vec();
+                    data.extend_from_slice(&self.read_data_sub_blocks()?);
+                    warn!("application extension has block size {}, skipping", block_size);
+
+                    self.sink.on_extension(SpecialPurposeExtension::Unknown {
+                        label: APPLICATION_EXTENSION,
+                        data: data.into_boxed_slice(),
+                    })?;
+                    return Ok(ParserState::DetermineNextBlock(None));
+                }
+
+                // some encoders put garbage in the identifier, don't bail on invalid utf-8.
+                let application_identifier: Box<str> =
+                    String::from_utf8_lossy(&self.read_bytes(8)?).into();
 
                 let application_authentication_code = self.read_bytes(3)?;
-                let application_data = self.read_data_sub_blocks()?;
+                let mut sub_block_sizes = Vec::new();
+                let application_data = self.read_sub_blocks(Some(&mut sub_block_sizes))?;
 
                 if application_identifier.as_ref() == "NETSCAPE"
                     && application_authentication_code.as_ref() == "2.0".as_bytes()
                 {
                     // PERF: we check the length twice essentially with this and the try_into below, make it so it only happens once.
-                    if application_data.len() != 3 {
-                        return Err(ParserError::UnexpectedApplicationDescriptorDataLength {
-                            name: application_identifier.into(),
-                            expected: 3,
-                            actual: application_data.len(),
+                    if application_data.len() != 3 || application_data[0] != 1 {
+                        if self.options.strict {
+                            return Err(ParserError::UnexpectedApplicationDescriptorDataLength {
+                                name: application_identifier.into(),
+                                expected: 3,
+                                actual: application_data.len(),
+                            }
+                            .into());
                         }
-                        .into());
-                    }
 
-                    debug_assert_eq!(1, application_data[0]);
-
-                    let loop_number = u16::from_le_bytes(application_data[1..3].try_into()?);
-                    self.loop_count = Some(match loop_number {
-                        0 => LoopCount::Infinite,
-                        number => LoopCount::Number(number),
-                    });
+                        warn!(
+                            "NETSCAPE2.0 extension has unexpected data {:?}, ignoring loop count",
+                            application_data
+                        );
+                    } else {
+                        let loop_number = u16::from_le_bytes(application_data[1..3].try_into()?);
+                        self.loop_count = Some(match loop_number {
+                            0 => LoopCount::Infinite,
+                            number => LoopCount::Number(number),
+                        });
+                    }
                 };
 
-                self.special_purpose_extensions
-                    .push(SpecialPurposeExtension::ApplicationBlock {
-                        application_identifier,
-                        application_authentication_code,
-                        application_data,
-                    });
+                let application = ApplicationExtension {
+                    identifier: application_identifier,
+                    authentication_code: application_authentication_code,
+                    data: application_data,
+                    sub_block_sizes: sub_block_sizes.into_boxed_slice(),
+                };
                 debug!(
-                    "processed application block, got: {:#?}",
-                    self.special_purpose_extensions.last()
+                    "processed application block, identifier={}{} bytes={}",
+                    application.identifier,
+                    String::from_utf8_lossy(&application.authentication_code),
+                    application.data.len()
                 );
+                self.sink
+                    .on_extension(SpecialPurposeExtension::ApplicationBlock(application))?;
                 Ok(ParserState::DetermineNextBlock(None))
             }
             Comment => {
                 // sequence of data sub-blocks
                 let data = self.read_data_sub_blocks()?;
                 debug!(
-                    "processed comment block, got: {}",
+                    "processed comment block, bytes={} text={:?}",
+                    data.len(),
                     String::from_utf8_lossy(&data)
                 );
-                self.special_purpose_extensions
-                    .push(SpecialPurposeExtension::CommentBlock(data));
+                self.sink
+                    .on_extension(SpecialPurposeExtension::CommentBlock(data))?;
                 Ok(ParserState::DetermineNextBlock(None))
             }
             GraphicControl => {
                 let block_size = self.read_byte()?;
-                debug_assert_eq!(block_size, 4);
+                if block_size != 4 {
+                    if self.options.strict {
+                        return Err(ParserError::InvalidBlockSize {
+                            block: "graphic control extension",
+                            expected: 4,
+                            actual: block_size,
+                        }
+                        .into());
+                    }
+
+                    warn!(
+                        "graphic control extension has block size {}, ignoring it",
+                        block_size
+                    );
+                    self.read_bytes(block_size.into())?;
+                    self.read_data_sub_blocks()?;
+                    return Ok(ParserState::DetermineNextBlock(None));
+                }
 
                 let packed_fields = self.read_byte()?;
                 // packed fields definition
@@ -518,7 +1379,19 @@ impl<T: Read + Debug> Decoder<T> {
                 };
 
                 let block_terminator = self.read_byte()?;
-                assert_eq!(block_terminator, 0);
+                if block_terminator != 0 {
+                    if self.options.strict {
+                        return Err(ParserError::MissingBlockTerminator(
+                            "graphic control extension",
+                        )
+                        .into());
+                    }
+
+                    // what we read is the size of an extra sub-block, skip past it and whatever
+                    // follows it.
+                    self.read_bytes(block_terminator.into())?;
+                    self.read_data_sub_blocks()?;
+                }
 
                 let graphic_control_extension = GraphicControlExtension {
                     disposal_method: DisposalMethod::from_u8(disposal_method),
@@ -528,8 +1401,12 @@ impl<T: Read + Debug> Decoder<T> {
                 };
 
                 debug!(
-                    "processed GraphicControlExtension: {:#?}",
-                    graphic_control_extension
+                    "processed graphic control extension, delay={} disposal={:?} \
+                     transparent={:?} user_input={}",
+                    delay_time,
+                    graphic_control_extension.disposal_method,
+                    transparent_color_index,
+                    needs_user_input
                 );
 
                 Ok(ParserState::DetermineNextBlock(Some(
@@ -539,27 +1416,65 @@ impl<T: Read + Debug> Decoder<T> {
             PlainText => {
                 // i do not want to support this right now...
                 let block_size = self.read_byte()?;
-                debug_assert_eq!(block_size, 12);
+                if block_size != 12 && self.options.strict {
+                    return Err(ParserError::InvalidBlockSize {
+                        block: "plain text extension",
+                        expected: 12,
+                        actual: block_size,
+                    }
+                    .into());
+                }
 
                 // skip data portion
-                self.read_bytes(12)?;
+                self.read_bytes(block_size.into())?;
 
                 self.read_data_sub_blocks()?;
 
                 Ok(ParserState::DetermineNextBlock(None))
             }
+            Unknown(label) => {
+                // every extension is made of data sub-blocks after the label, so even if we don't
+                // know what this is we can still skip past it.
+                let data = self.read_data_sub_blocks()?;
+                warn!(
+                    "skipping unknown extension with label 0x{:02x} ({} bytes)",
+                    label,
+                    data.len()
+                );
+
+                self.sink
+                    .on_extension(SpecialPurposeExtension::Unknown { label, data })?;
+                Ok(ParserState::DetermineNextBlock(None))
+            }
+        }
+    }
+
+    fn reserve_memory(&mut self, bytes: usize) -> Result<()> {
+        self.memory_used = self.options.reserve_memory(self.memory_used, bytes)?;
+        Ok(())
+    }
+
+    fn report_progress(&self) {
+        if let Some(progress) = &self.options.progress {
+            (progress.0)(&Progress {
+                bytes_read: self.bytes_read,
+                total_bytes: self.total_bytes,
+                frames_decoded: self.frames_read,
+            });
         }
     }
 
     fn read_bytes(&mut self, count: usize) -> Result<Box<[u8]>> {
         let mut buffer = vec![0; count];
         self.inner.read_exact(&mut buffer)?;
+        self.bytes_read += count as u64;
         Ok(buffer.into_boxed_slice())
     }
 
     fn read_byte(&mut self) -> Result<u8> {
         let mut buffer: [u8; 1] = [0; 1];
         self.inner.read_exact(&mut buffer)?;
+        self.bytes_read += 1;
         Ok(u8::from_le_bytes(buffer))
     }
 
@@ -569,34 +1484,639 @@ impl<T: Read + Debug> Decoder<T> {
 
         let mut buffer: [u8; 2] = [0; 2];
         self.inner.read_exact(&mut buffer)?;
+        self.bytes_read += 2;
         Ok(u16::from_le_bytes(buffer))
     }
 
     fn read_str(&mut self, count: usize) -> Result<Box<str>> {
         let mut buffer = vec![0; count];
-        self.inner.read(&mut buffer)?;
+        self.inner.read_exact(&mut buffer)?;
+        self.bytes_read += count as u64;
         Ok(String::from_utf8(buffer)?.into_boxed_str())
     }
 
-    fn read_data_sub_blocks(&mut self) -> Result<Box<[u8]>> {
+    fn skip_data_sub_blocks(&mut self) -> Result<()> {
         let mut block_size = self.read_byte()?;
+        while block_size != 0 {
+            let skipped = io::copy(&mut (&mut self.inner).take(block_size.into()), &mut io::sink())?;
+            self.bytes_read += skipped;
+
+            if skipped < block_size.into() {
+                if !self.options.recover_bad_sub_block_lengths() {
+                    return Err(ParserError::TruncatedSubBlock {
+                        expected: block_size,
+                        actual: skipped as usize,
+                    }
+                    .into());
+                }
+                break;
+            }
+
+            block_size = self.read_byte()?;
+        }
+
+        Ok(())
+    }
+
+    fn read_data_sub_blocks(&mut self) -> Result<Box<[u8]>> {
+        self.read_sub_blocks(None)
+    }
+
+    // joins the data sub-blocks, collecting the size of each one in `sizes`
+    fn read_sub_blocks(&mut self, sizes: Option<&mut Vec<u8>>) -> Result<Box<[u8]>> {
+        let mut result = Vec::new();
+        self.read_sub_blocks_into(&mut result, sizes)?;
+        Ok(result.into_boxed_slice())
+    }
 
-        // there could be more than one block, but we do know we'll at least have 1 sub-block.
-        // allocate capacity to account for it.
-        let mut result = Vec::with_capacity(block_size.into());
+    // appends the data sub-blocks to `result`, which can be a buffer that's reused from one
+    // block to the next
+    fn read_sub_blocks_into(
+        &mut self,
+        result: &mut Vec<u8>,
+        mut sizes: Option<&mut Vec<u8>>,
+    ) -> Result<()> {
+        let mut block_size = self.read_byte()?;
 
         // we might have read the block terminator at the end of the while loop, stop right there
         // because we're done.
         while block_size != 0 {
-            //println!("trying to read sub_blocks with block size of {:?}", block_size);
-            let mut sub_block_buffer = vec![0; block_size.into()];
+            if let Some(sizes) = sizes.as_mut() {
+                sizes.push(block_size);
+            }
+            let start = result.len();
+            result.resize(start + block_size as usize, 0);
+            let read = self.read_into(&mut result[start..])?;
+            result.truncate(start + read);
+
+            if read < block_size.into() {
+                if !self.options.recover_bad_sub_block_lengths() {
+                    return Err(ParserError::TruncatedSubBlock {
+                        expected: block_size,
+                        actual: read,
+                    }
+                    .into());
+                }
 
-            self.inner.read(&mut sub_block_buffer)?;
-            result.append(&mut sub_block_buffer);
+                // the stream is exhausted, there's no terminator to read.
+                warn!(
+                    "sub-block claims {} bytes but only {} are left, keeping what was read",
+                    block_size, read
+                );
+                break;
+            }
 
             block_size = self.read_byte()?;
         }
 
-        Ok(result.into_boxed_slice())
+        Ok(())
+    }
+
+    // reads until `buffer` is full or the stream ends, returning how much was read
+    fn read_into(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        let mut read = 0;
+        while read < buffer.len() {
+            match self.inner.read(&mut buffer[read..]) {
+                Ok(0) => break,
+                Ok(count) => read += count,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        self.bytes_read += read as u64;
+        Ok(read)
+    }
+}
+
+fn check_timeout(
+    timeout: Option<Duration>,
+    started: Option<Instant>,
+) -> std::result::Result<(), ParserError> {
+    match (timeout, started) {
+        (Some(timeout), Some(started)) if started.elapsed() >= timeout => {
+            Err(ParserError::TimedOut(timeout))
+        }
+        _ => Ok(()),
+    }
+}
+
+// GIF87a only has room for extensions, every one there is came with GIF89a. `warned` is set
+// once that's been warned about, so it's only warned about once a file
+fn check_extension_version(
+    options: &DecodeOptions,
+    version: Option<Version>,
+    label: &ExtensionType,
+    warned: &mut bool,
+) -> std::result::Result<(), ParserError> {
+    if version == Some(Version::V87a) && !matches!(label, ExtensionType::Unknown(_)) {
+        if options.strict {
+            return Err(ParserError::ExtensionNewerThanVersion(label.name()));
+        }
+        if !*warned {
+            warn!("{} in a GIF87a file, which only GIF89a has", label.name());
+            *warned = true;
+        }
+    }
+    Ok(())
+}
+
+/// Decodes the image data of a frame covering `pixel_count` pixels into `indicies`, replacing
+/// what was there. Outside of strict mode, corrupt or short data is padded out with the first
+/// color instead of failing.
+fn decode_image_data(
+    data_stream: &[u8],
+    lzw_code_size: u8,
+    pixel_count: usize,
+    strict: bool,
+    indicies: &mut Vec<u8>,
+) -> std::result::Result<(), ParserError> {
+    indicies.clear();
+    if let Err(err) = lzw::lzw_decode_into(
+        data_stream,
+        lzw_code_size.into(),
+        pixel_count,
+        strict,
+        indicies,
+    ) {
+        if strict {
+            return Err(err.into());
+        }
+        warn!("{}, keeping the {} indicies decoded so far", err, indicies.len());
+    }
+
+    if indicies.len() < pixel_count {
+        if strict {
+            return Err(ParserError::MissingImageData {
+                expected: pixel_count,
+                actual: indicies.len(),
+            });
+        }
+
+        // everything downstream relies on frames being fully covered, fill the rest
+        // with the first color like most decoders do.
+        warn!(
+            "image data only covers {} of {} pixels, padding the rest",
+            indicies.len(),
+            pixel_count
+        );
+        indicies.resize(pixel_count, 0);
+    }
+
+    Ok(())
+}
+
+// fails on the first pixel of `indicies`, those of frame number `index`, there's no color for,
+// which only happens with `PaletteIndexPolicy::Error`
+fn check_palette_indicies(
+    frame: &Frame,
+    indicies: &[u8],
+    index: usize,
+) -> std::result::Result<(), ParserError> {
+    if frame.palette_index_policy != PaletteIndexPolicy::Error {
+        return Ok(());
+    }
+    let mut indicies = indicies.iter();
+    match indicies.find(|&&pixel| frame.pixel(pixel).is_none()) {
+        Some(&pixel) => Err(ParserError::IndexOutOfRange {
+            frame: index,
+            index: pixel,
+            colors: frame.palette().map_or(0, |palette| palette.len() / 3),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Puts the rows of an interlaced frame back in order. They're stored as every 8th row from the
+/// first, every 8th from the fifth, every 4th from the third and then every other row from the
+/// second. They're written to `deinterlaced`, replacing what was there.
+fn deinterlace(indicies: &[u8], width: u16, deinterlaced: &mut Vec<u8>) {
+    deinterlaced.clear();
+    let width = width as usize;
+    if width == 0 {
+        deinterlaced.extend_from_slice(indicies);
+        return;
+    }
+
+    let height = indicies.len() / width;
+    let rows = [(0, 8), (4, 8), (2, 4), (1, 2)]
+        .into_iter()
+        .flat_map(|(start, step)| (start..height).step_by(step));
+
+    deinterlaced.resize(indicies.len(), 0);
+    for (row, y) in indicies.chunks_exact(width).zip(rows) {
+        deinterlaced[y * width..(y + 1) * width].copy_from_slice(row);
+    }
+}
+
+fn is_eof(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<io::Error>()
+        .is_some_and(|err| err.kind() == io::ErrorKind::UnexpectedEof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        DecodeBuffers, DecodeOptions, DecodeSink, Decoder, Frame, FrameDescriptor, Header,
+        ImageData, PaletteIndexPolicy, SpecialPurposeExtension, IMAGE_DESCRIPTOR_LABEL,
+        XMP_MAGIC_TRAILER,
+    };
+    use crate::compositor::Compositor;
+    use crate::encoder::Encoder;
+    use crate::fixtures::{encode_test_gif, two_color_frame, two_color_gif};
+
+    use anyhow::Result;
+    use std::io::{self, Cursor, Read};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    fn recovers_missing_trailer() {
+        let mut gif = encode_test_gif(2);
+        gif.pop();
+
+        let mut decoder = Decoder::new(Cursor::new(gif.clone()));
+        decoder.parse().unwrap();
+        assert_eq!(decoder.frames().len(), 2);
+
+        let mut decoder =
+            Decoder::new_with_options(Cursor::new(gif), DecodeOptions::new().strict(true));
+        assert!(decoder.parse().is_err());
+    }
+
+    #[test]
+    fn keeps_frames_before_an_error() {
+        // cut off in the middle of the last frame's image descriptor
+        let gif = encode_test_gif(3);
+        let descriptor = [IMAGE_DESCRIPTOR_LABEL, 0, 0, 0, 0, 8, 0, 8, 0];
+        let descriptors: Vec<usize> = gif
+            .windows(descriptor.len())
+            .enumerate()
+            .filter(|(_, window)| *window == descriptor)
+            .map(|(position, _)| position)
+            .collect();
+        let cut = &gif[..descriptors[2] + 3];
+
+        let mut decoder = Decoder::new(cut);
+        let partial = decoder.parse_partial().unwrap_err();
+        assert_eq!(partial.frames.len(), 2);
+        assert!(partial.to_string().ends_with("(after 2 frames)"));
+        assert_eq!(decoder.screen_size(), Some((8, 8)));
+
+        let mut decoder = Decoder::new(gif.as_slice());
+        decoder.parse_partial().unwrap();
+        assert_eq!(decoder.frames().len(), 3);
+    }
+
+    #[test]
+    fn copes_with_short_reads() {
+        // hands out a byte at a time, and gets interrupted before every one
+        #[derive(Debug)]
+        struct Trickle {
+            data: Cursor<Vec<u8>>,
+            interrupted: bool,
+        }
+
+        impl Read for Trickle {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.interrupted = !self.interrupted;
+                if self.interrupted {
+                    return Err(io::ErrorKind::Interrupted.into());
+                }
+                let len = buf.len().min(1);
+                self.data.read(&mut buf[..len])
+            }
+        }
+
+        let gif = encode_test_gif(3);
+        let mut expected = Decoder::new(Cursor::new(gif.clone()));
+        expected.parse().unwrap();
+
+        let mut decoder = Decoder::new(Trickle {
+            data: Cursor::new(gif),
+            interrupted: false,
+        });
+        decoder.parse().unwrap();
+        assert_eq!(decoder.frames().len(), 3);
+        for (frame, expected) in decoder.frames().iter().zip(expected.frames()) {
+            assert_eq!(frame.indicies(), expected.indicies());
+        }
+    }
+
+    #[test]
+    fn checks_extensions_against_the_version() {
+        // every frame comes with a graphic control extension, which GIF87a doesn't have
+        let mut gif = encode_test_gif(2);
+        gif[3..6].copy_from_slice(b"87a");
+
+        let mut decoder = Decoder::new(Cursor::new(gif.clone()));
+        decoder.parse().unwrap();
+        assert_eq!(decoder.frames().len(), 2);
+
+        let mut decoder =
+            Decoder::new_with_options(Cursor::new(gif), DecodeOptions::new().strict(true));
+        assert!(decoder.parse().is_err());
+    }
+
+    #[test]
+    fn hands_blocks_to_a_sink() {
+        #[derive(Default)]
+        struct Counter {
+            size: Option<(u16, u16)>,
+            index_counts: Vec<usize>,
+            comments: usize,
+        }
+
+        impl DecodeSink for Counter {
+            fn on_header(&mut self, header: &Header) -> Result<()> {
+                self.size = Some((header.width, header.height));
+                Ok(())
+            }
+
+            fn on_frame_descriptor(&mut self, descriptor: &FrameDescriptor) -> Result<ImageData> {
+                Ok(match descriptor.index {
+                    1 => ImageData::Skip,
+                    _ => ImageData::Decode,
+                })
+            }
+
+            fn on_frame_data(&mut self, frame: Frame) -> Result<()> {
+                self.index_counts.push(frame.indicies().len());
+                Ok(())
+            }
+
+            fn on_extension(&mut self, extension: SpecialPurposeExtension) -> Result<()> {
+                assert!(matches!(extension, SpecialPurposeExtension::CommentBlock(_)));
+                self.comments += 1;
+                Ok(())
+            }
+        }
+
+        let mut encoder = Encoder::new(Vec::new(), 8, 8);
+        encoder.write_comment(b"counted").unwrap();
+        for _ in 0..3 {
+            let palette: Box<[u8]> = (0..12).collect();
+            encoder
+                .write_frame(&Frame::new(8, 8, Box::new([1; 64]), palette))
+                .unwrap();
+        }
+        let gif = encoder.finish().unwrap();
+
+        let mut counter = Counter::default();
+        let mut decoder = Decoder::with_sink(Cursor::new(gif), DecodeOptions::new(), &mut counter);
+        decoder.parse().unwrap();
+        assert_eq!(decoder.screen_size(), Some((8, 8)));
+
+        assert_eq!(counter.size, Some((8, 8)));
+        assert_eq!(counter.index_counts, [64, 0, 64]);
+        assert_eq!(counter.comments, 1);
+    }
+
+    #[test]
+    fn reports_progress() {
+        let gif = encode_test_gif(3);
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let options = DecodeOptions::new().progress({
+            let reports = reports.clone();
+            move |progress| reports.lock().unwrap().push(*progress)
+        });
+
+        let mut decoder = Decoder::new_with_options(Cursor::new(gif.clone()), options);
+        assert_eq!(decoder.measure_length().unwrap(), gif.len() as u64);
+        decoder.parse().unwrap();
+
+        let reports = reports.lock().unwrap();
+        let frames: Vec<_> = reports
+            .iter()
+            .map(|progress| progress.frames_decoded)
+            .collect();
+        assert_eq!(frames, [1, 2, 3, 3]);
+        let last = reports.last().unwrap();
+        assert_eq!(last.fraction(), Some(1.0));
+        assert_eq!(last.estimated_frames(), Some(3));
+    }
+
+    #[test]
+    fn applies_the_palette_index_policy() {
+        // two colors, but the image data goes up to index 3
+        let gif = two_color_gif(2, 1, &[two_color_frame(2, 1, &[0, 3])]);
+
+        let rgba = |policy| {
+            let options = DecodeOptions::new().palette_index_policy(policy);
+            let mut decoder = Decoder::new_with_options(Cursor::new(gif.clone()), options);
+            decoder.parse().map(|_| decoder.frames()[0].rgba())
+        };
+        assert_eq!(
+            rgba(PaletteIndexPolicy::MapToBlack).unwrap(),
+            [255, 0, 0, 255, 0, 0, 0, 255]
+        );
+        assert_eq!(
+            rgba(PaletteIndexPolicy::ClampToLast).unwrap(),
+            [255, 0, 0, 255, 0, 0, 255, 255]
+        );
+        assert_eq!(
+            rgba(PaletteIndexPolicy::MapToTransparent).unwrap(),
+            [255, 0, 0, 255, 0, 0, 0, 0]
+        );
+        assert!(rgba(PaletteIndexPolicy::Error).is_err());
+    }
+
+    #[test]
+    fn enforces_limits() {
+        let gif = encode_test_gif(3);
+
+        let options = DecodeOptions::new().max_pixels_per_frame(63);
+        let mut decoder = Decoder::new_with_options(Cursor::new(gif.clone()), options);
+        assert!(decoder.parse().is_err());
+
+        let options = DecodeOptions::new().max_total_frames(2);
+        let mut decoder = Decoder::new_with_options(Cursor::new(gif.clone()), options);
+        assert!(decoder.parse().is_err());
+
+        let options = DecodeOptions::new().max_canvas_pixels(63);
+        let mut decoder = Decoder::new_with_options(Cursor::new(gif.clone()), options);
+        assert!(decoder.parse().is_err());
+
+        // two frames of 64 indicies and a 12 byte color table each
+        let options = DecodeOptions::new().max_memory(2 * (64 + 12));
+        let mut decoder = Decoder::new_with_options(Cursor::new(gif.clone()), options);
+        assert!(decoder.parse().is_err());
+
+        let options = DecodeOptions::new().timeout(Duration::ZERO);
+        let mut decoder = Decoder::new_with_options(Cursor::new(gif.clone()), options);
+        assert!(decoder.parse().is_err());
+
+        let options = DecodeOptions::new().timeout(Duration::from_secs(60));
+        let mut decoder = Decoder::new_with_options(Cursor::new(gif), options);
+        decoder.parse().unwrap();
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn decodes_in_parallel() {
+        let gif = encode_test_gif(5);
+
+        let mut decoder = Decoder::new(Cursor::new(gif.clone()));
+        decoder.parse().unwrap();
+        let mut parallel_decoder = Decoder::new(Cursor::new(gif));
+        parallel_decoder.parse_parallel().unwrap();
+
+        assert_eq!(parallel_decoder.frames().len(), 5);
+        for (frame, parallel_frame) in decoder.frames().iter().zip(parallel_decoder.frames()) {
+            assert_eq!(frame.indicies(), parallel_frame.indicies());
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn keeps_frames_decoded_in_parallel_before_an_error() {
+        let parse = |gif: &[u8], parallel: bool| {
+            let options = DecodeOptions::new().strict(true);
+            let mut decoder = Decoder::new_with_options(Cursor::new(gif.to_vec()), options);
+            let parsed = match parallel {
+                true => decoder.parse_parallel(),
+                false => decoder.parse(),
+            };
+            assert!(parsed.is_err());
+            decoder
+                .frames()
+                .iter()
+                .map(|frame| frame.indicies().len())
+                .collect::<Vec<_>>()
+        };
+
+        // the trailer is missing, which only strict decoding minds
+        let mut gif = encode_test_gif(2);
+        gif.pop();
+        assert_eq!(parse(&gif, false), [64, 64]);
+        assert_eq!(parse(&gif, true), [64, 64]);
+
+        // the second frame's image data doesn't decode, so neither it nor the third are kept
+        let mut gif = encode_test_gif(3);
+        let descriptor = [IMAGE_DESCRIPTOR_LABEL, 0, 0, 0, 0, 8, 0, 8, 0];
+        let second = gif
+            .windows(descriptor.len())
+            .enumerate()
+            .filter(|(_, window)| *window == descriptor)
+            .nth(1)
+            .map(|(position, _)| position)
+            .unwrap();
+        // past the packed fields, the four color palette and the LZW code size
+        let block = second + descriptor.len() + 1 + 12 + 1;
+        let block_length = gif[block] as usize;
+        gif[block + 1..block + 1 + block_length].fill(0xff);
+        assert_eq!(parse(&gif, false), [64]);
+        assert_eq!(parse(&gif, true), [64]);
+    }
+
+    #[test]
+    fn decodes_one_frame_at_a_time() {
+        let gif = encode_test_gif(2);
+        let mut decoder = Decoder::new(Cursor::new(gif));
+
+        assert_eq!(decoder.next_frame().unwrap().unwrap().indicies()[1], 1);
+        assert_eq!(decoder.next_frame().unwrap().unwrap().indicies()[1], 2);
+        assert!(decoder.next_frame().unwrap().is_none());
+        assert_eq!(decoder.frames().len(), 2);
+    }
+
+    #[test]
+    fn decodes_into_reused_buffers() {
+        let gif = encode_test_gif(3);
+        let mut decoder = Decoder::new(Cursor::new(gif.clone()));
+        decoder.parse().unwrap();
+
+        let mut buffers = DecodeBuffers::new();
+        let mut buffered_decoder = Decoder::new(Cursor::new(gif));
+        let mut compositor = Compositor::new(8, 8);
+        let mut expected_compositor = Compositor::new(8, 8);
+        let mut first_indicies = None;
+        for expected in decoder.frames() {
+            let frame = buffered_decoder
+                .decode_frame_into(&mut buffers)
+                .unwrap()
+                .unwrap();
+            assert!(frame.indicies().is_empty());
+            assert_eq!(buffers.indicies(), expected.indicies());
+            // every frame is the same size, so they're all decoded into the same memory
+            let indicies = buffers.indicies().as_ptr();
+            assert_eq!(*first_indicies.get_or_insert(indicies), indicies);
+
+            let frame = frame.clone();
+            assert_eq!(buffers.rgba(&frame), expected.rgba());
+            compositor.draw_from(&frame, &buffers);
+            expected_compositor.draw(expected);
+            assert_eq!(compositor.canvas(), expected_compositor.canvas());
+        }
+        assert!(buffered_decoder
+            .decode_frame_into(&mut buffers)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn counts_reused_buffers_once_against_the_memory_limit() {
+        let gif = encode_test_gif(6);
+        // two frames of 64 indicies and a 12 byte color table each
+        let options = DecodeOptions::new().max_memory(2 * (64 + 12));
+        let mut decoder = Decoder::new_with_options(Cursor::new(gif.clone()), options.clone());
+        assert!(decoder.parse().is_err());
+
+        // the indicies only take up one frame's worth, the color tables are all kept
+        let mut buffers = DecodeBuffers::new();
+        let mut decoder = Decoder::new_with_options(Cursor::new(gif), options);
+        let mut decoded = 0;
+        while decoder.decode_frame_into(&mut buffers).unwrap().is_some() {
+            decoded += 1;
+        }
+        assert_eq!(decoded, 6);
+    }
+
+    #[test]
+    fn normalizes_short_delays() {
+        let mut encoder = Encoder::new(Vec::new(), 1, 1);
+        for delay_time in [0, 1, 2, 50] {
+            let mut frame = Frame::new(1, 1, Box::new([0]), Box::new([0, 0, 0]));
+            frame.delay_time = delay_time;
+            encoder.write_frame(&frame).unwrap();
+        }
+        let mut decoder = Decoder::new(Cursor::new(encoder.finish().unwrap()));
+        decoder.parse().unwrap();
+
+        let durations = [100, 100, 20, 500].map(Duration::from_millis);
+        assert_eq!(decoder.frame_durations(), durations);
+        assert_eq!(decoder.total_duration(), Duration::from_millis(720));
+    }
+
+    #[test]
+    fn stops_at_max_frame_count() {
+        let gif = encode_test_gif(3);
+
+        let options = DecodeOptions::new().max_frame_count(2);
+        let mut decoder = Decoder::new_with_options(Cursor::new(gif), options);
+        decoder.parse().unwrap();
+        assert_eq!(decoder.frames().len(), 2);
+    }
+
+    #[test]
+    fn reads_xmp_packets() {
+        let packet = "<?xpacket begin=\"\u{feff}\"?><x:xmpmeta xmlns:x=\"adobe:ns:meta/\"/>\
+                      <?xpacket end=\"w\"?>";
+        let mut gif = encode_test_gif(1);
+        let trailer = gif.pop().unwrap();
+        gif.extend_from_slice(b"\x21\xff\x0bXMP DataXMP");
+        gif.extend_from_slice(packet.as_bytes());
+        gif.extend_from_slice(&XMP_MAGIC_TRAILER);
+        gif.extend_from_slice(&[0, trailer]);
+
+        let mut decoder = Decoder::new(Cursor::new(gif.clone()));
+        decoder.parse().unwrap();
+        assert_eq!(decoder.frames().len(), 1);
+        let extensions: Vec<_> = decoder.application_extensions().collect();
+        assert_eq!(extensions.len(), 1);
+        assert_eq!(extensions[0].identifier.as_ref(), "XMP Data");
+        assert_eq!(decoder.xmp().as_deref(), Some(packet));
+
+        let summary = Decoder::new(Cursor::new(gif)).scan().unwrap();
+        assert_eq!(summary.application_extensions[0].xmp().as_deref(), Some(packet));
     }
 }
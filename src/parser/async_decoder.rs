@@ -0,0 +1,346 @@
+use anyhow::Result;
+use futures_io::AsyncRead;
+
+use std::collections::VecDeque;
+use std::future::poll_fn;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::{
+    DecodeBuffers, DecodeOptions, Decoder, Frame, LoopCount, Version, EXTENSION_INTRODUCER,
+    IMAGE_DESCRIPTOR_LABEL,
+};
+
+// how much is read from the reader at a time
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Decodes a GIF from an [`AsyncRead`], so a file that's still arriving, an upload say, can be
+/// decoded without blocking the thread. Readers from async-std and smol can be used as they
+/// are, tokio's can be wrapped in a [`TokioReader`].
+///
+/// Blocks are read asynchronously until a whole frame has arrived, then parsed by a
+/// [`Decoder`], so everything it does and all of its [`DecodeOptions`] apply the same.
+#[derive(Debug)]
+pub struct AsyncDecoder<R> {
+    reader: R,
+    // read from `reader` but not looked at yet, from `position` on
+    buffer: Vec<u8>,
+    position: usize,
+    // whole blocks are handed over through the decoder's reader
+    decoder: Decoder<VecDeque<u8>>,
+    header_read: bool,
+    // set once `reader` runs out, after which the decoder has everything there is to read
+    eof: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncDecoder<R> {
+    pub fn new(reader: R) -> Self {
+        Self::new_with_options(reader, DecodeOptions::default())
+    }
+
+    pub fn new_with_options(reader: R, options: DecodeOptions) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            position: 0,
+            decoder: Decoder::new_with_options(VecDeque::new(), options),
+            header_read: false,
+            eof: false,
+        }
+    }
+
+    /// See [`Decoder::next_frame`].
+    pub async fn next_frame(&mut self) -> Result<Option<&Frame>> {
+        self.read_blocks().await?;
+        self.decoder.next_frame()
+    }
+
+    /// See [`Decoder::decode_frame_into`].
+    pub async fn decode_frame_into(
+        &mut self,
+        buffers: &mut DecodeBuffers,
+    ) -> Result<Option<&Frame>> {
+        self.read_blocks().await?;
+        self.decoder.decode_frame_into(buffers)
+    }
+
+    /// Reads and parses everything that's left of the file.
+    pub async fn parse(&mut self) -> Result<()> {
+        while self.next_frame().await?.is_some() {}
+        Ok(())
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        self.decoder.frames()
+    }
+
+    pub fn into_frames(self) -> Vec<Frame> {
+        self.decoder.into_frames()
+    }
+
+    /// The decoder the blocks are parsed by, to hand to `Animation::from_decoder` say, holding
+    /// anything that's been read but not parsed yet.
+    pub fn into_decoder(self) -> Decoder<VecDeque<u8>> {
+        self.decoder
+    }
+
+    pub fn loop_count(&self) -> Option<LoopCount> {
+        self.decoder.loop_count()
+    }
+
+    pub fn version(&self) -> Option<Version> {
+        self.decoder.version()
+    }
+
+    pub fn screen_size(&self) -> Option<(u16, u16)> {
+        self.decoder.screen_size()
+    }
+
+    pub fn global_palette(&self) -> Option<&[u8]> {
+        self.decoder.global_palette()
+    }
+
+    // hands the decoder the header, if it hasn't had it, and the blocks up to and including the
+    // next image or the trailer. Whatever the reader ends in the middle of is left for the
+    // decoder to make of, with `DecodeOptions::recover_truncated_trailer` and friends.
+    async fn read_blocks(&mut self) -> io::Result<()> {
+        if !self.header_read {
+            // the signature, the version and the logical screen descriptor up to its packed
+            // fields, which say whether there's a global color table after it
+            if !self.read_bytes(10).await? {
+                return Ok(());
+            }
+            let Some(packed_fields) = self.read_byte().await? else {
+                return Ok(());
+            };
+            if !self.read_bytes(2).await? || !self.read_color_table(packed_fields).await? {
+                return Ok(());
+            }
+            self.header_read = true;
+        }
+
+        loop {
+            let Some(introducer) = self.read_byte().await? else {
+                return Ok(());
+            };
+            match introducer {
+                EXTENSION_INTRODUCER => {
+                    // the label, then sub-blocks whatever the extension
+                    if self.read_byte().await?.is_none() || !self.read_data_sub_blocks().await? {
+                        return Ok(());
+                    }
+                }
+                IMAGE_DESCRIPTOR_LABEL => {
+                    if !self.read_bytes(8).await? {
+                        return Ok(());
+                    }
+                    let Some(packed_fields) = self.read_byte().await? else {
+                        return Ok(());
+                    };
+                    // the color table, then the LZW minimum code size before the image data
+                    if self.read_color_table(packed_fields).await?
+                        && self.read_byte().await?.is_some()
+                    {
+                        self.read_data_sub_blocks().await?;
+                    }
+                    return Ok(());
+                }
+                // the trailer, or something the decoder is going to complain about or, with
+                // recovery options, skip over, so it gets everything after it
+                _ => return self.read_to_end().await,
+            }
+        }
+    }
+
+    // reads the color table described by a logical screen or image descriptor's packed fields,
+    // which are laid out the same
+    async fn read_color_table(&mut self, packed_fields: u8) -> io::Result<bool> {
+        if packed_fields & 0b10000000 == 0 {
+            return Ok(true);
+        }
+        self.read_bytes(3 << ((packed_fields & 0b00000111) + 1))
+            .await
+    }
+
+    // the rest of these hand what they read to the decoder, returning `None` or false if the
+    // reader ran out first
+
+    async fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        if !self.fill_buffer().await? {
+            return Ok(None);
+        }
+        let byte = self.buffer[self.position];
+        self.position += 1;
+        self.decoder.inner.get_mut().push_back(byte);
+        Ok(Some(byte))
+    }
+
+    async fn read_bytes(&mut self, mut count: usize) -> io::Result<bool> {
+        while count > 0 {
+            if !self.fill_buffer().await? {
+                return Ok(false);
+            }
+            let available = &self.buffer[self.position..];
+            let read = available.len().min(count);
+            self.decoder.inner.get_mut().extend(&available[..read]);
+            self.position += read;
+            count -= read;
+        }
+        Ok(true)
+    }
+
+    async fn read_data_sub_blocks(&mut self) -> io::Result<bool> {
+        loop {
+            match self.read_byte().await? {
+                None => return Ok(false),
+                Some(0) => return Ok(true),
+                Some(block_size) => {
+                    if !self.read_bytes(block_size.into()).await? {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn read_to_end(&mut self) -> io::Result<()> {
+        while self.fill_buffer().await? {
+            self.decoder
+                .inner
+                .get_mut()
+                .extend(&self.buffer[self.position..]);
+            self.position = self.buffer.len();
+        }
+        Ok(())
+    }
+
+    // makes sure there's something in `buffer` to read, returning false at the end of the file
+    async fn fill_buffer(&mut self) -> io::Result<bool> {
+        if self.position < self.buffer.len() {
+            return Ok(true);
+        }
+        if self.eof {
+            return Ok(false);
+        }
+
+        self.buffer.resize(CHUNK_SIZE, 0);
+        let read = poll_fn(|cx| Pin::new(&mut self.reader).poll_read(cx, &mut self.buffer)).await;
+        let read = match read {
+            Ok(read) => read,
+            Err(err) => {
+                self.buffer.clear();
+                return Err(err);
+            }
+        };
+        self.buffer.truncate(read);
+        self.position = 0;
+        self.eof = read == 0;
+        Ok(!self.eof)
+    }
+}
+
+/// Wraps a tokio reader, a `tokio::fs::File` or `TcpStream` say, so it can be read by an
+/// [`AsyncDecoder`].
+#[derive(Debug)]
+pub struct TokioReader<R>(pub R);
+
+impl<R: tokio::io::AsyncRead + Unpin> AsyncRead for TokioReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut buf = tokio::io::ReadBuf::new(buf);
+        match Pin::new(&mut self.0).poll_read(cx, &mut buf) {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(buf.filled().len())),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_io::AsyncRead;
+
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use super::{AsyncDecoder, TokioReader};
+    use crate::encoder::Encoder;
+    use crate::fixtures::test_frames;
+    use crate::parser::{Decoder, Frame};
+
+    // hands out a byte at a time, and is only ready every other time it's polled
+    struct Trickle<'a> {
+        data: &'a [u8],
+        ready: bool,
+    }
+
+    impl AsyncRead for Trickle<'_> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            self.ready = !self.ready;
+            if !self.ready {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let Some((&byte, rest)) = self.data.split_first() else {
+                return Poll::Ready(Ok(0));
+            };
+            buf[0] = byte;
+            self.data = rest;
+            Poll::Ready(Ok(1))
+        }
+    }
+
+    fn indicies(frames: &[Frame]) -> Vec<&[u8]> {
+        frames.iter().map(Frame::indicies).collect()
+    }
+
+    #[test]
+    fn decodes_like_decoder() {
+        let mut encoder = Encoder::new(Vec::new(), 8, 8);
+        encoder.write_comment(b"made for a test").unwrap();
+        for frame in test_frames(3) {
+            encoder.write_frame(&frame).unwrap();
+        }
+        let gif = encoder.finish().unwrap();
+        let mut decoder = Decoder::new(gif.as_slice());
+        decoder.parse().unwrap();
+
+        let mut trickled = AsyncDecoder::new(Trickle {
+            data: &gif,
+            ready: false,
+        });
+        let first = pollster::block_on(trickled.next_frame()).unwrap().unwrap();
+        assert_eq!(first.indicies(), decoder.frames()[0].indicies());
+        pollster::block_on(trickled.parse()).unwrap();
+        assert_eq!(indicies(trickled.frames()), indicies(decoder.frames()));
+        assert_eq!(trickled.screen_size(), Some((8, 8)));
+        assert_eq!(
+            trickled.into_decoder().comments().collect::<Vec<_>>(),
+            [b"made for a test"]
+        );
+
+        let mut tokio = AsyncDecoder::new(TokioReader(gif.as_slice()));
+        pollster::block_on(tokio.parse()).unwrap();
+        assert_eq!(indicies(&tokio.into_frames()), indicies(decoder.frames()));
+
+        // cut off in the middle of the last frame, which both recover from the same way
+        let cut = &gif[..gif.len() - 8];
+        let mut decoder = Decoder::new(cut);
+        let parsed = decoder.parse();
+        let mut truncated = AsyncDecoder::new(cut);
+        assert_eq!(
+            pollster::block_on(truncated.parse()).is_ok(),
+            parsed.is_ok()
+        );
+        assert_eq!(indicies(truncated.frames()), indicies(decoder.frames()));
+    }
+}
@@ -0,0 +1,95 @@
+use super::{decode_image_data, deinterlace, Frame, ParserError};
+use crate::trace::Span;
+
+/// Scratch space for decoding frames, kept from one frame to the next so that once it's grown
+/// big enough decoding doesn't allocate. See `Decoder::decode_frame_into`,
+/// [`SliceFrame::decode`](super::SliceFrame::decode) and `Compositor::draw_from`.
+#[derive(Debug, Clone, Default)]
+pub struct DecodeBuffers {
+    // the data sub-blocks of a frame joined together
+    pub(super) image_data: Vec<u8>,
+    // the indicies of an interlaced frame as they're stored, before their rows are put in order
+    interlaced: Vec<u8>,
+    // the indicies of the last frame decoded into these buffers
+    indicies: Vec<u8>,
+    rgba: Vec<u8>,
+}
+
+impl DecodeBuffers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The indicies of the last frame decoded into these buffers, in row order.
+    pub fn indicies(&self) -> &[u8] {
+        &self.indicies
+    }
+
+    /// Expands the last frame decoded into these buffers to RGBA, four bytes per pixel, with
+    /// the palette and transparency of `frame`. See [`Frame::pixel`].
+    pub fn rgba(&mut self, frame: &Frame) -> &[u8] {
+        self.rgba.clear();
+        for &index in &self.indicies {
+            self.rgba
+                .extend_from_slice(&frame.pixel(index).unwrap_or([0, 0, 0, 255]));
+        }
+        &self.rgba
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.indicies.clear();
+    }
+
+    // decodes the data in `image_data`, see `decode`
+    pub(super) fn decode_image_data(
+        &mut self,
+        lzw_code_size: u8,
+        pixel_count: usize,
+        interlaced_width: Option<u16>,
+        strict: bool,
+    ) -> Result<(), ParserError> {
+        let image_data = std::mem::take(&mut self.image_data);
+        let decoded = self.decode(
+            &image_data,
+            lzw_code_size,
+            pixel_count,
+            interlaced_width,
+            strict,
+        );
+        self.image_data = image_data;
+        decoded
+    }
+
+    // decodes the image data of a frame covering `pixel_count` pixels into `indicies`, putting
+    // the rows of an interlaced frame `interlaced_width` pixels wide back in order
+    pub(super) fn decode(
+        &mut self,
+        data: &[u8],
+        lzw_code_size: u8,
+        pixel_count: usize,
+        interlaced_width: Option<u16>,
+        strict: bool,
+    ) -> Result<(), ParserError> {
+        let mut span = Span::start("lzw");
+        span.field("code_size", lzw_code_size);
+        span.field("bytes", data.len());
+        span.field("pixels", pixel_count);
+        span.field("interlaced", interlaced_width.is_some());
+        match interlaced_width {
+            Some(width) => {
+                decode_image_data(
+                    data,
+                    lzw_code_size,
+                    pixel_count,
+                    strict,
+                    &mut self.interlaced,
+                )?;
+                deinterlace(&self.interlaced, width, &mut self.indicies);
+            }
+            None => {
+                decode_image_data(data, lzw_code_size, pixel_count, strict, &mut self.indicies)?
+            }
+        }
+        Ok(())
+    }
+}
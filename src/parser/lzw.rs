@@ -1,86 +1,246 @@
-use super::bit_reader::BitReader;
+use log::trace;
+use thiserror::Error;
 
-pub fn lzw_decode(buf: &[u8], minimum_code_size: u32) -> Vec<u8> {
-    let mut code_table = init_code_table(minimum_code_size);
+use crate::bits::BitReader;
 
-    let clear_code: u16 = 1 << minimum_code_size;
+// the spec caps codes at 12 bits, once the table is full the encoder has to send a clear code.
+const MAX_CODE_TABLE_LENGTH: usize = 4096;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum LzwError {
+    #[error("lzw minimum code size {0} is out of range")]
+    InvalidMinimumCodeSize(u32),
+
+    #[error("lzw stream doesn't start with a clear code")]
+    MissingClearCode,
+
+    #[error("lzw stream contains code {code} but the table only has {table_length} entries")]
+    InvalidCode { code: u16, table_length: u16 },
+
+    #[error("lzw stream ended without an end of information code after {decoded} indicies")]
+    Truncated { decoded: usize },
+}
+
+/// Decodes at most `max_indicies` indicies, anything the stream produces past that point is
+/// dropped rather than allocated.
+pub fn lzw_decode(
+    buf: &[u8],
+    minimum_code_size: u32,
+    max_indicies: usize,
+) -> Result<Vec<u8>, LzwError> {
+    let mut indicies = Vec::new();
+    lzw_decode_into(buf, minimum_code_size, max_indicies, true, &mut indicies)?;
+    Ok(indicies)
+}
+
+/// Like `lzw_decode`, but appends to `indicies` so whatever was decoded before an error is kept.
+/// Streams that don't start with a clear code are only an error if `require_clear_code` is set.
+pub fn lzw_decode_into(
+    buf: &[u8],
+    minimum_code_size: u32,
+    max_indicies: usize,
+    require_clear_code: bool,
+    indicies: &mut Vec<u8>,
+) -> Result<(), LzwError> {
+    // codes can't be wider than 12 bits, and the code size always starts one above the minimum.
+    if !(1..=11).contains(&minimum_code_size) {
+        return Err(LzwError::InvalidMinimumCodeSize(minimum_code_size));
+    }
+
+    let max_indicies = indicies.len() + max_indicies;
+
+    let mut code_table = CodeTable::new(minimum_code_size);
+    // a code is expanded back to front, so its indicies are collected here before being output
+    let mut stack: Vec<u8> = Vec::with_capacity(MAX_CODE_TABLE_LENGTH);
+
+    let clear_code = code_table.clear_code;
     let end_of_information_code = clear_code + 1;
 
     let mut reader = BitReader::new(buf);
     let mut code_size = minimum_code_size + 1;
 
-    let mut indicies: Vec<u8> = Vec::new();
-
-    reader.next(code_size).unwrap();
-    let mut last_code = reader.next(code_size).unwrap() as usize;
+    // {CODE-1}, none right after a clear code
+    let mut last_code: Option<u16> = None;
+    let mut first_code = true;
 
-    let last_code_indicies = code_table.get(last_code as usize).unwrap().clone();
+    loop {
+        if indicies.len() >= max_indicies {
+            break;
+        }
 
-    // output the first code
-    indicies.extend_from_slice(&last_code_indicies);
+        let Some(code) = reader.next(code_size) else {
+            return Err(LzwError::Truncated {
+                decoded: indicies.len(),
+            });
+        };
 
-    // does code exist in the string table
-    while let Some(code) = reader.next(code_size) {
-        if code_table.len() == (1 << code_size) - 1 && code_size < 12 {
-            code_size += 1;
+        if first_code && code != clear_code && require_clear_code {
+            return Err(LzwError::MissingClearCode);
         }
-
-        if code == clear_code.into() {
+        first_code = false;
+
+        if code == clear_code {
+            trace!(
+                "clear code, decoded={} table_length={} code_size={}",
+                indicies.len(),
+                code_table.next_code,
+                code_size
+            );
             code_size = minimum_code_size + 1;
-            code_table = init_code_table(minimum_code_size);
-            last_code = reader.next(code_size).unwrap() as usize;
-            let last_code_indicies = code_table.get(last_code as usize).unwrap().clone();
-            indicies.extend_from_slice(&last_code_indicies);
+            code_table.reset();
+            last_code = None;
             continue;
         }
 
-        if code == end_of_information_code.into() {
+        if code == end_of_information_code {
             break;
         }
 
-        match code_table.get(code as usize) {
-            Some(code_indicies) => {
-                // output {CODE} to index stream
-                indicies.extend_from_slice(code_indicies);
+        let Some(previous_code) = last_code else {
+            // the first code after a clear code is output as is
+            if code >= clear_code {
+                return Err(LzwError::InvalidCode {
+                    code,
+                    table_length: code_table.next_code,
+                });
+            }
+            indicies.push(code_table.suffix[code as usize]);
+            last_code = Some(code);
+            continue;
+        };
+
+        let first_index = if code < code_table.next_code {
+            // output {CODE} to index stream, K is the first index in {CODE}
+            let first_index = code_table.expand(code, &mut stack);
+            indicies.extend(stack.iter().rev());
+            first_index
+        } else if code == code_table.next_code {
+            // output {CODE-1}+K to index stream, K is the first index of {CODE-1}
+            let first_index = code_table.expand(previous_code, &mut stack);
+            indicies.extend(stack.iter().rev());
+            indicies.push(first_index);
+            first_index
+        } else {
+            return Err(LzwError::InvalidCode {
+                code,
+                table_length: code_table.next_code,
+            });
+        };
+
+        // add {CODE-1}+K to the code table
+        if code_table.push(previous_code, first_index)
+            && code_table.next_code == (1 << code_size)
+            && code_size < 12
+        {
+            code_size += 1;
+        }
 
-                // get {CODE-1}
-                let mut new_code_table_entry = code_table.get(last_code).unwrap().clone();
-                //
-                // let K be the first index in {CODE}
-                let first_index_of_current_code = code_indicies.first().unwrap();
-                // get {CODE-1}+K
-                new_code_table_entry.push(*first_index_of_current_code);
+        // CODE-1 = CODE
+        last_code = Some(code);
+    }
 
-                // add {CODE-1}+K to the code table
-                code_table.push(new_code_table_entry);
+    indicies.truncate(max_indicies);
+    Ok(())
+}
 
-                // CODE-1 = CODE
-                last_code = code as usize;
-            }
-            None => {
-                // {CODE-1}
-                let mut new_code_table_entry = code_table.get(last_code).unwrap().clone();
-                // let K be the first index of {CODE-1}
-                let first_index_of_last_code = new_code_table_entry.first().unwrap();
-                // get {CODE-1}+K
-                new_code_table_entry.push(*first_index_of_last_code);
-
-                // output {CODE-1}+K to index stream
-                indicies.extend_from_slice(&new_code_table_entry);
-
-                // add {CODE-1}+K to code table
-                code_table.push(new_code_table_entry);
-
-                // CODE-1 = CODE
-                last_code = code as usize;
-            }
+// every entry past the clear and end of information codes is an earlier entry plus one index, so
+// the table only needs to store that earlier code and the index appended to it.
+struct CodeTable {
+    prefix: [u16; MAX_CODE_TABLE_LENGTH],
+    suffix: [u8; MAX_CODE_TABLE_LENGTH],
+    clear_code: u16,
+    next_code: u16,
+}
+
+impl CodeTable {
+    fn new(minimum_code_size: u32) -> Self {
+        let clear_code = 1_u16 << minimum_code_size;
+        let mut suffix = [0; MAX_CODE_TABLE_LENGTH];
+        for (code, index) in suffix.iter_mut().enumerate().take(clear_code as usize) {
+            *index = code as u8;
+        }
+
+        Self {
+            prefix: [0; MAX_CODE_TABLE_LENGTH],
+            suffix,
+            clear_code,
+            next_code: clear_code + 2,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.next_code = self.clear_code + 2;
+    }
+
+    /// Adds `prefix` + `index` as the next code, returns false once the table is full.
+    fn push(&mut self, prefix: u16, index: u8) -> bool {
+        if self.next_code as usize >= MAX_CODE_TABLE_LENGTH {
+            return false;
         }
+        self.prefix[self.next_code as usize] = prefix;
+        self.suffix[self.next_code as usize] = index;
+        self.next_code += 1;
+        true
     }
 
-    indicies
+    /// Fills `stack` with the indicies of `code`, last index first, and returns the first index.
+    fn expand(&self, mut code: u16, stack: &mut Vec<u8>) -> u8 {
+        stack.clear();
+        // prefixes always point at lower codes, so this ends at one of the initial codes
+        while code > self.clear_code {
+            stack.push(self.suffix[code as usize]);
+            code = self.prefix[code as usize];
+        }
+        let first_index = self.suffix[code as usize];
+        stack.push(first_index);
+        first_index
+    }
 }
 
-fn init_code_table(minimum_code_size: u32) -> Vec<Vec<u8>> {
-    let min_table_length: u16 = (1 << minimum_code_size) + 1;
-    (0..=min_table_length).map(|i| vec![i as u8]).collect()
+#[cfg(test)]
+mod tests {
+    use super::{lzw_decode, lzw_decode_into, LzwError};
+
+    // minimum code size 2: clear code 4, end of information 5, 3 bit codes
+
+    #[test]
+    fn decodes_a_complete_stream() {
+        // clear, 1, end of information
+        assert_eq!(lzw_decode(&[0b01_001_100, 0b1], 2, 16), Ok(vec![1]));
+    }
+
+    #[test]
+    fn reports_corrupt_streams() {
+        // 1, end of information
+        assert_eq!(
+            lzw_decode(&[0b00_101_001], 2, 16),
+            Err(LzwError::MissingClearCode)
+        );
+        let mut indicies = Vec::new();
+        assert_eq!(
+            lzw_decode_into(&[0b00_101_001], 2, 16, false, &mut indicies),
+            Ok(())
+        );
+        assert_eq!(indicies, [1]);
+
+        // clear, 7
+        assert_eq!(
+            lzw_decode(&[0b00_111_100], 2, 16),
+            Err(LzwError::InvalidCode {
+                code: 7,
+                table_length: 6
+            })
+        );
+
+        // clear, 1, and nothing else
+        assert_eq!(
+            lzw_decode(&[0b00_001_100], 2, 16),
+            Err(LzwError::Truncated { decoded: 1 })
+        );
+
+        assert_eq!(
+            lzw_decode(&[0b00_001_100], 12, 16),
+            Err(LzwError::InvalidMinimumCodeSize(12))
+        );
+    }
 }
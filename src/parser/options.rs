@@ -0,0 +1,266 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::ParserError;
+
+/// What becomes of pixels whose index is past the end of the palette, which the image data of
+/// broken GIFs can have. Frames keep the policy they were decoded with, see
+/// [`Frame::pixel`](super::Frame::pixel).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaletteIndexPolicy {
+    /// Refuse to decode frames with such pixels.
+    Error,
+    /// Show the last color of the palette.
+    ClampToLast,
+    /// Show opaque black, which is what browsers do.
+    #[default]
+    MapToBlack,
+    /// Show what's underneath, like the transparent index does.
+    MapToTransparent,
+}
+
+impl PaletteIndexPolicy {
+    /// The RGBA color `index` stands for in `palette`, following the policy if it's past the
+    /// end. `None` only for such indicies with [`PaletteIndexPolicy::Error`].
+    pub fn rgba(self, palette: &[u8], index: u8) -> Option<[u8; 4]> {
+        let color = |index: usize| {
+            let rgb = palette.get(index * 3..index * 3 + 3)?;
+            Some([rgb[0], rgb[1], rgb[2], 255])
+        };
+        if let Some(color) = color(index as usize) {
+            return Some(color);
+        }
+
+        match self {
+            PaletteIndexPolicy::Error => None,
+            PaletteIndexPolicy::ClampToLast => {
+                let last = (palette.len() / 3).checked_sub(1).and_then(color);
+                Some(last.unwrap_or([0, 0, 0, 255]))
+            }
+            PaletteIndexPolicy::MapToBlack => Some([0, 0, 0, 255]),
+            PaletteIndexPolicy::MapToTransparent => Some([0, 0, 0, 0]),
+        }
+    }
+}
+
+/// How far decoding has got, passed to the callback set with [`DecodeOptions::progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Bytes of the stream read so far.
+    pub bytes_read: u64,
+    /// How long the stream is, if the decoder was told with `Decoder::set_length` or
+    /// `Decoder::measure_length`.
+    pub total_bytes: Option<u64>,
+    /// Frames decoded so far. `parse_parallel` counts them as it decodes them, after the whole
+    /// stream has been read.
+    pub frames_decoded: usize,
+}
+
+impl Progress {
+    /// How much of the stream has been read, from 0 to 1.
+    pub fn fraction(&self) -> Option<f64> {
+        let total_bytes = self.total_bytes.filter(|&total_bytes| total_bytes > 0)?;
+        Some((self.bytes_read as f64 / total_bytes as f64).min(1.0))
+    }
+
+    /// How many frames there are likely to be, assuming the rest of the stream holds as many
+    /// per byte as what's been read so far.
+    pub fn estimated_frames(&self) -> Option<usize> {
+        let fraction = self.fraction().filter(|&fraction| fraction > 0.0)?;
+        Some(((self.frames_decoded as f64 / fraction).round() as usize).max(self.frames_decoded))
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct ProgressCallback(pub(super) Arc<dyn Fn(&Progress) + Send + Sync>);
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// Controls how forgiving the decoder is. The defaults favour getting *something* out of
+/// slightly broken files, which is what most real-world GIFs need.
+#[derive(Debug, Clone)]
+pub struct DecodeOptions {
+    pub(super) max_frame_count: Option<usize>,
+    pub(super) max_dimensions: Option<(u16, u16)>,
+    pub(super) strict: bool,
+    pub(super) tolerate_truncated_trailer: bool,
+    pub(super) tolerate_bad_sub_block_lengths: bool,
+    pub(super) max_memory: Option<usize>,
+    pub(super) max_pixels_per_frame: Option<usize>,
+    pub(super) max_canvas_pixels: Option<usize>,
+    pub(super) max_total_frames: Option<usize>,
+    pub(super) timeout: Option<Duration>,
+    pub(super) palette_index_policy: PaletteIndexPolicy,
+    pub(super) progress: Option<ProgressCallback>,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            max_frame_count: None,
+            max_dimensions: None,
+            strict: false,
+            tolerate_truncated_trailer: true,
+            tolerate_bad_sub_block_lengths: true,
+            max_memory: None,
+            max_pixels_per_frame: None,
+            max_canvas_pixels: None,
+            max_total_frames: None,
+            timeout: None,
+            palette_index_policy: PaletteIndexPolicy::default(),
+            progress: None,
+        }
+    }
+}
+
+impl DecodeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop decoding once this many frames have been read, ignoring the rest of the file.
+    pub fn max_frame_count(mut self, max_frame_count: usize) -> Self {
+        self.max_frame_count = Some(max_frame_count);
+        self
+    }
+
+    /// Reject files whose logical screen or frames are larger than `width` x `height`.
+    pub fn max_dimensions(mut self, width: u16, height: u16) -> Self {
+        self.max_dimensions = Some((width, height));
+        self
+    }
+
+    /// Fail on anything that isn't spec conformant. This overrides the `tolerate_*` options.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Treat the end of the stream as a trailer when it happens between blocks.
+    pub fn tolerate_truncated_trailer(mut self, tolerate: bool) -> Self {
+        self.tolerate_truncated_trailer = tolerate;
+        self
+    }
+
+    /// Keep whatever data is available when a sub-block claims to be longer than the rest of the
+    /// stream.
+    pub fn tolerate_bad_sub_block_lengths(mut self, tolerate: bool) -> Self {
+        self.tolerate_bad_sub_block_lengths = tolerate;
+        self
+    }
+
+    /// Upper bound in bytes on decoded image data and color tables held by the decoder.
+    pub fn max_memory(mut self, max_memory: usize) -> Self {
+        self.max_memory = Some(max_memory);
+        self
+    }
+
+    /// Reject frames whose width times height is larger than this.
+    pub fn max_pixels_per_frame(mut self, max_pixels_per_frame: usize) -> Self {
+        self.max_pixels_per_frame = Some(max_pixels_per_frame);
+        self
+    }
+
+    /// Reject files whose logical screen has more pixels than this. Compositing frames keeps
+    /// an RGBA canvas of the whole screen, so this bounds it at 4 bytes a pixel.
+    pub fn max_canvas_pixels(mut self, max_canvas_pixels: usize) -> Self {
+        self.max_canvas_pixels = Some(max_canvas_pixels);
+        self
+    }
+
+    /// Fail once the file contains more frames than this. Unlike `max_frame_count` this is
+    /// treated as an error rather than a request to stop early.
+    pub fn max_total_frames(mut self, max_total_frames: usize) -> Self {
+        self.max_total_frames = Some(max_total_frames);
+        self
+    }
+
+    /// Give up once decoding has taken longer than `timeout` of wall-clock time. Checked between
+    /// blocks, so a single huge frame can run over by however long it takes to decode.
+    ///
+    /// There's no clock on `wasm32-unknown-unknown`, so decoding with a timeout panics there.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// What to do with pixels whose index is past the end of the palette, opaque black by
+    /// default.
+    pub fn palette_index_policy(mut self, policy: PaletteIndexPolicy) -> Self {
+        self.palette_index_policy = policy;
+        self
+    }
+
+    /// Calls `callback` every time a frame has been decoded, and once more when the end of the
+    /// stream is reached. It's called on rayon's threads by `parse_parallel`.
+    pub fn progress(mut self, callback: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(ProgressCallback(Arc::new(callback)));
+        self
+    }
+
+    pub(super) fn recover_truncated_trailer(&self) -> bool {
+        !self.strict && self.tolerate_truncated_trailer
+    }
+
+    pub(super) fn recover_bad_sub_block_lengths(&self) -> bool {
+        !self.strict && self.tolerate_bad_sub_block_lengths
+    }
+
+    // the limits below are shared by every decoder, so they mean the same thing whichever one
+    // a file is read with
+
+    pub(super) fn check_dimensions(&self, width: u16, height: u16) -> Result<(), ParserError> {
+        match self.max_dimensions {
+            Some((max_width, max_height)) if width > max_width || height > max_height => {
+                Err(ParserError::DimensionsTooLarge {
+                    width,
+                    height,
+                    max_width,
+                    max_height,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(super) fn check_canvas_pixels(&self, width: u16, height: u16) -> Result<(), ParserError> {
+        let pixel_count = width as usize * height as usize;
+        check_limit("canvas pixels", pixel_count, self.max_canvas_pixels)
+    }
+
+    pub(super) fn check_pixels_per_frame(&self, pixel_count: usize) -> Result<(), ParserError> {
+        check_limit("pixels per frame", pixel_count, self.max_pixels_per_frame)
+    }
+
+    // called before reading another frame, with how many have been read
+    pub(super) fn check_total_frames(&self, frames_read: usize) -> Result<(), ParserError> {
+        check_limit("total frames", frames_read + 1, self.max_total_frames)
+    }
+
+    // returns the memory used once `bytes` more have been taken
+    pub(super) fn reserve_memory(&self, used: usize, bytes: usize) -> Result<usize, ParserError> {
+        let requested = used.saturating_add(bytes);
+        check_limit("memory", requested, self.max_memory)?;
+        Ok(requested)
+    }
+}
+
+fn check_limit(
+    limit: &'static str,
+    requested: usize,
+    max: Option<usize>,
+) -> Result<(), ParserError> {
+    match max {
+        Some(max) if requested > max => Err(ParserError::LimitExceeded {
+            limit,
+            requested,
+            max,
+        }),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,137 @@
+use anyhow::Result;
+
+use std::fmt::Debug;
+use std::io::Read;
+use std::time::Duration;
+
+use super::Decoder;
+use crate::compositor::Compositor;
+
+// how many frames apart the canvases kept for seeking backwards are
+const KEYFRAME_INTERVAL: usize = 16;
+
+/// Composited canvases kept around by `Decoder::frame_at`, so seeking only has to draw the
+/// frames since the closest one.
+#[derive(Debug, Clone, Default)]
+pub(super) struct SeekCache {
+    // keyframes[n] has every frame up to frame n * KEYFRAME_INTERVAL drawn on it
+    keyframes: Vec<Compositor>,
+    // the last frame that was asked for, so playing forwards draws one frame at a time
+    last: Option<(usize, Compositor)>,
+}
+
+impl<T: Read + Debug> Decoder<T> {
+    /// What's on screen while frame `index` is shown, as RGBA covering the logical screen.
+    /// Parses just far enough to reach the frame, or returns `None` if the file ends before it.
+    ///
+    /// Every 16th canvas is kept as a keyframe, so going back only redraws the frames since the
+    /// closest keyframe rather than everything from the start.
+    pub fn frame_at(&mut self, index: usize) -> Result<Option<Vec<u8>>> {
+        while self.frames().len() <= index {
+            if !self.advance_to_next_frame()? {
+                return Ok(None);
+            }
+        }
+
+        let (width, height) = self.screen_size().unwrap_or((0, 0));
+        let cache = &mut self.seek_cache;
+        let keyframe = (index / KEYFRAME_INTERVAL).min(cache.keyframes.len().saturating_sub(1));
+        let (drawn, mut compositor) = match cache.last.take() {
+            Some((last, compositor)) if last <= index && last >= keyframe * KEYFRAME_INTERVAL => {
+                (Some(last), compositor)
+            }
+            _ => match cache.keyframes.get(keyframe) {
+                Some(compositor) => (Some(keyframe * KEYFRAME_INTERVAL), compositor.clone()),
+                None => (None, Compositor::new(width, height)),
+            },
+        };
+
+        let start = drawn.map_or(0, |drawn| drawn + 1);
+        for (position, frame) in self.sink.frames[..=index].iter().enumerate().skip(start) {
+            compositor.draw(frame);
+            if position == cache.keyframes.len() * KEYFRAME_INTERVAL {
+                cache.keyframes.push(compositor.clone());
+            }
+        }
+
+        let canvas = compositor.canvas().into_owned();
+        cache.last = Some((index, compositor));
+        Ok(Some(canvas))
+    }
+
+    /// What's on screen `time` into the first play through the animation, see `frame_at`.
+    /// `None` once `time` is past the end.
+    pub fn frame_at_time(&mut self, time: Duration) -> Result<Option<Vec<u8>>> {
+        let mut start = Duration::ZERO;
+        let mut index = 0;
+        loop {
+            if self.frames().len() <= index && !self.advance_to_next_frame()? {
+                return Ok(None);
+            }
+
+            let end = start + self.sink.frames[index].duration();
+            if time < end {
+                return self.frame_at(index);
+            }
+            start = end;
+            index += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::animation::Animation;
+    use crate::fixtures::encode;
+    use crate::parser::{Decoder, DisposalMethod, Frame};
+
+    use std::time::Duration;
+
+    #[test]
+    fn seeks_to_any_frame() {
+        let palette: Box<[u8]> = Box::new([255, 0, 0, 0, 0, 255, 0, 255, 0]);
+        let mut background = Frame::new(40, 1, Box::new([0; 40]), palette.clone());
+        background.disposal_method = Some(DisposalMethod::DoNotDispose);
+        let mut frames = vec![background];
+        // a pixel moving along, leaving a trail except every third one which is restored
+        for x in 0..39 {
+            let mut pixel = Frame::new(1, 1, Box::new([1 + x as u8 % 2]), palette.clone());
+            pixel.left_position = x;
+            pixel.delay_time = 10;
+            pixel.disposal_method = Some(match x % 3 {
+                0 => DisposalMethod::RestoreToPrevious,
+                _ => DisposalMethod::DoNotDispose,
+            });
+            frames.push(pixel);
+        }
+        let gif = encode(40, 1, Some(&palette), &frames);
+        let composited: Vec<Vec<u8>> = Animation::decode(gif.as_slice())
+            .unwrap()
+            .composited_frames()
+            .collect();
+
+        let mut decoder = Decoder::new(gif.as_slice());
+        for index in [5, 6, 39, 17, 2, 33, 32, 0, 16, 20] {
+            assert_eq!(
+                decoder.frame_at(index).unwrap().as_ref(),
+                Some(&composited[index]),
+                "frame {}",
+                index
+            );
+        }
+        assert_eq!(decoder.frame_at(40).unwrap(), None);
+
+        let mut decoder = Decoder::new(gif.as_slice());
+        // the background has no delay, so it's played for a tenth of a second
+        let at = |millis| Duration::from_millis(millis);
+        assert_eq!(
+            decoder.frame_at_time(at(0)).unwrap().unwrap(),
+            composited[0]
+        );
+        assert_eq!(
+            decoder.frame_at_time(at(250)).unwrap().unwrap(),
+            composited[2]
+        );
+        assert_eq!(decoder.frame_at_time(at(4000)).unwrap(), None);
+    }
+}
@@ -0,0 +1,116 @@
+use anyhow::Result;
+
+use std::sync::Arc;
+
+use super::{Frame, SpecialPurposeExtension, Version};
+
+/// The header of a GIF, the logical screen descriptor and global color table included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub version: Version,
+    pub width: u16,
+    pub height: u16,
+    pub background_color_index: u8,
+    /// As it's stored, see [`super::pixel_aspect_ratio`].
+    pub pixel_aspect_ratio: u8,
+    pub global_palette: Option<Arc<[u8]>>,
+}
+
+/// An image descriptor, read before the frame's color table and image data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameDescriptor {
+    /// How many frames came before this one.
+    pub index: usize,
+    pub left: u16,
+    pub top: u16,
+    pub width: u16,
+    pub height: u16,
+    pub interlaced: bool,
+    pub has_local_palette: bool,
+}
+
+/// What happens to the image data of a frame, see [`DecodeSink::on_frame_descriptor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageData {
+    #[default]
+    Decode,
+    /// Skip over the image data, the frame is passed on without indicies.
+    Skip,
+}
+
+/// Receives the blocks of a GIF as a [`Decoder`](super::Decoder) parses them, so they can be
+/// looked at or thrown away as they come instead of being kept until the whole file is read.
+/// [`Buffered`] is the sink a decoder keeps everything in by default.
+///
+/// Errors returned from a callback stop parsing and are returned by the decoder.
+pub trait DecodeSink {
+    /// Called once, before anything else.
+    fn on_header(&mut self, _header: &Header) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called for every frame before its image data is read, which is skipped for
+    /// [`ImageData::Skip`].
+    fn on_frame_descriptor(&mut self, _descriptor: &FrameDescriptor) -> Result<ImageData> {
+        Ok(ImageData::Decode)
+    }
+
+    /// Called with every frame once its image data has been read, with the timing and
+    /// transparency of the graphic control extension in front of it.
+    fn on_frame_data(&mut self, frame: Frame) -> Result<()>;
+
+    /// Called for every application, comment and unknown extension. Graphic control
+    /// extensions are passed on with the frame they belong to, and plain text extensions are
+    /// skipped.
+    fn on_extension(&mut self, _extension: SpecialPurposeExtension) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<S: DecodeSink + ?Sized> DecodeSink for &mut S {
+    fn on_header(&mut self, header: &Header) -> Result<()> {
+        (**self).on_header(header)
+    }
+
+    fn on_frame_descriptor(&mut self, descriptor: &FrameDescriptor) -> Result<ImageData> {
+        (**self).on_frame_descriptor(descriptor)
+    }
+
+    fn on_frame_data(&mut self, frame: Frame) -> Result<()> {
+        (**self).on_frame_data(frame)
+    }
+
+    fn on_extension(&mut self, extension: SpecialPurposeExtension) -> Result<()> {
+        (**self).on_extension(extension)
+    }
+}
+
+/// Keeps every frame and extension, which is what `Decoder::frames` and friends read from.
+#[derive(Debug, Clone, Default)]
+pub struct Buffered {
+    pub(super) frames: Vec<Frame>,
+    pub(super) extensions: Vec<SpecialPurposeExtension>,
+}
+
+impl Buffered {
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Application, comment and unknown extensions in the order they were read.
+    pub fn extensions(&self) -> &[SpecialPurposeExtension] {
+        &self.extensions
+    }
+}
+
+impl DecodeSink for Buffered {
+    fn on_frame_data(&mut self, frame: Frame) -> Result<()> {
+        self.frames.push(frame);
+        Ok(())
+    }
+
+    fn on_extension(&mut self, extension: SpecialPurposeExtension) -> Result<()> {
+        self.extensions.push(extension);
+        Ok(())
+    }
+}
@@ -0,0 +1,681 @@
+use anyhow::Result;
+use log::warn;
+
+use std::io;
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::{
+    check_extension_version, check_timeout, DecodeBuffers, DecodeOptions, DisposalMethod,
+    ExtensionType, Frame, LoopCount, PaletteIndexPolicy, ParserError, Progress, Version,
+    APPLICATION_EXTENSION, EXTENSION_INTRODUCER, GRAPHIC_CONTROL_EXTENSION, IMAGE_DESCRIPTOR_LABEL,
+    TRAILER_LABEL,
+};
+
+/// Walks a GIF that's already in memory, borrowing color tables and compressed image data from
+/// it instead of copying them out. Frames are only decoded when asked for, see
+/// [`SliceFrame::decode`], so the same [`DecodeBuffers`] can be used for every frame.
+///
+/// The limits in [`DecodeOptions`] are checked as frames are read, the same way [`Decoder`]
+/// checks them, and progress is reported for every frame read rather than decoded.
+///
+/// [`Decoder`]: super::Decoder
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// let data = std::fs::read("cat.gif")?;
+/// let mut decoder = jif::parser::SliceDecoder::new(&data)?;
+/// let mut buffers = jif::parser::DecodeBuffers::new();
+/// while let Some(frame) = decoder.next_frame()? {
+///     let indicies = frame.decode(&mut buffers)?;
+///     println!("{} indicies, shown for {:?}", indicies.len(), frame.delay_time);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SliceDecoder<'a> {
+    data: &'a [u8],
+    position: usize,
+    options: DecodeOptions,
+    version: Version,
+    width: u16,
+    height: u16,
+    background_color_index: u8,
+    pixel_aspect_ratio: u8,
+    global_palette: Option<&'a [u8]>,
+    loop_count: Option<LoopCount>,
+    frames_read: usize,
+    done: bool,
+    // bytes of color tables and of the buffers frames decode into, like `Decoder` counts them
+    // for `decode_frame_into`
+    memory_used: usize,
+    // the most indicies a frame read so far decodes to
+    largest_frame: usize,
+    // only kept with `DecodeOptions::timeout` set
+    started: Option<Instant>,
+    warned_about_version: bool,
+}
+
+/// A frame borrowed from the data of a [`SliceDecoder`], with its image data still compressed.
+#[derive(Debug, Clone)]
+pub struct SliceFrame<'a> {
+    /// How many frames came before this one.
+    pub index: usize,
+    pub left_position: u16,
+    pub top_position: u16,
+    pub width: u16,
+    pub height: u16,
+    pub interlaced: bool,
+    pub needs_user_input: bool,
+    pub delay_time: u16,
+    pub disposal_method: Option<DisposalMethod>,
+    pub transparent_color_index: Option<u8>,
+    pub lzw_code_size: u8,
+    local_palette: Option<&'a [u8]>,
+    global_palette: Option<&'a [u8]>,
+    image_data: SubBlocks<'a>,
+    strict: bool,
+    palette_index_policy: PaletteIndexPolicy,
+}
+
+/// The data sub-blocks of a block, each one borrowed as it's stored.
+#[derive(Debug, Clone)]
+pub struct SubBlocks<'a> {
+    // starts at the size of the first sub-block, ends after the last one's data
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for SubBlocks<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let (&size, rest) = self.data.split_first()?;
+        // a short last sub-block is only kept when sub-block lengths are tolerated
+        let (block, rest) = rest.split_at((size as usize).min(rest.len()));
+        self.data = rest;
+        Some(block)
+    }
+}
+
+impl SubBlocks<'_> {
+    /// How many bytes the sub-blocks hold once they're joined.
+    pub fn data_len(&self) -> usize {
+        self.clone().map(<[u8]>::len).sum()
+    }
+}
+
+// what's needed from the graphic control extension in front of an image
+#[derive(Debug, Clone, Copy, Default)]
+struct Control {
+    disposal_method: Option<DisposalMethod>,
+    needs_user_input: bool,
+    delay_time: u16,
+    transparent_color_index: Option<u8>,
+}
+
+impl<'a> SliceDecoder<'a> {
+    /// Reads the header of `data`, the frames are read as they're asked for.
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        Self::with_options(data, DecodeOptions::default())
+    }
+
+    pub fn with_options(data: &'a [u8], options: DecodeOptions) -> Result<Self> {
+        if data.get(..3) != Some(b"GIF") {
+            return Err(ParserError::InvalidSignature.into());
+        }
+        let version = data.get(3..6).ok_or_else(eof)?;
+        let version = Version::parse(&String::from_utf8_lossy(version))?;
+        // the clock is only read with a timeout set, wasm32-unknown-unknown doesn't have one
+        let started = options.timeout.is_some().then(Instant::now);
+
+        let mut decoder = Self {
+            data,
+            position: 6,
+            options,
+            version,
+            width: 0,
+            height: 0,
+            background_color_index: 0,
+            pixel_aspect_ratio: 0,
+            global_palette: None,
+            loop_count: None,
+            frames_read: 0,
+            done: false,
+            memory_used: 0,
+            largest_frame: 0,
+            started,
+            warned_about_version: false,
+        };
+
+        decoder.width = decoder.read_u16()?;
+        decoder.height = decoder.read_u16()?;
+        let packed_fields = decoder.read_byte()?;
+        decoder.background_color_index = decoder.read_byte()?;
+        decoder.pixel_aspect_ratio = decoder.read_byte()?;
+        decoder
+            .options
+            .check_dimensions(decoder.width, decoder.height)?;
+        decoder
+            .options
+            .check_canvas_pixels(decoder.width, decoder.height)?;
+        if packed_fields & 0b10000000 != 0 {
+            decoder.global_palette = Some(decoder.read_color_table(packed_fields)?);
+        }
+
+        Ok(decoder)
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Logical screen size as `(width, height)`.
+    pub fn screen_size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    pub fn background_color_index(&self) -> u8 {
+        self.background_color_index
+    }
+
+    /// The width of a pixel over its height if the file says, see
+    /// [`pixel_aspect_ratio`](super::pixel_aspect_ratio).
+    pub fn pixel_aspect_ratio(&self) -> Option<f32> {
+        super::pixel_aspect_ratio(self.pixel_aspect_ratio)
+    }
+
+    pub fn global_palette(&self) -> Option<&'a [u8]> {
+        self.global_palette
+    }
+
+    /// Known once the NETSCAPE2.0 extension has been read, which is usually before the first
+    /// frame.
+    pub fn loop_count(&self) -> Option<LoopCount> {
+        self.loop_count
+    }
+
+    /// Reads up to the next frame, or returns `None` once the trailer has been reached.
+    /// Extensions other than graphic control and NETSCAPE2.0 are skipped.
+    pub fn next_frame(&mut self) -> Result<Option<SliceFrame<'a>>> {
+        if self.done {
+            return Ok(None);
+        }
+        if self
+            .options
+            .max_frame_count
+            .is_some_and(|max_frame_count| self.frames_read >= max_frame_count)
+        {
+            self.done = true;
+            return Ok(None);
+        }
+
+        let mut control = None;
+        loop {
+            check_timeout(self.options.timeout, self.started)?;
+            let label = match self.read_byte() {
+                Ok(label) => label,
+                Err(err) if self.options.recover_truncated_trailer() => {
+                    warn!("{}, treating it as the end of the gif", err);
+                    self.done = true;
+                    self.report_progress();
+                    return Ok(None);
+                }
+                Err(err) => return Err(err),
+            };
+
+            match label {
+                EXTENSION_INTRODUCER => {
+                    let label = self.read_byte()?;
+                    let extension_type = match ExtensionType::try_from(label) {
+                        Ok(extension_type) => extension_type,
+                        Err(err) if self.options.strict => return Err(err.into()),
+                        Err(_) => ExtensionType::Unknown(label),
+                    };
+                    check_extension_version(
+                        &self.options,
+                        Some(self.version),
+                        &extension_type,
+                        &mut self.warned_about_version,
+                    )?;
+                    let start = self.position;
+                    let blocks = self.read_sub_blocks()?;
+                    // like `Decoder`, only a graphic control extension right in front of the
+                    // image applies to it
+                    control = match label {
+                        GRAPHIC_CONTROL_EXTENSION => self.read_control(start, blocks)?,
+                        APPLICATION_EXTENSION => {
+                            self.read_application(blocks);
+                            None
+                        }
+                        _ => None,
+                    };
+                }
+                IMAGE_DESCRIPTOR_LABEL => {
+                    let frame = self.read_frame(control.unwrap_or_default())?;
+                    self.frames_read += 1;
+                    self.report_progress();
+                    return Ok(Some(frame));
+                }
+                TRAILER_LABEL => {
+                    self.done = true;
+                    self.report_progress();
+                    return Ok(None);
+                }
+                label => return Err(ParserError::UnexpectedLabel(label).into()),
+            }
+        }
+    }
+
+    fn read_frame(&mut self, control: Control) -> Result<SliceFrame<'a>> {
+        self.options.check_total_frames(self.frames_read)?;
+        let left_position = self.read_u16()?;
+        let top_position = self.read_u16()?;
+        let width = self.read_u16()?;
+        let height = self.read_u16()?;
+        self.options.check_dimensions(width, height)?;
+        let pixel_count = width as usize * height as usize;
+        self.options.check_pixels_per_frame(pixel_count)?;
+
+        let packed_fields = self.read_byte()?;
+        let local_palette = if packed_fields & 0b10000000 != 0 {
+            Some(self.read_color_table(packed_fields)?)
+        } else {
+            None
+        };
+        // frames are decoded into the same buffers, which only take more for a larger frame
+        let grown = pixel_count.saturating_sub(self.largest_frame);
+        self.memory_used = self.options.reserve_memory(self.memory_used, grown)?;
+        self.largest_frame = self.largest_frame.max(pixel_count);
+
+        let lzw_code_size = self.read_byte()?;
+        if self.options.strict && !(2..=8).contains(&lzw_code_size) {
+            return Err(ParserError::InvalidLzwCodeSize(lzw_code_size).into());
+        }
+        let image_data = self.read_sub_blocks()?;
+
+        Ok(SliceFrame {
+            index: self.frames_read,
+            left_position,
+            top_position,
+            width,
+            height,
+            interlaced: packed_fields & 0b01000000 != 0,
+            needs_user_input: control.needs_user_input,
+            delay_time: control.delay_time,
+            disposal_method: control.disposal_method,
+            transparent_color_index: control.transparent_color_index,
+            lzw_code_size,
+            local_palette,
+            global_palette: self.global_palette,
+            image_data,
+            strict: self.options.strict,
+            palette_index_policy: self.options.palette_index_policy,
+        })
+    }
+
+    // the graphic control extension whose sub-blocks start at `start`, `None` if it's the wrong
+    // size outside of strict mode
+    fn read_control(&self, start: usize, mut blocks: SubBlocks<'a>) -> Result<Option<Control>> {
+        let block = blocks.next().unwrap_or_default();
+        if block.len() != 4 || self.data[start] != 4 {
+            if self.options.strict {
+                return Err(ParserError::InvalidBlockSize {
+                    block: "graphic control extension",
+                    expected: 4,
+                    actual: self.data[start],
+                }
+                .into());
+            }
+            warn!(
+                "graphic control extension has block size {}, ignoring it",
+                self.data[start]
+            );
+            return Ok(None);
+        }
+        if blocks.next().is_some() && self.options.strict {
+            return Err(ParserError::MissingBlockTerminator("graphic control extension").into());
+        }
+
+        let packed_fields = block[0];
+        Ok(Some(Control {
+            disposal_method: DisposalMethod::from_u8((packed_fields >> 2) & 0b00000111),
+            needs_user_input: packed_fields & 0b00000010 != 0,
+            delay_time: u16::from_le_bytes([block[1], block[2]]),
+            transparent_color_index: (packed_fields & 0b00000001 != 0).then_some(block[3]),
+        }))
+    }
+
+    // picks the loop count out of a NETSCAPE2.0 extension, anything else is skipped
+    fn read_application(&mut self, mut blocks: SubBlocks<'a>) {
+        if blocks.next() != Some(b"NETSCAPE2.0") {
+            return;
+        }
+        if let Some(&[1, low, high]) = blocks.next() {
+            self.loop_count = Some(match u16::from_le_bytes([low, high]) {
+                0 => LoopCount::Infinite,
+                number => LoopCount::Number(number),
+            });
+        }
+    }
+
+    fn report_progress(&self) {
+        if let Some(progress) = &self.options.progress {
+            (progress.0)(&Progress {
+                bytes_read: self.position as u64,
+                total_bytes: Some(self.data.len() as u64),
+                frames_decoded: self.frames_read,
+            });
+        }
+    }
+
+    // the color table whose size is in the low bits of `packed_fields`
+    fn read_color_table(&mut self, packed_fields: u8) -> Result<&'a [u8]> {
+        let size = 3 << ((packed_fields & 0b00000111) + 1);
+        self.memory_used = self.options.reserve_memory(self.memory_used, size)?;
+        self.read_bytes(size)
+    }
+
+    // skips over a run of data sub-blocks and the block terminator after them
+    fn read_sub_blocks(&mut self) -> Result<SubBlocks<'a>> {
+        let start = self.position;
+        loop {
+            let size = self.read_byte()? as usize;
+            if size == 0 {
+                break;
+            }
+
+            let available = self.data.len() - self.position;
+            if available < size {
+                if !self.options.recover_bad_sub_block_lengths() {
+                    return Err(ParserError::TruncatedSubBlock {
+                        expected: size as u8,
+                        actual: available,
+                    }
+                    .into());
+                }
+                warn!(
+                    "sub-block claims {} bytes but only {} are left, keeping what was read",
+                    size, available
+                );
+                self.position = self.data.len();
+                return Ok(SubBlocks {
+                    data: &self.data[start..],
+                });
+            }
+            self.position += size;
+        }
+
+        // the block terminator isn't part of the data
+        Ok(SubBlocks {
+            data: &self.data[start..self.position - 1],
+        })
+    }
+
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + count)
+            .ok_or_else(eof)?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn read_byte(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+}
+
+impl<'a> SliceFrame<'a> {
+    /// The frame's own color table if it has one, the global one otherwise.
+    pub fn palette(&self) -> Option<&'a [u8]> {
+        self.local_palette.or(self.global_palette)
+    }
+
+    pub fn has_local_palette(&self) -> bool {
+        self.local_palette.is_some()
+    }
+
+    /// The compressed image data, as the sub-blocks it's stored in.
+    pub fn image_data(&self) -> SubBlocks<'a> {
+        self.image_data.clone()
+    }
+
+    /// Decodes the frame's indicies into `buffers`, in row order even if the frame is
+    /// interlaced. The sub-blocks are joined there too, or decoded where they are if there's
+    /// only one, so reusing `buffers` for the next frame means decoding doesn't allocate.
+    pub fn decode<'b>(&self, buffers: &'b mut DecodeBuffers) -> Result<&'b [u8]> {
+        let pixel_count = self.width as usize * self.height as usize;
+        let interlaced_width = self.interlaced.then_some(self.width);
+        let mut blocks = self.image_data();
+        match (blocks.next(), blocks.next()) {
+            (Some(block), None) => buffers.decode(
+                block,
+                self.lzw_code_size,
+                pixel_count,
+                interlaced_width,
+                self.strict,
+            )?,
+            _ => {
+                buffers.image_data.clear();
+                for block in self.image_data() {
+                    buffers.image_data.extend_from_slice(block);
+                }
+                buffers.decode_image_data(
+                    self.lzw_code_size,
+                    pixel_count,
+                    interlaced_width,
+                    self.strict,
+                )?;
+            }
+        }
+
+        let indicies = buffers.indicies();
+        if self.palette_index_policy == PaletteIndexPolicy::Error {
+            let colors = self.palette().map_or(0, |palette| palette.len() / 3);
+            let past_palette = |&&index: &&u8| {
+                index as usize >= colors && Some(index) != self.transparent_color_index
+            };
+            if let Some(&index) = indicies.iter().find(past_palette) {
+                return Err(ParserError::IndexOutOfRange {
+                    frame: self.index,
+                    index,
+                    colors,
+                }
+                .into());
+            }
+        }
+        Ok(indicies)
+    }
+
+    /// An owned [`Frame`] with `indicies`, as returned by `decode`. This copies them and the
+    /// palette.
+    pub fn to_frame(&self, indicies: &[u8]) -> Frame {
+        let mut frame = Frame::new(self.width, self.height, Box::new([]), Box::new([]));
+        frame.indicies = Arc::from(indicies);
+        frame.left_position = self.left_position;
+        frame.top_position = self.top_position;
+        frame.needs_user_input = self.needs_user_input;
+        frame.delay_time = self.delay_time;
+        frame.disposal_method = self.disposal_method;
+        frame.transparent_color_index = self.transparent_color_index;
+        frame.palette_index_policy = self.palette_index_policy;
+        match (self.local_palette, self.global_palette) {
+            (Some(palette), _) => frame.local_palette = Some(palette.into()),
+            (None, palette) => frame.set_global_palette(palette.map(Arc::from)),
+        }
+        frame
+    }
+}
+
+fn eof() -> anyhow::Error {
+    io::Error::from(io::ErrorKind::UnexpectedEof).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SliceDecoder;
+    use crate::encoder::Encoder;
+    use crate::fixtures::encode_test_gif;
+    use crate::parser::{DecodeBuffers, DecodeOptions, Decoder, DisposalMethod, Frame, LoopCount};
+
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    // reads every frame, failing on the first error
+    fn read_all(gif: &[u8], options: DecodeOptions) -> anyhow::Result<usize> {
+        let mut decoder = SliceDecoder::with_options(gif, options)?;
+        let mut frames = 0;
+        while decoder.next_frame()?.is_some() {
+            frames += 1;
+        }
+        Ok(frames)
+    }
+
+    #[test]
+    fn decodes_like_decoder() {
+        let palette: Box<[u8]> = Box::new([255, 0, 0, 0, 0, 255, 0, 255, 0]);
+        let mut encoder = Encoder::new(Vec::new(), 300, 4);
+        encoder.set_global_palette(&palette).unwrap();
+        encoder.set_loop_count(LoopCount::Infinite);
+        // enough pixels for the image data to take more than one sub-block
+        let mut state = 1_u32;
+        let noise = (0..1200)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state % 3) as u8
+            })
+            .collect();
+        let mut first = Frame::new(300, 4, noise, palette.clone());
+        first.delay_time = 7;
+        first.disposal_method = Some(DisposalMethod::DoNotDispose);
+        encoder.write_frame(&first).unwrap();
+        let mut second = Frame::new(1, 1, Box::new([1]), Box::new([9, 9, 9, 8, 8, 8]));
+        second.transparent_color_index = Some(0);
+        encoder.write_frame(&second).unwrap();
+        let gif = encoder.finish().unwrap();
+
+        let mut decoder = Decoder::new(gif.as_slice());
+        decoder.parse().unwrap();
+        let mut slice_decoder = SliceDecoder::new(&gif).unwrap();
+        assert_eq!(slice_decoder.screen_size(), (300, 4));
+        assert_eq!(slice_decoder.global_palette(), decoder.global_palette());
+
+        let mut buffers = DecodeBuffers::new();
+        for expected in decoder.frames() {
+            let frame = slice_decoder.next_frame().unwrap().unwrap();
+            let indicies = frame.decode(&mut buffers).unwrap();
+            assert_eq!(indicies, expected.indicies());
+            assert_eq!(frame.palette(), expected.palette());
+            assert_eq!(frame.delay_time, expected.delay_time);
+            assert_eq!(frame.disposal_method, expected.disposal_method);
+            assert_eq!(
+                frame.transparent_color_index,
+                expected.transparent_color_index
+            );
+
+            let owned = frame.to_frame(indicies);
+            assert_eq!(owned.rgba(), expected.rgba());
+        }
+        assert!(slice_decoder.next_frame().unwrap().is_none());
+        assert_eq!(slice_decoder.loop_count(), Some(LoopCount::Infinite));
+
+        // a one color palette doesn't cover the frame
+        let mut first = SliceDecoder::new(&gif)
+            .unwrap()
+            .next_frame()
+            .unwrap()
+            .unwrap();
+        assert!(first.image_data().count() > 1);
+        assert_eq!(
+            first.image_data().data_len(),
+            first.image_data().flatten().count()
+        );
+        first.local_palette = Some(&[0, 0, 0]);
+        first.palette_index_policy = crate::parser::PaletteIndexPolicy::Error;
+        assert!(first.decode(&mut buffers).is_err());
+
+        let options = DecodeOptions::new().max_dimensions(100, 100);
+        assert!(SliceDecoder::with_options(&gif, options).is_err());
+        assert!(SliceDecoder::new(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn enforces_limits() {
+        let gif = encode_test_gif(3);
+        assert_eq!(read_all(&gif, DecodeOptions::new()).unwrap(), 3);
+
+        let options = DecodeOptions::new().max_pixels_per_frame(63);
+        assert!(read_all(&gif, options).is_err());
+
+        let options = DecodeOptions::new().max_total_frames(2);
+        assert!(read_all(&gif, options).is_err());
+
+        let options = DecodeOptions::new().max_canvas_pixels(63);
+        assert!(read_all(&gif, options).is_err());
+
+        // one frame's worth of indicies, since they're decoded into the same buffers, and a 12
+        // byte color table a frame
+        let options = DecodeOptions::new().max_memory(64 + 3 * 12);
+        assert_eq!(read_all(&gif, options).unwrap(), 3);
+        let options = DecodeOptions::new().max_memory(64 + 3 * 12 - 1);
+        assert!(read_all(&gif, options).is_err());
+
+        let options = DecodeOptions::new().timeout(Duration::ZERO);
+        assert!(read_all(&gif, options).is_err());
+
+        let options = DecodeOptions::new().timeout(Duration::from_secs(60));
+        assert_eq!(read_all(&gif, options).unwrap(), 3);
+
+        // a huge screen with small frames is still a huge canvas
+        let mut encoder = Encoder::new(Vec::new(), 3000, 3000);
+        for _ in 0..3 {
+            let frame = Frame::new(1, 1, Box::new([0]), Box::new([0, 0, 0]));
+            encoder.write_frame(&frame).unwrap();
+        }
+        let gif = encoder.finish().unwrap();
+        let options = DecodeOptions::new()
+            .max_total_frames(1)
+            .max_canvas_pixels(100);
+        assert!(SliceDecoder::with_options(&gif, options).is_err());
+        let options = DecodeOptions::new().max_total_frames(1);
+        assert!(read_all(&gif, options).is_err());
+    }
+
+    #[test]
+    fn checks_extensions_against_the_version() {
+        // every frame comes with a graphic control extension, which GIF87a doesn't have
+        let mut gif = encode_test_gif(2);
+        gif[3..6].copy_from_slice(b"87a");
+
+        assert_eq!(read_all(&gif, DecodeOptions::new()).unwrap(), 2);
+        assert!(read_all(&gif, DecodeOptions::new().strict(true)).is_err());
+    }
+
+    #[test]
+    fn reports_progress() {
+        let gif = encode_test_gif(3);
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let options = DecodeOptions::new().progress({
+            let reports = reports.clone();
+            move |progress| reports.lock().unwrap().push(*progress)
+        });
+        read_all(&gif, options).unwrap();
+
+        let reports = reports.lock().unwrap();
+        let frames = reports
+            .iter()
+            .map(|progress| progress.frames_decoded)
+            .collect::<Vec<_>>();
+        // once a frame, then once more at the trailer
+        assert_eq!(frames, [1, 2, 3, 3]);
+        assert_eq!(reports.last().unwrap().fraction(), Some(1.0));
+    }
+}
@@ -0,0 +1,87 @@
+use super::Frame;
+
+/// What's counted about a frame by [`Frame::stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+    /// The frame's width times its height, 0 for frames left without indicies.
+    pub pixel_count: usize,
+    /// How many different indicies the frame's pixels use.
+    pub unique_indicies: usize,
+    /// Pixels that leave what's underneath them showing, see [`Frame::pixel`].
+    pub transparent_pixels: usize,
+    /// Pixels on screen that look different after the frame is drawn than after the frame
+    /// before it. Only known with the frames around it, see `Animation::frame_stats`.
+    pub changed_pixels: Option<usize>,
+}
+
+impl FrameStats {
+    /// How much of the frame is transparent, from 0 to 1.
+    pub fn transparent_fraction(&self) -> f64 {
+        match self.pixel_count {
+            0 => 0.0,
+            pixel_count => self.transparent_pixels as f64 / pixel_count as f64,
+        }
+    }
+}
+
+impl Frame {
+    /// Counts the frame's unique indicies and transparent pixels. The first call goes over
+    /// every pixel, after that the counts are kept with the frame and its clones.
+    pub fn stats(&self) -> FrameStats {
+        let counts = self.index_counts.get_or_init(|| {
+            let mut counts = [0; 256];
+            for &index in self.indicies() {
+                counts[index as usize] += 1;
+            }
+            counts
+        });
+
+        let mut stats = FrameStats {
+            pixel_count: self.indicies().len(),
+            unique_indicies: 0,
+            transparent_pixels: 0,
+            changed_pixels: None,
+        };
+        for (index, &count) in counts.iter().enumerate().filter(|(_, &count)| count > 0) {
+            stats.unique_indicies += 1;
+            // the transparent index, and indicies past the end of the palette with
+            // `PaletteIndexPolicy::MapToTransparent`
+            if self.pixel(index as u8).is_some_and(|pixel| pixel[3] == 0) {
+                stats.transparent_pixels += count;
+            }
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FrameStats;
+    use crate::parser::Frame;
+
+    #[test]
+    fn counts_colors_and_transparency() {
+        let palette: Box<[u8]> = Box::new([255, 0, 0, 0, 0, 255, 0, 255, 0]);
+        let mut frame = Frame::new(4, 1, Box::new([0, 2, 2, 1]), palette.clone());
+        let stats = frame.stats();
+        assert_eq!(
+            stats,
+            FrameStats {
+                pixel_count: 4,
+                unique_indicies: 3,
+                transparent_pixels: 0,
+                changed_pixels: None,
+            }
+        );
+
+        // the counts are kept, but transparency follows the frame
+        frame.transparent_color_index = Some(2);
+        assert_eq!(frame.stats().transparent_pixels, 2);
+        assert_eq!(frame.stats().transparent_fraction(), 0.5);
+
+        let cropped = frame.with_image(0, 0, 1, 1, Box::new([2]));
+        assert_eq!(cropped.stats().unique_indicies, 1);
+        assert_eq!(cropped.stats().transparent_fraction(), 1.0);
+        assert_eq!(frame.stats().unique_indicies, 3);
+    }
+}
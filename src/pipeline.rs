@@ -0,0 +1,341 @@
+//! Multi-step edits described in a TOML manifest, so they can be versioned and rerun:
+//!
+//! ```toml
+//! output = "small.gif"
+//!
+//! [[ops]]
+//! op = "trim"
+//! start = 10
+//!
+//! [[ops]]
+//! op = "crop"
+//! left = 0
+//! top = 20
+//! width = 200
+//! height = 150
+//!
+//! [[ops]]
+//! op = "speed"
+//! factor = 1.5
+//! ```
+//!
+//! Besides the built-in ops, a pipeline can run any [`Transform`] registered with
+//! [`Transforms`] under its own op name, so passes that live in other crates can be used
+//! without changing jif.
+
+use anyhow::Result;
+use serde::Deserialize;
+use thiserror::Error;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::animation::Animation;
+use crate::compositor::Rect;
+use crate::parser::LoopCount;
+
+/// The fields of one `[[ops]]` table, which a [`TransformFactory`] builds its transform from.
+pub use toml::Table;
+
+/// Names of the ops every pipeline understands, which can't be registered again.
+const BUILTIN_OPS: [&str; 4] = ["crop", "trim", "speed", "loop"];
+
+#[derive(Error, Debug)]
+enum PipelineError {
+    #[error("speed factor {0} isn't a positive number")]
+    InvalidSpeed(f32),
+    #[error("op {0} is built in and can't be registered")]
+    BuiltinOp(String),
+    #[error("op {0} is already registered")]
+    AlreadyRegistered(String),
+    #[error("every op needs an `op` key naming it")]
+    MissingOp,
+    #[error("op {name}: {source}")]
+    InvalidOp { name: String, source: anyhow::Error },
+}
+
+/// A pass over a whole animation. The built-in [`Op`]s are transforms, and other crates can
+/// add their own with [`Transforms::register`].
+pub trait Transform {
+    fn apply(&self, animation: &mut Animation) -> Result<()>;
+}
+
+/// Builds a transform from the fields of its `[[ops]]` table, without the `op` key. Fields are
+/// usually read with [`Table::try_into`].
+pub type TransformFactory = Box<dyn Fn(Table) -> Result<Box<dyn Transform>>>;
+
+/// Transforms that manifests can name as ops, besides the built-in ones.
+#[derive(Default)]
+pub struct Transforms {
+    factories: HashMap<String, TransformFactory>,
+}
+
+impl Transforms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes `[[ops]]` tables with `op = name` build their transform with `factory`. Fails if
+    /// `name` is a built-in op or has been registered already.
+    pub fn register<F>(&mut self, name: &str, factory: F) -> Result<()>
+    where
+        F: Fn(Table) -> Result<Box<dyn Transform>> + 'static,
+    {
+        if BUILTIN_OPS.contains(&name) {
+            return Err(PipelineError::BuiltinOp(name.to_string()).into());
+        }
+        if self.factories.contains_key(name) {
+            return Err(PipelineError::AlreadyRegistered(name.to_string()).into());
+        }
+
+        self.factories.insert(name.to_string(), Box::new(factory));
+        Ok(())
+    }
+
+    fn build(&self, mut table: Table) -> Result<Step> {
+        let name = match table.remove("op") {
+            Some(toml::Value::String(name)) => name,
+            _ => return Err(PipelineError::MissingOp.into()),
+        };
+
+        match self.factories.get(&name) {
+            Some(factory) => match factory(table) {
+                Ok(transform) => Ok(Step::Custom { name, transform }),
+                Err(source) => Err(PipelineError::InvalidOp { name, source }.into()),
+            },
+            None => {
+                table.insert("op".to_string(), toml::Value::String(name));
+                Ok(Step::Op(table.try_into()?))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Pipeline {
+    /// Where the result is written, relative to the manifest.
+    pub output: Option<PathBuf>,
+    pub ops: Vec<Step>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    output: Option<PathBuf>,
+    #[serde(default)]
+    ops: Vec<Table>,
+}
+
+/// One step of a pipeline, either a built-in op or a registered transform.
+pub enum Step {
+    Op(Op),
+    Custom {
+        name: String,
+        transform: Box<dyn Transform>,
+    },
+}
+
+impl fmt::Debug for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Op(op) => f.debug_tuple("Op").field(op).finish(),
+            Step::Custom { name, .. } => f.debug_struct("Custom").field("name", name).finish(),
+        }
+    }
+}
+
+impl Transform for Step {
+    fn apply(&self, animation: &mut Animation) -> Result<()> {
+        match self {
+            Step::Op(op) => op.apply(animation),
+            Step::Custom { transform, .. } => transform.apply(animation),
+        }
+    }
+}
+
+/// One step of a pipeline, `op` in the manifest is the snake case name of the variant.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Op {
+    Crop {
+        left: u16,
+        top: u16,
+        width: u16,
+        height: u16,
+    },
+    /// Keeps frames `start..end`, up to the last frame without an `end`.
+    Trim {
+        #[serde(default)]
+        start: usize,
+        end: Option<usize>,
+    },
+    /// Multiplies the playback speed.
+    Speed { factor: f32 },
+    /// Plays the animation `count` times over, 0 loops forever.
+    Loop { count: u16 },
+}
+
+impl Pipeline {
+    /// Reads a manifest that only uses the built-in ops.
+    pub fn from_toml(manifest: &str) -> Result<Self> {
+        Self::from_toml_with(manifest, &Transforms::new())
+    }
+
+    /// Reads a manifest that can also use the ops registered in `transforms`.
+    pub fn from_toml_with(manifest: &str, transforms: &Transforms) -> Result<Self> {
+        let manifest: Manifest = toml::from_str(manifest)?;
+        let ops = manifest
+            .ops
+            .into_iter()
+            .map(|table| transforms.build(table))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            output: manifest.output,
+            ops,
+        })
+    }
+
+    /// Runs every op on `animation`, in order.
+    pub fn run(&self, animation: &mut Animation) -> Result<()> {
+        for op in &self.ops {
+            op.apply(animation)?;
+        }
+        Ok(())
+    }
+}
+
+impl Transform for Op {
+    fn apply(&self, animation: &mut Animation) -> Result<()> {
+        match *self {
+            Op::Crop {
+                left,
+                top,
+                width,
+                height,
+            } => animation.crop(Rect::new(left, top, width, height)),
+            Op::Trim { start, end } => animation.trim(start..end.unwrap_or(usize::MAX))?,
+            Op::Speed { factor } => {
+                if !(factor.is_finite() && factor > 0.0) {
+                    return Err(PipelineError::InvalidSpeed(factor).into());
+                }
+                animation.change_speed(factor);
+            }
+            Op::Loop { count } => animation.set_loop_count(Some(match count {
+                0 => LoopCount::Infinite,
+                count => LoopCount::Number(count),
+            })),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Op, Pipeline, Step, Table, Transform, Transforms};
+    use crate::animation::Animation;
+    use crate::compositor::Rect;
+    use crate::fixtures::three_frame_gif;
+    use crate::parser::{Frame, LoopCount};
+
+    use anyhow::Result;
+    use serde::Deserialize;
+
+    fn three_frames() -> Animation {
+        Animation::decode(three_frame_gif(0).as_slice()).unwrap()
+    }
+
+    #[test]
+    fn runs_a_manifest() {
+        let pipeline = Pipeline::from_toml(
+            r#"
+            output = "out.gif"
+
+            [[ops]]
+            op = "trim"
+            end = 2
+
+            [[ops]]
+            op = "crop"
+            left = 1
+            top = 0
+            width = 1
+            height = 1
+
+            [[ops]]
+            op = "loop"
+            count = 0
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(
+            pipeline.ops[0],
+            Step::Op(Op::Trim {
+                start: 0,
+                end: Some(2)
+            })
+        ));
+
+        let mut animation = three_frames();
+        pipeline.run(&mut animation).unwrap();
+        let gif = animation.encode(Vec::new()).unwrap();
+        let mut animation = Animation::decode(gif.as_slice()).unwrap();
+
+        assert_eq!((animation.width(), animation.height()), (1, 1));
+        assert_eq!(animation.loop_count(), Some(LoopCount::Infinite));
+        let frames: Vec<&[u8]> = animation.frames().iter().map(Frame::indicies).collect();
+        assert_eq!(frames, [[1], [0]]);
+
+        assert!(Pipeline::from_toml("[[ops]]\nop = \"sharpen\"").is_err());
+        assert!(
+            Pipeline::from_toml("[[ops]]\nop = \"speed\"\nfactor = -1.0")
+                .unwrap()
+                .run(&mut animation)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn runs_registered_transforms() {
+        #[derive(Deserialize)]
+        struct LeftColumns {
+            columns: u16,
+        }
+
+        impl Transform for LeftColumns {
+            fn apply(&self, animation: &mut Animation) -> Result<()> {
+                animation.crop(Rect::new(0, 0, self.columns, animation.height()));
+                Ok(())
+            }
+        }
+
+        let mut transforms = Transforms::new();
+        transforms
+            .register("left_columns", |table: Table| {
+                Ok(Box::new(table.try_into::<LeftColumns>()?) as Box<dyn Transform>)
+            })
+            .unwrap();
+        assert!(transforms
+            .register("left_columns", |_| unreachable!())
+            .is_err());
+        assert!(transforms.register("crop", |_| unreachable!()).is_err());
+
+        let manifest =
+            "[[ops]]\nop = \"left_columns\"\ncolumns = 1\n\n[[ops]]\nop = \"trim\"\nstart = 1";
+        let pipeline = Pipeline::from_toml_with(manifest, &transforms).unwrap();
+        let mut animation = three_frames();
+        pipeline.run(&mut animation).unwrap();
+
+        let gif = animation.encode(Vec::new()).unwrap();
+        let animation = Animation::decode(gif.as_slice()).unwrap();
+        assert_eq!((animation.width(), animation.height()), (1, 1));
+        let frames: Vec<Vec<u8>> = animation.composited_frames().collect();
+        assert_eq!(frames, [[0, 0, 255, 255], [0, 0, 255, 255]]);
+
+        // without the registration the op is unknown, and bad fields are reported
+        assert!(Pipeline::from_toml(manifest).is_err());
+        let bad = "[[ops]]\nop = \"left_columns\"\ncolumns = \"one\"";
+        assert!(Pipeline::from_toml_with(bad, &transforms).is_err());
+    }
+}
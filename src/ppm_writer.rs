@@ -1,45 +1,118 @@
-use anyhow::Result;
-use std::fs::File;
-use std::io::{prelude::*, BufWriter};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use thiserror::Error;
 
-const MAGIC_NUMBER: &[u8] = b"P3";
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use crate::parser::PaletteIndexPolicy;
 
+const BINARY_MAGIC_NUMBER: &[u8] = b"P6";
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+#[derive(Error, Debug)]
+enum PpmError {
+    #[error("index {0} is past the end of the {1} color palette")]
+    IndexOutOfRange(u8, usize),
+}
+
+/// Writes `indexes` as a plain text (P3) PPM, looking their colors up in `color_table` and
+/// following `index_policy` for indexes past its end.
+// writing files needs a file system, which wasm32-unknown-unknown doesn't have
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 pub fn write_ppm(
     filename: &str,
     width: u16,
     height: u16,
     indexes: &[u8],
     color_table: &[u8],
-) -> Result<()> {
-    let file = File::create(filename)?;
-    //println!("writing {filename}");
-
-    let mut writer = BufWriter::new(&file);
-
-    writer.write(MAGIC_NUMBER)?;
-    writer.write(b"\n")?;
-    writer.write(format!("{} {}", width, height).as_bytes())?;
-    writer.write(b" 255")?;
-    writer.write(b"\n")?;
-
-    for index_row in indexes.chunks(width as usize) {
-        index_row
-            .iter()
-            .enumerate()
-            .try_for_each(|(i, idx)| -> Result<()> {
-                let color_idx = (*idx as usize) * 3;
-                let red = color_table.get(color_idx).unwrap();
-                let green = color_table.get(color_idx + 1).unwrap();
-                let blue = color_table.get(color_idx + 2).unwrap();
-
-                writer.write(format!("{: >3} {: >3} {: >3}", red, green, blue).as_bytes())?;
-                if i != (width - 1).into() {
-                    writer.write(b" ")?;
-                }
-                Ok(())
-            })?;
-        writer.write(b"\n")?;
+    index_policy: PaletteIndexPolicy,
+    options: crate::output::OutputOptions,
+) -> anyhow::Result<()> {
+    use anyhow::Result;
+    use std::io::prelude::*;
+
+    use crate::output::write_atomically;
+
+    const MAGIC_NUMBER: &[u8] = b"P3";
+
+    write_atomically(filename, options, |writer| {
+        writer.write_all(MAGIC_NUMBER)?;
+        writer.write_all(b"\n")?;
+        writer.write_all(format!("{} {}", width, height).as_bytes())?;
+        writer.write_all(b" 255")?;
+        writer.write_all(b"\n")?;
+
+        for index_row in indexes.chunks(width as usize) {
+            index_row
+                .iter()
+                .enumerate()
+                .try_for_each(|(i, idx)| -> Result<()> {
+                    // PPM has no alpha channel, so pixels mapped to transparent come out black
+                    let [red, green, blue, _] = index_policy
+                        .rgba(color_table, *idx)
+                        .ok_or(PpmError::IndexOutOfRange(*idx, color_table.len() / 3))?;
+
+                    writer.write_all(format!("{: >3} {: >3} {: >3}", red, green, blue).as_bytes())?;
+                    if i != (width - 1).into() {
+                        writer.write_all(b" ")?;
+                    }
+                    Ok(())
+                })?;
+            writer.write_all(b"\n")?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Encodes RGBA pixels as a binary PPM in memory, dropping the alpha channel.
+pub fn encode_ppm(width: u16, height: u16, rgba: &[u8]) -> Vec<u8> {
+    let header = format!("\n{} {} 255\n", width, height);
+
+    let mut ppm = Vec::with_capacity(BINARY_MAGIC_NUMBER.len() + header.len() + rgba.len() / 4 * 3);
+    ppm.extend_from_slice(BINARY_MAGIC_NUMBER);
+    ppm.extend_from_slice(header.as_bytes());
+    for pixel in rgba.chunks_exact(4) {
+        ppm.extend_from_slice(&pixel[..3]);
     }
+    ppm
+}
+
+#[cfg(all(test, not(all(target_arch = "wasm32", target_os = "unknown"))))]
+mod tests {
+    use super::{encode_ppm, write_ppm};
+    use crate::output::OutputOptions;
+    use crate::parser::PaletteIndexPolicy;
+
+    use std::fs;
 
-    Ok(())
+    #[test]
+    fn follows_the_palette_index_policy() {
+        let dir = std::env::temp_dir().join(format!("jif-ppm-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.ppm");
+        let filename = path.to_str().unwrap();
+        let palette = [255, 0, 0, 0, 0, 255];
+        let options = OutputOptions::default();
+        let write = |policy| write_ppm(filename, 3, 1, &[0, 1, 7], &palette, policy, options);
+
+        write(PaletteIndexPolicy::ClampToLast).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "P3\n3 1 255\n255   0   0   0   0 255   0   0 255\n"
+        );
+
+        // nothing is written when an index can't be shown
+        fs::remove_file(&path).unwrap();
+        let err = write(PaletteIndexPolicy::Error).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "index 7 is past the end of the 2 color palette"
+        );
+        assert!(!path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            encode_ppm(1, 1, &[1, 2, 3, 0]),
+            b"P6\n1 1 255\n\x01\x02\x03"
+        );
+    }
 }
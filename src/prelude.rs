@@ -0,0 +1,13 @@
+//! The types most programs using jif need, to glob import in one line:
+//!
+//! ```
+//! use jif::prelude::*;
+//! ```
+
+pub use crate::animation::{Animation, GifBuilder};
+pub use crate::compositor::Rect;
+pub use crate::edit::{Edit, EditList};
+pub use crate::encoder::Encoder;
+pub use crate::export::ImageFormat;
+pub use crate::parser::{DecodeOptions, Decoder, DisposalMethod, Frame, LoopCount};
+pub use crate::pipeline::{Pipeline, Transform, Transforms};
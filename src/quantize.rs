@@ -0,0 +1,380 @@
+//! Reducing truecolor images to a palette of at most 256 colors, so they can be stored in a
+//! GIF frame.
+
+use std::collections::HashMap;
+
+/// An image as palette indicies, ready for [`Frame::new`](crate::parser::Frame::new).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quantized {
+    /// RGB triplets.
+    pub palette: Box<[u8]>,
+    pub indicies: Box<[u8]>,
+    /// The index of transparent pixels, after every color of the palette, if there are any.
+    pub transparent_index: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuantizeOptions {
+    pub dither: DitherMode,
+}
+
+/// How pixels are mapped to a palette that doesn't have their exact color.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DitherMode {
+    /// Every pixel becomes the color that stands in for it, which leaves bands in gradients.
+    #[default]
+    None,
+    /// Floyd–Steinberg error diffusion: what each pixel is off by is spread over the pixels to
+    /// the right and below it, so areas average out to the right color.
+    FloydSteinberg,
+    /// A 4x4 Bayer matrix nudges pixels towards neighbouring colors in a fixed pattern. It's
+    /// coarser, but the pattern stays put between frames, which keeps frames that barely
+    /// change small.
+    Ordered,
+}
+
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+// colors with how many pixels have them
+struct ColorBox {
+    colors: Vec<([u8; 3], u32)>,
+}
+
+impl ColorBox {
+    // the channel with the widest range of values, and how wide it is
+    fn widest_channel(&self) -> (usize, u8) {
+        (0..3)
+            .map(|channel| {
+                let values = self.colors.iter().map(|(color, _)| color[channel]);
+                let min = values.clone().min().unwrap_or(0);
+                let max = values.max().unwrap_or(0);
+                (channel, max - min)
+            })
+            .max_by_key(|&(_, range)| range)
+            .unwrap_or((0, 0))
+    }
+
+    fn pixel_count(&self) -> u64 {
+        self.colors.iter().map(|&(_, count)| count as u64).sum()
+    }
+
+    // splits at the median pixel along the widest channel, so both halves cover about as many
+    // pixels
+    fn split(mut self) -> (ColorBox, ColorBox) {
+        let (channel, _) = self.widest_channel();
+        self.colors
+            .sort_unstable_by_key(|(color, _)| color[channel]);
+
+        let half = self.pixel_count().div_ceil(2);
+        let mut seen = 0;
+        let mut middle = self
+            .colors
+            .iter()
+            .position(|&(_, count)| {
+                seen += count as u64;
+                seen >= half
+            })
+            .unwrap_or(0)
+            + 1;
+        // both halves need a color
+        middle = middle.clamp(1, self.colors.len() - 1);
+
+        let upper = self.colors.split_off(middle);
+        (self, ColorBox { colors: upper })
+    }
+
+    // the average color of every pixel in the box
+    fn average(&self) -> [u8; 3] {
+        let pixels = self.pixel_count().max(1);
+        let mut sums = [0_u64; 3];
+        for &(color, count) in &self.colors {
+            for channel in 0..3 {
+                sums[channel] += color[channel] as u64 * count as u64;
+            }
+        }
+        sums.map(|sum| ((sum + pixels / 2) / pixels) as u8)
+    }
+}
+
+/// Picks at most `max_colors` colors for `rgba` by median cut: the colors are split into
+/// boxes along their widest channel until there are enough boxes, and each box becomes the
+/// average of its pixels. Images that already have few enough colors keep them exactly.
+///
+/// Pixels that are less than half opaque become transparent, and take up one of the
+/// `max_colors`. `max_colors` is clamped to 2..=256.
+pub fn median_cut(rgba: &[u8], max_colors: usize) -> Quantized {
+    let width = (rgba.len() / 4).min(u16::MAX as usize) as u16;
+    median_cut_with_options(rgba, width, max_colors, QuantizeOptions::default())
+}
+
+/// [`median_cut`] for an image `width` pixels wide, dithered as `options` says when colors
+/// have to be left out.
+pub fn median_cut_with_options(
+    rgba: &[u8],
+    width: u16,
+    max_colors: usize,
+    options: QuantizeOptions,
+) -> Quantized {
+    let max_colors = max_colors.clamp(2, 256);
+    let is_transparent = |pixel: &[u8]| pixel[3] < 128;
+
+    // colors in the order they first appear, so exact palettes are stable
+    let mut counts: HashMap<[u8; 3], u32> = HashMap::new();
+    let mut order = Vec::new();
+    let mut has_transparency = false;
+    for pixel in rgba.chunks_exact(4) {
+        if is_transparent(pixel) {
+            has_transparency = true;
+            continue;
+        }
+        let color = [pixel[0], pixel[1], pixel[2]];
+        *counts.entry(color).or_insert_with(|| {
+            order.push(color);
+            0
+        }) += 1;
+    }
+
+    let color_budget = max_colors - has_transparency as usize;
+    let mut palette: Vec<[u8; 3]> = Vec::new();
+    let mut color_indicies: HashMap<[u8; 3], u8> = HashMap::with_capacity(counts.len());
+
+    if counts.len() <= color_budget {
+        for (index, color) in order.into_iter().enumerate() {
+            color_indicies.insert(color, index as u8);
+            palette.push(color);
+        }
+    } else {
+        let mut boxes = vec![ColorBox {
+            colors: order
+                .into_iter()
+                .map(|color| (color, counts[&color]))
+                .collect(),
+        }];
+        while boxes.len() < color_budget {
+            // the box with the widest spread, among the ones with more than one color
+            let widest = boxes
+                .iter()
+                .enumerate()
+                .filter(|(_, color_box)| color_box.colors.len() > 1)
+                .max_by_key(|(_, color_box)| color_box.widest_channel().1)
+                .map(|(index, _)| index);
+            let Some(widest) = widest else {
+                break;
+            };
+
+            let (lower, upper) = boxes.swap_remove(widest).split();
+            boxes.push(lower);
+            boxes.push(upper);
+        }
+
+        for (index, color_box) in boxes.iter().enumerate() {
+            palette.push(color_box.average());
+            for &(color, _) in &color_box.colors {
+                color_indicies.insert(color, index as u8);
+            }
+        }
+    }
+
+    let reduced = palette.len() < counts.len();
+    let indicies: Vec<Option<u8>> = match options.dither {
+        DitherMode::FloydSteinberg if reduced => {
+            floyd_steinberg(rgba, width, &palette, is_transparent)
+        }
+        DitherMode::Ordered if reduced => ordered(rgba, width, &palette, is_transparent),
+        _ => rgba
+            .chunks_exact(4)
+            .map(|pixel| {
+                (!is_transparent(pixel)).then(|| color_indicies[&[pixel[0], pixel[1], pixel[2]]])
+            })
+            .collect(),
+    };
+
+    let transparent_index = has_transparency.then_some(palette.len() as u8);
+    if has_transparency {
+        palette.push([0, 0, 0]);
+    }
+
+    Quantized {
+        palette: palette.into_iter().flatten().collect(),
+        indicies: indicies
+            .into_iter()
+            .map(|index| index.or(transparent_index).unwrap_or(0))
+            .collect(),
+        transparent_index,
+    }
+}
+
+fn nearest(palette: &[[u8; 3]], color: [f32; 3]) -> u8 {
+    let distance = |entry: &[u8; 3]| -> f32 {
+        (0..3)
+            .map(|channel| (entry[channel] as f32 - color[channel]).powi(2))
+            .sum()
+    };
+    (0..palette.len())
+        .min_by(|&a, &b| distance(&palette[a]).total_cmp(&distance(&palette[b])))
+        .unwrap_or(0) as u8
+}
+
+fn floyd_steinberg(
+    rgba: &[u8],
+    width: u16,
+    palette: &[[u8; 3]],
+    is_transparent: impl Fn(&[u8]) -> bool,
+) -> Vec<Option<u8>> {
+    let width = (width as usize).max(1);
+    // what's carried over to this row and the next one, with a pixel of slack on either side
+    let mut errors = vec![[0.0_f32; 3]; width + 2];
+    let mut next_errors = vec![[0.0_f32; 3]; width + 2];
+    let mut indicies = Vec::with_capacity(rgba.len() / 4);
+
+    for row in rgba.chunks(width * 4) {
+        for (x, pixel) in row.chunks_exact(4).enumerate() {
+            // transparent pixels don't pass any error on
+            if is_transparent(pixel) {
+                indicies.push(None);
+                continue;
+            }
+
+            let target: [f32; 3] = std::array::from_fn(|channel| {
+                (pixel[channel] as f32 + errors[x + 1][channel]).clamp(0.0, 255.0)
+            });
+            let index = nearest(palette, target);
+            indicies.push(Some(index));
+
+            for channel in 0..3 {
+                let error = target[channel] - palette[index as usize][channel] as f32;
+                errors[x + 2][channel] += error * 7.0 / 16.0;
+                next_errors[x][channel] += error * 3.0 / 16.0;
+                next_errors[x + 1][channel] += error * 5.0 / 16.0;
+                next_errors[x + 2][channel] += error / 16.0;
+            }
+        }
+
+        std::mem::swap(&mut errors, &mut next_errors);
+        next_errors.fill([0.0; 3]);
+    }
+
+    indicies
+}
+
+fn ordered(
+    rgba: &[u8],
+    width: u16,
+    palette: &[[u8; 3]],
+    is_transparent: impl Fn(&[u8]) -> bool,
+) -> Vec<Option<u8>> {
+    let width = (width as usize).max(1);
+    // how far apart neighbouring colors are on average, per channel
+    let distance = |a: &[u8; 3], b: &[u8; 3]| -> f32 {
+        (0..3)
+            .map(|channel| (a[channel] as f32 - b[channel] as f32).powi(2))
+            .sum::<f32>()
+            .sqrt()
+    };
+    let spread = palette
+        .iter()
+        .map(|color| {
+            palette
+                .iter()
+                .filter(|other| *other != color)
+                .map(|other| distance(color, other))
+                .fold(f32::INFINITY, f32::min)
+        })
+        .filter(|distance| distance.is_finite())
+        .sum::<f32>()
+        / palette.len() as f32
+        / 3.0_f32.sqrt();
+
+    rgba.chunks_exact(4)
+        .enumerate()
+        .map(|(pixel_index, pixel)| {
+            if is_transparent(pixel) {
+                return None;
+            }
+            let (x, y) = (pixel_index % width, pixel_index / width);
+            let offset = ((BAYER[y % 4][x % 4] as f32 + 0.5) / 16.0 - 0.5) * spread;
+            let target = std::array::from_fn(|channel| pixel[channel] as f32 + offset);
+            Some(nearest(palette, target))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{median_cut, median_cut_with_options, DitherMode, QuantizeOptions};
+
+    #[test]
+    fn keeps_images_with_few_colors_exact() {
+        let rgba = [
+            [10, 20, 30, 255],
+            [0, 0, 0, 0],
+            [40, 50, 60, 200],
+            [10, 20, 30, 255],
+        ]
+        .concat();
+
+        let quantized = median_cut(&rgba, 256);
+        assert_eq!(*quantized.palette, [10, 20, 30, 40, 50, 60, 0, 0, 0]);
+        assert_eq!(*quantized.indicies, [0, 2, 1, 0]);
+        assert_eq!(quantized.transparent_index, Some(2));
+    }
+
+    #[test]
+    fn reduces_to_at_most_max_colors() {
+        // a gradient from black to red, and a few blue pixels
+        let mut rgba: Vec<u8> = (0..=255).flat_map(|red| [red, 0, 0, 255]).collect();
+        rgba.extend([0, 0, 255, 255].repeat(4));
+
+        let quantized = median_cut(&rgba, 16);
+        assert_eq!(quantized.palette.len(), 16 * 3);
+        assert_eq!(quantized.transparent_index, None);
+
+        let color = |index: u8| {
+            let start = index as usize * 3;
+            &quantized.palette[start..start + 3]
+        };
+        for (pixel, &index) in rgba.chunks_exact(4).zip(quantized.indicies.iter()) {
+            let error = pixel[..3]
+                .iter()
+                .zip(color(index))
+                .map(|(&a, &b)| a.abs_diff(b))
+                .max()
+                .unwrap();
+            assert!(error <= 16, "{pixel:?} became {:?}", color(index));
+        }
+        // the blue pixels get a color of their own, even though there are only a few
+        assert_eq!(color(*quantized.indicies.last().unwrap()), [0, 0, 255]);
+    }
+
+    #[test]
+    fn dithers_gradients() {
+        // a 64x4 gray gradient reduced to 4 colors
+        let rgba: Vec<u8> = (0..4)
+            .flat_map(|_| (0..64).flat_map(|x| [x * 4, x * 4, x * 4, 255]))
+            .collect();
+
+        // how far the average of each 4x4 block is from the original
+        let block_error = |dither| {
+            let quantized = median_cut_with_options(&rgba, 64, 4, QuantizeOptions { dither });
+            let error: f64 = (0..16)
+                .map(|block| {
+                    let (mut original, mut encoded) = (0.0, 0.0);
+                    for y in 0..4 {
+                        for x in block * 4..block * 4 + 4 {
+                            let pixel = y * 64 + x;
+                            original += rgba[pixel * 4] as f64;
+                            encoded +=
+                                quantized.palette[quantized.indicies[pixel] as usize * 3] as f64;
+                        }
+                    }
+                    (original - encoded).abs() / 16.0
+                })
+                .sum();
+            error / 16.0
+        };
+
+        let banded = block_error(DitherMode::None);
+        assert!(block_error(DitherMode::FloydSteinberg) < banded / 2.0);
+        assert!(block_error(DitherMode::Ordered) < banded / 2.0);
+    }
+}
@@ -0,0 +1,154 @@
+//! Writes every frame the viewer presents to a directory, with a manifest of when each one was
+//! presented, for checking that playback keeps to the delays the GIF asks for. `--headless`
+//! records the same way, with the times frames would be presented at.
+
+use anyhow::Result;
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use jif::export::{ImageFormat, Png};
+use jif::output::{write_atomically, OutputOptions};
+
+/// The manifest written next to the frames, one line per presented frame.
+pub const MANIFEST: &str = "timing.csv";
+
+struct Image {
+    path: PathBuf,
+    width: u16,
+    height: u16,
+    rgba: Vec<u8>,
+}
+
+/// Frames are encoded and written on a thread of their own, so recording doesn't hold up the
+/// playback it's timing.
+pub struct Recorder {
+    dir: PathBuf,
+    manifest: BufWriter<File>,
+    // how many frames have been recorded, frames are numbered in the order they were presented
+    recorded: usize,
+    images: Option<Sender<Image>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl Recorder {
+    /// Starts recording to `dir`, which is created if it doesn't exist.
+    pub fn create(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let mut manifest = BufWriter::new(File::create(dir.join(MANIFEST))?);
+        writeln!(manifest, "image,gif,frame,delay_ms,presented_ms")?;
+
+        let (images, received) = mpsc::channel::<Image>();
+        let writer = thread::spawn(move || {
+            for image in received {
+                let png = Png::encode(image.width, image.height, &image.rgba);
+                let written = write_atomically(&image.path, OutputOptions::default(), |file| {
+                    Ok(file.write_all(&png)?)
+                });
+                if let Err(err) = written {
+                    log::error!("couldn't record {}: {}", image.path.display(), err);
+                }
+            }
+        });
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            manifest,
+            recorded: 0,
+            images: Some(images),
+            writer: Some(writer),
+        })
+    }
+
+    /// Records `rgba`, which shows frame `index` of `gif` for `delay`, as presented `presented`
+    /// after playback started.
+    pub fn record(
+        &mut self,
+        (width, height): (u16, u16),
+        rgba: Vec<u8>,
+        gif: &str,
+        index: usize,
+        delay: Duration,
+        presented: Duration,
+    ) -> Result<()> {
+        let name = format!("{:06}.{}", self.recorded, Png::EXTENSION);
+        writeln!(
+            self.manifest,
+            "{},{},{},{:.3},{:.3}",
+            name,
+            escape(gif),
+            index,
+            delay.as_secs_f64() * 1000.0,
+            presented.as_secs_f64() * 1000.0
+        )?;
+        self.recorded += 1;
+
+        let image = Image {
+            path: self.dir.join(name),
+            width,
+            height,
+            rgba,
+        };
+        if let Some(images) = &self.images {
+            images.send(image)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    // the frames that are still queued are written before the viewer exits
+    fn drop(&mut self) {
+        let _ = self.manifest.flush();
+        self.images.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+// quotes a field of the manifest if it needs it
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, Recorder, MANIFEST};
+
+    use std::fs;
+    use std::time::Duration;
+
+    #[test]
+    fn writes_frames_and_timings() {
+        let dir = std::env::temp_dir().join(format!("jif-record-{}", std::process::id()));
+        let mut recorder = Recorder::create(&dir).unwrap();
+        for (index, presented) in [0, 100, 210].into_iter().enumerate() {
+            let rgba = vec![index as u8; 2 * 4];
+            let delay = Duration::from_millis(100);
+            let presented = Duration::from_millis(presented);
+            recorder
+                .record((2, 1), rgba, "a, b.gif", index, delay, presented)
+                .unwrap();
+        }
+        drop(recorder);
+
+        let manifest = fs::read_to_string(dir.join(MANIFEST)).unwrap();
+        let lines: Vec<&str> = manifest.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[3], "000002.png,\"a, b.gif\",2,100.000,210.000");
+        assert!(dir.join("000002.png").exists());
+        fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(escape("cat.gif"), "cat.gif");
+        assert_eq!(escape("say \"hi\".gif"), "\"say \"\"hi\"\".gif\"");
+    }
+}
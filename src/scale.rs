@@ -0,0 +1,207 @@
+//! Resizing images, either as palette indicies or as RGBA.
+
+/// How pixels are picked when an RGBA image is resized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    /// The source pixel closest to the middle of each new pixel. Fast and keeps hard edges, but
+    /// drops detail when scaling down.
+    Nearest,
+    /// The average of every source pixel under each new pixel, the best choice for scaling
+    /// down.
+    #[default]
+    Box,
+    /// A blend of the four source pixels around the middle of each new pixel, smoother than
+    /// nearest when scaling up.
+    Bilinear,
+}
+
+/// Fits `width` x `height` into a `max_size` x `max_size` square, keeping the aspect ratio.
+/// Sizes that already fit are left as they are, and neither side ends up smaller than 1.
+pub fn fit(width: u16, height: u16, max_size: u16) -> (u16, u16) {
+    let longest = width.max(height);
+    if longest <= max_size {
+        return (width, height);
+    }
+
+    let scale = |side: u16| {
+        ((side as u32 * max_size as u32 + longest as u32 / 2) / longest as u32).max(1) as u16
+    };
+    (scale(width), scale(height))
+}
+
+/// The size `width` x `height` pixels that are `pixel_aspect_ratio` times as wide as they are
+/// tall take up when shown with square pixels. Only ever stretched, never squashed, so no
+/// detail is lost.
+pub fn square_pixels(width: u16, height: u16, pixel_aspect_ratio: f32) -> (u16, u16) {
+    let stretch =
+        |side: u16, factor: f32| (side as f32 * factor).round().clamp(1.0, u16::MAX as f32) as u16;
+    if pixel_aspect_ratio >= 1.0 {
+        (stretch(width, pixel_aspect_ratio), height)
+    } else {
+        (width, stretch(height, 1.0 / pixel_aspect_ratio))
+    }
+}
+
+/// Resizes `width` x `height` palette indicies by picking the nearest one, which is the only
+/// way to resize them without needing new colors.
+pub fn resize_indexed(
+    indicies: &[u8],
+    width: u16,
+    height: u16,
+    new_width: u16,
+    new_height: u16,
+) -> Vec<u8> {
+    let mut resized = Vec::with_capacity(new_width as usize * new_height as usize);
+    for y in 0..new_height as usize {
+        let row = nearest(y, new_height, height) * width as usize;
+        for x in 0..new_width as usize {
+            resized.push(indicies[row + nearest(x, new_width, width)]);
+        }
+    }
+    resized
+}
+
+/// Resizes `width` x `height` pixels of RGBA data with `filter`. Colors are weighed by their
+/// alpha when they're mixed, so transparent pixels don't darken the edges of what's next to
+/// them.
+pub fn resize_rgba(
+    rgba: &[u8],
+    width: u16,
+    height: u16,
+    new_width: u16,
+    new_height: u16,
+    filter: Filter,
+) -> Vec<u8> {
+    if width == 0 || height == 0 {
+        return vec![0; new_width as usize * new_height as usize * 4];
+    }
+
+    let pixel = |x: usize, y: usize| {
+        let offset = (y * width as usize + x) * 4;
+        &rgba[offset..offset + 4]
+    };
+
+    let mut resized = Vec::with_capacity(new_width as usize * new_height as usize * 4);
+    for y in 0..new_height as usize {
+        for x in 0..new_width as usize {
+            match filter {
+                Filter::Nearest => resized.extend_from_slice(pixel(
+                    nearest(x, new_width, width),
+                    nearest(y, new_height, height),
+                )),
+                Filter::Box => {
+                    let mut mix = Mix::default();
+                    for source_y in span(y, new_height, height) {
+                        for source_x in span(x, new_width, width) {
+                            mix.add(pixel(source_x, source_y), 1.0);
+                        }
+                    }
+                    resized.extend_from_slice(&mix.finish());
+                }
+                Filter::Bilinear => {
+                    let (left, right, x_weight) = neighbours(x, new_width, width);
+                    let (top, bottom, y_weight) = neighbours(y, new_height, height);
+
+                    let mut mix = Mix::default();
+                    mix.add(pixel(left, top), (1.0 - x_weight) * (1.0 - y_weight));
+                    mix.add(pixel(right, top), x_weight * (1.0 - y_weight));
+                    mix.add(pixel(left, bottom), (1.0 - x_weight) * y_weight);
+                    mix.add(pixel(right, bottom), x_weight * y_weight);
+                    resized.extend_from_slice(&mix.finish());
+                }
+            }
+        }
+    }
+    resized
+}
+
+// the source pixel under the middle of pixel `index` of `size`
+fn nearest(index: usize, size: u16, source_size: u16) -> usize {
+    (2 * index + 1) * source_size as usize / (2 * size as usize)
+}
+
+// the source pixels under pixel `index` of `size`, at least one
+fn span(index: usize, size: u16, source_size: u16) -> std::ops::Range<usize> {
+    let start = index * source_size as usize / size as usize;
+    let end = ((index + 1) * source_size as usize / size as usize).max(start + 1);
+    start..end
+}
+
+// the two source pixels either side of the middle of pixel `index` of `size`, and how far
+// between them it is
+fn neighbours(index: usize, size: u16, source_size: u16) -> (usize, usize, f64) {
+    let last = source_size as usize - 1;
+    let center = ((index as f64 + 0.5) * source_size as f64 / size as f64 - 0.5).max(0.0);
+    let before = (center as usize).min(last);
+    let after = (before + 1).min(last);
+    (before, after, center - before as f64)
+}
+
+// a weighted average of pixels, with colors weighed by their alpha as well
+#[derive(Default)]
+struct Mix {
+    color: [f64; 3],
+    alpha: f64,
+    weight: f64,
+}
+
+impl Mix {
+    fn add(&mut self, pixel: &[u8], weight: f64) {
+        let alpha = pixel[3] as f64 * weight;
+        for (sum, &channel) in self.color.iter_mut().zip(pixel) {
+            *sum += channel as f64 * alpha;
+        }
+        self.alpha += alpha;
+        self.weight += weight;
+    }
+
+    fn finish(&self) -> [u8; 4] {
+        if self.alpha == 0.0 {
+            return [0; 4];
+        }
+        let [red, green, blue] = self.color.map(|sum| (sum / self.alpha).round() as u8);
+        [red, green, blue, (self.alpha / self.weight).round() as u8]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fit, resize_indexed, resize_rgba, square_pixels, Filter};
+
+    #[test]
+    fn resizes_indicies_and_rgba() {
+        assert_eq!(fit(400, 300, 256), (256, 192));
+        assert_eq!(fit(1000, 1, 10), (10, 1));
+        assert_eq!(fit(16, 16, 256), (16, 16));
+
+        assert_eq!(square_pixels(100, 50, 2.0), (200, 50));
+        assert_eq!(square_pixels(100, 50, 0.5), (100, 100));
+
+        let indicies = [0, 1, 2, 3, 4, 5, 6, 7];
+        assert_eq!(resize_indexed(&indicies, 4, 2, 2, 1), [5, 7]);
+        assert_eq!(resize_indexed(&[0, 1], 2, 1, 4, 1), [0, 0, 1, 1]);
+
+        // a transparent pixel next to an opaque one
+        let rgba = [0, 0, 0, 0, 200, 100, 50, 255];
+        assert_eq!(
+            resize_rgba(&rgba, 2, 1, 1, 1, Filter::Box),
+            [200, 100, 50, 128]
+        );
+        assert_eq!(
+            resize_rgba(&rgba, 2, 1, 1, 1, Filter::Nearest),
+            [200, 100, 50, 255]
+        );
+
+        let rgba = [0, 0, 0, 255, 200, 100, 50, 255];
+        assert_eq!(
+            resize_rgba(&rgba, 2, 1, 4, 1, Filter::Bilinear),
+            [
+                [0, 0, 0, 255],
+                [50, 25, 13, 255],
+                [150, 75, 38, 255],
+                [200, 100, 50, 255]
+            ]
+            .concat()
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! The progress bar along the bottom of the viewer, which can be clicked or dragged to seek.
+
+use std::ops::Range;
+
+use wgpu::{BindGroup, Buffer, Device, Queue, RenderPass, RenderPipeline, TextureFormat};
+
+// how tall the bar is drawn, in physical pixels
+const HEIGHT: f32 = 6.0;
+// how close to the bottom of the window a click has to be to grab the bar, in physical pixels
+const GRAB_HEIGHT: f64 = 24.0;
+
+pub struct ScrubBar {
+    pipeline: RenderPipeline,
+    bind_group: BindGroup,
+    uniforms: Buffer,
+    pub visible: bool,
+    // the left mouse button went down on the bar and hasn't been released yet
+    pub dragging: bool,
+}
+
+impl ScrubBar {
+    pub fn new(device: &Device, format: TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Scrub Bar"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("scrub_bar.wgsl").into()),
+        });
+
+        // progress and height, padded to the 16 bytes uniform buffers are laid out in
+        let uniforms = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Scrub Bar"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: None,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniforms.as_entire_binding(),
+            }],
+            label: None,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Scrub Bar"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            bind_group,
+            uniforms,
+            visible: true,
+            dragging: false,
+        }
+    }
+
+    /// Whether a click `y` pixels down a window `window_height` tall lands on the bar.
+    pub fn contains(&self, y: f64, window_height: u32) -> bool {
+        self.visible && y >= window_height as f64 - GRAB_HEIGHT
+    }
+
+    /// Draws the bar filled up to `progress`, from 0 to 1, over whatever the render pass has
+    /// drawn so far.
+    pub fn draw(
+        &self,
+        queue: &Queue,
+        render_pass: &mut RenderPass,
+        progress: f32,
+        window_height: u32,
+    ) {
+        if !self.visible {
+            return;
+        }
+
+        let height = 2.0 * HEIGHT / window_height.max(1) as f32;
+        let mut uniforms = [0; 16];
+        uniforms[..4].copy_from_slice(&progress.to_ne_bytes());
+        uniforms[4..8].copy_from_slice(&height.to_ne_bytes());
+        queue.write_buffer(&self.uniforms, 0, &uniforms);
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..6, 0..1);
+    }
+}
+
+/// The frame of `frames` under `x` pixels across a window `window_width` wide.
+pub fn frame_at_position(x: f64, window_width: u32, frames: &Range<usize>) -> usize {
+    let along = (x / window_width.max(1) as f64).clamp(0.0, 1.0);
+    let offset = (along * frames.len() as f64) as usize;
+    (frames.start + offset)
+        .min(frames.end.saturating_sub(1))
+        .max(frames.start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::frame_at_position;
+
+    #[test]
+    fn maps_clicks_to_frames() {
+        assert_eq!(frame_at_position(0.0, 100, &(0..10)), 0);
+        assert_eq!(frame_at_position(55.0, 100, &(0..10)), 5);
+        assert_eq!(frame_at_position(100.0, 100, &(0..10)), 9);
+        // dragging past the edges of the window
+        assert_eq!(frame_at_position(-20.0, 100, &(4..8)), 4);
+        assert_eq!(frame_at_position(250.0, 100, &(4..8)), 7);
+    }
+}
@@ -0,0 +1,44 @@
+struct ScrubBar {
+  // how much of the bar is filled, from 0 to 1
+  progress: f32,
+  // how tall the bar is, in clip space
+  height: f32,
+  padding: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> bar: ScrubBar;
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  // from 0 at the left of the bar to 1 at the right
+  @location(0) along: f32,
+}
+
+@vertex
+fn vs_main(
+  @builtin(vertex_index) in_vertex_index: u32,
+) -> VertexOutput {
+  var corners = array<vec2<f32>, 6>(
+    vec2(0.0, 1.0),
+    vec2(1.0, 1.0),
+    vec2(1.0, 0.0),
+    vec2(0.0, 1.0),
+    vec2(1.0, 0.0),
+    vec2(0.0, 0.0),
+  );
+  let corner = corners[in_vertex_index];
+
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(corner.x * 2.0 - 1.0, corner.y * bar.height - 1.0, 0.0, 1.0);
+  out.along = corner.x;
+  return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  if in.along <= bar.progress {
+    return vec4<f32>(1.0, 1.0, 1.0, 0.8);
+  }
+  return vec4<f32>(0.0, 0.0, 0.0, 0.5);
+}
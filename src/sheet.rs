@@ -0,0 +1,132 @@
+//! Packs every composited frame of an animation into one image, laid out left to right and top
+//! to bottom on a grid, for engines and tools that animate by moving a rect over a texture.
+
+use serde::Serialize;
+
+use crate::animation::Animation;
+
+/// Where one frame sits on a sprite sheet, in pixels, and how long it's shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SheetFrame {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Hundredths of a second, as in the GIF.
+    pub delay_time: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpriteSheet {
+    pub width: u32,
+    pub height: u32,
+    pub columns: u32,
+    pub rows: u32,
+    /// `width` x `height` pixels of RGBA data. Cells after the last frame are transparent.
+    pub rgba: Vec<u8>,
+    pub frames: Vec<SheetFrame>,
+}
+
+impl SpriteSheet {
+    /// Lays the frames of `animation` out in rows of `columns`, or a single row if there are
+    /// fewer frames than that.
+    pub fn new(animation: &Animation, columns: u32) -> Self {
+        let frame_count = animation.frames().len() as u32;
+        let columns = columns.min(frame_count).max(1);
+        let rows = frame_count.div_ceil(columns).max(1);
+
+        let (frame_width, frame_height) = (animation.width() as u32, animation.height() as u32);
+        let width = frame_width * columns;
+        let height = frame_height * rows;
+        let mut rgba = vec![0; width as usize * height as usize * 4];
+        let mut frames = Vec::with_capacity(frame_count as usize);
+
+        let row_length = frame_width as usize * 4;
+        for (index, (canvas, frame)) in animation
+            .composited_frames()
+            .zip(animation.frames())
+            .enumerate()
+        {
+            let index = index as u32;
+            let (x, y) = (
+                (index % columns) * frame_width,
+                (index / columns) * frame_height,
+            );
+
+            for (row, pixels) in canvas.chunks_exact(row_length).enumerate() {
+                let start = ((y as usize + row) * width as usize + x as usize) * 4;
+                rgba[start..start + row_length].copy_from_slice(pixels);
+            }
+
+            frames.push(SheetFrame {
+                x,
+                y,
+                width: frame_width,
+                height: frame_height,
+                delay_time: frame.delay_time,
+            });
+        }
+
+        Self {
+            width,
+            height,
+            columns,
+            rows,
+            rgba,
+            frames,
+        }
+    }
+
+    /// Lays the frames out on a grid about as wide as it's tall in frames, which keeps long
+    /// animations within the texture size limits of most GPUs.
+    pub fn square(animation: &Animation) -> Self {
+        let columns = (animation.frames().len() as f64).sqrt().ceil() as u32;
+        Self::new(animation, columns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SheetFrame, SpriteSheet};
+    use crate::animation::Animation;
+    use crate::fixtures::{two_color_frame, two_color_gif};
+
+    #[test]
+    fn lays_frames_out_on_a_grid() {
+        let frames = [(10, [0, 1]), (20, [1, 0]), (30, [1, 1])].map(|(delay_time, indicies)| {
+            let mut frame = two_color_frame(2, 1, &indicies);
+            frame.delay_time = delay_time;
+            frame
+        });
+        let animation = Animation::decode(two_color_gif(2, 1, &frames).as_slice()).unwrap();
+
+        let sheet = SpriteSheet::new(&animation, 2);
+        assert_eq!((sheet.width, sheet.height), (4, 2));
+        assert_eq!((sheet.columns, sheet.rows), (2, 2));
+        assert_eq!(
+            sheet.frames[2],
+            SheetFrame {
+                x: 0,
+                y: 1,
+                width: 2,
+                height: 1,
+                delay_time: 30
+            }
+        );
+
+        let (red, blue, clear) = ([255, 0, 0, 255], [0, 0, 255, 255], [0; 4]);
+        let pixels: Vec<[u8; 4]> = sheet
+            .rgba
+            .chunks_exact(4)
+            .map(|pixel| pixel.try_into().unwrap())
+            .collect();
+        assert_eq!(pixels, [red, blue, blue, red, blue, blue, clear, clear]);
+
+        // more columns than frames is a single row
+        let sheet = SpriteSheet::new(&animation, 8);
+        assert_eq!((sheet.width, sheet.height, sheet.rows), (6, 1, 1));
+
+        let sheet = SpriteSheet::square(&animation);
+        assert_eq!((sheet.columns, sheet.rows), (2, 2));
+    }
+}
@@ -0,0 +1,96 @@
+//! The structural similarity index (SSIM) of two frames, a measure of how alike they look
+//! that follows human perception better than counting changed pixels.
+
+const WINDOW: usize = 8;
+// stabilize the division for windows that are flat or dark, from the SSIM paper
+const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+/// The mean SSIM of two `width` x `height` RGBA images, compared by luma over 8x8 windows.
+/// 1.0 means they're identical, and the value falls towards 0 as they look less alike.
+/// Transparent pixels count as black.
+pub fn ssim(width: u16, height: u16, a: &[u8], b: &[u8]) -> f64 {
+    let (width, height) = (width as usize, height as usize);
+    let a = luma(a);
+    let b = luma(b);
+
+    let mut total = 0.0;
+    let mut windows = 0;
+    for top in (0..height).step_by(WINDOW) {
+        for left in (0..width).step_by(WINDOW) {
+            let bottom = (top + WINDOW).min(height);
+            let right = (left + WINDOW).min(width);
+            let count = ((bottom - top) * (right - left)) as f64;
+
+            let (mut sum_a, mut sum_b) = (0.0, 0.0);
+            let (mut sum_aa, mut sum_bb, mut sum_ab) = (0.0, 0.0, 0.0);
+            for y in top..bottom {
+                for x in left..right {
+                    let (a, b) = (a[y * width + x], b[y * width + x]);
+                    sum_a += a;
+                    sum_b += b;
+                    sum_aa += a * a;
+                    sum_bb += b * b;
+                    sum_ab += a * b;
+                }
+            }
+
+            let (mean_a, mean_b) = (sum_a / count, sum_b / count);
+            let variance_a = sum_aa / count - mean_a * mean_a;
+            let variance_b = sum_bb / count - mean_b * mean_b;
+            let covariance = sum_ab / count - mean_a * mean_b;
+
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (variance_a + variance_b + C2));
+            windows += 1;
+        }
+    }
+
+    if windows == 0 {
+        return 1.0;
+    }
+    total / windows as f64
+}
+
+fn luma(rgba: &[u8]) -> Vec<f64> {
+    rgba.chunks_exact(4)
+        .map(|pixel| {
+            let alpha = pixel[3] as f64 / 255.0;
+            (0.299 * pixel[0] as f64 + 0.587 * pixel[1] as f64 + 0.114 * pixel[2] as f64) * alpha
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ssim;
+
+    // a 16x16 diagonal gradient
+    fn gradient() -> Vec<u8> {
+        (0..16 * 16)
+            .flat_map(|pixel| {
+                let value = ((pixel % 16 + pixel / 16) * 8) as u8;
+                [value, value, value, 255]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn measures_how_alike_frames_look() {
+        let original = gradient();
+        assert_eq!(ssim(16, 16, &original, &original), 1.0);
+
+        let mut nudged = original.clone();
+        nudged[0] += 2;
+        let nudged = ssim(16, 16, &original, &nudged);
+        assert!(nudged > 0.99 && nudged < 1.0);
+
+        let inverted: Vec<u8> = original
+            .chunks_exact(4)
+            .flat_map(|pixel| [255 - pixel[0], 255 - pixel[1], 255 - pixel[2], 255])
+            .collect();
+        assert!(ssim(16, 16, &original, &inverted) < 0.5);
+
+        assert_eq!(ssim(0, 0, &[], &[]), 1.0);
+    }
+}
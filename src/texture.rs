@@ -0,0 +1,238 @@
+//! What the viewer puts in its texture, worked out on the CPU. The window uploads it to the GPU
+//! and `--headless` keeps it in memory, so both end up with the same pixels.
+
+use wgpu::TextureFormat;
+
+use jif::compositor::{Compositor, Rect};
+use jif::parser::Frame;
+
+/// Byte order of the texture frames are uploaded to. It follows the surface so colors come out
+/// right without swizzling every pixel on the way to the GPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelOrder {
+    Rgba,
+    Bgra,
+}
+
+impl ChannelOrder {
+    pub fn for_surface(surface_format: TextureFormat) -> (Self, TextureFormat) {
+        match surface_format {
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => {
+                (ChannelOrder::Bgra, surface_format)
+            }
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => {
+                (ChannelOrder::Rgba, surface_format)
+            }
+            // float surfaces are linear, like srgb ones after the hardware has converted them
+            format if format.is_srgb() || format == TextureFormat::Rgba16Float => {
+                (ChannelOrder::Rgba, TextureFormat::Rgba8UnormSrgb)
+            }
+            // the surface expects values that are already srgb encoded, so pass them through
+            _ => (ChannelOrder::Rgba, TextureFormat::Rgba8Unorm),
+        }
+    }
+}
+
+/// Pixels for a rectangle of the texture, in its channel order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextureWrite {
+    pub left: u16,
+    pub top: u16,
+    pub width: u16,
+    pub height: u16,
+    pub pixels: Vec<u8>,
+}
+
+impl TextureWrite {
+    /// `rect` of an RGBA canvas covering the logical screen, going to the same place in a
+    /// texture as big as the screen.
+    pub fn region(
+        canvas: &[u8],
+        (screen_width, _): (u16, u16),
+        rect: Rect,
+        channel_order: ChannelOrder,
+    ) -> Self {
+        let mut pixels = Vec::with_capacity(rect.width as usize * rect.height as usize * 4);
+        for y in rect.top as usize..rect.top as usize + rect.height as usize {
+            let offset = (y * screen_width as usize + rect.left as usize) * 4;
+            pixels.extend_from_slice(&canvas[offset..offset + rect.width as usize * 4]);
+        }
+
+        if channel_order == ChannelOrder::Bgra {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        TextureWrite {
+            left: rect.left,
+            top: rect.top,
+            width: rect.width,
+            height: rect.height,
+            pixels,
+        }
+    }
+
+    /// Two canvases covering the logical screen on top of each other, half see-through, so
+    /// anything that jumps from one to the other stands out.
+    pub fn blend(
+        first: &[u8],
+        last: &[u8],
+        (width, height): (u16, u16),
+        channel_order: ChannelOrder,
+    ) -> Self {
+        let blended: Vec<u8> = first
+            .iter()
+            .zip(last)
+            .map(|(&first, &last)| ((first as u16 + last as u16) / 2) as u8)
+            .collect();
+        let screen = Rect::new(0, 0, width, height);
+        TextureWrite::region(&blended, (width, height), screen, channel_order)
+    }
+
+    /// Copies the pixels into a texture kept in memory, `texture_width` pixels wide, the same
+    /// way uploading them to the GPU does.
+    pub fn apply(&self, texture: &mut [u8], texture_width: u16) {
+        let row_length = self.width as usize * 4;
+        if row_length == 0 {
+            return;
+        }
+
+        for (y, row) in self.pixels.chunks_exact(row_length).enumerate() {
+            let offset =
+                ((self.top as usize + y) * texture_width as usize + self.left as usize) * 4;
+            texture[offset..offset + row_length].copy_from_slice(row);
+        }
+    }
+}
+
+/// What a texture as big as the logical screen holds while frames are played. Played in order,
+/// each frame is composited onto the last and only the part of the screen it changed has to be
+/// written, so a small frame that keeps what's underneath only touches its own pixels.
+#[derive(Debug, Clone)]
+pub struct TextureCanvas {
+    compositor: Compositor,
+    // the frame the texture shows, none if something else has been written to it since
+    shown: Option<usize>,
+}
+
+impl TextureCanvas {
+    pub fn new((width, height): (u16, u16)) -> Self {
+        TextureCanvas {
+            compositor: Compositor::new(width, height),
+            shown: None,
+        }
+    }
+
+    /// Forgets what's in the texture, after writing something other than a frame to it.
+    pub fn invalidate(&mut self) {
+        self.shown = None;
+    }
+
+    /// What to write to the texture to show `frames[index]`. Following on from the frame
+    /// before it, that's whatever the frame and the disposal before it changed. Anywhere else
+    /// the frames are composited from the first one again and the whole texture is written.
+    pub fn show(
+        &mut self,
+        frames: &[Frame],
+        index: usize,
+        channel_order: ChannelOrder,
+    ) -> TextureWrite {
+        let screen_size = (self.compositor.width(), self.compositor.height());
+        let changed = match self.shown {
+            Some(shown) if shown == index => Rect::new(0, 0, 0, 0),
+            Some(shown) if shown + 1 == index => self.compositor.draw(&frames[index]),
+            _ => {
+                self.compositor.reset();
+                for frame in &frames[..=index] {
+                    self.compositor.draw(frame);
+                }
+                Rect::new(0, 0, screen_size.0, screen_size.1)
+            }
+        };
+        self.shown = Some(index);
+
+        TextureWrite::region(
+            &self.compositor.canvas(),
+            screen_size,
+            changed,
+            channel_order,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChannelOrder, TextureCanvas, TextureWrite};
+    use jif::compositor::Rect;
+    use jif::parser::{DisposalMethod, Frame};
+
+    #[test]
+    fn fills_the_texture_like_the_gpu() {
+        let (red, blue) = ([255, 0, 0, 255], [0, 0, 255, 255]);
+        let canvas = [red, blue, blue, red].concat();
+        let corner =
+            TextureWrite::region(&canvas, (2, 2), Rect::new(1, 1, 1, 1), ChannelOrder::Rgba);
+        assert_eq!((corner.left, corner.top), (1, 1));
+        assert_eq!(corner.pixels, red);
+
+        let bgra = TextureWrite::region(&canvas, (2, 2), Rect::new(0, 0, 2, 1), ChannelOrder::Bgra);
+        assert_eq!(bgra.pixels, [[0, 0, 255, 255], [255, 0, 0, 255]].concat());
+
+        let mut texture = vec![0; 16];
+        corner.apply(&mut texture, 2);
+        assert_eq!(texture, [[0; 4], [0; 4], [0; 4], red].concat());
+
+        let other = [blue; 4].concat();
+        let blended = TextureWrite::blend(&canvas, &other, (2, 2), ChannelOrder::Rgba);
+        assert_eq!(&blended.pixels[..8], [[127, 0, 127, 255], blue].concat());
+    }
+
+    #[test]
+    fn only_writes_what_frames_change() {
+        let palette: Box<[u8]> = Box::new([255, 0, 0, 0, 0, 255]);
+        let (red, blue) = ([255, 0, 0, 255], [0, 0, 255, 255]);
+        let mut background = Frame::new(3, 2, Box::new([0; 6]), palette.clone());
+        background.disposal_method = Some(DisposalMethod::DoNotDispose);
+        let mut dot = Frame::new(1, 1, Box::new([1]), palette.clone());
+        dot.left_position = 2;
+        dot.top_position = 1;
+        dot.disposal_method = Some(DisposalMethod::RestoreToBackgroundColor);
+        let mut other_dot = dot.clone();
+        other_dot.left_position = 0;
+        let frames = [background, dot, other_dot];
+
+        let mut canvas = TextureCanvas::new((3, 2));
+        let mut texture = vec![0; 3 * 2 * 4];
+        let write = canvas.show(&frames, 0, ChannelOrder::Rgba);
+        assert_eq!((write.width, write.height), (3, 2));
+        write.apply(&mut texture, 3);
+
+        let write = canvas.show(&frames, 1, ChannelOrder::Rgba);
+        assert_eq!(
+            (write.left, write.top, write.width, write.height),
+            (2, 1, 1, 1)
+        );
+        write.apply(&mut texture, 3);
+        assert_eq!(texture, [red, red, red, red, red, blue].concat());
+
+        // the dot that's cleared away and the new one
+        let write = canvas.show(&frames, 2, ChannelOrder::Rgba);
+        assert_eq!(
+            (write.left, write.top, write.width, write.height),
+            (0, 1, 3, 1)
+        );
+        write.apply(&mut texture, 3);
+        assert_eq!(texture, [red, red, red, blue, red, [0; 4]].concat());
+        assert!(canvas
+            .show(&frames, 2, ChannelOrder::Rgba)
+            .pixels
+            .is_empty());
+
+        // jumping back starts over
+        let write = canvas.show(&frames, 1, ChannelOrder::Rgba);
+        assert_eq!((write.width, write.height), (3, 2));
+        write.apply(&mut texture, 3);
+        assert_eq!(texture, [red, red, red, red, red, blue].concat());
+    }
+}
@@ -0,0 +1,200 @@
+//! Thumbnails of the first frame, for when a still picture of a GIF is all that's needed and
+//! decoding the whole animation would be wasted work, and posters of whichever frame shows the
+//! animation best.
+
+use anyhow::Result;
+
+use std::fmt::Debug;
+use std::io::Read;
+
+use crate::compositor::{blend_over, Compositor};
+use crate::parser::Decoder;
+use crate::scale::{fit, resize_rgba, square_pixels, Filter};
+
+/// An RGBA image of what's on screen while the first frame is shown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+    pub width: u16,
+    pub height: u16,
+    pub rgba: Vec<u8>,
+}
+
+impl<T: Read + Debug> Decoder<T> {
+    /// Decodes just the first image of the file and draws it on the logical screen over the
+    /// background color, leaving the rest of the file unread. Without a background color the
+    /// pixels the image doesn't cover stay transparent. A file without images gives the bare
+    /// background.
+    pub fn decode_first_frame(&mut self) -> Result<Thumbnail> {
+        let frame = self.next_frame()?.cloned();
+        let (width, height) = self.screen_size().unwrap_or((0, 0));
+
+        let mut compositor = Compositor::new(width, height);
+        if let Some(frame) = &frame {
+            compositor.draw(frame);
+        }
+        let mut rgba = compositor.canvas().into_owned();
+
+        if let Some([red, green, blue]) = self.background_color() {
+            for pixel in rgba.chunks_exact_mut(4) {
+                if pixel[3] == 0 {
+                    pixel.copy_from_slice(&[red, green, blue, 255]);
+                }
+            }
+        }
+
+        Ok(Thumbnail {
+            width,
+            height,
+            rgba,
+        })
+    }
+
+    /// What's on screen while frame `index` is shown, or `None` if the file ends before it.
+    /// Without an index that's the first frame that leaves nothing on screen transparent, since
+    /// the first frames of some GIFs only draw part of the picture, or the first frame if
+    /// there's no such frame. Unlike `decode_first_frame`, transparent pixels are left as they
+    /// are, see [`Thumbnail::flatten`].
+    pub fn decode_poster(&mut self, index: Option<usize>) -> Result<Option<Thumbnail>> {
+        if let Some(index) = index {
+            let canvas = self.frame_at(index)?;
+            let (width, height) = self.screen_size().unwrap_or((0, 0));
+            return Ok(canvas.map(|rgba| Thumbnail {
+                width,
+                height,
+                rgba,
+            }));
+        }
+
+        let mut compositor = None;
+        let mut poster = None;
+        while self.next_frame()?.is_some() {
+            let (width, height) = self.screen_size().unwrap_or((0, 0));
+            let compositor = compositor.get_or_insert_with(|| Compositor::new(width, height));
+            compositor.draw(self.frames().last().expect("a frame was just decoded"));
+
+            let canvas = compositor.canvas();
+            if canvas.chunks_exact(4).all(|pixel| pixel[3] == 255) {
+                poster = Some(canvas.into_owned());
+                break;
+            }
+            poster.get_or_insert_with(|| canvas.into_owned());
+        }
+
+        let (width, height) = self.screen_size().unwrap_or((0, 0));
+        Ok(Some(Thumbnail {
+            width,
+            height,
+            rgba: poster.unwrap_or_else(|| vec![0; width as usize * height as usize * 4]),
+        }))
+    }
+}
+
+impl Thumbnail {
+    /// Stretches the thumbnail so it looks right with square pixels, for a GIF with pixels
+    /// `pixel_aspect_ratio` times as wide as they are tall, see [`Decoder::pixel_aspect_ratio`].
+    pub fn correct_aspect_ratio(self, pixel_aspect_ratio: f32, filter: Filter) -> Thumbnail {
+        let (width, height) = square_pixels(self.width, self.height, pixel_aspect_ratio);
+        if (width, height) == (self.width, self.height) {
+            return self;
+        }
+
+        Thumbnail {
+            width,
+            height,
+            rgba: resize_rgba(&self.rgba, self.width, self.height, width, height, filter),
+        }
+    }
+
+    /// Puts the thumbnail in front of `background`, leaving it opaque.
+    pub fn flatten(mut self, background: [u8; 3]) -> Thumbnail {
+        let [red, green, blue] = background;
+        for pixel in self.rgba.chunks_exact_mut(4) {
+            let over = [pixel[0], pixel[1], pixel[2], pixel[3]];
+            pixel.copy_from_slice(&blend_over([red, green, blue, 255], over));
+        }
+        self
+    }
+
+    /// Scales the thumbnail down to fit in a `max_size` x `max_size` square, keeping its aspect
+    /// ratio. Thumbnails that already fit are returned as they are, they're never scaled up.
+    pub fn fit(self, max_size: u16, filter: Filter) -> Thumbnail {
+        let (width, height) = fit(self.width, self.height, max_size);
+        if (width, height) == (self.width, self.height) {
+            return self;
+        }
+
+        Thumbnail {
+            width,
+            height,
+            rgba: resize_rgba(&self.rgba, self.width, self.height, width, height, filter),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Thumbnail;
+    use crate::fixtures::{two_color_frame, two_color_gif};
+    use crate::parser::{Decoder, DisposalMethod};
+    use crate::scale::Filter;
+
+    #[test]
+    fn thumbnails_the_first_frame() {
+        // a blue pixel in the corner, and a second frame that never gets decoded
+        let frames = [two_color_frame(1, 1, &[1]), two_color_frame(4, 2, &[1; 8])];
+        let gif = two_color_gif(4, 2, &frames);
+
+        let mut decoder = Decoder::new(gif.as_slice());
+        let thumbnail = decoder.decode_first_frame().unwrap();
+        assert_eq!(decoder.frames().len(), 1);
+
+        // the background color is the first color of the global palette
+        let (red, blue) = ([255, 0, 0, 255], [0, 0, 255, 255]);
+        let mut expected = [red; 8];
+        expected[0] = blue;
+        assert_eq!((thumbnail.width, thumbnail.height), (4, 2));
+        assert_eq!(thumbnail.rgba, expected.concat());
+
+        let nearest = thumbnail.clone().fit(2, Filter::Nearest);
+        assert_eq!((nearest.width, nearest.height), (2, 1));
+        assert_eq!(nearest.rgba, [red, red].concat());
+
+        let boxed = thumbnail.clone().fit(2, Filter::Box);
+        assert_eq!(boxed.rgba, [[191, 0, 64, 255], red].concat());
+
+        assert_eq!(thumbnail.clone().fit(256, Filter::Box), thumbnail);
+    }
+
+    #[test]
+    fn picks_a_poster_frame() {
+        // the left half, then the right half, then all blue
+        let mut left = two_color_frame(1, 1, &[0]);
+        left.disposal_method = Some(DisposalMethod::DoNotDispose);
+        let mut right = two_color_frame(1, 1, &[1]);
+        right.left_position = 1;
+        let gif = two_color_gif(2, 1, &[left, right, two_color_frame(2, 1, &[1, 1])]);
+
+        let (red, blue, clear) = ([255, 0, 0, 255], [0, 0, 255, 255], [0, 0, 0, 0]);
+        let mut decoder = Decoder::new(gif.as_slice());
+        let poster = decoder.decode_poster(None).unwrap().unwrap();
+        assert_eq!(poster.rgba, [red, blue].concat());
+        assert_eq!(decoder.frames().len(), 2);
+
+        let mut decoder = Decoder::new(gif.as_slice());
+        let poster = decoder.decode_poster(Some(0)).unwrap().unwrap();
+        assert_eq!(poster.rgba, [red, clear].concat());
+        let flattened = poster.flatten([255, 255, 255]);
+        assert_eq!(flattened.rgba, [red, [255, 255, 255, 255]].concat());
+        assert_eq!(decoder.decode_poster(Some(3)).unwrap(), None);
+    }
+
+    #[test]
+    fn weighs_colors_by_alpha() {
+        let thumbnail = Thumbnail {
+            width: 2,
+            height: 1,
+            rgba: vec![0, 0, 0, 0, 200, 100, 50, 255],
+        };
+        assert_eq!(thumbnail.fit(1, Filter::Box).rgba, [200, 100, 50, 128]);
+    }
+}
@@ -0,0 +1,46 @@
+//! Timing for the stages a GIF goes through while it's decoded and played, logged at trace
+//! level so that `RUST_LOG=jif=trace` gives a trace of where the time goes, or
+//! `RUST_LOG=jif::trace=trace` just the timings.
+
+use log::{log_enabled, trace, Level};
+
+use std::fmt::{Display, Write};
+use std::time::Instant;
+
+/// Times a stage until it's dropped, then logs how long it took along with the fields recorded
+/// on it, like `lzw code_size=8 bytes=1234 took 420µs`. Does nothing with trace logging off.
+#[derive(Debug)]
+#[must_use = "the stage is only timed until the span is dropped"]
+pub struct Span {
+    stage: &'static str,
+    fields: String,
+    // none with trace logging off
+    started: Option<Instant>,
+}
+
+impl Span {
+    pub fn start(stage: &'static str) -> Self {
+        // wasm32-unknown-unknown doesn't have a clock to read
+        let has_clock = !cfg!(all(target_arch = "wasm32", target_os = "unknown"));
+        Self {
+            stage,
+            fields: String::new(),
+            started: (has_clock && log_enabled!(Level::Trace)).then(Instant::now),
+        }
+    }
+
+    /// Records `name=value`, to be logged with the stage.
+    pub fn field(&mut self, name: &str, value: impl Display) {
+        if self.started.is_some() {
+            let _ = write!(self.fields, " {}={}", name, value);
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(started) = self.started {
+            trace!("{}{} took {:?}", self.stage, self.fields, started.elapsed());
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! Images burned into every frame of an animation, see `Animation::watermark`.
+
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::compositor::Rect;
+
+#[derive(Error, Debug)]
+enum WatermarkError {
+    #[error("not a binary PPM or PAM image")]
+    UnknownFormat,
+    #[error("the image header is cut short or malformed")]
+    BadHeader,
+    #[error("only 8 bit images are supported, this one has a maximum value of {0}")]
+    UnsupportedMaxValue(u32),
+    #[error("PAM images need 3 channels, or 4 with alpha, this one has {0}")]
+    UnsupportedDepth(u32),
+    #[error("the image is {0}x{1}, bigger than a GIF can be")]
+    TooBig(u32, u32),
+    #[error("the image data is cut short")]
+    Truncated,
+}
+
+/// Where a watermark goes on the logical screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Position {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+/// An RGBA image to burn into frames.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watermark {
+    width: u16,
+    height: u16,
+    rgba: Vec<u8>,
+}
+
+impl Watermark {
+    /// How far a watermark in a corner is kept from the edges of the screen, in pixels.
+    pub const MARGIN: u16 = 4;
+
+    /// A watermark of `width` x `height` pixels of RGBA data.
+    pub fn new(width: u16, height: u16, rgba: Vec<u8>) -> Self {
+        assert_eq!(rgba.len(), width as usize * height as usize * 4);
+        Self {
+            width,
+            height,
+            rgba,
+        }
+    }
+
+    /// Reads a binary PPM (`P6`), which is opaque, or a PAM (`P7`) with an `RGB` or
+    /// `RGB_ALPHA` tuple type, which is how alpha is usually stored next to PPMs.
+    pub fn decode_pnm(data: &[u8]) -> Result<Self> {
+        let mut header = Header { data, position: 2 };
+        let (width, height, depth) = match data.get(..2) {
+            Some(b"P6") => {
+                let width = header.number()?;
+                let height = header.number()?;
+                let max_value = header.number()?;
+                if max_value != 255 {
+                    return Err(WatermarkError::UnsupportedMaxValue(max_value).into());
+                }
+                // exactly one whitespace character before the pixels
+                header.position += 1;
+                (width, height, 3)
+            }
+            Some(b"P7") => header.pam()?,
+            _ => return Err(WatermarkError::UnknownFormat.into()),
+        };
+
+        let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+            return Err(WatermarkError::TooBig(width, height).into());
+        };
+        let len = width as usize * height as usize * depth;
+        let pixels = data
+            .get(header.position..)
+            .and_then(|pixels| pixels.get(..len))
+            .ok_or(WatermarkError::Truncated)?;
+
+        let rgba = match depth {
+            4 => pixels.to_vec(),
+            _ => pixels
+                .chunks_exact(3)
+                .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+                .collect(),
+        };
+        Ok(Self::new(width, height, rgba))
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    /// Scales every pixel's alpha by `opacity`, from 0 to 1.
+    pub fn set_opacity(&mut self, opacity: f32) {
+        let opacity = opacity.clamp(0.0, 1.0);
+        for pixel in self.rgba.chunks_exact_mut(4) {
+            pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+        }
+    }
+
+    /// Where the watermark goes on a `width` x `height` screen. It can hang off the screen if
+    /// it doesn't fit.
+    pub fn rect(&self, width: u16, height: u16, position: Position) -> Rect {
+        // the start of a side that ends a margin before the edge of the screen
+        let far = |screen: u16, size: u16| screen.saturating_sub(size.saturating_add(Self::MARGIN));
+        let (left, top) = match position {
+            Position::TopLeft => (Self::MARGIN, Self::MARGIN),
+            Position::TopRight => (far(width, self.width), Self::MARGIN),
+            Position::BottomLeft => (Self::MARGIN, far(height, self.height)),
+            Position::BottomRight => (far(width, self.width), far(height, self.height)),
+            Position::Center => (
+                width.saturating_sub(self.width) / 2,
+                height.saturating_sub(self.height) / 2,
+            ),
+        };
+        Rect::new(left, top, self.width, self.height)
+    }
+}
+
+// the text header of a PPM or PAM, read up to the pixels
+struct Header<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Header<'_> {
+    // the next whitespace separated word, skipping comments
+    fn token(&mut self) -> Result<&str> {
+        loop {
+            match self.data.get(self.position) {
+                Some(b'#') => {
+                    while !matches!(self.data.get(self.position), Some(b'\n') | None) {
+                        self.position += 1;
+                    }
+                }
+                Some(byte) if byte.is_ascii_whitespace() => self.position += 1,
+                Some(_) => break,
+                None => return Err(WatermarkError::BadHeader.into()),
+            }
+        }
+
+        let start = self.position;
+        while matches!(self.data.get(self.position), Some(byte) if !byte.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+        std::str::from_utf8(&self.data[start..self.position])
+            .map_err(|_| WatermarkError::BadHeader.into())
+    }
+
+    fn number(&mut self) -> Result<u32> {
+        self.token()?
+            .parse()
+            .map_err(|_| WatermarkError::BadHeader.into())
+    }
+
+    // the width, height and depth of a PAM
+    fn pam(&mut self) -> Result<(u32, u32, usize)> {
+        let (mut width, mut height, mut depth, mut max_value) = (None, None, None, None);
+        loop {
+            match self.token()? {
+                "WIDTH" => width = Some(self.number()?),
+                "HEIGHT" => height = Some(self.number()?),
+                "DEPTH" => depth = Some(self.number()?),
+                "MAXVAL" => max_value = Some(self.number()?),
+                // the depth says all there is to know
+                "TUPLTYPE" => {
+                    self.token()?;
+                }
+                "ENDHDR" => break,
+                _ => return Err(WatermarkError::BadHeader.into()),
+            }
+        }
+        // ENDHDR ends with a newline
+        self.position += 1;
+
+        let (Some(width), Some(height), Some(depth), Some(max_value)) =
+            (width, height, depth, max_value)
+        else {
+            return Err(WatermarkError::BadHeader.into());
+        };
+        if max_value != 255 {
+            return Err(WatermarkError::UnsupportedMaxValue(max_value).into());
+        }
+        if !matches!(depth, 3 | 4) {
+            return Err(WatermarkError::UnsupportedDepth(depth).into());
+        }
+        Ok((width, height, depth as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Position, Watermark};
+    use crate::compositor::Rect;
+
+    #[test]
+    fn decodes_ppm_and_pam() {
+        let ppm = [
+            b"P6\n# a comment\n2 1\n255\n".as_slice(),
+            &[255, 0, 0, 0, 0, 255],
+        ]
+        .concat();
+        let watermark = Watermark::decode_pnm(&ppm).unwrap();
+        assert_eq!((watermark.width(), watermark.height()), (2, 1));
+        assert_eq!(watermark.rgba(), [255, 0, 0, 255, 0, 0, 255, 255]);
+
+        let header = "P7\nWIDTH 1\nHEIGHT 2\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n";
+        let pam = [header.as_bytes(), &[1, 2, 3, 4, 5, 6, 7, 8]].concat();
+        let mut watermark = Watermark::decode_pnm(&pam).unwrap();
+        assert_eq!((watermark.width(), watermark.height()), (1, 2));
+        watermark.set_opacity(0.5);
+        assert_eq!(watermark.rgba(), [1, 2, 3, 2, 5, 6, 7, 4]);
+
+        assert!(Watermark::decode_pnm(&pam[..pam.len() - 1]).is_err());
+        assert!(Watermark::decode_pnm(b"P3\n1 1\n255\n0 0 0\n").is_err());
+    }
+
+    #[test]
+    fn sits_in_a_corner() {
+        let watermark = Watermark::new(10, 5, vec![0; 200]);
+        assert_eq!(
+            watermark.rect(100, 50, Position::BottomRight),
+            Rect::new(86, 41, 10, 5)
+        );
+        assert_eq!(
+            watermark.rect(100, 50, Position::TopRight),
+            Rect::new(86, 4, 10, 5)
+        );
+        assert_eq!(
+            watermark.rect(100, 50, Position::Center),
+            Rect::new(45, 22, 10, 5)
+        );
+        assert_eq!(
+            watermark.rect(8, 8, Position::BottomLeft),
+            Rect::new(4, 0, 10, 5)
+        );
+    }
+}
@@ -0,0 +1,40 @@
+//! Minimized inputs that used to make the decoder panic. Decoding them may fail, but it must
+//! never panic, and whatever frames come out have to be fully covered by their indicies.
+
+use jif::parser::{DecodeOptions, Decoder};
+
+use std::fs;
+use std::io::Cursor;
+
+#[test]
+fn fixtures_do_not_panic() {
+    let mut fixture_count = 0;
+
+    for entry in fs::read_dir("tests/fixtures").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "gif") {
+            continue;
+        }
+        let data = fs::read(&path).unwrap();
+
+        for strict in [false, true] {
+            let options = DecodeOptions::new().strict(strict).max_memory(1 << 24);
+            let mut decoder = Decoder::new_with_options(Cursor::new(data.clone()), options);
+
+            if decoder.parse().is_ok() {
+                for frame in decoder.frames() {
+                    assert_eq!(
+                        frame.indicies().len(),
+                        frame.width as usize * frame.height as usize,
+                        "{} decoded to a partially covered frame",
+                        path.display()
+                    );
+                }
+            }
+        }
+
+        fixture_count += 1;
+    }
+
+    assert!(fixture_count > 0, "no fixtures found");
+}
@@ -0,0 +1,150 @@
+//! Small GIFs covering the trickier parts of the format, checked against what they should look
+//! like once composited. Each composited frame is compared by an FNV-1a hash of its RGBA bytes,
+//! worked out independently of this crate when the fixtures were made.
+//!
+//! A failure prints the hash the frame actually had. Only update a golden after checking the
+//! frame by eye, e.g. with `jif extract`.
+
+use jif::animation::Animation;
+use jif::parser::{Decoder, Version};
+
+use std::fs;
+
+struct Golden {
+    name: &'static str,
+    version: Version,
+    size: (u16, u16),
+    frames: &'static [u64],
+}
+
+const GOLDENS: &[Golden] = &[
+    // every row different, and tall enough for all four interlace passes
+    Golden {
+        name: "interlaced",
+        version: Version::V89a,
+        size: (9, 11),
+        frames: &[0x9c2f716b483fe757, 0xe9402c3706e4377a],
+    },
+    // transparent indicies, with restore to background on one of the frames
+    Golden {
+        name: "transparent",
+        version: Version::V89a,
+        size: (6, 5),
+        frames: &[
+            0x5123c51486c1d27d,
+            0x2c45c2c7ae256acf,
+            0x581a7077d9028a7f,
+            0xa74ae18453d29122,
+        ],
+    },
+    // a frame with its own 16 color palette between two using the global one
+    Golden {
+        name: "local_palette",
+        version: Version::V89a,
+        size: (8, 6),
+        frames: &[0x840648951a82bf1d, 0xda6ba7f6e8e5c48c, 0x18713abbb34526eb],
+    },
+    // two restore to previous frames in a row, the second partly transparent
+    Golden {
+        name: "disposal_previous",
+        version: Version::V89a,
+        size: (7, 7),
+        frames: &[
+            0x293e0071ccd2c470,
+            0x822a9faff0a8c739,
+            0x212721caa983c474,
+            0x6558066f7765770b,
+            0xb9faa2e2a81bf5a8,
+        ],
+    },
+    // no extensions at all
+    Golden {
+        name: "gif87a",
+        version: Version::V87a,
+        size: (5, 4),
+        frames: &[0x5fecc1645eba7de4, 0x832dc45aeb4193b6],
+    },
+];
+
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn read_golden(golden: &Golden) -> Vec<u8> {
+    fs::read(format!("tests/golden/{}.gif", golden.name)).unwrap()
+}
+
+fn check_frames(golden: &Golden, animation: &Animation, how: &str) {
+    assert_eq!(
+        (animation.width(), animation.height()),
+        golden.size,
+        "{} ({})",
+        golden.name,
+        how
+    );
+
+    let hashes: Vec<u64> = animation
+        .composited_frames()
+        .map(|rgba| fnv1a(&rgba))
+        .collect();
+    assert_eq!(
+        hashes.len(),
+        golden.frames.len(),
+        "{} ({})",
+        golden.name,
+        how
+    );
+    for (index, (&hash, &expected)) in hashes.iter().zip(golden.frames).enumerate() {
+        assert_eq!(
+            hash, expected,
+            "frame {} of {} ({}) was {:#018x}",
+            index, golden.name, how, hash
+        );
+    }
+}
+
+#[test]
+fn composited_frames_match_goldens() {
+    for golden in GOLDENS {
+        let data = read_golden(golden);
+
+        let summary = Decoder::new(data.as_slice()).scan().unwrap();
+        assert_eq!(summary.version, golden.version, "{}", golden.name);
+
+        let animation = Animation::decode(data.as_slice()).unwrap();
+        check_frames(golden, &animation, "decode");
+    }
+}
+
+#[test]
+fn seeking_matches_goldens() {
+    for golden in GOLDENS {
+        let data = read_golden(golden);
+        let mut decoder = Decoder::new(data.as_slice());
+        // backwards, so every frame is drawn from a keyframe or the start
+        for index in (0..golden.frames.len()).rev() {
+            let canvas = decoder.frame_at(index).unwrap().unwrap();
+            assert_eq!(
+                fnv1a(&canvas),
+                golden.frames[index],
+                "frame {} of {} (seeking)",
+                index,
+                golden.name
+            );
+        }
+    }
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn parallel_decoding_matches_goldens() {
+    for golden in GOLDENS {
+        let data = read_golden(golden);
+        let mut decoder = Decoder::new(data.as_slice());
+        decoder.parse_parallel().unwrap();
+        let animation = Animation::from_decoder(decoder);
+        check_frames(golden, &animation, "parallel");
+    }
+}